@@ -3,7 +3,8 @@
 use std::time::Duration;
 
 use affair::{Executor, TokioSpawn};
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use atomo_rocks::{chunk_name, CheckpointImporter, CheckpointManifest, Options};
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{ChainId, NodeInfo};
 use tracing::{error, info};
@@ -90,15 +91,45 @@ fn sync_query(&self) -> Self::SyncExecutor {
 
     async fn load_from_checkpoint(
         config: &Config,
-        checkpoint: Vec<u8>,
         checkpoint_hash: [u8; 32],
+        blockstore: &C::BlockstoreInterface,
     ) -> Result<()> {
+        let db_path = config
+            .db_path
+            .as_ref()
+            .context("db_path must be specified to load a checkpoint")?;
+        let manifest = blockstore
+            .read_all_to_vec(&checkpoint_hash)
+            .await
+            .context("Checkpoint manifest is not in the blockstore")?;
+        let manifest = CheckpointManifest::deserialize(&manifest)?;
+
+        // The checkpoint is imported next to the database one chunk at a time. If a previous
+        // import of the same checkpoint was interrupted only the remaining chunks are imported.
+        let import_path = db_path.as_path().with_extension("checkpoint");
+        let mut importer = CheckpointImporter::new(&import_path, manifest, Options::default())?;
+        let missing: Vec<_> = importer
+            .missing()?
+            .into_iter()
+            .map(|chunk| (chunk.table.to_owned(), chunk.index, chunk.hash))
+            .collect();
+        for (table, index, hash) in missing {
+            let content = blockstore.read_all_to_vec(&hash).await.with_context(|| {
+                format!(
+                    "Checkpoint chunk {} is not in the blockstore",
+                    chunk_name(&table, index)
+                )
+            })?;
+            importer.import_chunk(&table, index, &content)?;
+        }
+        let (import_path, _) = importer.finish()?;
+
         // Due to a race condition on shutdowns when a node checkpoints, we should sleep and try
         // again if there is a lock on the DB at this stage of the process
         let mut counter = 0;
 
         loop {
-            match Env::new(config, Some((checkpoint_hash, &checkpoint))) {
+            match Env::new(config, Some(&import_path)) {
                 Ok(mut env) => {
                     info!(
                         "Successfully built database from checkpoint with hash {checkpoint_hash:?}"
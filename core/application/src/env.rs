@@ -1,11 +1,12 @@
 use std::collections::BTreeSet;
 use std::path::Path;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use affair::AsyncWorker as WorkerTrait;
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use atomo::{Atomo, AtomoBuilder, DefaultSerdeBackend, QueryPerm, UpdatePerm};
-use atomo_rocks::{Cache as RocksCache, Env as RocksEnv, Options};
+use atomo_rocks::{Cache as RocksCache, CheckpointManifest, Env as RocksEnv, Options};
 use fleek_crypto::{ClientPublicKey, ConsensusPublicKey, EthAddress, NodePublicKey};
 use hp_fixed::unsigned::HpUfixed;
 use lightning_interfaces::prelude::*;
@@ -14,20 +15,30 @@
     Blake3Hash,
     Block,
     BlockExecutionResponse,
+    BridgeSigners,
     Committee,
     CommodityTypes,
     CompressionAlgorithm,
+    Delegation,
     Epoch,
     ExecutionData,
     Metadata,
     NodeIndex,
     NodeInfo,
     NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
+    ProposalStatus,
+    ProposalVote,
     ProtocolParams,
     ReportedReputationMeasurements,
     Service,
     ServiceId,
     ServiceRevenue,
+    SlashRecord,
+    StateDiff,
+    Tokens,
     TotalServed,
     TransactionReceipt,
     TransactionResponse,
@@ -35,78 +46,45 @@
     Value,
 };
 use lightning_metrics::increment_counter;
-use tracing::warn;
+use tracing::{info, warn};
 
 use crate::config::{Config, Mode, StorageConfig};
 use crate::genesis::{Genesis, GenesisPrices};
+use crate::migrations::{self, MigrationReport, MIGRATIONS, SCHEMA_VERSION};
 use crate::query_runner::QueryRunner;
+use crate::slashing::MisbehaviorVerifiers;
 use crate::state::State;
 use crate::storage::{AtomoStorage, AtomoStorageBuilder};
 use crate::table::StateTables;
 
 pub struct Env<P> {
     pub inner: Atomo<P, AtomoStorage>,
+    /// The verifiers used to check the proofs of misbehavior of slash transactions.
+    misbehavior_verifiers: Arc<MisbehaviorVerifiers>,
 }
 
 impl Env<UpdatePerm> {
-    pub fn new(config: &Config, checkpoint: Option<([u8; 32], &[u8])>) -> Result<Self> {
-        let storage = match config.storage {
+    pub fn new(config: &Config, checkpoint: Option<&Path>) -> Result<Self> {
+        // The pending migrations are run before the state is opened with its indexes, since
+        // building an index reads the entries of the indexed table as their current type. An
+        // in-memory state is always empty, so there is nothing to migrate.
+        let checkpoint = match config.storage {
             StorageConfig::RocksDb => {
-                let db_path = config
-                    .db_path
-                    .as_ref()
-                    .context("db_path must be specified for RocksDb backend")?;
-                let mut db_options = if let Some(db_options) = config.db_options.as_ref() {
-                    let (options, _) = Options::load_latest(
-                        db_options,
-                        RocksEnv::new().context("Failed to create rocks db env.")?,
-                        false,
-                        // TODO(matthias): I set this lru cache size arbitrarily
-                        RocksCache::new_lru_cache(100),
-                    )
-                    .context("Failed to create rocks db options.")?;
-                    options
-                } else {
-                    Options::default()
-                };
-                db_options.create_if_missing(true);
-                db_options.create_missing_column_families(true);
-                match checkpoint {
-                    Some((hash, checkpoint)) => AtomoStorageBuilder::new(Some(db_path.as_path()))
-                        .with_options(db_options)
-                        .from_checkpoint(hash, checkpoint),
-                    None => {
-                        AtomoStorageBuilder::new(Some(db_path.as_path())).with_options(db_options)
-                    },
+                let report = Self::migrate(config, checkpoint)?;
+                for step in &report.steps {
+                    info!(
+                        "Migrated the state to schema version {}: {}",
+                        step.version, step.description
+                    );
                 }
+                // The checkpoint was moved to the database when it was opened for the migrations.
+                None
             },
-            StorageConfig::InMemory => AtomoStorageBuilder::new::<&Path>(None),
+            StorageConfig::InMemory => checkpoint,
         };
 
-        let mut atomo = AtomoBuilder::<AtomoStorageBuilder, DefaultSerdeBackend>::new(storage);
-        atomo = atomo
-            .with_table::<Metadata, Value>("metadata")
-            .with_table::<EthAddress, AccountInfo>("account")
-            .with_table::<ClientPublicKey, EthAddress>("client_keys")
-            .with_table::<NodeIndex, NodeInfo>("node")
-            .with_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
-            .with_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
-            .with_table::<(NodeIndex, NodeIndex), Duration>("latencies")
-            .with_table::<Epoch, Committee>("committee")
-            .with_table::<ServiceId, Service>("service")
-            .with_table::<ProtocolParams, u128>("parameter")
-            .with_table::<NodeIndex, Vec<ReportedReputationMeasurements>>("rep_measurements")
-            .with_table::<NodeIndex, u8>("rep_scores")
-            .with_table::<NodeIndex, u8>("submitted_rep_measurements")
-            .with_table::<NodeIndex, NodeServed>("current_epoch_served")
-            .with_table::<NodeIndex, NodeServed>("last_epoch_served")
-            .with_table::<Epoch, TotalServed>("total_served")
-            .with_table::<CommodityTypes, HpUfixed<6>>("commodity_prices")
-            .with_table::<ServiceId, ServiceRevenue>("service_revenue")
-            .with_table::<TxHash, ()>("executed_digests")
-            .with_table::<NodeIndex, u8>("uptime")
-            .with_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
-            .with_table::<NodeIndex, BTreeSet<Blake3Hash>>("node_to_cid")
+        let atomo = with_tables(AtomoBuilder::new(storage_builder(config, checkpoint)?));
+        let atomo = with_indexes(atomo)
             .enable_iter("current_epoch_served")
             .enable_iter("rep_measurements")
             .enable_iter("submitted_rep_measurements")
@@ -116,33 +94,48 @@ impl Env<UpdatePerm> {
             .enable_iter("executed_digests")
             .enable_iter("uptime")
             .enable_iter("service_revenue")
-            .enable_iter("cid_to_node")
-            .enable_iter("node_to_cid");
-
-        #[cfg(debug_assertions)]
-        {
-            atomo = atomo
-                .enable_iter("consensus_key_to_index")
-                .enable_iter("pub_key_to_index");
-        }
+            .enable_iter("node_to_cid")
+            .enable_iter("jailed_nodes")
+            .enable_iter("pending_withdrawals")
+            .enable_iter("delegations")
+            .enable_iter("proposals")
+            .enable_state_tree();
 
         Ok(Self {
             inner: atomo.build()?,
+            misbehavior_verifiers: Default::default(),
         })
     }
 
+    /// Runs the migrations of the state that are newer than its schema version, see
+    /// [`crate::migrations`]. The state is opened without its indexes, they are built from the
+    /// migrated entries once the state is opened by [`Env::new`].
+    pub fn migrate(config: &Config, checkpoint: Option<&Path>) -> Result<MigrationReport> {
+        let atomo = with_tables(AtomoBuilder::new(storage_builder(config, checkpoint)?));
+        let mut atomo = migrations::with_legacy_tables(atomo).build()?;
+        migrations::migrate(&mut atomo, MIGRATIONS)
+    }
+
+    /// Use the given registry to verify the proofs of misbehavior of slash transactions instead
+    /// of [`MisbehaviorVerifiers::default`].
+    pub fn with_misbehavior_verifiers(mut self, verifiers: MisbehaviorVerifiers) -> Self {
+        self.misbehavior_verifiers = Arc::new(verifiers);
+        self
+    }
+
     #[autometrics::autometrics]
-    async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecutionResponse
-    where
-        F: FnOnce() -> P,
-        P: IncrementalPutInterface,
-    {
-        let response = self.inner.run(move |ctx| {
+    async fn run<C: Collection>(
+        &mut self,
+        mut block: Block,
+        blockstore: &C::BlockstoreInterface,
+    ) -> BlockExecutionResponse {
+        let misbehavior_verifiers = self.misbehavior_verifiers.clone();
+        let (mut response, inverse) = self.inner.run_with_inverse(move |ctx| {
             // Create the app/execution environment
             let backend = StateTables {
                 table_selector: ctx,
             };
-            let app = State::new(backend);
+            let app = State::new(backend).with_misbehavior_verifiers(misbehavior_verifiers);
             let last_block_hash = app.get_block_hash();
             // increment the block_number
             let block_number = app.increment_block_number();
@@ -155,13 +148,18 @@ async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecution
                 node_registry_delta: Vec::new(),
                 txn_receipts: Vec::with_capacity(block.transactions.len()),
                 block_number,
+                state_root: [0; 32],
+                state_diff: StateDiff::default(),
             };
 
             // Execute each transaction and add the results to the block response
             for (index, txn) in &mut block.transactions.iter_mut().enumerate() {
-                let results = match app.verify_transaction(txn) {
-                    Ok(_) => app.execute_transaction(txn.clone()),
-                    Err(err) => TransactionResponse::Revert(err),
+                let (results, fee) = match app
+                    .verify_transaction(txn)
+                    .and_then(|_| app.charge_transaction_fee(txn))
+                {
+                    Ok(fee) => (app.execute_transaction(txn.clone()), fee),
+                    Err(err) => (TransactionResponse::Revert(err), None),
                 };
 
                 // If the transaction moved the epoch forward, acknowledge that in the block
@@ -170,11 +168,12 @@ async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecution
                     response.change_epoch = true;
                 }
 
+                // Events that depend on the state are emitted during the execution, the other
+                // ones are derived from the transaction.
+                let emitted = app.take_event();
                 let mut event = None;
                 if let TransactionResponse::Success(_) = results {
-                    if let Some(e) = txn.event() {
-                        event = Some(e);
-                    }
+                    event = emitted.or_else(|| txn.event());
                 }
 
                 let receipt = TransactionReceipt {
@@ -186,6 +185,7 @@ async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecution
                     to: txn.to(),
                     response: results,
                     event,
+                    fee,
                 };
                 /* Todo(dalton): Check if the transaction resulted in the committee change(Like a current validator getting slashed)
                     if so acknowledge that in the block response
@@ -199,6 +199,25 @@ async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecution
             response
         });
 
+        // The state tree is always enabled for the application state.
+        response.state_root = self
+            .inner
+            .state_root()
+            .expect("The state tree should be enabled");
+
+        response.state_diff = StateDiff {
+            tables: inverse
+                .into_iter()
+                .map(|(table, changes)| {
+                    let changes = changes
+                        .into_iter()
+                        .map(|(key, value)| (key.into_vec(), value.map(|value| value.into_vec())))
+                        .collect();
+                    (table, changes)
+                })
+                .collect(),
+        };
+
         if response.change_epoch {
             increment_counter!(
                 "epoch_change_by_txn",
@@ -207,33 +226,74 @@ async fn run<F, P>(&mut self, mut block: Block, get_putter: F) -> BlockExecution
                 )
             );
 
-            let storage = self.inner.get_storage_backend_unsafe();
-            // This will return `None` only if the InMemory backend is used.
-            if let Some(checkpoint) = storage.serialize() {
-                let mut blockstore_put = get_putter();
-                if blockstore_put
-                    .write(checkpoint.as_slice(), CompressionAlgorithm::Uncompressed)
-                    .is_ok()
-                {
-                    if let Ok(state_hash) = blockstore_put.finalize().await {
-                        // Only temporary: write the checkpoint to disk directly.
-                        self.update_last_epoch_hash(state_hash);
-                    } else {
-                        warn!("Failed to finalize writing checkpoint to blockstore");
-                    }
-                } else {
-                    warn!("Failed to write checkpoint to blockstore");
-                }
+            match self.write_checkpoint::<C>(blockstore).await {
+                Ok(Some(state_hash)) => self.update_last_epoch_hash(state_hash),
+                Ok(None) => {},
+                Err(e) => warn!("Failed to write checkpoint to blockstore: {e:?}"),
             }
         }
 
         response
     }
 
+    /// Writes a checkpoint of the current state to the blockstore and returns the hash of its
+    /// manifest. Every chunk of the checkpoint is written with its own putter, followed by the
+    /// manifest. Returns `None` if the storage backend does not support checkpoints.
+    ///
+    /// Each chunk is pinned as soon as it is written, so that putting the rest of the checkpoint
+    /// can not evict it. The pins of the previous checkpoint are released by the caller once the
+    /// checkpoint is recorded, see [`UpdateWorker`].
+    pub async fn write_checkpoint<C: Collection>(
+        &mut self,
+        blockstore: &C::BlockstoreInterface,
+    ) -> Result<Option<[u8; 32]>> {
+        let latest = self.last_epoch_hash();
+        let storage = self.inner.get_storage_backend_unsafe();
+        // This will return `None` only if the InMemory backend is used.
+        let Some(writer) = storage.checkpoint() else {
+            return Ok(None);
+        };
+
+        let mut pinned = BTreeSet::new();
+        let result: Result<[u8; 32]> = async {
+            let mut manifest = CheckpointManifest::default();
+            for chunk in writer {
+                let chunk = chunk?;
+                let hash = put_pinned::<C>(blockstore, &chunk.content).await?;
+                pinned.insert(hash);
+                manifest.push(&chunk.table, hash);
+            }
+
+            let state_hash = put_pinned::<C>(blockstore, &manifest.serialize()).await?;
+            pinned.insert(state_hash);
+            // The importers identify the checkpoint by the hash of its manifest.
+            ensure!(
+                state_hash == manifest.hash(),
+                "Checkpoint manifest was stored under an unexpected hash"
+            );
+            Ok(state_hash)
+        }
+        .await;
+
+        if result.is_err() {
+            // Release the chunks of the incomplete checkpoint, except the ones it shares with
+            // the latest checkpoint.
+            let latest = match latest {
+                Some(latest) => checkpoint_content::<C>(blockstore, latest).await,
+                None => BTreeSet::new(),
+            };
+            for hash in pinned.difference(&latest) {
+                blockstore.unpin(hash).await;
+            }
+        }
+        result.map(Some)
+    }
+
     /// Returns an identical environment but with query permissions
     pub fn query_socket(&self) -> Env<QueryPerm> {
         Env {
             inner: self.inner.query(),
+            misbehavior_verifiers: self.misbehavior_verifiers.clone(),
         }
     }
 
@@ -283,8 +343,7 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                 ctx.get_table::<NodeIndex, NodeServed>("current_epoch_served");
             let mut latencies_table =
                 ctx.get_table::<(NodeIndex, NodeIndex), Duration>("latencies");
-            let mut consensus_key_to_index_table = ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index");
-            let mut pub_key_to_index_table = ctx.get_table::<NodePublicKey, NodeIndex>("pub_key_to_index");
+            let nodes_by_pub_key = ctx.get_index::<NodePublicKey, NodeIndex>("nodes_by_pub_key");
 
             // TODO(matthias): should we hash the genesis state instead?
             metadata_table.insert(Metadata::LastEpochHash, Value::Hash([0; 32]));
@@ -293,6 +352,8 @@ pub fn genesis(&mut self, config: &Config) -> bool {
 
             metadata_table.insert(Metadata::BlockNumber, Value::BlockNumber(0));
 
+            metadata_table.insert(Metadata::SchemaVersion, Value::SchemaVersion(SCHEMA_VERSION));
+
             metadata_table.insert(
                 Metadata::ProtocolFundAddress,
                 Value::AccountPublicKey(genesis.protocol_fund_address),
@@ -300,6 +361,13 @@ pub fn genesis(&mut self, config: &Config) -> bool {
 
             metadata_table.insert(Metadata::GovernanceAddress,
                 Value::AccountPublicKey(genesis.governance_address));
+            metadata_table.insert(
+                Metadata::BridgeSigners,
+                Value::BridgeSigners(BridgeSigners {
+                    signers: genesis.bridge_signers.clone(),
+                    threshold: genesis.bridge_threshold,
+                }),
+            );
             let governance_account = AccountInfo {
                 flk_balance: 0u64.into(),
                 stables_balance: 0u64.into(),
@@ -344,6 +412,29 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                 ProtocolParams::NodeCount,
                 genesis.node_count as u128
             );
+            param_table.insert(ProtocolParams::ServiceBond, genesis.service_bond as u128);
+            param_table.insert(ProtocolParams::SlashPercentage, genesis.slash_percentage as u128);
+            param_table.insert(ProtocolParams::JailDuration, genesis.jail_duration as u128);
+            param_table.insert(ProtocolParams::GasPrice, genesis.gas_price as u128);
+            param_table.insert(ProtocolParams::StablesGasPrice, genesis.stables_gas_price as u128);
+            param_table.insert(ProtocolParams::BaseGas, genesis.base_gas as u128);
+            param_table.insert(ProtocolParams::TransferGas, genesis.transfer_gas as u128);
+            param_table.insert(ProtocolParams::StakingGas, genesis.staking_gas as u128);
+            param_table.insert(ProtocolParams::BridgeGas, genesis.bridge_gas as u128);
+            param_table.insert(ProtocolParams::ServiceGas, genesis.service_gas as u128);
+            param_table.insert(ProtocolParams::GovernanceQuorum, genesis.governance_quorum as u128);
+            param_table.insert(
+                ProtocolParams::GovernanceVotingPeriod,
+                genesis.governance_voting_period as u128,
+            );
+            param_table.insert(
+                ProtocolParams::GovernanceTimelock,
+                genesis.governance_timelock as u128,
+            );
+            param_table.insert(
+                ProtocolParams::GovernanceMaxOpenProposals,
+                genesis.governance_max_open_proposals as u128,
+            );
 
             let epoch_end: u64 = genesis.epoch_time + genesis.epoch_start;
             let mut committee_members = Vec::with_capacity(4);
@@ -360,8 +451,6 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                     _ => 0,
                 };
 
-                consensus_key_to_index_table.insert(node_info.consensus_key, node_index);
-                pub_key_to_index_table.insert(node_info.public_key, node_index);
                 node_table.insert(node_index, node_info);
                 metadata_table.insert(
                     Metadata::NextNodeIndex,
@@ -405,7 +494,7 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                     Service {
                         owner: service.owner,
                         commodity_type: service.commodity_type,
-                        slashing: (),
+                        slashing: service.slashing.clone(),
                     },
                 )
             }
@@ -441,9 +530,9 @@ pub fn genesis(&mut self, config: &Config) -> bool {
                 for lat in latencies {
                     assert!(lat.node_public_key_lhs < lat.node_public_key_rhs,
                         "Invalid latency entry, node_public_key_lhs must be smaller than node_public_key_rhs");
-                    let index_lhs = pub_key_to_index_table.get(lat.node_public_key_lhs)
+                    let index_lhs = *nodes_by_pub_key.get(&lat.node_public_key_lhs).first()
                         .expect("Invalid latency entry, node doesn't have an index.");
-                    let index_rhs = pub_key_to_index_table.get(lat.node_public_key_rhs)
+                    let index_rhs = *nodes_by_pub_key.get(&lat.node_public_key_rhs).first()
                         .expect("Invalid latency entry, node doesn't have an index.");
                     latencies_table.insert(
                         (index_lhs, index_rhs),
@@ -467,6 +556,19 @@ pub fn genesis(&mut self, config: &Config) -> bool {
             app.set_last_epoch_hash(state_hash);
         })
     }
+
+    /// Returns the hash of the checkpoint that was written at the end of the last epoch.
+    pub fn last_epoch_hash(&self) -> Option<[u8; 32]> {
+        self.inner.query().run(|ctx| {
+            match ctx
+                .get_table::<Metadata, Value>("metadata")
+                .get(Metadata::LastEpochHash)
+            {
+                Some(Value::Hash(hash)) => Some(hash),
+                _ => None,
+            }
+        })
+    }
 }
 
 impl Default for Env<UpdatePerm> {
@@ -485,12 +587,187 @@ impl<C: Collection> UpdateWorker<C> {
     pub fn new(env: Env<UpdatePerm>, blockstore: C::BlockstoreInterface) -> Self {
         Self { env, blockstore }
     }
+
+    /// Unpins the checkpoint of the previous epoch once the checkpoint of the new epoch is
+    /// written, so that the latest checkpoint is the only one that is never evicted. The new
+    /// checkpoint is pinned while it is written, see [`Env::write_checkpoint`].
+    async fn unpin_previous_checkpoint(&self, previous: Option<[u8; 32]>) {
+        let (Some(current), Some(previous)) = (self.env.last_epoch_hash(), previous) else {
+            return;
+        };
+        if previous == current {
+            return;
+        }
+
+        // The chunks of the tables that did not change are shared between the checkpoints.
+        let current = checkpoint_content::<C>(&self.blockstore, current).await;
+        for hash in checkpoint_content::<C>(&self.blockstore, previous).await {
+            if !current.contains(&hash) {
+                self.blockstore.unpin(&hash).await;
+            }
+        }
+    }
+}
+
+/// Returns the builder of the storage of the application state.
+fn storage_builder(config: &Config, checkpoint: Option<&Path>) -> Result<AtomoStorageBuilder> {
+    let storage = match config.storage {
+        StorageConfig::RocksDb => {
+            let db_path = config
+                .db_path
+                .as_ref()
+                .context("db_path must be specified for RocksDb backend")?;
+            let mut db_options = if let Some(db_options) = config.db_options.as_ref() {
+                let (options, _) = Options::load_latest(
+                    db_options,
+                    RocksEnv::new().context("Failed to create rocks db env.")?,
+                    false,
+                    // TODO(matthias): I set this lru cache size arbitrarily
+                    RocksCache::new_lru_cache(100),
+                )
+                .context("Failed to create rocks db options.")?;
+                options
+            } else {
+                Options::default()
+            };
+            db_options.create_if_missing(true);
+            db_options.create_missing_column_families(true);
+            match checkpoint {
+                Some(checkpoint) => AtomoStorageBuilder::new(Some(db_path.as_path()))
+                    .with_options(db_options)
+                    .from_checkpoint(checkpoint),
+                None => {
+                    AtomoStorageBuilder::new(Some(db_path.as_path())).with_options(db_options)
+                },
+            }
+        },
+        StorageConfig::InMemory => AtomoStorageBuilder::new::<&Path>(None),
+    };
+    Ok(storage)
+}
+
+/// Declares the tables of the application state.
+fn with_tables(
+    atomo: AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>,
+) -> AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend> {
+    atomo
+        .with_table::<Metadata, Value>("metadata")
+        .with_table::<EthAddress, AccountInfo>("account")
+        .with_table::<(EthAddress, EthAddress, Tokens), HpUfixed<18>>("allowances")
+        .with_table::<ClientPublicKey, EthAddress>("client_keys")
+        .with_table::<NodeIndex, NodeInfo>("node")
+        .with_table::<(NodeIndex, NodeIndex), Duration>("latencies")
+        .with_table::<Epoch, Committee>("committee")
+        .with_table::<ServiceId, Service>("service")
+        .with_table::<ServiceId, HpUfixed<18>>("service_bonds")
+        .with_table::<ProtocolParams, u128>("parameter")
+        .with_table::<NodeIndex, Vec<ReportedReputationMeasurements>>("rep_measurements")
+        .with_table::<NodeIndex, u8>("rep_scores")
+        .with_table::<NodeIndex, u8>("submitted_rep_measurements")
+        .with_table::<NodeIndex, NodeServed>("current_epoch_served")
+        .with_table::<NodeIndex, NodeServed>("last_epoch_served")
+        .with_table::<Epoch, TotalServed>("total_served")
+        .with_table::<CommodityTypes, HpUfixed<6>>("commodity_prices")
+        .with_table::<ServiceId, ServiceRevenue>("service_revenue")
+        .with_table::<TxHash, ()>("executed_digests")
+        .with_table::<NodeIndex, u8>("uptime")
+        .with_table::<NodeIndex, BTreeSet<Blake3Hash>>("node_to_cid")
+        .with_table::<NodeIndex, Vec<SlashRecord>>("slashes")
+        .with_table::<NodeIndex, Epoch>("jailed_nodes")
+        .with_table::<u64, PendingWithdrawal>("pending_withdrawals")
+        .with_table::<u64, Epoch>("processed_deposits")
+        .with_table::<(ClientPublicKey, NodeIndex), u64>("client_session_nonces")
+        .with_table::<(NodeIndex, EthAddress), Delegation>("delegations")
+        .with_table::<ProposalId, Proposal>("proposals")
+        .with_table::<(ProposalId, EthAddress), ProposalVote>("proposal_votes")
+        .with_table::<(ProposalId, EthAddress), HpUfixed<18>>("proposal_weights")
+}
+
+/// Declares the indexes over the tables of the application state. The indexes that do not exist
+/// yet are built from the entries of their table, which have to be at the current schema version.
+fn with_indexes(
+    atomo: AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>,
+) -> AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend> {
+    atomo
+        .with_index::<NodeIndex, NodeInfo, ConsensusPublicKey, _>(
+            "nodes_by_consensus_key",
+            "node",
+            |_, node| vec![node.consensus_key],
+        )
+        .with_index::<NodeIndex, NodeInfo, NodePublicKey, _>(
+            "nodes_by_pub_key",
+            "node",
+            |_, node| vec![node.public_key],
+        )
+        .with_index::<NodeIndex, NodeInfo, EthAddress, _>(
+            "nodes_by_owner",
+            "node",
+            |_, node| vec![node.owner],
+        )
+        .with_index::<NodeIndex, BTreeSet<Blake3Hash>, Blake3Hash, _>(
+            "cid_providers",
+            "node_to_cid",
+            |_, cids| cids.iter().copied().collect(),
+        )
+        .with_index::<(NodeIndex, EthAddress), Delegation, NodeIndex, _>(
+            "delegators",
+            "delegations",
+            |(node, _), _| vec![*node],
+        )
+        .with_index::<(NodeIndex, EthAddress), Delegation, EthAddress, _>(
+            "delegations_by_delegator",
+            "delegations",
+            |(_, delegator), _| vec![*delegator],
+        )
+        .with_index::<ProposalId, Proposal, ProposalStatus, _>(
+            "proposals_by_status",
+            "proposals",
+            |_, proposal| vec![proposal.status],
+        )
+        .with_index::<(ProposalId, EthAddress), HpUfixed<18>, ProposalId, _>(
+            "proposal_weights_by_proposal",
+            "proposal_weights",
+            |(id, _), _| vec![*id],
+        )
+}
+
+/// Puts the content in the blockstore and pins it before anything else is put.
+async fn put_pinned<C: Collection>(
+    blockstore: &C::BlockstoreInterface,
+    content: &[u8],
+) -> Result<[u8; 32]> {
+    let mut blockstore_put = blockstore.put(None);
+    blockstore_put.write(content, CompressionAlgorithm::Uncompressed)?;
+    let hash = blockstore_put.finalize().await?;
+    blockstore.pin(&hash).await;
+    Ok(hash)
+}
+
+/// Returns the hashes of the manifest and the chunks of a checkpoint.
+async fn checkpoint_content<C: Collection>(
+    blockstore: &C::BlockstoreInterface,
+    hash: [u8; 32],
+) -> BTreeSet<[u8; 32]> {
+    let mut content = BTreeSet::from([hash]);
+    if let Some(manifest) = blockstore
+        .read_all_to_vec(&hash)
+        .await
+        .and_then(|manifest| CheckpointManifest::deserialize(&manifest).ok())
+    {
+        content.extend(manifest.chunks().map(|chunk| chunk.hash));
+    }
+    content
 }
 
 impl<C: Collection> WorkerTrait for UpdateWorker<C> {
     type Request = Block;
     type Response = BlockExecutionResponse;
     async fn handle(&mut self, req: Self::Request) -> Self::Response {
-        self.env.run(req, || self.blockstore.put(None)).await
+        let previous_checkpoint = self.env.last_epoch_hash();
+        let response = self.env.run::<C>(req, &self.blockstore).await;
+        if response.change_epoch {
+            self.unpin_previous_checkpoint(previous_checkpoint).await;
+        }
+        response
     }
 }
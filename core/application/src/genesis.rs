@@ -7,6 +7,7 @@
 use lightning_interfaces::types::{
     CommodityTypes,
     Epoch,
+    MisbehaviorType,
     NodeInfo,
     NodePorts,
     NodeServed,
@@ -33,6 +34,22 @@ pub struct Genesis {
     pub consumer_rebate: u64,
     pub max_boost: u16,
     pub max_lock_time: u64,
+    pub service_bond: u64,
+    pub slash_percentage: u16,
+    pub jail_duration: Epoch,
+    pub gas_price: u64,
+    pub stables_gas_price: u64,
+    pub base_gas: u64,
+    pub transfer_gas: u64,
+    pub staking_gas: u64,
+    pub bridge_gas: u64,
+    pub service_gas: u64,
+    pub governance_quorum: u16,
+    pub governance_voting_period: Epoch,
+    pub governance_timelock: Epoch,
+    pub governance_max_open_proposals: u32,
+    pub bridge_signers: Vec<EthAddress>,
+    pub bridge_threshold: u16,
     pub node_info: Vec<GenesisNode>,
     pub service: Vec<GenesisService>,
     pub account: Vec<GenesisAccount>,
@@ -58,6 +75,8 @@ pub struct GenesisService {
     pub id: u32,
     pub owner: EthAddress,
     pub commodity_type: CommodityTypes,
+    #[serde(default)]
+    pub slashing: Vec<MisbehaviorType>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -116,6 +135,7 @@ fn from(value: &GenesisNode) -> Self {
             nonce: 0,
             secondary_nonce: 0,
             ports: value.ports.clone(),
+            commission_rate: 0,
         }
     }
 }
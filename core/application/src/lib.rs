@@ -2,7 +2,9 @@
 pub mod config;
 pub mod env;
 pub mod genesis;
+pub mod migrations;
 pub mod query_runner;
+pub mod slashing;
 pub mod state;
 pub(crate) mod storage;
 pub mod table;
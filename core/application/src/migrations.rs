@@ -0,0 +1,315 @@
+//! Migrations of the application state between schema versions.
+//!
+//! The tables of the application state are stored with bincode, so changing the type of a table
+//! (for example adding a field to `NodeInfo`) makes the entries of existing databases and
+//! checkpoints unreadable. Every such change has to come with a [`Migration`] that rewrites the
+//! existing entries, appended to [`MIGRATIONS`].
+//!
+//! The version of the schema a database is at is recorded under [`Metadata::SchemaVersion`], a
+//! database without it predates versioning and is at version 0. Upon startup the pending
+//! migrations are run in order, each one in its own atomic update along with the new version.
+//!
+//! The migrations run on the state opened without its indexes, the indexes that do not exist yet
+//! are built from the migrated entries afterwards. An existing index is not rebuilt, so a
+//! migration must not change the keys an existing index extracts from the entries.
+
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::Hash;
+use std::net::IpAddr;
+
+use anyhow::{bail, Result};
+use atomo::{Atomo, AtomoBuilder, DefaultSerdeBackend, TableSelector, UpdatePerm};
+use fleek_crypto::{ConsensusPublicKey, EthAddress, NodePublicKey};
+use lightning_interfaces::types::{
+    Blake3Hash,
+    CommodityTypes,
+    Epoch,
+    Metadata,
+    NodeIndex,
+    NodeInfo,
+    NodePorts,
+    Participation,
+    ProtocolParams,
+    Service,
+    ServiceId,
+    Staking,
+    Value,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{AtomoStorage, AtomoStorageBuilder};
+
+/// The execution context a migration step runs in.
+pub type MigrationContext = TableSelector<AtomoStorage, DefaultSerdeBackend>;
+
+/// A single step that migrates the application state from the previous schema version.
+pub struct Migration {
+    /// The schema version the state is at after this step.
+    pub version: u32,
+    /// A short description of the change, shown to the operator.
+    pub description: &'static str,
+    /// Rewrites the affected tables.
+    pub migrate: fn(&MigrationContext),
+}
+
+/// The migrations of the application state, ordered by version. The version of each migration
+/// must be one more than the one before it, starting at 1.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Add the commission rate to the node info",
+        migrate: add_commission_rate,
+    },
+    Migration {
+        version: 2,
+        description: "Add the governance parameters",
+        migrate: add_governance_params,
+    },
+    Migration {
+        version: 3,
+        description: "Add the slashable misbehaviors to the services",
+        migrate: add_service_slashing,
+    },
+    Migration {
+        version: 4,
+        description: "Remove the node and content lookup tables that were replaced by indexes",
+        migrate: remove_lookup_tables,
+    },
+    Migration {
+        version: 5,
+        description: "Add the limit on the open governance proposals of an account",
+        migrate: add_max_open_proposals,
+    },
+];
+
+/// The schema version of the application state of this build.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// The outcome of running the pending migrations on a database.
+#[derive(Debug)]
+pub struct MigrationReport {
+    /// The schema version of the database before the migrations.
+    pub from: u32,
+    /// The schema version of the database after the migrations.
+    pub to: u32,
+    /// The steps that were run, in order.
+    pub steps: Vec<MigrationStepReport>,
+}
+
+/// The outcome of a single migration step.
+#[derive(Debug)]
+pub struct MigrationStepReport {
+    pub version: u32,
+    pub description: &'static str,
+    /// The number of entries that were changed in each table.
+    pub changes: BTreeMap<String, usize>,
+}
+
+/// Declares the tables of previous schema versions that are no longer part of the state, so that
+/// the migrations can remove their entries.
+pub fn with_legacy_tables(
+    atomo: AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>,
+) -> AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend> {
+    atomo
+        .with_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
+        .with_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
+        .with_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
+}
+
+/// Returns the schema version of the state, or `None` if the state is empty.
+pub fn schema_version(atomo: &Atomo<UpdatePerm, AtomoStorage>) -> Option<u32> {
+    atomo.query().run(|ctx| {
+        let metadata = ctx.get_table::<Metadata, Value>("metadata");
+        match metadata.get(Metadata::SchemaVersion) {
+            Some(Value::SchemaVersion(version)) => Some(version),
+            // The state predates schema versioning.
+            _ if metadata.get(Metadata::Epoch).is_some() => Some(0),
+            _ => None,
+        }
+    })
+}
+
+/// Runs the migrations that are newer than the schema version of the state, in order. An empty
+/// state is left untouched, since the genesis is applied at the latest version.
+pub fn migrate(
+    atomo: &mut Atomo<UpdatePerm, AtomoStorage>,
+    migrations: &[Migration],
+) -> Result<MigrationReport> {
+    let latest = migrations.len() as u32;
+    let Some(from) = schema_version(atomo) else {
+        return Ok(MigrationReport {
+            from: latest,
+            to: latest,
+            steps: Vec::new(),
+        });
+    };
+
+    if from > latest {
+        bail!(
+            "The schema version of the state ({from}) is newer than the latest supported version \
+             ({latest})"
+        );
+    }
+
+    let mut steps = Vec::new();
+    for (index, migration) in migrations.iter().enumerate() {
+        if migration.version != index as u32 + 1 {
+            bail!(
+                "Migration '{}' has version {} but should have version {}",
+                migration.description,
+                migration.version,
+                index + 1
+            );
+        }
+
+        if migration.version <= from {
+            continue;
+        }
+
+        let ((), inverse) = atomo.run_with_inverse(|ctx| {
+            (migration.migrate)(ctx);
+            ctx.get_table::<Metadata, Value>("metadata").insert(
+                Metadata::SchemaVersion,
+                Value::SchemaVersion(migration.version),
+            );
+        });
+
+        steps.push(MigrationStepReport {
+            version: migration.version,
+            description: migration.description,
+            changes: inverse
+                .into_iter()
+                .map(|(table, changes)| (table, changes.len()))
+                .collect(),
+        });
+    }
+
+    Ok(MigrationReport {
+        from,
+        to: latest,
+        steps,
+    })
+}
+
+/// Rewrites every entry of a table from the type its values had in the previous schema version
+/// to the one the table is opened with now.
+///
+/// The migrations run without the indexes declared, since an index would read the previous
+/// values as the current type.
+pub fn migrate_values<K, Old, New>(ctx: &MigrationContext, table: &str, f: impl Fn(Old) -> New)
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    Old: Serialize + DeserializeOwned + Any,
+    New: Serialize + DeserializeOwned + Any,
+{
+    let entries = ctx
+        .get_table_unchecked::<K, Old>(table)
+        .range(..)
+        .collect::<Vec<_>>();
+
+    let mut table = ctx.get_table::<K, New>(table);
+    for (key, value) in entries {
+        table.insert(key, f(value));
+    }
+}
+
+/// Removes every entry of a table.
+pub fn clear_table<K, V>(ctx: &MigrationContext, table: &str)
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    V: Serialize + DeserializeOwned + Any,
+{
+    let mut table = ctx.get_table::<K, V>(table);
+    let keys = table.range(..).map(|(key, _)| key).collect::<Vec<_>>();
+    for key in keys {
+        table.remove(key);
+    }
+}
+
+/// The node info before the commission rate of delegations was added.
+#[derive(Serialize, Deserialize)]
+struct NodeInfoV0 {
+    owner: EthAddress,
+    public_key: NodePublicKey,
+    consensus_key: ConsensusPublicKey,
+    staked_since: Epoch,
+    stake: Staking,
+    domain: IpAddr,
+    worker_domain: IpAddr,
+    ports: NodePorts,
+    worker_public_key: NodePublicKey,
+    participation: Participation,
+    nonce: u64,
+    secondary_nonce: u128,
+}
+
+fn add_commission_rate(ctx: &MigrationContext) {
+    migrate_values::<NodeIndex, NodeInfoV0, NodeInfo>(ctx, "node", |node| NodeInfo {
+        owner: node.owner,
+        public_key: node.public_key,
+        consensus_key: node.consensus_key,
+        staked_since: node.staked_since,
+        stake: node.stake,
+        domain: node.domain,
+        worker_domain: node.worker_domain,
+        ports: node.ports,
+        worker_public_key: node.worker_public_key,
+        participation: node.participation,
+        nonce: node.nonce,
+        secondary_nonce: node.secondary_nonce,
+        commission_rate: 0,
+    });
+}
+
+/// Seeds the governance parameters with the values of the default genesis, states from before
+/// on-chain governance do not have them.
+fn add_governance_params(ctx: &MigrationContext) {
+    let mut parameters = ctx.get_table::<ProtocolParams, u128>("parameter");
+    for (param, value) in [
+        (ProtocolParams::GovernanceQuorum, 40),
+        (ProtocolParams::GovernanceVotingPeriod, 3),
+        (ProtocolParams::GovernanceTimelock, 1),
+    ] {
+        if parameters.get(&param).is_none() {
+            parameters.insert(param, value);
+        }
+    }
+}
+
+/// The service before the misbehaviors it can be slashed for were added.
+#[derive(Serialize, Deserialize)]
+struct ServiceV2 {
+    owner: EthAddress,
+    commodity_type: CommodityTypes,
+    slashing: (),
+}
+
+/// Existing services do not declare any misbehavior, their owners can add them with an update.
+fn add_service_slashing(ctx: &MigrationContext) {
+    migrate_values::<ServiceId, ServiceV2, Service>(ctx, "service", |service| Service {
+        owner: service.owner,
+        commodity_type: service.commodity_type,
+        slashing: Vec::new(),
+    });
+}
+
+/// Seeds the limit on the open proposals of an account with the value of the default genesis.
+fn add_max_open_proposals(ctx: &MigrationContext) {
+    let mut parameters = ctx.get_table::<ProtocolParams, u128>("parameter");
+    if parameters
+        .get(&ProtocolParams::GovernanceMaxOpenProposals)
+        .is_none()
+    {
+        parameters.insert(ProtocolParams::GovernanceMaxOpenProposals, 2);
+    }
+}
+
+/// The lookups of the nodes by their keys and of the providers of a content are served by the
+/// `nodes_by_pub_key`, `nodes_by_consensus_key` and `cid_providers` indexes.
+fn remove_lookup_tables(ctx: &MigrationContext) {
+    clear_table::<Blake3Hash, BTreeSet<NodeIndex>>(ctx, "cid_to_node");
+    clear_table::<NodePublicKey, NodeIndex>(ctx, "pub_key_to_index");
+    clear_table::<ConsensusPublicKey, NodeIndex>(ctx, "consensus_key_to_index");
+}
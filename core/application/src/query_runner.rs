@@ -5,6 +5,7 @@
 use atomo::{
     Atomo,
     AtomoBuilder,
+    AtomoSnapshot,
     DefaultSerdeBackend,
     KeyIterator,
     QueryPerm,
@@ -17,21 +18,33 @@
     Blake3Hash,
     Committee,
     CommodityTypes,
+    Delegation,
     Epoch,
     Metadata,
     NodeIndex,
     NodeInfo,
     NodeServed,
+    PendingWithdrawal,
+    ProofLeaf,
+    Proposal,
+    ProposalId,
+    ProposalStatus,
+    ProposalVote,
     ProtocolParams,
     ReportedReputationMeasurements,
     Service,
     ServiceId,
     ServiceRevenue,
+    SlashRecord,
+    StateDiff,
+    StateProof,
+    Tokens,
     TotalServed,
     TransactionRequest,
     TransactionResponse,
     TxHash,
     Value,
+    ValueWithProof,
 };
 use lightning_interfaces::SyncQueryRunnerInterface;
 
@@ -44,9 +57,9 @@ pub struct QueryRunner {
     inner: Atomo<QueryPerm, AtomoStorage>,
     metadata_table: ResolvedTableReference<Metadata, Value>,
     account_table: ResolvedTableReference<EthAddress, AccountInfo>,
+    allowances_table: ResolvedTableReference<(EthAddress, EthAddress, Tokens), HpUfixed<18>>,
     client_table: ResolvedTableReference<ClientPublicKey, EthAddress>,
     node_table: ResolvedTableReference<NodeIndex, NodeInfo>,
-    pub_key_to_index: ResolvedTableReference<NodePublicKey, NodeIndex>,
     committee_table: ResolvedTableReference<Epoch, Committee>,
     services_table: ResolvedTableReference<ServiceId, Service>,
     param_table: ResolvedTableReference<ProtocolParams, u128>,
@@ -60,8 +73,13 @@ pub struct QueryRunner {
     _commodity_price: ResolvedTableReference<CommodityTypes, HpUfixed<6>>,
     executed_digests_table: ResolvedTableReference<TxHash, ()>,
     uptime_table: ResolvedTableReference<NodeIndex, u8>,
-    _cid_to_node: ResolvedTableReference<Blake3Hash, BTreeSet<NodeIndex>>,
     _node_to_cid: ResolvedTableReference<NodeIndex, BTreeSet<Blake3Hash>>,
+    slashes_table: ResolvedTableReference<NodeIndex, Vec<SlashRecord>>,
+    jailed_nodes_table: ResolvedTableReference<NodeIndex, Epoch>,
+    pending_withdrawals_table: ResolvedTableReference<u64, PendingWithdrawal>,
+    delegations_table: ResolvedTableReference<(NodeIndex, EthAddress), Delegation>,
+    proposals_table: ResolvedTableReference<ProposalId, Proposal>,
+    proposal_votes_table: ResolvedTableReference<(ProposalId, EthAddress), ProposalVote>,
 }
 
 impl SyncQueryRunnerInterface for QueryRunner {
@@ -71,9 +89,10 @@ fn new(atomo: Atomo<QueryPerm, AtomoStorage>) -> Self {
         Self {
             metadata_table: atomo.resolve::<Metadata, Value>("metadata"),
             account_table: atomo.resolve::<EthAddress, AccountInfo>("account"),
+            allowances_table: atomo
+                .resolve::<(EthAddress, EthAddress, Tokens), HpUfixed<18>>("allowances"),
             client_table: atomo.resolve::<ClientPublicKey, EthAddress>("client_keys"),
             node_table: atomo.resolve::<NodeIndex, NodeInfo>("node"),
-            pub_key_to_index: atomo.resolve::<NodePublicKey, NodeIndex>("pub_key_to_index"),
             committee_table: atomo.resolve::<Epoch, Committee>("committee"),
             services_table: atomo.resolve::<ServiceId, Service>("service"),
             param_table: atomo.resolve::<ProtocolParams, u128>("parameter"),
@@ -88,19 +107,25 @@ fn new(atomo: Atomo<QueryPerm, AtomoStorage>) -> Self {
             _service_revenue: atomo.resolve::<ServiceId, ServiceRevenue>("service_revenue"),
             executed_digests_table: atomo.resolve::<TxHash, ()>("executed_digests"),
             uptime_table: atomo.resolve::<NodeIndex, u8>("uptime"),
-            _cid_to_node: atomo.resolve::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node"),
             _node_to_cid: atomo.resolve::<NodeIndex, BTreeSet<Blake3Hash>>("node_to_cid"),
+            slashes_table: atomo.resolve::<NodeIndex, Vec<SlashRecord>>("slashes"),
+            jailed_nodes_table: atomo.resolve::<NodeIndex, Epoch>("jailed_nodes"),
+            pending_withdrawals_table: atomo
+                .resolve::<u64, PendingWithdrawal>("pending_withdrawals"),
+            delegations_table: atomo.resolve::<(NodeIndex, EthAddress), Delegation>("delegations"),
+            proposals_table: atomo.resolve::<ProposalId, Proposal>("proposals"),
+            proposal_votes_table: atomo
+                .resolve::<(ProposalId, EthAddress), ProposalVote>("proposal_votes"),
             inner: atomo,
         }
     }
 
     fn atomo_from_checkpoint(
         path: impl AsRef<Path>,
-        hash: [u8; 32],
-        checkpoint: &[u8],
+        checkpoint: impl AsRef<Path>,
     ) -> anyhow::Result<Atomo<QueryPerm, Self::Backend>> {
         let backend = AtomoStorageBuilder::new(Some(path.as_ref()))
-            .from_checkpoint(hash, checkpoint)
+            .from_checkpoint(checkpoint.as_ref())
             .read_only();
 
         let atomo = Self::register_tables(
@@ -124,6 +149,21 @@ fn atomo_from_path(path: impl AsRef<Path>) -> anyhow::Result<Atomo<QueryPerm, Se
         Ok(atomo)
     }
 
+    fn atomo_from_path_with_diff(
+        path: impl AsRef<Path>,
+        diff: StateDiff,
+    ) -> anyhow::Result<Atomo<QueryPerm, Self::Backend>> {
+        let backend = AtomoStorageBuilder::historical(path.as_ref(), diff);
+
+        let atomo = Self::register_tables(
+            AtomoBuilder::<AtomoStorageBuilder, DefaultSerdeBackend>::new(backend),
+        )
+        .build()?
+        .query();
+
+        Ok(atomo)
+    }
+
     fn get_metadata(&self, key: &Metadata) -> Option<Value> {
         self.inner.run(|ctx| self.metadata_table.get(ctx).get(key))
     }
@@ -139,6 +179,19 @@ fn get_account_info<V>(
             .map(selector)
     }
 
+    fn get_allowance(
+        &self,
+        owner: &EthAddress,
+        spender: &EthAddress,
+        token: &Tokens,
+    ) -> Option<HpUfixed<18>> {
+        self.inner.run(|ctx| {
+            self.allowances_table
+                .get(ctx)
+                .get((*owner, *spender, token.clone()))
+        })
+    }
+
     fn client_key_to_account_key(&self, pub_key: &ClientPublicKey) -> Option<EthAddress> {
         self.inner
             .run(|ctx| self.client_table.get(ctx).get(pub_key))
@@ -162,8 +215,12 @@ fn get_node_table_iter<V>(&self, closure: impl FnOnce(KeyIterator<NodeIndex>) ->
     }
 
     fn pubkey_to_index(&self, pub_key: &NodePublicKey) -> Option<NodeIndex> {
-        self.inner
-            .run(|ctx| self.pub_key_to_index.get(ctx).get(pub_key))
+        self.inner.run(|ctx| {
+            ctx.get_index::<NodePublicKey, NodeIndex>("nodes_by_pub_key")
+                .get(pub_key)
+                .first()
+                .copied()
+        })
     }
 
     #[inline]
@@ -236,21 +293,124 @@ fn simulate_txn(&self, txn: TransactionRequest) -> TransactionResponse {
                 table_selector: ctx,
             };
             let app = State::new(backend);
+            if let Err(err) = app.charge_transaction_fee(&txn) {
+                return TransactionResponse::Revert(err);
+            }
             app.execute_transaction(txn)
         })
     }
 
+    fn get_transaction_gas(&self, txn: &TransactionRequest) -> u128 {
+        self.inner.run(|ctx| {
+            let backend = StateTables {
+                table_selector: ctx,
+            };
+            State::new(backend).transaction_gas(txn)
+        })
+    }
+
     fn get_node_uptime(&self, node_index: &NodeIndex) -> Option<u8> {
         self.inner
             .run(|ctx| self.uptime_table.get(ctx).get(node_index))
     }
 
     fn get_cid_providers(&self, cid: &Blake3Hash) -> Option<BTreeSet<NodeIndex>> {
-        self.inner.run(|ctx| self._cid_to_node.get(ctx).get(cid))
+        let providers: BTreeSet<_> = self.inner.run(|ctx| {
+            ctx.get_index::<Blake3Hash, NodeIndex>("cid_providers")
+                .get(cid)
+                .into_iter()
+                .collect()
+        });
+        (!providers.is_empty()).then_some(providers)
     }
 
     fn get_content_registry(&self, node_index: &NodeIndex) -> Option<BTreeSet<Blake3Hash>> {
         self.inner
             .run(|ctx| self._node_to_cid.get(ctx).get(node_index))
     }
+
+    fn get_slashes(&self, node_index: &NodeIndex) -> Option<Vec<SlashRecord>> {
+        self.inner
+            .run(|ctx| self.slashes_table.get(ctx).get(node_index))
+    }
+
+    fn get_jailed_until(&self, node_index: &NodeIndex) -> Option<Epoch> {
+        self.inner
+            .run(|ctx| self.jailed_nodes_table.get(ctx).get(node_index))
+    }
+
+    fn get_pending_withdrawal(&self, id: &u64) -> Option<PendingWithdrawal> {
+        self.inner
+            .run(|ctx| self.pending_withdrawals_table.get(ctx).get(id))
+    }
+
+    fn get_pending_withdrawals_iter<V>(&self, closure: impl FnOnce(KeyIterator<u64>) -> V) -> V {
+        self.inner
+            .run(|ctx| closure(self.pending_withdrawals_table.get(ctx).keys()))
+    }
+
+    fn get_delegation(&self, node: &NodeIndex, delegator: &EthAddress) -> Option<Delegation> {
+        self.inner
+            .run(|ctx| self.delegations_table.get(ctx).get((*node, *delegator)))
+    }
+
+    fn get_delegators(&self, node: &NodeIndex) -> BTreeSet<EthAddress> {
+        self.inner.run(|ctx| {
+            ctx.get_index::<NodeIndex, (NodeIndex, EthAddress)>("delegators")
+                .get(node)
+                .into_iter()
+                .map(|(_, delegator)| delegator)
+                .collect()
+        })
+    }
+
+    fn get_proposal(&self, id: &ProposalId) -> Option<Proposal> {
+        self.inner.run(|ctx| self.proposals_table.get(ctx).get(id))
+    }
+
+    fn get_proposals_iter<V>(&self, closure: impl FnOnce(KeyIterator<ProposalId>) -> V) -> V {
+        self.inner
+            .run(|ctx| closure(self.proposals_table.get(ctx).keys()))
+    }
+
+    fn get_proposal_ids_by_status(&self, status: &ProposalStatus) -> BTreeSet<ProposalId> {
+        self.inner.run(|ctx| {
+            ctx.get_index::<ProposalStatus, ProposalId>("proposals_by_status")
+                .get(status)
+                .into_iter()
+                .collect()
+        })
+    }
+
+    fn get_proposal_vote(&self, id: &ProposalId, voter: &EthAddress) -> Option<ProposalVote> {
+        self.inner
+            .run(|ctx| self.proposal_votes_table.get(ctx).get((*id, *voter)))
+    }
+
+    fn get_state_root(&self) -> Option<[u8; 32]> {
+        self.inner.run(|ctx| ctx.state_root())
+    }
+
+    fn get_state_proof(&self, table: &str, key: &[u8]) -> Option<ValueWithProof> {
+        self.inner.run(|ctx| {
+            let (value, proof) = ctx.get_raw_with_proof(table, key)?;
+            Some(ValueWithProof {
+                state_root: ctx.state_root()?,
+                value,
+                proof: StateProof {
+                    table_roots: proof.table_roots,
+                    table: proof.table,
+                    siblings: proof.siblings,
+                    leaf: proof.leaf.map(|leaf| ProofLeaf {
+                        path: leaf.path,
+                        value_hash: leaf.value_hash,
+                    }),
+                },
+            })
+        })
+    }
+
+    fn snapshot(&self) -> AtomoSnapshot<AtomoStorage> {
+        self.inner.snapshot()
+    }
 }
@@ -0,0 +1,152 @@
+//! Verification of the proofs of misbehavior submitted through `UpdateMethod::Slash`.
+//!
+//! Every [`MisbehaviorType`] has a [`MisbehaviorVerifier`] registered in
+//! [`MisbehaviorVerifiers`]. A service lists the kinds of misbehavior it accepts, and a slash is
+//! only executed if the verifier for the submitted proof accepts it. The registry used by the
+//! application can be replaced with [`crate::env::Env::with_misbehavior_verifiers`].
+//!
+//! Proofs are built from artifacts the node actually signs: consensus attestations are sent
+//! through the broadcast, which signs every message with the main key of the sender.
+
+use std::collections::BTreeMap;
+
+use fleek_crypto::{NodePublicKey, PublicKey};
+use lightning_interfaces::schema::broadcast::Message;
+use lightning_interfaces::schema::{AutoImplSerde, LightningMessage};
+use lightning_interfaces::types::{
+    AttestationEvidence,
+    CommitteeAttestation,
+    MisbehaviorType,
+    NodeIndex,
+    ParcelHeader,
+    ProofOfMisbehavior,
+    Topic,
+};
+use lightning_interfaces::ToDigest;
+use serde::{Deserialize, Serialize};
+
+/// A verifier for a single kind of misbehavior.
+pub trait MisbehaviorVerifier: Send + Sync {
+    /// Returns true if the proof shows that the node with the given public key and index
+    /// misbehaved.
+    fn verify(&self, node: &NodePublicKey, index: NodeIndex, proof: &ProofOfMisbehavior) -> bool;
+}
+
+/// The registry of misbehavior verifiers.
+pub struct MisbehaviorVerifiers {
+    verifiers: BTreeMap<MisbehaviorType, Box<dyn MisbehaviorVerifier>>,
+}
+
+impl MisbehaviorVerifiers {
+    /// Create an empty registry.
+    pub fn empty() -> Self {
+        Self {
+            verifiers: BTreeMap::new(),
+        }
+    }
+
+    /// Register the verifier for the given kind of misbehavior, replacing any verifier that was
+    /// previously registered for it.
+    pub fn with_verifier<V: MisbehaviorVerifier + 'static>(
+        mut self,
+        misbehavior: MisbehaviorType,
+        verifier: V,
+    ) -> Self {
+        self.verifiers.insert(misbehavior, Box::new(verifier));
+        self
+    }
+
+    /// Verify the proof with the verifier registered for its kind of misbehavior. Returns false
+    /// if no verifier is registered.
+    pub fn verify(
+        &self,
+        node: &NodePublicKey,
+        index: NodeIndex,
+        proof: &ProofOfMisbehavior,
+    ) -> bool {
+        self.verifiers
+            .get(&proof.misbehavior_type())
+            .map(|verifier| verifier.verify(node, index, proof))
+            .unwrap_or(false)
+    }
+}
+
+impl Default for MisbehaviorVerifiers {
+    fn default() -> Self {
+        Self::empty().with_verifier(
+            MisbehaviorType::ConflictingAttestations,
+            ConflictingAttestationsVerifier,
+        )
+    }
+}
+
+/// The consensus messages as they are sent over the broadcast. Only attestations are accepted as
+/// evidence, decoding any other consensus message fails.
+///
+/// This must stay compatible with the encoding of `PubSubMsg` in the consensus.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ConsensusMessage {
+    Attestation(CommitteeAttestation),
+}
+
+impl AutoImplSerde for ConsensusMessage {}
+
+/// Returns the digest of the parcel with the given header, the digest committee members attest
+/// to.
+pub fn parcel_digest(parcel: &ParcelHeader) -> [u8; 32] {
+    let mut bytes = Vec::with_capacity(68);
+    bytes.extend_from_slice(&parcel.num_transactions.to_le_bytes());
+    bytes.extend_from_slice(&parcel.batch_digest);
+    bytes.extend_from_slice(&parcel.last_executed);
+    fleek_blake3::hash(&bytes).into()
+}
+
+/// Checks that a node broadcast two attestations for different parcels building on the same
+/// parent.
+pub struct ConflictingAttestationsVerifier;
+
+impl ConflictingAttestationsVerifier {
+    /// Returns the attestation carried by the evidence if the node signed the broadcast message
+    /// and the attestation is for the parcel in the evidence.
+    fn attestation(
+        node: &NodePublicKey,
+        index: NodeIndex,
+        evidence: &AttestationEvidence,
+    ) -> Option<CommitteeAttestation> {
+        // The broadcast only signs the topic and the payload of a message.
+        let message = Message {
+            origin: index,
+            signature: evidence.signature,
+            topic: Topic::Consensus,
+            timestamp: 0,
+            payload: evidence.payload.clone(),
+        };
+        if !node.verify(&evidence.signature, &message.to_digest()) {
+            return None;
+        }
+
+        let ConsensusMessage::Attestation(attestation) =
+            ConsensusMessage::decode(&evidence.payload).ok()?;
+        (attestation.node_index == index && attestation.digest == parcel_digest(&evidence.parcel))
+            .then_some(attestation)
+    }
+}
+
+impl MisbehaviorVerifier for ConflictingAttestationsVerifier {
+    fn verify(&self, node: &NodePublicKey, index: NodeIndex, proof: &ProofOfMisbehavior) -> bool {
+        let ProofOfMisbehavior::ConflictingAttestations { first, second } = proof;
+
+        if first.parcel.last_executed != second.parcel.last_executed {
+            return false;
+        }
+        match (
+            Self::attestation(node, index, first),
+            Self::attestation(node, index, second),
+        ) {
+            (Some(first), Some(second)) => {
+                first.epoch == second.epoch && first.digest != second.digest
+            },
+            _ => false,
+        }
+    }
+}
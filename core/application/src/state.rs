@@ -1,17 +1,20 @@
+use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::net::IpAddr;
 use std::ops::DerefMut;
+use std::sync::Arc;
 use std::time::Duration;
 
 use ethers::abi::AbiDecode;
-use ethers::types::{Transaction as EthersTransaction, H160};
+use ethers::types::{Transaction as EthersTransaction, U256};
 use fleek_blake3::Hasher;
 use fleek_crypto::{
     ClientPublicKey,
     ConsensusPublicKey,
     EthAddress,
     NodePublicKey,
+    PublicKey,
     TransactionSender,
 };
 use hp_fixed::unsigned::HpUfixed;
@@ -19,11 +22,17 @@
 use lightning_interfaces::types::{
     AccountInfo,
     Blake3Hash,
+    BridgeSignature,
+    BridgeSigners,
     Committee,
     CommodityTypes,
     ContentUpdate,
+    Delegation,
+    DeliveryAcknowledgmentMessage,
     DeliveryAcknowledgmentProof,
+    DepositAttestation,
     Epoch,
+    Event,
     ExecutionData,
     ExecutionError,
     Metadata,
@@ -32,23 +41,33 @@
     NodePorts,
     NodeServed,
     Participation,
+    PendingWithdrawal,
     ProofOfConsensus,
     ProofOfMisbehavior,
+    Proposal,
+    ProposalAction,
+    ProposalId,
+    ProposalStatus,
+    ProposalVote,
     ProtocolParams,
     ReportedReputationMeasurements,
     ReputationMeasurements,
     Service,
     ServiceId,
     ServiceRevenue,
+    SlashRecord,
     Staking,
     Tokens,
     TotalServed,
+    TransactionFee,
     TransactionRequest,
     TransactionResponse,
     TxHash,
     UpdateMethod,
     UpdateRequest,
     Value,
+    WithdrawalSettlement,
+    FLEEK_CONTRACT_ADDRESS,
     MAX_MEASUREMENTS_PER_TX,
     MAX_MEASUREMENTS_SUBMIT,
     MAX_UPDATES_CONTENT_REGISTRY,
@@ -58,8 +77,10 @@
 use lightning_reputation::types::WeightedReputationMeasurements;
 use lightning_utils::eth::fleek_contract::FleekContractCalls;
 use lightning_utils::eth::{
+    ApproveCall,
     DepositCall,
     StakeCall,
+    TransferFromCall,
     UnstakeCall,
     WithdrawCall,
     WithdrawUnstakedCall,
@@ -68,7 +89,8 @@
 use rand::seq::SliceRandom;
 use rand::SeedableRng;
 
-use crate::table::{Backend, TableRef};
+use crate::slashing::MisbehaviorVerifiers;
+use crate::table::{Backend, IndexRef, TableRef};
 
 /// Minimum number of reported measurements that have to be available for a node.
 /// If less measurements have been reported, no reputation score will be computed in that epoch.
@@ -94,11 +116,6 @@
 /// submits an OptIn transaction.
 const MINIMUM_UPTIME: u8 = 40;
 
-/// To support ethereum tooling, all signed ethereum transactions will be pointed to this address
-/// otherwise, if there is a value and a different address they are trying to transfer the native
-/// token FLK
-const FLEEK_CONTRACT: H160 = H160([6; 20]);
-
 lazy_static! {
     static ref BIG_HUNDRED: HpUfixed<18> = HpUfixed::<18>::from(100_u64);
 }
@@ -110,13 +127,16 @@
 pub struct State<B: Backend> {
     pub metadata: B::Ref<Metadata, Value>,
     pub account_info: B::Ref<EthAddress, AccountInfo>,
+    pub allowances: B::Ref<(EthAddress, EthAddress, Tokens), HpUfixed<18>>,
     pub client_keys: B::Ref<ClientPublicKey, EthAddress>,
     pub node_info: B::Ref<NodeIndex, NodeInfo>,
-    pub consensus_key_to_index: B::Ref<ConsensusPublicKey, NodeIndex>,
-    pub pub_key_to_index: B::Ref<NodePublicKey, NodeIndex>,
+    pub nodes_by_consensus_key: B::Index<ConsensusPublicKey, NodeIndex>,
+    pub nodes_by_pub_key: B::Index<NodePublicKey, NodeIndex>,
+    pub nodes_by_owner: B::Index<EthAddress, NodeIndex>,
     pub latencies: B::Ref<(NodeIndex, NodeIndex), Duration>,
     pub committee_info: B::Ref<Epoch, Committee>,
     pub services: B::Ref<ServiceId, Service>,
+    pub service_bonds: B::Ref<ServiceId, HpUfixed<18>>,
     pub parameters: B::Ref<ProtocolParams, u128>,
     pub rep_measurements: B::Ref<NodeIndex, Vec<ReportedReputationMeasurements>>,
     pub rep_scores: B::Ref<NodeIndex, u8>,
@@ -128,9 +148,26 @@ pub struct State<B: Backend> {
     pub commodity_prices: B::Ref<CommodityTypes, HpUfixed<6>>,
     pub executed_digests: B::Ref<TxHash, ()>,
     pub uptime: B::Ref<NodeIndex, u8>,
-    pub cid_to_node: B::Ref<Blake3Hash, BTreeSet<NodeIndex>>,
+    pub cid_providers: B::Index<Blake3Hash, NodeIndex>,
     pub node_to_cid: B::Ref<NodeIndex, BTreeSet<Blake3Hash>>,
+    pub slashes: B::Ref<NodeIndex, Vec<SlashRecord>>,
+    pub jailed_nodes: B::Ref<NodeIndex, Epoch>,
+    pub pending_withdrawals: B::Ref<u64, PendingWithdrawal>,
+    pub processed_deposits: B::Ref<u64, Epoch>,
+    pub client_session_nonces: B::Ref<(ClientPublicKey, NodeIndex), u64>,
+    pub delegations: B::Ref<(NodeIndex, EthAddress), Delegation>,
+    pub delegators: B::Index<NodeIndex, (NodeIndex, EthAddress)>,
+    pub delegations_by_delegator: B::Index<EthAddress, (NodeIndex, EthAddress)>,
+    pub proposals: B::Ref<ProposalId, Proposal>,
+    pub proposals_by_status: B::Index<ProposalStatus, ProposalId>,
+    pub proposal_votes: B::Ref<(ProposalId, EthAddress), ProposalVote>,
+    pub proposal_weights: B::Ref<(ProposalId, EthAddress), HpUfixed<18>>,
+    pub proposal_weights_by_proposal: B::Index<ProposalId, (ProposalId, EthAddress)>,
     pub backend: B,
+    /// The event emitted by the transaction that is being executed, see [`Self::emit`].
+    event: RefCell<Option<Event>>,
+    /// The verifiers used to check proofs of misbehavior.
+    misbehavior_verifiers: Arc<MisbehaviorVerifiers>,
 }
 
 impl<B: Backend> State<B> {
@@ -138,12 +175,15 @@ pub fn new(backend: B) -> Self {
         Self {
             metadata: backend.get_table_reference("metadata"),
             account_info: backend.get_table_reference("account"),
+            allowances: backend.get_table_reference("allowances"),
             client_keys: backend.get_table_reference("client_keys"),
             node_info: backend.get_table_reference("node"),
-            consensus_key_to_index: backend.get_table_reference("consensus_key_to_index"),
-            pub_key_to_index: backend.get_table_reference("pub_key_to_index"),
+            nodes_by_consensus_key: backend.get_index_reference("nodes_by_consensus_key"),
+            nodes_by_pub_key: backend.get_index_reference("nodes_by_pub_key"),
+            nodes_by_owner: backend.get_index_reference("nodes_by_owner"),
             committee_info: backend.get_table_reference("committee"),
             services: backend.get_table_reference("service"),
+            service_bonds: backend.get_table_reference("service_bonds"),
             parameters: backend.get_table_reference("parameter"),
             rep_measurements: backend.get_table_reference("rep_measurements"),
             latencies: backend.get_table_reference("latencies"),
@@ -156,12 +196,34 @@ pub fn new(backend: B) -> Self {
             service_revenue: backend.get_table_reference("service_revenue"),
             executed_digests: backend.get_table_reference("executed_digests"),
             uptime: backend.get_table_reference("uptime"),
-            cid_to_node: backend.get_table_reference("cid_to_node"),
+            cid_providers: backend.get_index_reference("cid_providers"),
             node_to_cid: backend.get_table_reference("node_to_cid"),
+            slashes: backend.get_table_reference("slashes"),
+            jailed_nodes: backend.get_table_reference("jailed_nodes"),
+            pending_withdrawals: backend.get_table_reference("pending_withdrawals"),
+            processed_deposits: backend.get_table_reference("processed_deposits"),
+            client_session_nonces: backend.get_table_reference("client_session_nonces"),
+            delegations: backend.get_table_reference("delegations"),
+            delegators: backend.get_index_reference("delegators"),
+            delegations_by_delegator: backend.get_index_reference("delegations_by_delegator"),
+            proposals: backend.get_table_reference("proposals"),
+            proposals_by_status: backend.get_index_reference("proposals_by_status"),
+            proposal_votes: backend.get_table_reference("proposal_votes"),
+            proposal_weights: backend.get_table_reference("proposal_weights"),
+            proposal_weights_by_proposal: backend
+                .get_index_reference("proposal_weights_by_proposal"),
             backend,
+            event: RefCell::new(None),
+            misbehavior_verifiers: Default::default(),
         }
     }
 
+    /// Use the given registry to verify proofs of misbehavior instead of the default one.
+    pub fn with_misbehavior_verifiers(mut self, verifiers: Arc<MisbehaviorVerifiers>) -> Self {
+        self.misbehavior_verifiers = verifiers;
+        self
+    }
+
     pub fn execute_transaction(&self, txn: TransactionRequest) -> TransactionResponse {
         let hash = txn.hash();
         let (sender, response) = match txn {
@@ -180,16 +242,28 @@ pub fn execute_transaction(&self, txn: TransactionRequest) -> TransactionRespons
         response
     }
 
+    /// Returns the event emitted by the last executed transaction, if any.
+    pub fn take_event(&self) -> Option<Event> {
+        self.event.take()
+    }
+
+    /// Record the event of the transaction that is being executed. This is for the events that
+    /// depend on the state, the other ones are derived from the transaction itself, see
+    /// `TransactionRequest::event`.
+    fn emit(&self, event: Event) {
+        *self.event.borrow_mut() = Some(event);
+    }
+
     /// This function is the entry point of a transaction
     fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
         // Execute transaction
         let response = match txn.payload.method {
             UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
-                commodity,
+                commodity: _,
                 service_id,
                 proofs,
                 metadata: _,
-            } => self.submit_pod(txn.payload.sender, commodity, service_id, proofs),
+            } => self.submit_pod(txn.payload.sender, service_id, proofs),
 
             UpdateMethod::Withdraw {
                 amount,
@@ -203,10 +277,28 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
                 amount,
             } => self.deposit(txn.payload.sender, proof, amount, token),
 
+            UpdateMethod::SettleWithdrawal {
+                withdrawal_id,
+                signatures,
+            } => self.settle_withdrawal(txn.payload.sender, withdrawal_id, signatures),
+
             UpdateMethod::Transfer { amount, token, to } => {
                 self.transfer(txn.payload.sender, amount, token, to)
             },
 
+            UpdateMethod::Approve {
+                token,
+                spender,
+                amount,
+            } => self.approve(txn.payload.sender, token, spender, amount),
+
+            UpdateMethod::TransferFrom {
+                token,
+                from,
+                to,
+                amount,
+            } => self.transfer_from(txn.payload.sender, token, from, to, amount),
+
             UpdateMethod::Stake {
                 amount,
                 node_public_key,
@@ -237,6 +329,23 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
                 self.withdraw_unstaked(txn.payload.sender, node, recipient)
             },
 
+            UpdateMethod::Delegate { node, amount } => {
+                self.delegate(txn.payload.sender, node, amount)
+            },
+
+            UpdateMethod::Undelegate { node, amount } => {
+                self.undelegate(txn.payload.sender, node, amount)
+            },
+
+            UpdateMethod::WithdrawDelegation { node, recipient } => {
+                self.withdraw_delegation(txn.payload.sender, node, recipient)
+            },
+
+            UpdateMethod::ChangeCommissionRate {
+                node,
+                commission_rate,
+            } => self.change_commission_rate(txn.payload.sender, node, commission_rate),
+
             UpdateMethod::ChangeEpoch { epoch } => self.change_epoch(txn.payload.sender, epoch),
 
             UpdateMethod::AddService {
@@ -260,6 +369,19 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
             UpdateMethod::ChangeProtocolParam { param, value } => {
                 self.change_protocol_param(txn.payload.sender, param, value)
             },
+            UpdateMethod::ChangeBridgeSigners { signers, threshold } => {
+                self.change_bridge_signers(txn.payload.sender, signers, threshold)
+            },
+            UpdateMethod::SubmitProposal { action } => {
+                self.submit_proposal(txn.payload.sender, action)
+            },
+            UpdateMethod::VoteOnProposal {
+                proposal_id,
+                approve,
+            } => self.vote_on_proposal(txn.payload.sender, proposal_id, approve),
+            UpdateMethod::ExecuteProposal { proposal_id } => {
+                self.execute_proposal(txn.payload.sender, proposal_id)
+            },
             UpdateMethod::OptIn {} => self.opt_in(txn.payload.sender),
             UpdateMethod::OptOut {} => self.opt_out(txn.payload.sender),
             UpdateMethod::UpdateContentRegistry { updates } => {
@@ -267,16 +389,6 @@ fn execute_fleek_transaction(&self, txn: UpdateRequest) -> TransactionResponse {
             },
         };
 
-        #[cfg(debug_assertions)]
-        {
-            let node_info_len = self.node_info.keys().count();
-            let consensus_key_to_index_len = self.consensus_key_to_index.keys().count();
-            let pub_key_to_index_len = self.pub_key_to_index.keys().count();
-            assert_eq!(node_info_len, consensus_key_to_index_len);
-            assert_eq!(node_info_len, pub_key_to_index_len);
-            assert_eq!(pub_key_to_index_len, consensus_key_to_index_len);
-        }
-
         response
     }
 
@@ -289,16 +401,45 @@ fn execute_ethereum_transaction(&self, txn: EthersTransaction) -> TransactionRes
         };
         let sender: EthAddress = txn.from.0.into();
 
-        if to_address == FLEEK_CONTRACT {
+        // To support ethereum tooling, all signed ethereum transactions will be pointed to the
+        // Fleek contract, otherwise, if there is a value and a different address they are trying
+        // to transfer the native token FLK
+        if to_address.0 == FLEEK_CONTRACT_ADDRESS.0 {
             // They are calling one of our state transitions functions
             #[allow(unused)]
             match FleekContractCalls::decode(&txn.input) {
-                Ok(FleekContractCalls::Deposit(DepositCall { token, amount })) => {
-                    // TODO(matthias): add proof of consensus to abi once we know its format
+                Ok(FleekContractCalls::Deposit(DepositCall {
+                    token,
+                    amount,
+                    deposit_id,
+                    signers,
+                    signatures,
+                })) => {
                     let Ok(token) = Tokens::try_from(token) else {
                         return TransactionResponse::Revert(ExecutionError::InvalidToken);
                     };
-                    self.deposit(sender.into(), ProofOfConsensus {}, amount.into(), token)
+                    if signers.len() != signatures.len() {
+                        return TransactionResponse::Revert(ExecutionError::InvalidProof);
+                    }
+                    let Some(signatures) = signers
+                        .into_iter()
+                        .zip(signatures)
+                        .map(|(signer, signature)| {
+                            let signature: [u8; 65] = signature.as_ref().try_into().ok()?;
+                            Some(BridgeSignature {
+                                signer: signer.0.into(),
+                                signature: signature.into(),
+                            })
+                        })
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        return TransactionResponse::Revert(ExecutionError::InvalidProof);
+                    };
+                    let proof = ProofOfConsensus {
+                        deposit_id,
+                        signatures,
+                    };
+                    self.deposit(sender.into(), proof, amount.into(), token)
                 },
                 Ok(FleekContractCalls::Withdraw(WithdrawCall {
                     amount,
@@ -310,6 +451,33 @@ fn execute_ethereum_transaction(&self, txn: EthersTransaction) -> TransactionRes
                     };
                     self.withdraw(sender.into(), recipient.0.into(), amount.into(), token)
                 },
+                Ok(FleekContractCalls::Approve(ApproveCall {
+                    token,
+                    spender,
+                    amount,
+                })) => {
+                    let Ok(token) = Tokens::try_from(token) else {
+                        return TransactionResponse::Revert(ExecutionError::InvalidToken);
+                    };
+                    self.approve(sender.into(), token, spender.0.into(), amount.into())
+                },
+                Ok(FleekContractCalls::TransferFrom(TransferFromCall {
+                    token,
+                    from,
+                    to,
+                    amount,
+                })) => {
+                    let Ok(token) = Tokens::try_from(token) else {
+                        return TransactionResponse::Revert(ExecutionError::InvalidToken);
+                    };
+                    self.transfer_from(
+                        sender.into(),
+                        token,
+                        from.0.into(),
+                        to.0.into(),
+                        amount.into(),
+                    )
+                },
                 Ok(FleekContractCalls::Unstake(UnstakeCall {
                     amount,
                     node_public_key,
@@ -371,26 +539,40 @@ fn execute_ethereum_transaction(&self, txn: EthersTransaction) -> TransactionRes
     fn submit_pod(
         &self,
         sender: TransactionSender,
-        commodity: u128,
         service_id: u32,
-        _acknowledgments: Vec<DeliveryAcknowledgmentProof>,
+        acknowledgments: Vec<DeliveryAcknowledgmentProof>,
     ) -> TransactionResponse {
-        // Todo: function not done
         let sender: NodeIndex = match self.only_node(sender) {
             Ok(index) => index,
             Err(e) => return e,
         };
-        let account_owner = match self.node_info.get(&sender) {
-            Some(node_info) => node_info.owner,
+        let node_public_key = match self.node_info.get(&sender) {
+            Some(node_info) => node_info.public_key,
             None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
         };
 
         if self.services.get(&service_id).is_none() {
             return TransactionResponse::Revert(ExecutionError::InvalidServiceId);
         }
-        // Todo: build proof based on delivery acks
-        if !self.verify_proof_of_delivery(&account_owner, &sender, &commodity, &service_id, ()) {
-            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        // The node is credited with what the valid acknowledgments add up to, rather than with
+        // the commodity it claims
+        let (commodity, client_accounts, session_nonces) = match self.verify_proof_of_delivery(
+            &sender,
+            &node_public_key,
+            service_id,
+            &acknowledgments,
+        ) {
+            Ok(verified) => verified,
+            Err(e) => return TransactionResponse::Revert(e),
+        };
+
+        // Every client pays for what it acknowledged out of its bandwidth balance
+        for (address, account) in client_accounts {
+            self.account_info.set(address, account);
+        }
+        for (client, session_nonce) in session_nonces {
+            self.client_session_nonces
+                .set((client, sender), session_nonce);
         }
 
         let commodity_type = self
@@ -443,12 +625,70 @@ fn submit_pod(
 
     fn withdraw(
         &self,
-        _sender: TransactionSender,
-        _reciever: EthAddress,
-        _amount: HpUfixed<18>,
-        _token: Tokens,
+        sender: TransactionSender,
+        reciever: EthAddress,
+        amount: HpUfixed<18>,
+        token: Tokens,
     ) -> TransactionResponse {
-        TransactionResponse::Revert(ExecutionError::Unimplemented)
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        // The withdrawal is queued in the precision of the token, so that exactly the debited
+        // amount is released on the L2
+        let amount = to_token_precision(&token, amount);
+        if amount == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::ZeroAmount);
+        }
+
+        let mut account = self.account_info.get(&sender).unwrap_or_default();
+
+        // Check that they have the funds and debit the account
+        match token {
+            Tokens::FLK => {
+                if account.flk_balance < amount {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                account.flk_balance -= amount.clone();
+            },
+            Tokens::USDC => {
+                // Stables are what nodes and service builders get paid in
+                let stables: HpUfixed<6> = amount.clone().convert_precision();
+                if account.stables_balance < stables {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                account.stables_balance -= stables;
+            },
+        }
+
+        let epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        let id = match self.metadata.get(&Metadata::NextWithdrawalId) {
+            Some(Value::NextWithdrawalId(id)) => id,
+            _ => 0,
+        };
+
+        // Queue the withdrawal for the bridge relayer to settle it on the L2
+        self.pending_withdrawals.set(
+            id,
+            PendingWithdrawal {
+                id,
+                receiver: reciever,
+                token,
+                amount,
+                epoch,
+            },
+        );
+        self.metadata
+            .set(Metadata::NextWithdrawalId, Value::NextWithdrawalId(id + 1));
+        self.account_info.set(sender, account);
+
+        TransactionResponse::Success(ExecutionData::None)
     }
 
     fn deposit(
@@ -465,11 +705,22 @@ fn deposit(
             Err(e) => return e,
         };
 
+        // Every deposit on the L2 can only be claimed once
+        if self.processed_deposits.get(&proof.deposit_id).is_some() {
+            return TransactionResponse::Revert(ExecutionError::DepositAlreadyProcessed);
+        }
+
         // Verify the proof from the bridge
-        if !self.verify_proof_of_consensus(proof) {
+        if !self.verify_proof_of_consensus(&proof, sender, &token, &amount) {
             return TransactionResponse::Revert(ExecutionError::InvalidProof);
         }
 
+        let epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        self.processed_deposits.set(proof.deposit_id, epoch);
+
         let mut account = self.account_info.get(&sender).unwrap_or_default();
 
         // Check the token bridged and increment that amount
@@ -482,6 +733,39 @@ fn deposit(
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    fn settle_withdrawal(
+        &self,
+        sender: TransactionSender,
+        withdrawal_id: u64,
+        signatures: Vec<BridgeSignature>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        if let Err(e) = self.only_account_owner(sender) {
+            return e;
+        }
+
+        if self.pending_withdrawals.get(&withdrawal_id).is_none() {
+            return TransactionResponse::Revert(ExecutionError::WithdrawalNotPending);
+        }
+
+        // The bridge signers attest that the withdrawal was paid out on the L2
+        let Some(Value::ChainId(chain_id)) = self.metadata.get(&Metadata::ChainId) else {
+            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        };
+        let digest = WithdrawalSettlement {
+            chain_id,
+            withdrawal_id,
+        }
+        .to_digest();
+        if !self.verify_bridge_signatures(&signatures, &digest) {
+            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        }
+
+        self.pending_withdrawals.remove(&withdrawal_id);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     fn transfer(
         &self,
         sender: TransactionSender,
@@ -521,6 +805,100 @@ fn transfer(
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    fn approve(
+        &self,
+        sender: TransactionSender,
+        token: Tokens,
+        spender: EthAddress,
+        amount: HpUfixed<18>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let owner = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        if owner == spender {
+            return TransactionResponse::Revert(ExecutionError::CantSendToYourself);
+        }
+
+        // The allowance is kept in the precision of the token, so that it is spent by exactly
+        // the amounts that are transferred
+        let amount = to_token_precision(&token, amount);
+
+        // An allowance of zero is the same as no allowance
+        if amount == HpUfixed::zero() {
+            self.allowances.remove(&(owner, spender, token.clone()));
+        } else {
+            self.allowances
+                .set((owner, spender, token.clone()), amount.clone());
+        }
+
+        self.emit(Event::approval(token.address(), owner, spender, amount));
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn transfer_from(
+        &self,
+        sender: TransactionSender,
+        token: Tokens,
+        from: EthAddress,
+        to: EthAddress,
+        amount: HpUfixed<18>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let spender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        if from == to {
+            return TransactionResponse::Revert(ExecutionError::CantSendToYourself);
+        }
+        let amount = to_token_precision(&token, amount);
+
+        // Check that the owner allowed the spender to transfer this much
+        let allowance_key = (from, spender, token.clone());
+        let allowance = self.allowances.get(&allowance_key).unwrap_or_default();
+        if allowance < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientAllowance);
+        }
+
+        let mut from_account = self.account_info.get(&from).unwrap_or_default();
+        let mut to_account = self.account_info.get(&to).unwrap_or_default();
+
+        // Check that the owner has the funds and move them
+        match token {
+            Tokens::FLK => {
+                if from_account.flk_balance < amount {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                from_account.flk_balance -= amount.clone();
+                to_account.flk_balance += amount.clone();
+            },
+            Tokens::USDC => {
+                let stables: HpUfixed<6> = amount.clone().convert_precision();
+                if from_account.stables_balance < stables {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                from_account.stables_balance -= stables.clone();
+                to_account.stables_balance += stables;
+            },
+        }
+
+        let allowance = allowance - amount.clone();
+        if allowance == HpUfixed::zero() {
+            self.allowances.remove(&allowance_key);
+        } else {
+            self.allowances.set(allowance_key, allowance);
+        }
+        self.account_info.set(from, from_account);
+        self.account_info.set(to, to_account);
+
+        self.emit(Event::transfer(token.address(), from, to, amount));
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn stake(
         &self,
@@ -552,12 +930,12 @@ fn stake(
             return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
         }
 
-        let node_index = self.pub_key_to_index.get(&node_public_key);
+        let node_index = self.get_node_index(&node_public_key);
         // Make sure the networking index and bls are the same
         if let Some(consensus_key) = node_consensus_key {
             // If the consensus key is indexed make sure it is the same as the indexed node public
             // key, if its none indexed and the node key is, that is fine
-            if self.consensus_key_to_index.get(&consensus_key) != node_index {
+            if self.get_node_index_by_consensus_key(&consensus_key) != node_index {
                 return TransactionResponse::Revert(ExecutionError::ConsensusKeyAlreadyIndexed);
             }
         }
@@ -620,6 +998,7 @@ fn stake(
                         participation: Participation::False,
                         nonce: 0,
                         secondary_nonce: 0,
+                        commission_rate: 0,
                     };
                     self.create_node(node);
                 } else {
@@ -792,6 +1171,176 @@ fn withdraw_unstaked(
         TransactionResponse::Success(ExecutionData::None)
     }
 
+    fn delegate(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        amount: HpUfixed<18>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let index = match self.get_node_index(&node_public_key) {
+            Some(index) => index,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // The owner of a node stakes on it directly, it can not delegate to its own node
+        match self.node_info.get(&index) {
+            Some(node) if node.owner == sender => {
+                return TransactionResponse::Revert(ExecutionError::CantDelegateToOwnNode);
+            },
+            Some(_) => {},
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        }
+
+        let mut delegator = self.account_info.get(&sender).unwrap_or_default();
+
+        // Make sure the sender has at least the amount of FLK he is trying to delegate
+        if delegator.flk_balance < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+        }
+
+        let mut delegation = self.delegations.get(&(index, sender)).unwrap_or_default();
+        delegator.flk_balance -= amount.clone();
+        delegation.staked += amount;
+
+        // Commit changes to the delegator and the delegation
+        self.account_info.set(sender, delegator);
+        self.delegations.set((index, sender), delegation);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn undelegate(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        amount: HpUfixed<18>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let index = match self.get_node_index(&node_public_key) {
+            Some(index) => index,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        let mut delegation = match self.delegations.get(&(index, sender)) {
+            Some(delegation) => delegation,
+            None => return TransactionResponse::Revert(ExecutionError::NoDelegation),
+        };
+
+        // Make sure the sender has at least that much delegated
+        if delegation.staked < amount {
+            return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+        }
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        let lock_time = self.parameters.get(&ProtocolParams::LockTime).unwrap_or(0);
+
+        // Same as unstaking, the lock time of all the locked tokens is pushed back
+        delegation.staked -= amount.clone();
+        delegation.locked += amount;
+        delegation.locked_until = current_epoch + lock_time as u64;
+
+        self.delegations.set((index, sender), delegation);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn withdraw_delegation(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        recipient: Option<EthAddress>,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let index = match self.get_node_index(&node_public_key) {
+            Some(index) => index,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        let mut delegation = match self.delegations.get(&(index, sender)) {
+            Some(delegation) => delegation,
+            None => return TransactionResponse::Revert(ExecutionError::NoDelegation),
+        };
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        // Make sure there are locked tokens and that the lock time is passed
+        if delegation.locked == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::NoLockedTokens);
+        }
+        if delegation.locked_until > current_epoch {
+            return TransactionResponse::Revert(ExecutionError::TokensLocked);
+        }
+
+        // if there is no recipient the delegator will receive the withdrawal
+        let recipient = recipient.unwrap_or(sender);
+        let mut receiver = self.account_info.get(&recipient).unwrap_or_default();
+        receiver.flk_balance += delegation.locked;
+        delegation.locked = HpUfixed::zero();
+
+        self.account_info.set(recipient, receiver);
+        // A delegation that is fully withdrawn is removed so that it is no longer part of the
+        // reward split of the node
+        if delegation.staked == HpUfixed::zero() {
+            self.delegations.remove(&(index, sender));
+        } else {
+            self.delegations.set((index, sender), delegation);
+        }
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn change_commission_rate(
+        &self,
+        sender: TransactionSender,
+        node_public_key: NodePublicKey,
+        commission_rate: u8,
+    ) -> TransactionResponse {
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let (index, mut node) = match self.get_node_info(node_public_key.into()) {
+            Some(node) => node,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the caller is the owner of the node
+        if sender != node.owner {
+            return TransactionResponse::Revert(ExecutionError::NotNodeOwner);
+        }
+        if commission_rate > 100 {
+            return TransactionResponse::Revert(ExecutionError::InvalidCommissionRate);
+        }
+
+        node.commission_rate = commission_rate;
+        self.node_info.set(index, node);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
     fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionResponse {
         // Only Nodes can call this function
         let index = match self.only_node(sender) {
@@ -834,9 +1383,6 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
             // Should we refactor change_epoch so it operates in two steps?
             //  1. Validate all mutations that will be made and stage them.
             //  2. Submit staged changes.
-            // Then, `clear_content_registry` could become
-            // `stage_clear_content_registry' and return the new state for the
-            // tables instead of applying the changes itself.
             self.clean_up_content_registry();
 
             // Clear executed digests.
@@ -844,6 +1390,19 @@ fn change_epoch(&self, sender: TransactionSender, epoch: Epoch) -> TransactionRe
                 self.executed_digests.remove(&digest);
             }
 
+            // Release the nodes whose jail time is over.
+            for node in self.jailed_nodes.keys() {
+                if let Some(jailed_until) = self.jailed_nodes.get(&node) {
+                    if jailed_until <= current_epoch + 1 {
+                        self.jailed_nodes.remove(&node);
+                    }
+                }
+            }
+
+            // Tally the governance proposals whose vote is over and execute the ones that passed,
+            // before the new committee is chosen so that it already follows the changes.
+            self.process_proposals(current_epoch);
+
             self.committee_info.set(current_epoch, current_committee);
             // Get new committee
             let new_committee = self.choose_new_committee();
@@ -961,65 +1520,408 @@ fn update_latencies(&self) {
             }
         }
 
-        // Process latency measurements. If latency measurements are available for both directions
-        // between two nodes, we use the average.
-        let mut latency_map = HashMap::new();
-        for node in self.rep_measurements.keys() {
-            if let Some(reported_measurements) = self.rep_measurements.get(&node) {
-                for measurement in reported_measurements {
-                    if let Some(latency) = measurement.measurements.latency {
-                        let (node_lhs, node_rhs) = if node < measurement.reporting_node {
-                            (node, measurement.reporting_node)
-                        } else {
-                            (measurement.reporting_node, node)
-                        };
-                        let latency =
-                            if let Some(opp_latency) = latency_map.get(&(node_lhs, node_rhs)) {
-                                (latency + *opp_latency) / 2
-                            } else {
-                                latency
-                            };
-                        latency_map.insert((node_lhs, node_rhs), latency);
-                    }
-                }
+        // Process latency measurements. If latency measurements are available for both directions
+        // between two nodes, we use the average.
+        let mut latency_map = HashMap::new();
+        for node in self.rep_measurements.keys() {
+            if let Some(reported_measurements) = self.rep_measurements.get(&node) {
+                for measurement in reported_measurements {
+                    if let Some(latency) = measurement.measurements.latency {
+                        let (node_lhs, node_rhs) = if node < measurement.reporting_node {
+                            (node, measurement.reporting_node)
+                        } else {
+                            (measurement.reporting_node, node)
+                        };
+                        let latency =
+                            if let Some(opp_latency) = latency_map.get(&(node_lhs, node_rhs)) {
+                                (latency + *opp_latency) / 2
+                            } else {
+                                latency
+                            };
+                        latency_map.insert((node_lhs, node_rhs), latency);
+                    }
+                }
+            }
+        }
+
+        // Store the latencies that were reported in this epoch.
+        // This will potentially overwrite latency measurements from previous epochs.
+        for ((index_lhs, index_rhs), latency) in latency_map {
+            // Todo (dalton): Check if this check is needed, it may be done before being added to
+            // latancy map
+            if self.node_info.get(&index_lhs).is_some() && self.node_info.get(&index_rhs).is_some()
+            {
+                self.latencies.set((index_lhs, index_rhs), latency);
+            }
+        }
+    }
+
+    // This method can panic if the governance address wasn't previously stored in the application
+    // state. The governance address should be seeded though the genesis.
+    fn change_protocol_param(
+        &self,
+        sender: TransactionSender,
+        param: ProtocolParams,
+        value: u128,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        // TODO(matthias): should be panic here or revert? Since the governance address will be
+        // seeded though genesis, this should never happen.
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => panic!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        }
+        self.parameters.set(param, value);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    // This method can panic if the governance address wasn't previously stored in the application
+    // state. The governance address should be seeded though the genesis.
+    fn change_bridge_signers(
+        &self,
+        sender: TransactionSender,
+        signers: Vec<EthAddress>,
+        threshold: u16,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
+            Some(Value::AccountPublicKey(address)) => address,
+            _ => panic!("Governance address is missing from state."),
+        };
+        if sender != governance_address {
+            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+        }
+
+        let bridge_signers = BridgeSigners { signers, threshold };
+        if !bridge_signers.is_valid() {
+            return TransactionResponse::Revert(ExecutionError::InvalidBridgeSigners);
+        }
+        self.metadata.set(
+            Metadata::BridgeSigners,
+            Value::BridgeSigners(bridge_signers),
+        );
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn submit_proposal(
+        &self,
+        sender: TransactionSender,
+        action: ProposalAction,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        // Only accounts that have a say in governance can submit proposals
+        if self.voting_weight(&sender) == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::NoVotingWeight);
+        }
+        if let Err(e) = self.validate_proposal_action(&action) {
+            return TransactionResponse::Revert(e);
+        }
+        // Every proposal takes a snapshot of the weight of all the stakers, so the number of
+        // proposals an account can have open is limited
+        let max_open_proposals = self
+            .parameters
+            .get(&ProtocolParams::GovernanceMaxOpenProposals)
+            .unwrap_or(2) as usize;
+        let open_proposals = self
+            .proposals_by_status
+            .get(&ProposalStatus::Voting)
+            .into_iter()
+            .filter_map(|id| self.proposals.get(&id))
+            .filter(|proposal| proposal.proposer == sender)
+            .count();
+        if open_proposals >= max_open_proposals {
+            return TransactionResponse::Revert(ExecutionError::TooManyOpenProposals);
+        }
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        let voting_period = self
+            .parameters
+            .get(&ProtocolParams::GovernanceVotingPeriod)
+            .unwrap_or(1) as u64;
+        let timelock = self
+            .parameters
+            .get(&ProtocolParams::GovernanceTimelock)
+            .unwrap_or(0) as u64;
+
+        let id = match self.metadata.get(&Metadata::NextProposalId) {
+            Some(Value::NextProposalId(id)) => id,
+            _ => 0,
+        };
+        self.metadata
+            .set(Metadata::NextProposalId, Value::NextProposalId(id + 1));
+
+        // The votes are weighted by the stake at the time the proposal was submitted, so tokens
+        // that move to another account during the vote can not be used to vote twice
+        let mut total_weight = HpUfixed::<18>::zero();
+        for (account, weight) in self.voting_weights() {
+            total_weight += weight.clone();
+            self.proposal_weights.set((id, account), weight);
+        }
+
+        // A proposal is open for at least one epoch change
+        let voting_ends = current_epoch + voting_period.max(1);
+        self.proposals.set(
+            id,
+            Proposal {
+                id,
+                proposer: sender,
+                action,
+                created_at: current_epoch,
+                voting_ends,
+                executable_at: voting_ends + timelock,
+                votes_for: HpUfixed::zero(),
+                votes_against: HpUfixed::zero(),
+                total_weight,
+                status: ProposalStatus::Voting,
+            },
+        );
+        TransactionResponse::Success(ExecutionData::UInt(id as u128))
+    }
+
+    fn vote_on_proposal(
+        &self,
+        sender: TransactionSender,
+        proposal_id: ProposalId,
+        approve: bool,
+    ) -> TransactionResponse {
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let mut proposal = match self.proposals.get(&proposal_id) {
+            Some(proposal) => proposal,
+            None => return TransactionResponse::Revert(ExecutionError::ProposalDoesNotExist),
+        };
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        if proposal.status != ProposalStatus::Voting || current_epoch >= proposal.voting_ends {
+            return TransactionResponse::Revert(ExecutionError::VotingClosed);
+        }
+        if self.proposal_votes.get(&(proposal_id, sender)).is_some() {
+            return TransactionResponse::Revert(ExecutionError::AlreadyVoted);
+        }
+
+        let weight = self
+            .proposal_weights
+            .get(&(proposal_id, sender))
+            .unwrap_or_default();
+        if weight == HpUfixed::zero() {
+            return TransactionResponse::Revert(ExecutionError::NoVotingWeight);
+        }
+
+        if approve {
+            proposal.votes_for += weight.clone();
+        } else {
+            proposal.votes_against += weight.clone();
+        }
+        self.proposal_votes
+            .set((proposal_id, sender), ProposalVote { approve, weight });
+        self.proposals.set(proposal_id, proposal);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    fn execute_proposal(
+        &self,
+        sender: TransactionSender,
+        proposal_id: ProposalId,
+    ) -> TransactionResponse {
+        // Anyone can trigger the execution of a passed proposal
+        if let Err(e) = self.only_account_owner(sender) {
+            return e;
+        }
+
+        let proposal = match self.proposals.get(&proposal_id) {
+            Some(proposal) => proposal,
+            None => return TransactionResponse::Revert(ExecutionError::ProposalDoesNotExist),
+        };
+
+        let current_epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+        if proposal.status != ProposalStatus::Passed || current_epoch < proposal.executable_at {
+            return TransactionResponse::Revert(ExecutionError::ProposalNotExecutable);
+        }
+
+        match self.apply_proposal(proposal) {
+            Ok(()) => TransactionResponse::Success(ExecutionData::None),
+            // The proposal is marked as failed either way, the revert only reports why
+            Err(e) => TransactionResponse::Revert(e),
+        }
+    }
+
+    /// Tallies the proposals whose vote ends with the given epoch and executes the passed
+    /// proposals that could be executed in it but were not. This is called when the given epoch
+    /// ends.
+    fn process_proposals(&self, epoch: Epoch) {
+        for id in self.proposals_by_status.get(&ProposalStatus::Voting) {
+            let Some(mut proposal) = self.proposals.get(&id) else {
+                continue;
+            };
+            if proposal.voting_ends > epoch + 1 {
+                continue;
+            }
+
+            // The quorum is a share of all the FLK that could have voted
+            let quorum_percentage: HpUfixed<18> = self
+                .parameters
+                .get(&ProtocolParams::GovernanceQuorum)
+                .unwrap_or(0)
+                .into();
+            let quorum = &proposal.total_weight * &(&quorum_percentage / &(*BIG_HUNDRED));
+            let votes = &proposal.votes_for + &proposal.votes_against;
+
+            proposal.status = if votes > HpUfixed::zero()
+                && votes >= quorum
+                && proposal.votes_for > proposal.votes_against
+            {
+                ProposalStatus::Passed
+            } else {
+                ProposalStatus::Rejected
+            };
+            self.proposals.set(id, proposal);
+
+            // The weights are only needed while the proposal is open for votes
+            for key in self.proposal_weights_by_proposal.get(&id) {
+                self.proposal_weights.remove(&key);
+            }
+        }
+
+        for id in self.proposals_by_status.get(&ProposalStatus::Passed) {
+            let Some(proposal) = self.proposals.get(&id) else {
+                continue;
+            };
+            if proposal.executable_at <= epoch {
+                // A proposal that can not be applied is marked as failed
+                let _ = self.apply_proposal(proposal);
+            }
+        }
+    }
+
+    /// Applies the action of a passed proposal and marks it as executed, or as failed if the
+    /// action is not valid on the current state.
+    fn apply_proposal(&self, mut proposal: Proposal) -> Result<(), ExecutionError> {
+        let result = self.validate_proposal_action(&proposal.action);
+        if result.is_ok() {
+            match proposal.action.clone() {
+                ProposalAction::ChangeProtocolParam { param, value } => {
+                    self.parameters.set(param, value);
+                },
+                ProposalAction::AddService {
+                    service,
+                    service_id,
+                } => {
+                    self.services.set(service_id, service);
+                },
+                ProposalAction::RemoveService { service_id } => {
+                    // Validated above
+                    let service = self.services.get(&service_id).unwrap();
+                    self.release_service(service_id, service);
+                },
+                ProposalAction::ChangeCommitteeSize { committee_size } => {
+                    self.parameters
+                        .set(ProtocolParams::CommitteeSize, committee_size as u128);
+                },
             }
         }
 
-        // Store the latencies that were reported in this epoch.
-        // This will potentially overwrite latency measurements from previous epochs.
-        for ((index_lhs, index_rhs), latency) in latency_map {
-            // Todo (dalton): Check if this check is needed, it may be done before being added to
-            // latancy map
-            if self.node_info.get(&index_lhs).is_some() && self.node_info.get(&index_rhs).is_some()
-            {
-                self.latencies.set((index_lhs, index_rhs), latency);
-            }
+        proposal.status = match result {
+            Ok(()) => ProposalStatus::Executed,
+            Err(_) => ProposalStatus::Failed,
+        };
+        self.proposals.set(proposal.id, proposal);
+        result
+    }
+
+    /// Checks that the action of a proposal can be applied to the current state.
+    fn validate_proposal_action(&self, action: &ProposalAction) -> Result<(), ExecutionError> {
+        match action {
+            ProposalAction::ChangeProtocolParam { .. } => Ok(()),
+            ProposalAction::AddService {
+                service,
+                service_id,
+            } => {
+                // A proposal can not take over a registered service and its bond
+                if self.services.get(service_id).is_some() {
+                    return Err(ExecutionError::ServiceAlreadyExists);
+                }
+                // Same as `add_service`, nodes have to be able to get paid for the service
+                if self.commodity_prices.get(&service.commodity_type).is_none() {
+                    return Err(ExecutionError::InvalidCommodityType);
+                }
+                Ok(())
+            },
+            ProposalAction::RemoveService { service_id } => {
+                if self.services.get(service_id).is_none() {
+                    return Err(ExecutionError::NonExistingService);
+                }
+                Ok(())
+            },
+            ProposalAction::ChangeCommitteeSize { committee_size } => {
+                let node_count = self.parameters.get(&ProtocolParams::NodeCount).unwrap_or(0);
+                if *committee_size == 0 || *committee_size as u128 > node_count {
+                    return Err(ExecutionError::InvalidProposal);
+                }
+                Ok(())
+            },
         }
     }
 
-    // This method can panic if the governance address wasn't previously stored in the application
-    // state. The governance address should be seeded though the genesis.
-    fn change_protocol_param(
-        &self,
-        sender: TransactionSender,
-        param: ProtocolParams,
-        value: u128,
-    ) -> TransactionResponse {
-        let sender = match self.only_account_owner(sender) {
-            Ok(account) => account,
-            Err(e) => return e,
-        };
-        // TODO(matthias): should be panic here or revert? Since the governance address will be
-        // seeded though genesis, this should never happen.
-        let governance_address = match self.metadata.get(&Metadata::GovernanceAddress) {
-            Some(Value::AccountPublicKey(address)) => address,
-            _ => panic!("Governance address is missing from state."),
-        };
-        if sender != governance_address {
-            return TransactionResponse::Revert(ExecutionError::OnlyGovernance);
+    /// Returns the voting weight of an account in governance, which is the FLK it has staked on
+    /// the nodes it owns and delegated to nodes.
+    fn voting_weight(&self, account: &EthAddress) -> HpUfixed<18> {
+        let staked = self
+            .nodes_by_owner
+            .get(account)
+            .into_iter()
+            .filter_map(|index| self.node_info.get(&index))
+            .fold(HpUfixed::zero(), |total, node| total + node.stake.staked);
+        self.delegations_by_delegator
+            .get(account)
+            .into_iter()
+            .filter_map(|key| self.delegations.get(&key))
+            .fold(staked, |total, delegation| total + delegation.staked)
+    }
+
+    /// Returns the voting weight of every account that has a say in governance.
+    fn voting_weights(&self) -> BTreeMap<EthAddress, HpUfixed<18>> {
+        let mut weights = BTreeMap::<EthAddress, HpUfixed<18>>::new();
+        for node in self
+            .node_info
+            .keys()
+            .filter_map(|index| self.node_info.get(&index))
+        {
+            *weights.entry(node.owner).or_default() += node.stake.staked;
         }
-        self.parameters.set(param, value);
-        TransactionResponse::Success(ExecutionData::None)
+        for ((_, delegator), delegation) in self
+            .delegations
+            .keys()
+            .filter_map(|key| Some((key, self.delegations.get(&key)?)))
+        {
+            *weights.entry(delegator).or_default() += delegation.staked;
+        }
+        weights.retain(|_, weight| *weight > HpUfixed::zero());
+        weights
     }
 
     fn opt_in(&self, sender: TransactionSender) -> TransactionResponse {
@@ -1056,7 +1958,7 @@ fn get_node_registry(&self) -> BTreeMap<NodeIndex, NodeInfo> {
         self.node_info
             .keys()
             .filter_map(|key| self.node_info.get(&key).map(|node| (key, node)))
-            .filter(|(_, node)| node.stake.staked > HpUfixed::zero())
+            .filter(|(index, node)| self.node_stake(index, node) > HpUfixed::zero())
             .collect()
     }
 
@@ -1074,29 +1976,209 @@ fn get_node_registry(&self) -> BTreeMap<NodeIndex, NodeInfo> {
 
     fn add_service(
         &self,
-        _sender: TransactionSender,
-        _service: Service,
-        _service_id: ServiceId,
+        sender: TransactionSender,
+        service: Service,
+        service_id: ServiceId,
     ) -> TransactionResponse {
-        TransactionResponse::Revert(ExecutionError::Unimplemented)
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        // Make sure there is a price for the commodity this service serves, otherwise nodes
+        // would not be able to get paid for serving it in `submit_pod`
+        if self.commodity_prices.get(&service.commodity_type).is_none() {
+            return TransactionResponse::Revert(ExecutionError::InvalidCommodityType);
+        }
+
+        let event = match self.services.get(&service_id) {
+            Some(current) => {
+                // The service id is already taken, only the owner of the service can update it.
+                // The owner is allowed to hand the service (and its bond) over to a new owner.
+                if current.owner != sender {
+                    return TransactionResponse::Revert(ExecutionError::ServiceAlreadyExists);
+                }
+                Event::service_updated(service_id, service.owner, service.commodity_type)
+            },
+            None => {
+                // A new service can only be registered on behalf of the sender
+                if service.owner != sender {
+                    return TransactionResponse::Revert(ExecutionError::NotServiceOwner);
+                }
+
+                let bond: HpUfixed<18> = self
+                    .parameters
+                    .get(&ProtocolParams::ServiceBond)
+                    .unwrap_or(0)
+                    .into();
+                let mut owner = self.account_info.get(&sender).unwrap_or_default();
+
+                // Make sure the sender has enough FLK to bond the service
+                if owner.flk_balance < bond {
+                    return TransactionResponse::Revert(ExecutionError::InsufficientBalance);
+                }
+                owner.flk_balance -= bond.clone();
+
+                self.account_info.set(sender, owner);
+                self.service_bonds.set(service_id, bond);
+                Event::service_added(service_id, service.owner, service.commodity_type)
+            },
+        };
+
+        self.services.set(service_id, service);
+        self.emit(event);
+        TransactionResponse::Success(ExecutionData::None)
     }
 
     fn remove_service(
         &self,
-        _sender: TransactionSender,
-        _service_id: ServiceId,
+        sender: TransactionSender,
+        service_id: ServiceId,
     ) -> TransactionResponse {
-        TransactionResponse::Revert(ExecutionError::Unimplemented)
+        // This transaction is only callable by AccountOwners and not nodes
+        // So revert if the sender is a node public key
+        let sender = match self.only_account_owner(sender) {
+            Ok(account) => account,
+            Err(e) => return e,
+        };
+
+        let service = match self.services.get(&service_id) {
+            Some(service) => service,
+            None => return TransactionResponse::Revert(ExecutionError::NonExistingService),
+        };
+
+        // Make sure the caller is the owner of the service
+        if service.owner != sender {
+            return TransactionResponse::Revert(ExecutionError::NotServiceOwner);
+        }
+
+        self.release_service(service_id, service);
+        TransactionResponse::Success(ExecutionData::None)
+    }
+
+    /// Removes a service and releases its bond back to the owner. Services seeded through genesis
+    /// or added through governance are not bonded.
+    fn release_service(&self, service_id: ServiceId, service: Service) {
+        if let Some(bond) = self.service_bonds.get(&service_id) {
+            let mut owner = self.account_info.get(&service.owner).unwrap_or_default();
+            owner.flk_balance += bond;
+            self.account_info.set(service.owner, owner);
+            self.service_bonds.remove(&service_id);
+        }
+
+        // Note: revenue this service already collected in the current epoch stays in the
+        // `service_revenue` table until the epoch changes, see `distribute_rewards`.
+        self.services.remove(&service_id);
     }
 
     fn slash(
         &self,
         _sender: TransactionSender,
-        _proof: ProofOfMisbehavior,
-        _service_id: ServiceId,
-        _node: NodePublicKey,
+        proof: ProofOfMisbehavior,
+        service_id: ServiceId,
+        node: NodePublicKey,
     ) -> TransactionResponse {
-        TransactionResponse::Revert(ExecutionError::Unimplemented)
+        // Anyone holding a valid proof of misbehavior is allowed to report it
+        let service = match self.services.get(&service_id) {
+            Some(service) => service,
+            None => return TransactionResponse::Revert(ExecutionError::NonExistingService),
+        };
+        let node_index = match self.get_node_index(&node) {
+            Some(index) => index,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+        let mut node_info = match self.node_info.get(&node_index) {
+            Some(info) => info,
+            None => return TransactionResponse::Revert(ExecutionError::NodeDoesNotExist),
+        };
+
+        // Make sure the service accepts this kind of misbehavior
+        let misbehavior = proof.misbehavior_type();
+        if !service.slashing.contains(&misbehavior) {
+            return TransactionResponse::Revert(ExecutionError::MisbehaviorNotSlashable);
+        }
+
+        // A node can only be slashed once for the same offense
+        let offense = proof.to_digest();
+        let mut slashes = self.slashes.get(&node_index).unwrap_or_default();
+        if slashes.iter().any(|slash| slash.offense == offense) {
+            return TransactionResponse::Revert(ExecutionError::AlreadySlashed);
+        }
+
+        if !self.misbehavior_verifiers.verify(&node, node_index, &proof) {
+            return TransactionResponse::Revert(ExecutionError::InvalidProof);
+        }
+
+        let epoch = match self.metadata.get(&Metadata::Epoch) {
+            Some(Value::Epoch(epoch)) => epoch,
+            _ => 0,
+        };
+
+        // Take the penalty from both the staked and the locked tokens, so a node can not escape
+        // a slash by unstaking right before it gets reported.
+        let slash_percentage: HpUfixed<18> = self
+            .parameters
+            .get(&ProtocolParams::SlashPercentage)
+            .unwrap_or(0)
+            .into();
+        let slash_percentage = HpUfixed::<18>::min(&slash_percentage, &(*BIG_HUNDRED)).to_owned();
+        let staked_penalty = &node_info.stake.staked * &slash_percentage / &(*BIG_HUNDRED);
+        let locked_penalty = &node_info.stake.locked * &slash_percentage / &(*BIG_HUNDRED);
+        node_info.stake.staked -= staked_penalty.clone();
+        node_info.stake.locked -= locked_penalty.clone();
+        self.node_info.set(node_index, node_info);
+        let mut amount = staked_penalty + locked_penalty;
+
+        // The FLK delegated to the node backs it as well, so the delegations are slashed by the
+        // same percentage
+        for key in self.delegators.get(&node_index) {
+            let Some(mut delegation) = self.delegations.get(&key) else {
+                continue;
+            };
+            let staked_penalty = &delegation.staked * &slash_percentage / &(*BIG_HUNDRED);
+            let locked_penalty = &delegation.locked * &slash_percentage / &(*BIG_HUNDRED);
+            delegation.staked -= staked_penalty.clone();
+            delegation.locked -= locked_penalty.clone();
+            self.delegations.set(key, delegation);
+            amount += staked_penalty + locked_penalty;
+        }
+
+        // The slashed tokens go to the protocol fund
+        let protocol_owner = match self.metadata.get(&Metadata::ProtocolFundAddress) {
+            Some(Value::AccountPublicKey(owner)) => owner,
+            _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
+        };
+        let mut protocol_account = self.account_info.get(&protocol_owner).unwrap_or_default();
+        protocol_account.flk_balance += amount.clone();
+        self.account_info.set(protocol_owner, protocol_account);
+
+        // Jail the node, it is not eligible for a committee until the jail time is over. The node
+        // stays on the current committee until the epoch changes, the consensus of the epoch
+        // runs with a fixed committee and the quorum of the epoch change is taken over it
+        let jail_duration = self
+            .parameters
+            .get(&ProtocolParams::JailDuration)
+            .unwrap_or(0) as u64;
+        let jailed_until = self
+            .jailed_nodes
+            .get(&node_index)
+            .unwrap_or_default()
+            .max(epoch + 1 + jail_duration);
+        self.jailed_nodes.set(node_index, jailed_until);
+
+        slashes.push(SlashRecord {
+            epoch,
+            service_id,
+            misbehavior,
+            offense,
+            amount,
+            jailed_until,
+        });
+        self.slashes.set(node_index, slashes);
+
+        TransactionResponse::Success(ExecutionData::None)
     }
 
     fn submit_reputation_measurements(
@@ -1160,47 +2242,35 @@ fn update_content_registry(
             Err(e) => return e,
         };
 
-        let mut staged_cid_to_node = HashMap::new();
+        // The providers of each cid are indexed from `node_to_cid`, so only the content registry
+        // of the node has to be updated.
+        let mut staged_cids = HashSet::new();
         let mut staged_cids_provided = self.node_to_cid.get(&node_index).unwrap_or_default();
         let empty_cids_provided = staged_cids_provided.is_empty();
 
         for update in updates {
             // Check if they sent multiple updates for the same CID.
-            if staged_cid_to_node.contains_key(&update.cid) {
+            if !staged_cids.insert(update.cid) {
                 return TransactionResponse::Revert(ExecutionError::TooManyUpdatesForContent);
             }
 
-            let providers = self.cid_to_node.get(&update.cid);
-
-            // Check if a removal request makes sense given our state.
-            if update.remove && (providers.is_none() || empty_cids_provided) {
-                return TransactionResponse::Revert(ExecutionError::InvalidStateForContentRemoval);
-            }
-
-            let mut providers = providers.unwrap_or_default();
-
             if update.remove {
-                if !providers.remove(&node_index) {
-                    return TransactionResponse::Revert(ExecutionError::InvalidContentRemoval);
+                // Check if a removal request makes sense given our state.
+                if empty_cids_provided || self.cid_providers.get(&update.cid).is_empty() {
+                    return TransactionResponse::Revert(
+                        ExecutionError::InvalidStateForContentRemoval,
+                    );
                 }
                 if !staged_cids_provided.remove(&update.cid) {
-                    // Unreachable.
                     return TransactionResponse::Revert(ExecutionError::InvalidContentRemoval);
                 }
             } else {
-                providers.insert(node_index);
                 staged_cids_provided.insert(update.cid);
             }
-
-            staged_cid_to_node.insert(update.cid, providers);
         }
 
         self.node_to_cid.set(node_index, staged_cids_provided);
 
-        for (cid, providers) in staged_cid_to_node {
-            self.cid_to_node.set(cid, providers);
-        }
-
         TransactionResponse::Success(ExecutionData::None)
     }
 
@@ -1253,13 +2323,14 @@ fn distribute_rewards(&self) {
 
         let mut total_reward_share: HpUfixed<18> = HpUfixed::from(0_u64);
         let mut local_shares_map: HashMap<NodeIndex, HpUfixed<18>> = HashMap::new();
-        let mut node_info_map: HashMap<NodeIndex, NodeInfo> = HashMap::new();
+        let mut reward_shares_map: HashMap<NodeIndex, Vec<(EthAddress, HpUfixed<18>)>> =
+            HashMap::new();
 
         for node in self.current_epoch_served.keys() {
             // safe to unwrap since all the nodes in current_epoch_served table are in node info
             // this is checked in submit_pod contract/function
             let node_info = self.node_info.get(&node).unwrap();
-            node_info_map.insert(node, node_info.clone());
+            let reward_shares = self.node_reward_shares(node, &node_info);
 
             let stables_revenue: HpUfixed<6> = self
                 .current_epoch_served
@@ -1269,10 +2340,14 @@ fn distribute_rewards(&self) {
 
             let node_service_proportion =
                 &stables_revenue.convert_precision::<18>() / &reward_pool.convert_precision::<18>();
-            self.mint_and_transfer_stables(
-                stables_revenue * &node_share.convert_precision(),
-                node_info.owner,
-            );
+            let stables_rewards = stables_revenue * &node_share.convert_precision();
+            for (recipient, share) in &reward_shares {
+                self.mint_and_transfer_stables(
+                    &stables_rewards * &share.convert_precision(),
+                    *recipient,
+                );
+            }
+            reward_shares_map.insert(node, reward_shares);
 
             let locked_until = node_info.stake.stake_locked_until;
             let local_boost: HpUfixed<3> = self.get_boost(locked_until, &epoch);
@@ -1283,12 +2358,14 @@ fn distribute_rewards(&self) {
 
         let base_reward = &emissions_for_node / &total_reward_share;
 
-        for (node, node_info) in node_info_map.iter() {
+        for (node, reward_shares) in reward_shares_map.iter() {
             let local_share = local_shares_map.get(node).unwrap();
             let flk_rewards = &base_reward * local_share;
 
             // todo: add service builders and protocols share in stables too
-            self.mint_and_transfer_flk(flk_rewards, node_info.owner);
+            for (recipient, share) in reward_shares {
+                self.mint_and_transfer_flk(&flk_rewards * share, *recipient);
+            }
             self.current_epoch_served.remove(node);
         }
 
@@ -1302,7 +2379,12 @@ fn distribute_rewards(&self) {
         let services_stable_reward_pool = &reward_pool * &service_share.convert_precision();
         let services_flk_reward_pool = &emissions * &service_share;
         for service_id in self.service_revenue.keys() {
-            let service_owner = self.services.get(&service_id).unwrap().owner;
+            // A service that was removed during this epoch forfeits its share of the rewards.
+            let Some(service) = self.services.get(&service_id) else {
+                self.service_revenue.remove(&service_id);
+                continue;
+            };
+            let service_owner = service.owner;
             let service_revenue = self.service_revenue.get(&service_id).unwrap_or_default();
             let revenue_proportion: HpUfixed<18> =
                 &service_revenue.convert_precision() / &reward_pool.convert_precision();
@@ -1336,6 +2418,47 @@ fn distribute_rewards(&self) {
         self.mint_and_transfer_flk(&emissions * &protocol_share, protocol_owner);
     }
 
+    /// Returns how the rewards of a node are split between its owner and its delegators, as the
+    /// fraction of the rewards each of them receives. The rewards are split pro-rata to the stake
+    /// of the owner and the FLK delegated to the node, and the owner keeps the commission rate of
+    /// the node from the share of the delegators.
+    fn node_reward_shares(
+        &self,
+        index: NodeIndex,
+        node: &NodeInfo,
+    ) -> Vec<(EthAddress, HpUfixed<18>)> {
+        let one = HpUfixed::<18>::from(1_u64);
+        let delegations: Vec<(EthAddress, HpUfixed<18>)> = self
+            .delegators
+            .get(&index)
+            .into_iter()
+            .filter_map(|key| Some((key.1, self.delegations.get(&key)?.staked)))
+            .filter(|(_, staked)| *staked > HpUfixed::zero())
+            .collect();
+        if delegations.is_empty() {
+            return vec![(node.owner, one)];
+        }
+
+        let total_stake = delegations
+            .iter()
+            .fold(node.stake.staked.clone(), |total, (_, staked)| {
+                total + staked
+            });
+        let commission: HpUfixed<18> =
+            HpUfixed::<18>::from(node.commission_rate as u64) / &(*BIG_HUNDRED);
+        let delegators_cut = &one - &commission;
+
+        let mut owner_share = one;
+        let mut shares = Vec::with_capacity(delegations.len() + 1);
+        for (delegator, staked) in delegations {
+            let share = &(&staked / &total_stake) * &delegators_cut;
+            owner_share = owner_share - &share;
+            shares.push((delegator, share));
+        }
+        shares.push((node.owner, owner_share));
+        shares
+    }
+
     /// Settles the auction for the current epoch and returns a list of the active set of nodes
     /// Uses quick sort algorithm for effecient sorting
     fn settle_auction(&self, nodes: Vec<(NodeIndex, NodeInfo)>) -> Vec<(NodeIndex, NodeInfo)> {
@@ -1400,7 +2523,9 @@ fn compare_nodes_in_auction(
     ) -> bool {
         // todo(dalton): This is where we add tiebreakers like reputation score. Or modifiers on the
         // stake
-        match pos.1.stake.staked.cmp(&pivot.1.stake.staked) {
+        let pos_stake = self.node_stake(&pos.0, &pos.1);
+        let pivot_stake = self.node_stake(&pivot.0, &pivot.1);
+        match pos_stake.cmp(&pivot_stake) {
             Ordering::Less => true,
             Ordering::Greater => false,
             Ordering::Equal => {
@@ -1511,10 +2636,16 @@ fn choose_new_committee(&self) -> Committee {
             _ => 0,
         };
 
+        // Jailed nodes are not eligible for the committee of the next epoch
         let node_registry: Vec<(NodeIndex, NodeInfo)> = self
             .get_node_registry()
             .into_iter()
             .filter(|index| index.1.participation == Participation::True)
+            .filter(|index| {
+                self.jailed_nodes
+                    .get(&index.0)
+                    .map_or(true, |jailed_until| jailed_until <= epoch + 1)
+            })
             .collect();
 
         let committee_size = self.parameters.get(&ProtocolParams::CommitteeSize).unwrap();
@@ -1572,6 +2703,115 @@ fn choose_new_committee(&self) -> Committee {
         }
     }
 
+    /// Returns the gas a transaction is charged for. Transactions sent by nodes and deposits,
+    /// which are how an account gets funded in the first place, are free.
+    pub fn transaction_gas(&self, txn: &TransactionRequest) -> u128 {
+        let param = match txn {
+            TransactionRequest::UpdateRequest(payload) => {
+                if !matches!(payload.payload.sender, TransactionSender::AccountOwner(_)) {
+                    return 0;
+                }
+                match &payload.payload.method {
+                    UpdateMethod::Deposit { .. } => return 0,
+                    UpdateMethod::Transfer { .. }
+                    | UpdateMethod::Approve { .. }
+                    | UpdateMethod::TransferFrom { .. } => ProtocolParams::TransferGas,
+                    UpdateMethod::Withdraw { .. } | UpdateMethod::SettleWithdrawal { .. } => {
+                        ProtocolParams::BridgeGas
+                    },
+                    UpdateMethod::Stake { .. }
+                    | UpdateMethod::StakeLock { .. }
+                    | UpdateMethod::Unstake { .. }
+                    | UpdateMethod::WithdrawUnstaked { .. }
+                    | UpdateMethod::Delegate { .. }
+                    | UpdateMethod::Undelegate { .. }
+                    | UpdateMethod::WithdrawDelegation { .. }
+                    | UpdateMethod::ChangeCommissionRate { .. } => ProtocolParams::StakingGas,
+                    UpdateMethod::AddService { .. }
+                    | UpdateMethod::RemoveService { .. }
+                    | UpdateMethod::Slash { .. } => ProtocolParams::ServiceGas,
+                    _ => ProtocolParams::BaseGas,
+                }
+            },
+            TransactionRequest::EthereumRequest(payload) => match payload.to {
+                Some(to) if to.0 == FLEEK_CONTRACT_ADDRESS.0 => {
+                    match FleekContractCalls::decode(&payload.input) {
+                        Ok(FleekContractCalls::Deposit(_)) => return 0,
+                        Ok(FleekContractCalls::Withdraw(_)) => ProtocolParams::BridgeGas,
+                        Ok(FleekContractCalls::Approve(_))
+                        | Ok(FleekContractCalls::TransferFrom(_)) => ProtocolParams::TransferGas,
+                        Ok(FleekContractCalls::Stake(_))
+                        | Ok(FleekContractCalls::Unstake(_))
+                        | Ok(FleekContractCalls::WithdrawUnstaked(_)) => ProtocolParams::StakingGas,
+                        _ => ProtocolParams::BaseGas,
+                    }
+                },
+                Some(_) => ProtocolParams::TransferGas,
+                None => ProtocolParams::BaseGas,
+            },
+        };
+        self.parameters.get(&param).unwrap_or(0)
+    }
+
+    /// Charges the sender of a verified transaction for its gas and routes the fee to the
+    /// protocol fund. The fee is paid in FLK if the sender's balance covers it and in stables
+    /// otherwise. This has to run before the transaction is executed, so that a sender that can
+    /// not pay is rejected before any state is changed.
+    pub fn charge_transaction_fee(
+        &self,
+        txn: &TransactionRequest,
+    ) -> Result<Option<TransactionFee>, ExecutionError> {
+        let gas = self.transaction_gas(txn);
+        let TransactionSender::AccountOwner(sender) = txn.sender() else {
+            return Ok(None);
+        };
+        if gas == 0 {
+            return Ok(None);
+        }
+
+        let mut sender_account = self.account_info.get(&sender).unwrap_or_default();
+        let gas_price = self.parameters.get(&ProtocolParams::GasPrice).unwrap_or(0);
+        let flk_fee = HpUfixed::<18>::from(U256::from(gas) * U256::from(gas_price));
+        let fee = if sender_account.flk_balance >= flk_fee {
+            sender_account.flk_balance -= flk_fee.clone();
+            self.account_info.set(sender, sender_account);
+            self.credit_protocol_fund(|account| account.flk_balance += flk_fee);
+            TransactionFee {
+                gas,
+                gas_price,
+                token: Tokens::FLK,
+            }
+        } else {
+            let gas_price = self
+                .parameters
+                .get(&ProtocolParams::StablesGasPrice)
+                .unwrap_or(0);
+            let stables_fee = HpUfixed::<6>::from(U256::from(gas) * U256::from(gas_price));
+            if sender_account.stables_balance < stables_fee {
+                return Err(ExecutionError::InsufficientBalanceForFee);
+            }
+            sender_account.stables_balance -= stables_fee.clone();
+            self.account_info.set(sender, sender_account);
+            self.credit_protocol_fund(|account| account.stables_balance += stables_fee);
+            TransactionFee {
+                gas,
+                gas_price,
+                token: Tokens::USDC,
+            }
+        };
+        Ok(Some(fee))
+    }
+
+    fn credit_protocol_fund<F: FnOnce(&mut AccountInfo)>(&self, credit: F) {
+        let protocol_owner = match self.metadata.get(&Metadata::ProtocolFundAddress) {
+            Some(Value::AccountPublicKey(owner)) => owner,
+            _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
+        };
+        let mut protocol_account = self.account_info.get(&protocol_owner).unwrap_or_default();
+        credit(&mut protocol_account);
+        self.account_info.set(protocol_owner, protocol_account);
+    }
+
     /// This function takes in the Transaction and verifies the Signature matches the Sender. It
     /// also checks the nonce of the sender and makes sure it is equal to the account nonce + 1,
     /// to prevent replay attacks and enforce ordering. Additionally, it verifies ChainID
@@ -1605,7 +2845,7 @@ fn verify_fleek_transaction(&self, txn: &UpdateRequest) -> Result<(), ExecutionE
         match txn.payload.sender {
             // Todo Sunday(dalton): Clean up this match nesting
             TransactionSender::NodeMain(node) => {
-                if let Some(index) = self.pub_key_to_index.get(&node) {
+                if let Some(index) = self.get_node_index(&node) {
                     if let Some(info) = self.node_info.get(&index) {
                         if txn.payload.nonce != info.nonce + 1
                             || txn.payload.secondary_nonce <= info.secondary_nonce
@@ -1620,7 +2860,7 @@ fn verify_fleek_transaction(&self, txn: &UpdateRequest) -> Result<(), ExecutionE
                 }
             },
             TransactionSender::NodeConsensus(node) => {
-                if let Some(index) = self.consensus_key_to_index.get(&node) {
+                if let Some(index) = self.get_node_index_by_consensus_key(&node) {
                     if let Some(info) = self.node_info.get(&index) {
                         if txn.payload.nonce != info.nonce + 1
                             || txn.payload.secondary_nonce <= info.secondary_nonce
@@ -1676,30 +2916,167 @@ fn verify_ethereum_transaction(
         }
     }
 
-    /// Takes in a zk Proof Of Delivery and returns true if valid
+    /// Verifies the acknowledgments of a batch one by one and drops the invalid ones, so that a
+    /// single bad acknowledgment does not void the rest of the batch. An acknowledgment is
+    /// invalid if it is not signed by a known client for this node and service, if its session
+    /// nonce was already used or if the client can not pay for it.
+    ///
+    /// Returns the total commodity of the valid acknowledgments, the accounts of the clients
+    /// after paying for them and the new session nonces of the clients. If none of the
+    /// acknowledgments is valid, fails with the reason the first one was dropped.
+    #[allow(clippy::type_complexity)]
     fn verify_proof_of_delivery(
         &self,
-        _client: &EthAddress,
-        _provider: &NodeIndex,
-        _commodity: &u128,
-        _service_id: &u32,
-        _proof: (),
+        provider: &NodeIndex,
+        provider_public_key: &NodePublicKey,
+        service_id: u32,
+        acknowledgments: &[DeliveryAcknowledgmentProof],
+    ) -> Result<
+        (
+            u128,
+            BTreeMap<EthAddress, AccountInfo>,
+            BTreeMap<ClientPublicKey, u64>,
+        ),
+        ExecutionError,
+    > {
+        let mut accounts: BTreeMap<EthAddress, AccountInfo> = BTreeMap::new();
+        let mut session_nonces: BTreeMap<ClientPublicKey, u64> = BTreeMap::new();
+        let mut acknowledged: u128 = 0;
+        let mut first_error = None;
+
+        for ack in acknowledgments {
+            let address = match self.verify_acknowledgment(
+                provider,
+                provider_public_key,
+                service_id,
+                ack,
+                &session_nonces,
+            ) {
+                Ok(address) => address,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    continue;
+                },
+            };
+
+            let mut account = accounts
+                .get(&address)
+                .cloned()
+                .unwrap_or_else(|| self.account_info.get(&address).unwrap_or_default());
+            if account.bandwidth_balance < ack.commodity {
+                first_error.get_or_insert(ExecutionError::InsufficientBalance);
+                continue;
+            }
+            let Some(total) = acknowledged.checked_add(ack.commodity) else {
+                first_error.get_or_insert(ExecutionError::InvalidProof);
+                continue;
+            };
+
+            account.bandwidth_balance -= ack.commodity;
+            accounts.insert(address, account);
+            session_nonces.insert(ack.client, ack.session_nonce);
+            acknowledged = total;
+        }
+
+        if session_nonces.is_empty() {
+            return Err(first_error.unwrap_or(ExecutionError::InvalidProof));
+        }
+        Ok((acknowledged, accounts, session_nonces))
+    }
+
+    /// Checks a single acknowledgment of a batch and returns the address of the client that
+    /// gave it. `session_nonces` are the nonces of the acknowledgments of the batch that were
+    /// already accepted.
+    fn verify_acknowledgment(
+        &self,
+        provider: &NodeIndex,
+        provider_public_key: &NodePublicKey,
+        service_id: u32,
+        ack: &DeliveryAcknowledgmentProof,
+        session_nonces: &BTreeMap<ClientPublicKey, u64>,
+    ) -> Result<EthAddress, ExecutionError> {
+        let Some(Value::ChainId(chain_id)) = self.metadata.get(&Metadata::ChainId) else {
+            return Err(ExecutionError::InvalidProof);
+        };
+        let digest = DeliveryAcknowledgmentMessage {
+            chain_id,
+            service_id,
+            node: *provider_public_key,
+            commodity: ack.commodity,
+            session_nonce: ack.session_nonce,
+        }
+        .to_digest();
+        if !ack.client.verify(&ack.signature, &digest) {
+            return Err(ExecutionError::InvalidProof);
+        }
+
+        // The session nonces of a client have to increase, so an acknowledgment can only be
+        // submitted once
+        let last_nonce = session_nonces
+            .get(&ack.client)
+            .copied()
+            .or_else(|| self.client_session_nonces.get(&(ack.client, *provider)));
+        if last_nonce.is_some_and(|nonce| ack.session_nonce <= nonce) {
+            return Err(ExecutionError::InvalidSessionNonce);
+        }
+
+        self.client_keys
+            .get(&ack.client)
+            .ok_or(ExecutionError::UnknownClient)
+    }
+
+    /// Takes in a Proof Of Consensus and returns true if enough distinct bridge signers signed off
+    /// on the deposit
+    fn verify_proof_of_consensus(
+        &self,
+        proof: &ProofOfConsensus,
+        recipient: EthAddress,
+        token: &Tokens,
+        amount: &HpUfixed<18>,
     ) -> bool {
-        true
+        let Some(Value::ChainId(chain_id)) = self.metadata.get(&Metadata::ChainId) else {
+            return false;
+        };
+
+        let digest = DepositAttestation {
+            chain_id,
+            deposit_id: proof.deposit_id,
+            recipient,
+            token: token.clone(),
+            amount: amount.clone(),
+        }
+        .to_digest();
+        self.verify_bridge_signatures(&proof.signatures, &digest)
     }
 
-    /// Takes in a zk Proof Of Consensus and returns true if valid
-    fn verify_proof_of_consensus(&self, _proof: ProofOfConsensus) -> bool {
-        true
+    /// Returns true if enough of the current bridge signers signed the digest.
+    fn verify_bridge_signatures(&self, signatures: &[BridgeSignature], digest: &[u8; 32]) -> bool {
+        let Some(Value::BridgeSigners(bridge_signers)) =
+            self.metadata.get(&Metadata::BridgeSigners)
+        else {
+            return false;
+        };
+        if bridge_signers.threshold == 0 {
+            return false;
+        }
+
+        let signed = signatures
+            .iter()
+            .filter(|signature| {
+                bridge_signers.signers.contains(&signature.signer)
+                    && signature.signer.verify(&signature.signature, digest)
+            })
+            .map(|signature| signature.signer)
+            .collect::<BTreeSet<_>>();
+        signed.len() >= bridge_signers.threshold as usize
     }
 
     /// Creates a new node. A new node should only be created through this function.
     fn create_node(&self, node: NodeInfo) -> bool {
         // If this public key or network key is already indexed to no create it
-        if self.pub_key_to_index.get(&node.public_key).is_some()
+        if self.get_node_index(&node.public_key).is_some()
             || self
-                .consensus_key_to_index
-                .get(&node.consensus_key)
+                .get_node_index_by_consensus_key(&node.consensus_key)
                 .is_some()
         {
             return false;
@@ -1708,10 +3085,7 @@ fn create_node(&self, node: NodeInfo) -> bool {
             Some(Value::NextNodeIndex(index)) => index,
             _ => 0,
         };
-        self.pub_key_to_index.set(node.public_key, node_index);
-        self.consensus_key_to_index
-            .set(node.consensus_key, node_index);
-
+        // The node is indexed by its keys along with it.
         self.node_info.set(node_index, node);
         self.metadata.set(
             Metadata::NextNodeIndex,
@@ -1723,12 +3097,8 @@ fn create_node(&self, node: NodeInfo) -> bool {
     /// Remove a node. A node should only be removed through this function.
     #[allow(unused)]
     fn remove_node(&self, node: NodePublicKey) -> bool {
-        if let Some(index) = self.pub_key_to_index.get(&node) {
-            if let Some(info) = self.node_info.get(&index) {
-                self.consensus_key_to_index.remove(&info.consensus_key)
-            }
+        if let Some(index) = self.get_node_index(&node) {
             self.node_info.remove(&index);
-            self.pub_key_to_index.remove(&node);
             true
         } else {
             false
@@ -1742,13 +3112,13 @@ fn remove_node(&self, node: NodePublicKey) -> bool {
     fn increment_nonce(&self, sender: TransactionSender) {
         match sender {
             TransactionSender::NodeMain(node) => {
-                let index = self.pub_key_to_index.get(&node).unwrap();
+                let index = self.get_node_index(&node).unwrap();
                 let mut node_info = self.node_info.get(&index).unwrap();
                 node_info.nonce += 1;
                 self.node_info.set(index, node_info);
             },
             TransactionSender::NodeConsensus(node) => {
-                let index = self.consensus_key_to_index.get(&node).unwrap();
+                let index = self.get_node_index_by_consensus_key(&node).unwrap();
                 let mut node_info = self.node_info.get(&index).unwrap();
                 node_info.nonce += 1;
                 self.node_info.set(index, node_info);
@@ -1787,8 +3157,7 @@ fn only_account_owner(
     // Does not panic
     fn only_node(&self, sender: TransactionSender) -> Result<NodeIndex, TransactionResponse> {
         let node_index = match sender {
-            TransactionSender::NodeMain(public_key) => match self.pub_key_to_index.get(&public_key)
-            {
+            TransactionSender::NodeMain(public_key) => match self.get_node_index(&public_key) {
                 Some(node_index) => node_index,
                 None => {
                     return Err(TransactionResponse::Revert(
@@ -1797,7 +3166,7 @@ fn only_node(&self, sender: TransactionSender) -> Result<NodeIndex, TransactionR
                 },
             },
             TransactionSender::NodeConsensus(public_key) => {
-                match self.consensus_key_to_index.get(&public_key) {
+                match self.get_node_index_by_consensus_key(&public_key) {
                     Some(node_index) => node_index,
                     None => {
                         return Err(TransactionResponse::Revert(
@@ -1830,18 +3199,45 @@ fn is_valid_node(&self, node_index: &NodeIndex) -> Option<bool> {
             .unwrap();
         self.node_info
             .get(node_index)
-            .map(|node_info| node_info.stake.staked >= min_amount.into())
+            .map(|node_info| self.node_stake(node_index, &node_info) >= min_amount.into())
+    }
+
+    /// Returns the FLK staked on a node, which is the stake of its owner plus the stake delegated
+    /// to it.
+    fn node_stake(&self, index: &NodeIndex, node: &NodeInfo) -> HpUfixed<18> {
+        self.delegators
+            .get(index)
+            .into_iter()
+            .filter_map(|key| self.delegations.get(&key))
+            .fold(node.stake.staked.clone(), |total, delegation| {
+                total + delegation.staked
+            })
+    }
+
+    /// Returns the index of the node with the given public key.
+    fn get_node_index(&self, public_key: &NodePublicKey) -> Option<NodeIndex> {
+        self.nodes_by_pub_key.get(public_key).first().copied()
+    }
+
+    /// Returns the index of the node with the given consensus key.
+    fn get_node_index_by_consensus_key(
+        &self,
+        consensus_key: &ConsensusPublicKey,
+    ) -> Option<NodeIndex> {
+        self.nodes_by_consensus_key
+            .get(consensus_key)
+            .first()
+            .copied()
     }
 
     fn get_node_info(&self, sender: TransactionSender) -> Option<(NodeIndex, NodeInfo)> {
         match sender {
-            TransactionSender::NodeMain(public_key) => match self.pub_key_to_index.get(&public_key)
-            {
+            TransactionSender::NodeMain(public_key) => match self.get_node_index(&public_key) {
                 Some(index) => self.node_info.get(&index).map(|info| (index, info)),
                 None => None,
             },
             TransactionSender::NodeConsensus(public_key) => {
-                match self.consensus_key_to_index.get(&public_key) {
+                match self.get_node_index_by_consensus_key(&public_key) {
                     Some(index) => self.node_info.get(&index).map(|info| (index, info)),
                     None => None,
                 }
@@ -1879,38 +3275,25 @@ fn _get_epoch(&self) -> u64 {
         }
     }
 
-    fn clear_content_registry(&self, node_index: &NodeIndex) -> Result<(), ExecutionError> {
-        let cids = self.node_to_cid.get(node_index).unwrap_or_default();
-
-        // Let's stage the changes before applying.
-        let mut staged_cid_to_node = HashMap::new();
-        for cid in cids {
-            let mut providers = self
-                .cid_to_node
-                .get(&cid)
-                .ok_or(ExecutionError::InvalidStateForContentRemoval)?;
-            if !providers.remove(node_index) {
-                return Err(ExecutionError::InvalidStateForContentRemoval);
-            }
-            staged_cid_to_node.insert(cid, providers);
-        }
-
-        // Apply changes.
-        for (cid, providers) in staged_cid_to_node {
-            self.cid_to_node.set(cid, providers);
-        }
+    fn clear_content_registry(&self, node_index: &NodeIndex) {
+        // The providers of each cid are indexed from `node_to_cid` and updated along with it.
         self.node_to_cid.remove(node_index);
-
-        Ok(())
     }
 
     fn clean_up_content_registry(&self) {
         for (index, info) in self.get_node_registry() {
             if matches!(info.participation, Participation::False) {
-                // Note: Unless there is a bug in the content registry system,
-                // this operation should not fail.
-                let _ = self.clear_content_registry(&index);
+                self.clear_content_registry(&index);
             }
         }
     }
 }
+
+/// Truncates an amount to the precision the token is accounted with. Stables only have 6
+/// decimals.
+fn to_token_precision(token: &Tokens, amount: HpUfixed<18>) -> HpUfixed<18> {
+    match token {
+        Tokens::FLK => amount,
+        Tokens::USDC => amount.convert_precision::<6>().convert_precision(),
+    }
+}
@@ -1,14 +1,26 @@
+use std::ops::Bound;
 use std::path::PathBuf;
 
-use atomo::{InMemoryStorage, StorageBackend, StorageBackendConstructor};
-use atomo_rocks::{Options, RocksBackend, RocksBackendBuilder};
-
-pub enum AtomoStorageBuilder<'a> {
+use atomo::storage::RawIterator;
+use atomo::{
+    Direction,
+    InMemoryStorage,
+    Overlay,
+    OverlayStorage,
+    OverlayStorageBuilder,
+    StorageBackend,
+    StorageBackendConstructor,
+};
+use atomo_rocks::{CheckpointWriter, Options, RocksBackend, RocksBackendBuilder};
+use lightning_interfaces::types::StateDiff;
+
+pub enum AtomoStorageBuilder {
     InMemory(InMemoryStorage),
-    RocksDb(RocksBackendBuilder<'a>),
+    RocksDb(RocksBackendBuilder),
+    Historical(OverlayStorageBuilder<RocksBackendBuilder>),
 }
 
-impl<'a> AtomoStorageBuilder<'a> {
+impl AtomoStorageBuilder {
     #[inline(always)]
     pub fn new<P: Into<PathBuf>>(path: Option<P>) -> Self {
         match path {
@@ -17,10 +29,29 @@ pub fn new<P: Into<PathBuf>>(path: Option<P>) -> Self {
         }
     }
 
+    /// Create a read-only builder that serves the state at the given path with the reverse diff
+    /// applied on top of it, without modifying the database.
+    pub fn historical<P: Into<PathBuf>>(path: P, diff: StateDiff) -> Self {
+        let changes = diff
+            .tables
+            .into_iter()
+            .map(|(table, changes)| {
+                let overlay = changes
+                    .into_iter()
+                    .map(|(key, value)| (key.into_boxed_slice(), value.map(Vec::into_boxed_slice)))
+                    .collect::<Overlay>();
+                (table, overlay)
+            })
+            .collect();
+        let builder = RocksBackendBuilder::new(path).read_only();
+        AtomoStorageBuilder::Historical(OverlayStorageBuilder::new(builder, changes))
+    }
+
     #[inline(always)]
     pub fn read_only(self) -> Self {
         match self {
             AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::Historical(builder) => AtomoStorageBuilder::Historical(builder),
             AtomoStorageBuilder::RocksDb(builder) => {
                 let builder = builder.read_only();
                 AtomoStorageBuilder::RocksDb(builder)
@@ -32,6 +63,7 @@ pub fn read_only(self) -> Self {
     pub fn with_options(self, opts: Options) -> Self {
         match self {
             AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::Historical(builder) => AtomoStorageBuilder::Historical(builder),
             AtomoStorageBuilder::RocksDb(builder) => {
                 let builder = builder.with_options(opts);
                 AtomoStorageBuilder::RocksDb(builder)
@@ -44,6 +76,7 @@ pub fn with_options(self, opts: Options) -> Self {
     pub fn with_table_option(self, name: &str, opts: Options) -> Self {
         match self {
             AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::Historical(builder) => AtomoStorageBuilder::Historical(builder),
             AtomoStorageBuilder::RocksDb(builder) => {
                 let builder = builder.with_table_option(name, opts);
                 AtomoStorageBuilder::RocksDb(builder)
@@ -54,18 +87,19 @@ pub fn with_table_option(self, name: &str, opts: Options) -> Self {
     #[inline(always)]
     #[allow(unused)]
     #[allow(clippy::wrong_self_convention)]
-    pub fn from_checkpoint(self, hash: [u8; 32], checkpoint: &'a [u8]) -> Self {
+    pub fn from_checkpoint<P: Into<PathBuf>>(self, checkpoint: P) -> Self {
         match self {
             AtomoStorageBuilder::InMemory(builder) => AtomoStorageBuilder::InMemory(builder),
+            AtomoStorageBuilder::Historical(builder) => AtomoStorageBuilder::Historical(builder),
             AtomoStorageBuilder::RocksDb(builder) => {
-                let builder = builder.from_checkpoint(hash, checkpoint);
+                let builder = builder.from_checkpoint(checkpoint);
                 AtomoStorageBuilder::RocksDb(builder)
             },
         }
     }
 }
 
-impl<'a> StorageBackendConstructor for AtomoStorageBuilder<'a> {
+impl StorageBackendConstructor for AtomoStorageBuilder {
     type Storage = AtomoStorage;
 
     type Error = anyhow::Error;
@@ -74,6 +108,7 @@ fn open_table(&mut self, name: String) {
         match self {
             AtomoStorageBuilder::InMemory(builder) => builder.open_table(name),
             AtomoStorageBuilder::RocksDb(builder) => builder.open_table(name),
+            AtomoStorageBuilder::Historical(builder) => builder.open_table(name),
         }
     }
 
@@ -87,6 +122,10 @@ fn build(self) -> Result<Self::Storage, Self::Error> {
                 let storage = builder.build()?;
                 Ok(AtomoStorage::RocksDb(storage))
             },
+            AtomoStorageBuilder::Historical(builder) => {
+                let storage = builder.build()?;
+                Ok(AtomoStorage::Historical(storage))
+            },
         }
     }
 }
@@ -94,13 +133,15 @@ fn build(self) -> Result<Self::Storage, Self::Error> {
 pub enum AtomoStorage {
     InMemory(InMemoryStorage),
     RocksDb(RocksBackend),
+    Historical(OverlayStorage<RocksBackend>),
 }
 
 impl AtomoStorage {
-    pub fn serialize(&self) -> Option<Vec<u8>> {
+    pub fn checkpoint(&self) -> Option<CheckpointWriter<'_>> {
         match &self {
             AtomoStorage::InMemory(_storage) => None,
-            AtomoStorage::RocksDb(storage) => Some(storage.serialize()),
+            AtomoStorage::RocksDb(storage) => Some(storage.checkpoint()),
+            AtomoStorage::Historical(_storage) => None,
         }
     }
 }
@@ -122,6 +163,15 @@ fn commit(&self, batch: atomo::batch::VerticalBatch) {
         match &self {
             AtomoStorage::InMemory(storage) => storage.commit(batch),
             AtomoStorage::RocksDb(storage) => storage.commit(batch),
+            AtomoStorage::Historical(storage) => storage.commit(batch),
+        }
+    }
+
+    fn is_read_only(&self) -> bool {
+        match &self {
+            AtomoStorage::InMemory(storage) => storage.is_read_only(),
+            AtomoStorage::RocksDb(storage) => storage.is_read_only(),
+            AtomoStorage::Historical(storage) => storage.is_read_only(),
         }
     }
 
@@ -129,6 +179,7 @@ fn keys(&self, tid: u8) -> Vec<atomo::batch::BoxedVec> {
         match &self {
             AtomoStorage::InMemory(storage) => storage.keys(tid),
             AtomoStorage::RocksDb(storage) => storage.keys(tid),
+            AtomoStorage::Historical(storage) => storage.keys(tid),
         }
     }
 
@@ -136,6 +187,7 @@ fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
         match &self {
             AtomoStorage::InMemory(storage) => storage.get(tid, key),
             AtomoStorage::RocksDb(storage) => storage.get(tid, key),
+            AtomoStorage::Historical(storage) => storage.get(tid, key),
         }
     }
 
@@ -143,6 +195,21 @@ fn contains(&self, tid: u8, key: &[u8]) -> bool {
         match &self {
             AtomoStorage::InMemory(storage) => storage.contains(tid, key),
             AtomoStorage::RocksDb(storage) => storage.contains(tid, key),
+            AtomoStorage::Historical(storage) => storage.contains(tid, key),
+        }
+    }
+
+    fn range(
+        &self,
+        tid: u8,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_> {
+        match &self {
+            AtomoStorage::InMemory(storage) => storage.range(tid, start, end, direction),
+            AtomoStorage::RocksDb(storage) => storage.range(tid, start, end, direction),
+            AtomoStorage::Historical(storage) => storage.range(tid, start, end, direction),
         }
     }
 }
@@ -2,7 +2,14 @@
 use std::cell::RefCell;
 use std::hash::Hash;
 
-use atomo::{KeyIterator, SerdeBackend, StorageBackend, TableRef as AtomoTableRef, TableSelector};
+use atomo::{
+    IndexRef as AtomoIndexRef,
+    KeyIterator,
+    SerdeBackend,
+    StorageBackend,
+    TableRef as AtomoTableRef,
+    TableSelector,
+};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
@@ -17,6 +24,17 @@ fn get_table_reference<
         &self,
         id: &str,
     ) -> Self::Ref<K, V>;
+
+    type Index<I: Eq + Hash + Send + Serialize + DeserializeOwned
+     + 'static, K: Eq + Hash + Send + Serialize + DeserializeOwned + 'static>: IndexRef<I, K>;
+
+    fn get_index_reference<
+        I: Eq + Hash + Send + Serialize + DeserializeOwned,
+        K: Eq + Hash + Send + Serialize + DeserializeOwned,
+    >(
+        &self,
+        id: &str,
+    ) -> Self::Index<I, K>;
 }
 
 pub trait TableRef<K, V> {
@@ -26,6 +44,10 @@ pub trait TableRef<K, V> {
     fn remove(&self, key: &K);
 }
 
+pub trait IndexRef<I, K> {
+    fn get(&self, key: &I) -> Vec<K>;
+}
+
 pub struct StateTables<'selector, B: StorageBackend, S: SerdeBackend> {
     pub table_selector: &'selector TableSelector<B, S>,
 }
@@ -45,6 +67,21 @@ fn get_table_reference<
     ) -> Self::Ref<K, V> {
         AtomoTable(RefCell::new(self.table_selector.get_table(id)))
     }
+
+    type Index<
+        I: Eq + Hash + Send + Serialize + DeserializeOwned + 'static,
+        K: Eq + Hash + Send + Serialize + DeserializeOwned + 'static,
+    > = AtomoIndex<'selector, I, K, B, S>;
+
+    fn get_index_reference<
+        I: Eq + Hash + Send + Serialize + DeserializeOwned,
+        K: Eq + Hash + Send + Serialize + DeserializeOwned,
+    >(
+        &self,
+        id: &str,
+    ) -> Self::Index<I, K> {
+        AtomoIndex(self.table_selector.get_index(id))
+    }
 }
 
 pub struct AtomoTable<
@@ -79,3 +116,24 @@ fn remove(&self, key: &K) {
         self.0.borrow_mut().remove(key)
     }
 }
+
+pub struct AtomoIndex<
+    'selector,
+    I: Hash + Eq + Serialize + DeserializeOwned + 'static,
+    K: Hash + Eq + Serialize + DeserializeOwned + 'static,
+    B: StorageBackend,
+    S: SerdeBackend,
+>(AtomoIndexRef<'selector, I, K, B, S>);
+
+impl<
+    'selector,
+    I: Hash + Eq + Serialize + DeserializeOwned + Any,
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    B: StorageBackend,
+    S: SerdeBackend,
+> IndexRef<I, K> for AtomoIndex<'selector, I, K, B, S>
+{
+    fn get(&self, key: &I) -> Vec<K> {
+        self.0.get(key)
+    }
+}
@@ -1,66 +1,113 @@
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::net::IpAddr;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 use std::time::SystemTime;
 
 use affair::Socket;
 use anyhow::{anyhow, Result};
+use atomo::{
+    Atomo,
+    AtomoBuilder,
+    DefaultSerdeBackend,
+    ProofLeaf,
+    SerdeBackend,
+    StateProof,
+    UpdatePerm,
+};
+use atomo_rocks::Options;
+use ethers::abi::AbiEncode;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Transaction as EthersTransaction, U256};
 use fleek_crypto::{
     AccountOwnerSecretKey,
+    ClientPublicKey,
+    ClientSignature,
     ConsensusPublicKey,
     ConsensusSecretKey,
     EthAddress,
     NodePublicKey,
     NodeSecretKey,
+    NodeSignature,
     SecretKey,
 };
 use hp_fixed::signed::HpFixed;
 use hp_fixed::unsigned::HpUfixed;
+use lazy_static::lazy_static;
 use lightning_interfaces::prelude::*;
+use lightning_interfaces::schema::broadcast::Message;
 use lightning_interfaces::types::{
     AccountInfo,
+    AttestationEvidence,
     Blake3Hash,
     Block,
     BlockExecutionResponse,
+    BridgeSignature,
+    BridgeSigners,
     ChainId,
+    CommitteeAttestation,
     CommodityTypes,
     ContentUpdate,
+    Delegation,
+    DeliveryAcknowledgmentMessage,
     DeliveryAcknowledgmentProof,
+    DepositAttestation,
     Epoch,
+    Event,
     ExecutionData,
     ExecutionError,
     HandshakePorts,
     Metadata,
+    MisbehaviorType,
     NodeIndex,
     NodeInfo,
     NodePorts,
+    ParcelHeader,
     Participation,
+    PendingWithdrawal,
     ProofOfConsensus,
+    ProofOfMisbehavior,
+    ProposalAction,
+    ProposalStatus,
+    ProposalVote,
     ProtocolParams,
     ReputationMeasurements,
+    Service,
+    ServiceId,
     Staking,
     Tokens,
+    Topic,
     TotalServed,
+    TransactionFee,
     TransactionRequest,
     TransactionResponse,
     UpdateMethod,
     UpdatePayload,
     UpdateRequest,
     Value,
+    WithdrawalSettlement,
+    FLEEK_CONTRACT_ADDRESS,
     MAX_MEASUREMENTS_PER_TX,
     MAX_MEASUREMENTS_SUBMIT,
 };
 use lightning_interfaces::PagingParams;
 use lightning_test_utils::json_config::JsonConfigProvider;
+use lightning_test_utils::relayer::MockRelayer;
 use lightning_test_utils::{random, reputation};
 use lightning_utils::application::QueryRunnerExt;
+use lightning_utils::eth::{ApproveCall, TransferFromCall};
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 use crate::app::Application;
 use crate::config::{Config, Mode, StorageConfig};
+use crate::env::Env;
 use crate::genesis::{Genesis, GenesisAccount, GenesisNode, GenesisPrices, GenesisService};
+use crate::migrations::{migrate, migrate_values, Migration, MigrationContext, SCHEMA_VERSION};
 use crate::query_runner::QueryRunner;
+use crate::slashing::{parcel_digest, ConsensusMessage};
+use crate::storage::{AtomoStorage, AtomoStorageBuilder};
 
 partial!(TestBinding {
     ConfigProviderInterface = JsonConfigProvider;
@@ -68,6 +115,27 @@
 });
 
 const CHAIN_ID: ChainId = 1337;
+const BRIDGE_THRESHOLD: u16 = 2;
+
+const TEST_CLIENT_BANDWIDTH_BALANCE: u64 = 1_000_000_000;
+
+const TEST_GAS_PRICE: u64 = 1_000_000_000;
+const TEST_STABLES_GAS_PRICE: u64 = 10;
+const TEST_TRANSFER_GAS: u64 = 21000;
+
+lazy_static! {
+    static ref BRIDGE_SIGNER_KEYS: Vec<AccountOwnerSecretKey> =
+        (0..3).map(|_| AccountOwnerSecretKey::generate()).collect();
+    static ref TEST_CLIENT_SECRET_KEY: ConsensusSecretKey = ConsensusSecretKey::generate();
+    static ref TEST_CLIENT_ADDRESS: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+}
+
+/// Deposit ids have to be unique, so every deposit made in the tests gets a fresh one.
+static NEXT_DEPOSIT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Session nonces have to increase, so every delivery acknowledgment made in the tests gets a
+/// fresh one.
+static NEXT_SESSION_NONCE: AtomicU64 = AtomicU64::new(0);
 
 pub struct Params {
     epoch_time: Option<u64>,
@@ -82,7 +150,7 @@ pub struct Params {
 /// Helper struct for keeping track of a node's private keys.
 #[derive(Clone)]
 struct GenesisCommitteeKeystore {
-    _owner_secret_key: AccountOwnerSecretKey,
+    owner_secret_key: AccountOwnerSecretKey,
     node_secret_key: NodeSecretKey,
     consensus_secret_key: ConsensusSecretKey,
     _worker_secret_key: NodeSecretKey,
@@ -514,6 +582,23 @@ fn test_genesis() -> Genesis {
         max_boost: 4,
         // 1460 days(epoch) meaning 4 years
         max_lock_time: 1460,
+        service_bond: 1000,
+        slash_percentage: 10,
+        jail_duration: 2,
+        // Fees are free in the tests, so that balances only reflect the transactions themselves.
+        gas_price: 0,
+        stables_gas_price: 0,
+        base_gas: 21000,
+        transfer_gas: 21000,
+        staking_gas: 50000,
+        bridge_gas: 60000,
+        service_gas: 40000,
+        governance_quorum: 50,
+        governance_voting_period: 2,
+        governance_timelock: 1,
+        governance_max_open_proposals: 2,
+        bridge_signers: test_bridge_signers(),
+        bridge_threshold: BRIDGE_THRESHOLD,
         // Set to 1 million for testing, to be determined when initial allocations are set
         supply_at_genesis: 1000000,
         protocol_fund_address: protocol_address,
@@ -524,20 +609,25 @@ fn test_genesis() -> Genesis {
                 id: 0,
                 owner: EthAddress::from_str("0xDC0A31F9eeb151f82BF1eE6831095284fC215Ee7").unwrap(),
                 commodity_type: CommodityTypes::Bandwidth,
+                slashing: vec![MisbehaviorType::ConflictingAttestations],
             },
             GenesisService {
                 id: 1,
                 owner: EthAddress::from_str("0x684166BDbf530a256d7c92Fa0a4128669aFd9B9F").unwrap(),
                 commodity_type: CommodityTypes::Compute,
+                slashing: vec![],
             },
         ],
-        account: vec![GenesisAccount {
-            public_key: genesis_node_owner,
-            flk_balance: HpUfixed::<18>::from(100690000000000000000u128),
-            stables_balance: 100,
-            bandwidth_balance: 100,
-        }],
-        client: HashMap::new(),
+        account: vec![
+            GenesisAccount {
+                public_key: genesis_node_owner,
+                flk_balance: HpUfixed::<18>::from(100690000000000000000u128),
+                stables_balance: 100,
+                bandwidth_balance: 100,
+            },
+            test_client_account(),
+        ],
+        client: HashMap::from([(test_client(), *TEST_CLIENT_ADDRESS)]),
         commodity_prices: vec![
             GenesisPrices {
                 commodity: CommodityTypes::Bandwidth,
@@ -555,15 +645,23 @@ fn test_genesis() -> Genesis {
 
 /// Initialize application state with provided or default configuration.
 fn init_app(config: Option<Config>) -> (ExecutionEngineSocket, QueryRunner) {
-    let config = config.or(Some(Config {
-        genesis: None,
-        mode: Mode::Dev,
-        testnet: false,
-        storage: StorageConfig::InMemory,
-        db_path: None,
-        db_options: None,
-    }));
-    do_init_app(config.unwrap())
+    let config = config.unwrap_or_else(|| {
+        // Use the default genesis, but with bridge signers the tests hold the keys of and
+        // without fees.
+        let mut genesis = Genesis::load().unwrap();
+        genesis.epoch_start = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        genesis.bridge_signers = test_bridge_signers();
+        genesis.bridge_threshold = BRIDGE_THRESHOLD;
+        genesis.client.insert(test_client(), *TEST_CLIENT_ADDRESS);
+        genesis.account.push(test_client_account());
+        genesis.gas_price = 0;
+        genesis.stables_gas_price = 0;
+        test_config(genesis)
+    });
+    do_init_app(config)
 }
 
 /// Initialize application with provided configuration.
@@ -656,6 +754,54 @@ fn test_config(genesis: Genesis) -> Config {
     }
 }
 
+/// The addresses of the bridge signers used in the tests.
+fn test_bridge_signers() -> Vec<EthAddress> {
+    BRIDGE_SIGNER_KEYS
+        .iter()
+        .map(|secret_key| secret_key.to_pk().into())
+        .collect()
+}
+
+/// The public key of the client used in the tests.
+fn test_client() -> ClientPublicKey {
+    ClientPublicKey(TEST_CLIENT_SECRET_KEY.to_pk().0)
+}
+
+/// The genesis account of the client used in the tests, it pays for the delivery
+/// acknowledgments.
+fn test_client_account() -> GenesisAccount {
+    GenesisAccount {
+        public_key: *TEST_CLIENT_ADDRESS,
+        flk_balance: HpUfixed::<18>::zero(),
+        stables_balance: 0,
+        bandwidth_balance: TEST_CLIENT_BANDWIDTH_BALANCE,
+    }
+}
+
+/// Initialize application with fees enabled and the given genesis accounts.
+fn init_app_with_fees(accounts: Vec<GenesisAccount>) -> (ExecutionEngineSocket, QueryRunner) {
+    let mut genesis = test_genesis();
+    genesis.gas_price = TEST_GAS_PRICE;
+    genesis.stables_gas_price = TEST_STABLES_GAS_PRICE;
+    genesis.account.extend(accounts);
+    init_app_with_genesis(&genesis)
+}
+
+/// The fee for `gas` in FLK, with the gas price used in the tests.
+fn flk_fee(gas: u64) -> HpUfixed<18> {
+    HpUfixed::<18>::from(U256::from(gas) * U256::from(TEST_GAS_PRICE))
+}
+
+/// The fee for `gas` in stables, with the stables gas price used in the tests.
+fn stables_fee(gas: u64) -> HpUfixed<6> {
+    HpUfixed::<6>::from(U256::from(gas) * U256::from(TEST_STABLES_GAS_PRICE))
+}
+
+/// Query Account's nonce
+fn get_account_nonce(query_runner: &QueryRunner, address: &EthAddress) -> u64 {
+    do_get_account_info::<u64>(query_runner, address, |a| a.nonce).unwrap_or(0)
+}
+
 /// Prepare test Reputation Measurements based on provided `uptime`.
 fn test_reputation_measurements(uptime: u8) -> ReputationMeasurements {
     ReputationMeasurements {
@@ -693,7 +839,7 @@ fn create_genesis_committee(
         );
         committee.push(node);
         keystore.push(GenesisCommitteeKeystore {
-            _owner_secret_key: owner_secret_key,
+            owner_secret_key,
             _worker_secret_key: node_secret_key.clone(),
             node_secret_key,
             consensus_secret_key,
@@ -805,7 +951,35 @@ fn prepare_update_request_account(
     }
 }
 
+/// Prepare an Ethereum transaction calling a function of the Fleek contract, signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+fn prepare_ethereum_request<C: AbiEncode>(
+    call: C,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> TransactionRequest {
+    let sender: EthAddress = secret_key.to_pk().into();
+    let mut txn = EthersTransaction {
+        from: sender.0.into(),
+        to: Some(FLEEK_CONTRACT_ADDRESS.0.into()),
+        nonce: nonce.into(),
+        chain_id: Some(CHAIN_ID.into()),
+        input: call.encode().into(),
+        ..Default::default()
+    };
+    let typed_txn: TypedTransaction = (&txn).into();
+    let signature = secret_key.sign(typed_txn.rlp().as_ref()).0;
+    txn.r = U256::from_big_endian(&signature[0..32]);
+    txn.s = U256::from_big_endian(&signature[32..64]);
+    // EIP-155 recovery id
+    txn.v = (signature[64] as u64 + 35 + 2 * CHAIN_ID as u64).into();
+    txn.hash = txn.hash();
+    txn.into()
+}
+
 /// Prepare an `UpdateRequest` for `UpdateMethod::Deposit` signed with `AccountOwnerSecretKey`.
+/// The deposit gets a fresh deposit id and is signed by enough of the test bridge signers.
 /// Passing the private key around like this should only be done for testing.
 fn prepare_deposit_update(
     amount: &HpUfixed<18>,
@@ -814,7 +988,13 @@ fn prepare_deposit_update(
 ) -> UpdateRequest {
     prepare_update_request_account(
         UpdateMethod::Deposit {
-            proof: ProofOfConsensus {},
+            proof: prepare_proof_of_consensus(
+                next_deposit_id(),
+                secret_key.to_pk().into(),
+                Tokens::FLK,
+                amount,
+                &BRIDGE_SIGNER_KEYS[..BRIDGE_THRESHOLD as usize],
+            ),
             token: Tokens::FLK,
             amount: amount.clone(),
         },
@@ -823,6 +1003,55 @@ fn prepare_deposit_update(
     )
 }
 
+/// Returns a deposit id that was not used before.
+fn next_deposit_id() -> u64 {
+    NEXT_DEPOSIT_ID.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
+/// Prepare a `ProofOfConsensus` for a deposit, signed by each of the given bridge signers.
+fn prepare_proof_of_consensus(
+    deposit_id: u64,
+    recipient: EthAddress,
+    token: Tokens,
+    amount: &HpUfixed<18>,
+    signers: &[AccountOwnerSecretKey],
+) -> ProofOfConsensus {
+    let digest = DepositAttestation {
+        chain_id: CHAIN_ID,
+        deposit_id,
+        recipient,
+        token,
+        amount: amount.clone(),
+    }
+    .to_digest();
+    ProofOfConsensus {
+        deposit_id,
+        signatures: signers
+            .iter()
+            .map(|secret_key| BridgeSignature {
+                signer: secret_key.to_pk().into(),
+                signature: secret_key.sign(&digest),
+            })
+            .collect(),
+    }
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::ChangeBridgeSigners` signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+fn prepare_change_bridge_signers_update(
+    signers: Vec<EthAddress>,
+    threshold: u16,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::ChangeBridgeSigners { signers, threshold },
+        secret_key,
+        nonce,
+    )
+}
+
 /// Prepare an `UpdateRequest` for `UpdateMethod::Stake` signed with `AccountOwnerSecretKey`.
 /// For the first `Stake`, use `prepare_initial_stake_update`.
 /// Passing the private key around like this should only be done for testing.
@@ -939,11 +1168,18 @@ fn prepare_pod_request(
     secret_key: &NodeSecretKey,
     nonce: u64,
 ) -> UpdateRequest {
+    let proof = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        commodity,
+        service_id,
+        &secret_key.to_pk(),
+        NEXT_SESSION_NONCE.fetch_add(1, AtomicOrdering::Relaxed),
+    );
     prepare_update_request_node(
         UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
             commodity,  // units of data served
             service_id, // service 0 serving bandwidth
-            proofs: vec![DeliveryAcknowledgmentProof],
+            proofs: vec![proof],
             metadata: None,
         },
         secret_key,
@@ -952,6 +1188,30 @@ fn prepare_pod_request(
     )
 }
 
+/// Prepare a `DeliveryAcknowledgmentProof` signed by the client.
+fn prepare_delivery_acknowledgment(
+    client_secret_key: &ConsensusSecretKey,
+    commodity: u128,
+    service_id: u32,
+    node: &NodePublicKey,
+    session_nonce: u64,
+) -> DeliveryAcknowledgmentProof {
+    let digest = DeliveryAcknowledgmentMessage {
+        chain_id: CHAIN_ID,
+        service_id,
+        node: *node,
+        commodity,
+        session_nonce,
+    }
+    .to_digest();
+    DeliveryAcknowledgmentProof {
+        client: ClientPublicKey(client_secret_key.to_pk().0),
+        commodity,
+        session_nonce,
+        signature: ClientSignature(client_secret_key.sign(&digest).0),
+    }
+}
+
 /// Prepare an `UpdateRequest` for `UpdateMethod::SubmitDeliveryAcknowledgmentAggregation` signed
 /// with `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
 /// testing.
@@ -1035,6 +1295,146 @@ fn prepare_content_registry_update(
     )
 }
 
+/// Prepare an `UpdateRequest` for `UpdateMethod::AddService` signed with `AccountOwnerSecretKey`.
+/// Passing the private key around like this should only be done for testing.
+fn prepare_add_service_update(
+    service: Service,
+    service_id: ServiceId,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::AddService {
+            service,
+            service_id,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::RemoveService` signed with
+/// `AccountOwnerSecretKey`. Passing the private key around like this should only be done for
+/// testing.
+fn prepare_remove_service_update(
+    service_id: ServiceId,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::RemoveService { service_id },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::Withdraw` signed with `AccountOwnerSecretKey`.
+/// Passing the private key around like this should only be done for testing.
+fn prepare_withdraw_update(
+    amount: &HpUfixed<18>,
+    token: Tokens,
+    receiving_address: EthAddress,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::Withdraw {
+            amount: amount.clone(),
+            token,
+            receiving_address,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::SettleWithdrawal` signed with
+/// `AccountOwnerSecretKey`. The settlement is signed by each of the given bridge signers.
+/// Passing the private key around like this should only be done for testing.
+fn prepare_settle_withdrawal_update(
+    withdrawal_id: u64,
+    signers: &[AccountOwnerSecretKey],
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    let digest = WithdrawalSettlement {
+        chain_id: CHAIN_ID,
+        withdrawal_id,
+    }
+    .to_digest();
+    prepare_update_request_account(
+        UpdateMethod::SettleWithdrawal {
+            withdrawal_id,
+            signatures: signers
+                .iter()
+                .map(|signer| BridgeSignature {
+                    signer: signer.to_pk().into(),
+                    signature: signer.sign(&digest),
+                })
+                .collect(),
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Prepare an `UpdateRequest` for `UpdateMethod::Slash` signed with `AccountOwnerSecretKey`.
+/// Passing the private key around like this should only be done for testing.
+fn prepare_slash_update(
+    proof_of_misbehavior: ProofOfMisbehavior,
+    service_id: ServiceId,
+    node: &NodePublicKey,
+    secret_key: &AccountOwnerSecretKey,
+    nonce: u64,
+) -> UpdateRequest {
+    prepare_update_request_account(
+        UpdateMethod::Slash {
+            service_id,
+            node: *node,
+            proof_of_misbehavior,
+        },
+        secret_key,
+        nonce,
+    )
+}
+
+/// Create an attestation of the node with the given index for a parcel building on
+/// `last_executed`, and sign its broadcast message with `NodeSecretKey`.
+fn sign_attestation(
+    node_index: NodeIndex,
+    epoch: Epoch,
+    last_executed: [u8; 32],
+    batch_digest: [u8; 32],
+    secret_key: &NodeSecretKey,
+) -> AttestationEvidence {
+    let parcel = ParcelHeader {
+        num_transactions: 1,
+        batch_digest,
+        last_executed,
+    };
+    let attestation = CommitteeAttestation {
+        digest: parcel_digest(&parcel),
+        node_index,
+        epoch,
+    };
+    let mut payload = Vec::new();
+    ConsensusMessage::Attestation(attestation)
+        .encode(&mut payload)
+        .unwrap();
+    let message = Message {
+        origin: node_index,
+        signature: NodeSignature([0; 64]),
+        topic: Topic::Consensus,
+        timestamp: 0,
+        payload,
+    };
+    AttestationEvidence {
+        signature: secret_key.sign(&message.to_digest()),
+        payload: message.payload,
+        parcel,
+    }
+}
+
 /// Helper (async) function that submit a transaction to the application via `UpdateSocket`.
 /// Returns `Result<BlockExecutionResponse>`.
 async fn run_transaction(
@@ -1070,6 +1470,7 @@ fn paging_params(ignore_stake: bool, start: u32, limit: usize) -> PagingParams {
         ignore_stake,
         start,
         limit,
+        cursor: None,
     }
 }
 
@@ -1109,7 +1510,7 @@ fn add_to_committee(
         true,
     ));
     keystore.push(GenesisCommitteeKeystore {
-        _owner_secret_key: owner_secret_key,
+        owner_secret_key,
         _worker_secret_key: node_secret_key.clone(),
         node_secret_key,
         consensus_secret_key,
@@ -1810,7 +2211,13 @@ async fn test_submit_pod_reverts_account_key() {
     let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
         commodity: 2000,
         service_id: 1,
-        proofs: vec![DeliveryAcknowledgmentProof],
+        proofs: vec![prepare_delivery_acknowledgment(
+            &TEST_CLIENT_SECRET_KEY,
+            2000,
+            1,
+            &NodeSecretKey::generate().to_pk(),
+            0,
+        )],
         metadata: None,
     };
     let update = prepare_update_request_account(submit_pod, &secret_key, 1);
@@ -1829,7 +2236,13 @@ async fn test_submit_pod_reverts_node_does_not_exist() {
     let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
         commodity: 2000,
         service_id: 1,
-        proofs: vec![DeliveryAcknowledgmentProof],
+        proofs: vec![prepare_delivery_acknowledgment(
+            &TEST_CLIENT_SECRET_KEY,
+            2000,
+            1,
+            &node_secret_key.to_pk(),
+            0,
+        )],
         metadata: None,
     };
     let update = prepare_update_request_node(submit_pod, &node_secret_key, 1, None);
@@ -1863,7 +2276,13 @@ async fn test_submit_pod_reverts_insufficient_stake() {
     let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
         commodity: 2000,
         service_id: 1,
-        proofs: vec![DeliveryAcknowledgmentProof],
+        proofs: vec![prepare_delivery_acknowledgment(
+            &TEST_CLIENT_SECRET_KEY,
+            2000,
+            1,
+            &node_secret_key.to_pk(),
+            0,
+        )],
         metadata: None,
     };
     let update = prepare_update_request_node(submit_pod, &node_secret_key, 1, None);
@@ -1883,101 +2302,1419 @@ async fn test_submit_pod_reverts_invalid_service_id() {
 }
 
 #[tokio::test]
-async fn test_is_valid_node() {
-    let (update_socket, query_runner) = init_app(None);
-
-    let owner_secret_key = AccountOwnerSecretKey::generate();
-    let node_pub_key = NodeSecretKey::generate().to_pk();
-
-    // Stake minimum required amount.
-    let minimum_stake_amount = query_runner.get_staking_amount().into();
-    deposit_and_stake!(
-        &update_socket,
-        &owner_secret_key,
-        1,
-        &minimum_stake_amount,
-        &node_pub_key,
-        [0; 96].into()
-    );
+async fn test_submit_pod_debits_client_bandwidth_balance() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
 
-    // Make sure that this node is a valid node.
-    assert!(query_runner.is_valid_node(&node_pub_key));
+    let node_secret_key = &keystore[0].node_secret_key;
+    let node_index = get_node_index(&query_runner, &node_secret_key.to_pk());
 
-    // Generate new keys for a different node.
-    let owner_secret_key = AccountOwnerSecretKey::generate();
-    let node_pub_key = NodeSecretKey::generate().to_pk();
+    let update = prepare_pod_request(1000, 0, node_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
 
-    // Stake less than the minimum required amount.
-    let less_than_minimum_stake_amount = minimum_stake_amount / HpUfixed::<18>::from(2u16);
-    deposit_and_stake!(
-        &update_socket,
-        &owner_secret_key,
-        1,
-        &less_than_minimum_stake_amount,
-        &node_pub_key,
-        [1; 96].into()
+    assert_eq!(
+        get_account_balance(&query_runner, &TEST_CLIENT_ADDRESS),
+        TEST_CLIENT_BANDWIDTH_BALANCE as u128 - 1000
+    );
+    assert_eq!(
+        query_runner
+            .get_current_epoch_served(&node_index)
+            .unwrap()
+            .served[0],
+        1000
     );
-    // Make sure that this node is not a valid node.
-    assert!(!query_runner.is_valid_node(&node_pub_key));
 }
 
 #[tokio::test]
-async fn test_change_protocol_params() {
-    let governance_secret_key = AccountOwnerSecretKey::generate();
-    let governance_public_key = governance_secret_key.to_pk();
-
-    let mut genesis = test_genesis();
-    genesis.governance_address = governance_public_key.into();
+async fn test_submit_pod_reverts_invalid_acknowledgment() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
 
-    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
+    let node_secret_key = &keystore[0].node_secret_key;
+    let submit_pod = |commodity, proofs| UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+        commodity,
+        service_id: 0,
+        proofs,
+        metadata: None,
+    };
 
-    let param = ProtocolParams::LockTime;
-    let new_value = 5;
+    // The node claims more than the client acknowledged.
+    let mut proof = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        100,
+        0,
+        &node_secret_key.to_pk(),
+        1,
+    );
+    proof.commodity = 1000;
     let update =
-        prepare_change_protocol_param_request(&param, &new_value, &governance_secret_key, 1);
-    run_update!(update, &update_socket);
-    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value);
-
-    let new_value = 8;
+        prepare_update_request_node(submit_pod(1000, vec![proof]), node_secret_key, 1, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The acknowledgment was given to another node.
+    let proof = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        1000,
+        0,
+        &keystore[1].node_secret_key.to_pk(),
+        1,
+    );
     let update =
-        prepare_change_protocol_param_request(&param, &new_value, &governance_secret_key, 2);
-    run_update!(update, &update_socket);
-    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value);
-
-    // Make sure that another private key cannot change protocol parameters.
-    let some_secret_key = AccountOwnerSecretKey::generate();
-    let minimum_stake_amount = query_runner.get_staking_amount().into();
-    deposit!(&update_socket, &some_secret_key, 1, &minimum_stake_amount);
-
-    let malicious_value = 1;
+        prepare_update_request_node(submit_pod(1000, vec![proof]), node_secret_key, 2, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The acknowledgment was given on another network.
+    let digest = DeliveryAcknowledgmentMessage {
+        chain_id: CHAIN_ID + 1,
+        service_id: 0,
+        node: node_secret_key.to_pk(),
+        commodity: 1000,
+        session_nonce: 1,
+    }
+    .to_digest();
+    let proof = DeliveryAcknowledgmentProof {
+        client: ClientPublicKey(TEST_CLIENT_SECRET_KEY.to_pk().0),
+        commodity: 1000,
+        session_nonce: 1,
+        signature: ClientSignature(TEST_CLIENT_SECRET_KEY.sign(&digest).0),
+    };
     let update =
-        prepare_change_protocol_param_request(&param, &malicious_value, &some_secret_key, 2);
-    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyGovernance);
-    // Lock time should still be 8.
-    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value)
+        prepare_update_request_node(submit_pod(1000, vec![proof]), node_secret_key, 3, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The client is not known to the network.
+    let proof = prepare_delivery_acknowledgment(
+        &ConsensusSecretKey::generate(),
+        1000,
+        0,
+        &node_secret_key.to_pk(),
+        1,
+    );
+    let update =
+        prepare_update_request_node(submit_pod(1000, vec![proof]), node_secret_key, 4, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::UnknownClient);
+
+    // Nothing was served and the client did not pay anything.
+    let node_index = get_node_index(&query_runner, &node_secret_key.to_pk());
+    assert!(
+        query_runner
+            .get_current_epoch_served(&node_index)
+            .map_or(true, |node_served| node_served
+                .served
+                .iter()
+                .all(|s| *s == 0))
+    );
+    assert_eq!(
+        get_account_balance(&query_runner, &TEST_CLIENT_ADDRESS),
+        TEST_CLIENT_BANDWIDTH_BALANCE as u128
+    );
 }
 
 #[tokio::test]
-async fn test_change_protocol_params_reverts_not_account_key() {
+async fn test_submit_pod_reverts_replayed_acknowledgment() {
     let committee_size = 4;
     let (committee, keystore) = create_genesis_committee(committee_size);
     let (update_socket, query_runner) = test_init_app(committee);
 
-    let param = ProtocolParams::LockTime;
-    let initial_value = query_runner.get_protocol_param(&param).unwrap();
-    let new_value = initial_value + 1;
-
-    let change_method = UpdateMethod::ChangeProtocolParam {
-        param: param.clone(),
-        value: new_value,
+    let node_secret_key = &keystore[0].node_secret_key;
+    let proof = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        1000,
+        0,
+        &node_secret_key.to_pk(),
+        5,
+    );
+    let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+        commodity: 1000,
+        service_id: 0,
+        proofs: vec![proof.clone()],
+        metadata: None,
     };
+    let update = prepare_update_request_node(submit_pod.clone(), node_secret_key, 1, None);
+    expect_tx_success!(update, &update_socket);
 
-    // Assert that reverts for Node Key
-    let update =
-        prepare_update_request_node(change_method.clone(), &keystore[0].node_secret_key, 1, None);
-    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyAccountOwner);
-    assert_eq!(
-        query_runner.get_protocol_param(&param).unwrap(),
+    // The same acknowledgment can not be paid out twice.
+    let update = prepare_update_request_node(submit_pod, node_secret_key, 2, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidSessionNonce);
+
+    // Neither can it be repeated within one batch, the repetition is dropped.
+    let proof = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        1000,
+        0,
+        &node_secret_key.to_pk(),
+        6,
+    );
+    let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+        commodity: 2000,
+        service_id: 0,
+        proofs: vec![proof.clone(), proof],
+        metadata: None,
+    };
+    let update = prepare_update_request_node(submit_pod, node_secret_key, 3, None);
+    expect_tx_success!(update, &update_socket);
+
+    assert_eq!(
+        get_account_balance(&query_runner, &TEST_CLIENT_ADDRESS),
+        TEST_CLIENT_BANDWIDTH_BALANCE as u128 - 2000
+    );
+}
+
+#[tokio::test]
+async fn test_submit_pod_drops_invalid_acknowledgments() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_secret_key = &keystore[0].node_secret_key;
+    let node_index = get_node_index(&query_runner, &node_secret_key.to_pk());
+
+    let valid = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        1000,
+        0,
+        &node_secret_key.to_pk(),
+        1,
+    );
+    // The node inflated the commodity the client acknowledged.
+    let mut inflated = prepare_delivery_acknowledgment(
+        &TEST_CLIENT_SECRET_KEY,
+        100,
+        0,
+        &node_secret_key.to_pk(),
+        2,
+    );
+    inflated.commodity = 5000;
+    // The client is not known to the network.
+    let unknown = prepare_delivery_acknowledgment(
+        &ConsensusSecretKey::generate(),
+        1000,
+        0,
+        &node_secret_key.to_pk(),
+        1,
+    );
+    let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+        commodity: 7000,
+        service_id: 0,
+        proofs: vec![inflated, valid, unknown],
+        metadata: None,
+    };
+    let update = prepare_update_request_node(submit_pod, node_secret_key, 1, None);
+    expect_tx_success!(update, &update_socket);
+
+    // Only the valid acknowledgment is paid out.
+    assert_eq!(
+        query_runner
+            .get_current_epoch_served(&node_index)
+            .unwrap()
+            .served[0],
+        1000
+    );
+    assert_eq!(
+        get_account_balance(&query_runner, &TEST_CLIENT_ADDRESS),
+        TEST_CLIENT_BANDWIDTH_BALANCE as u128 - 1000
+    );
+}
+
+#[tokio::test]
+async fn test_submit_pod_reverts_insufficient_client_balance() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+
+    let client_secret_key = ConsensusSecretKey::generate();
+    let client_address: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let mut genesis = test_genesis();
+    genesis.node_info = committee;
+    genesis
+        .client
+        .insert(ClientPublicKey(client_secret_key.to_pk().0), client_address);
+    genesis.account.push(GenesisAccount {
+        public_key: client_address,
+        flk_balance: HpUfixed::<18>::zero(),
+        stables_balance: 0,
+        bandwidth_balance: 100,
+    });
+    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
+
+    let node_secret_key = &keystore[0].node_secret_key;
+    let proof =
+        prepare_delivery_acknowledgment(&client_secret_key, 1000, 0, &node_secret_key.to_pk(), 1);
+    let submit_pod = UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
+        commodity: 1000,
+        service_id: 0,
+        proofs: vec![proof],
+        metadata: None,
+    };
+    let update = prepare_update_request_node(submit_pod, node_secret_key, 1, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
+
+    assert_eq!(get_account_balance(&query_runner, &client_address), 100);
+}
+
+#[tokio::test]
+async fn test_add_service_works() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let deposit_amount: HpUfixed<18> = 5_000_u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &deposit_amount);
+
+    let service = Service {
+        owner,
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service.clone(), 2, &owner_secret_key, 2);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
+    assert_eq!(
+        result.txn_receipts[0].event,
+        Some(Event::service_added(2, owner, CommodityTypes::Bandwidth))
+    );
+
+    // The service is registered and the bond is taken from the owner's balance.
+    assert_eq!(query_runner.get_service_info(&2), Some(service));
+    let bond: HpUfixed<18> = 1_000_u64.into();
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        deposit_amount - bond
+    );
+
+    // Nodes can now submit delivery acknowledgments for the new service.
+    let update = prepare_pod_request(1000, 2, &keystore[0].node_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+}
+
+#[tokio::test]
+async fn test_add_service_reverts_node_key() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, _query_runner) = test_init_app(committee);
+
+    let add_service = UpdateMethod::AddService {
+        service: Service {
+            owner: AccountOwnerSecretKey::generate().to_pk().into(),
+            commodity_type: CommodityTypes::Bandwidth,
+            slashing: vec![],
+        },
+        service_id: 2,
+    };
+    let update = prepare_update_request_node(add_service, &keystore[0].node_secret_key, 1, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyAccountOwner);
+}
+
+#[tokio::test]
+async fn test_add_service_reverts_not_service_owner() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &owner_secret_key, 1, &5_000_u64.into());
+
+    // Registering a service on behalf of another account is not allowed.
+    let service = Service {
+        owner: AccountOwnerSecretKey::generate().to_pk().into(),
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 2, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::NotServiceOwner);
+}
+
+#[tokio::test]
+async fn test_add_service_reverts_service_already_exists() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &owner_secret_key, 1, &5_000_u64.into());
+
+    // Service 0 is registered at genesis and owned by a different account.
+    let service = Service {
+        owner: owner_secret_key.to_pk().into(),
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 0, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::ServiceAlreadyExists);
+}
+
+#[tokio::test]
+async fn test_add_service_reverts_invalid_commodity_type() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &owner_secret_key, 1, &5_000_u64.into());
+
+    // There is no price for GPU in the test genesis.
+    let service = Service {
+        owner: owner_secret_key.to_pk().into(),
+        commodity_type: CommodityTypes::Gpu,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 2, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidCommodityType);
+}
+
+#[tokio::test]
+async fn test_add_service_reverts_insufficient_balance() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &owner_secret_key, 1, &500_u64.into());
+
+    let service = Service {
+        owner: owner_secret_key.to_pk().into(),
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 2, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
+}
+
+#[tokio::test]
+async fn test_add_service_update_by_owner() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let deposit_amount: HpUfixed<18> = 5_000_u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &deposit_amount);
+
+    let service = Service {
+        owner,
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 2, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+
+    // Updating an existing service does not take another bond.
+    let service = Service {
+        owner,
+        commodity_type: CommodityTypes::Compute,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service.clone(), 2, &owner_secret_key, 3);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
+    assert_eq!(
+        result.txn_receipts[0].event,
+        Some(Event::service_updated(2, owner, CommodityTypes::Compute))
+    );
+
+    assert_eq!(query_runner.get_service_info(&2), Some(service));
+    let bond: HpUfixed<18> = 1_000_u64.into();
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        deposit_amount - bond
+    );
+}
+
+#[tokio::test]
+async fn test_remove_service_works() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let deposit_amount: HpUfixed<18> = 5_000_u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &deposit_amount);
+
+    let service = Service {
+        owner,
+        commodity_type: CommodityTypes::Bandwidth,
+        slashing: vec![],
+    };
+    let update = prepare_add_service_update(service, 2, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+
+    let update = prepare_remove_service_update(2, &owner_secret_key, 3);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
+    assert_eq!(
+        result.txn_receipts[0].event,
+        Some(Event::service_removed(2, owner))
+    );
+
+    // The service is gone and the bond was returned to the owner.
+    assert_eq!(query_runner.get_service_info(&2), None);
+    assert_eq!(get_flk_balance(&query_runner, &owner), deposit_amount);
+
+    // Nodes can no longer submit delivery acknowledgments for the removed service.
+    let update = prepare_pod_request(1000, 2, &keystore[0].node_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidServiceId);
+}
+
+#[tokio::test]
+async fn test_remove_service_reverts_non_existing_service() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_remove_service_update(1069, &owner_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::NonExistingService);
+}
+
+#[tokio::test]
+async fn test_remove_service_reverts_not_service_owner() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    // Service 0 is registered at genesis and owned by a different account.
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_remove_service_update(0, &owner_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::NotServiceOwner);
+}
+
+#[tokio::test]
+async fn test_slash_conflicting_attestations_works() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    let staked = get_staked(&query_runner, &node_pub_key);
+
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // 10% of the stake is taken and sent to the protocol fund.
+    let penalty: HpUfixed<18> = &staked * &HpUfixed::<18>::from(10u64) / HpUfixed::from(100u64);
+    assert_eq!(get_staked(&query_runner, &node_pub_key), &staked - &penalty);
+    let protocol_address =
+        EthAddress::from_str("0x2a8cf657769c264b0c7f88e3a716afdeaec1c318").unwrap();
+    assert_eq!(get_flk_balance(&query_runner, &protocol_address), penalty);
+
+    // The slash is recorded and the node is jailed.
+    let slashes = query_runner.get_slashes(&node_index).unwrap();
+    assert_eq!(slashes.len(), 1);
+    assert_eq!(slashes[0].epoch, 0);
+    assert_eq!(slashes[0].service_id, 0);
+    assert_eq!(
+        slashes[0].misbehavior,
+        MisbehaviorType::ConflictingAttestations
+    );
+    assert_eq!(slashes[0].amount, penalty);
+    assert_eq!(slashes[0].jailed_until, 3);
+    assert_eq!(query_runner.get_jailed_until(&node_index), Some(3));
+
+    // The node is only removed from the committee when the epoch changes.
+    assert!(
+        query_runner
+            .get_committee_members_by_index()
+            .contains(&node_index)
+    );
+}
+
+#[tokio::test]
+async fn test_slash_reduces_delegations() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    let staked = get_staked(&query_runner, &node_pub_key);
+
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    let delegated: HpUfixed<18> = 1_000u64.into();
+    deposit!(&update_socket, &delegator_secret_key, 1, &delegated);
+    let update = prepare_update_request_account(
+        UpdateMethod::Delegate {
+            node: node_pub_key,
+            amount: delegated.clone(),
+        },
+        &delegator_secret_key,
+        2,
+    );
+    expect_tx_success!(update, &update_socket);
+
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // 10% of both the stake of the owner and the delegated stake is taken.
+    let ten_percent = |amount: &HpUfixed<18>| -> HpUfixed<18> {
+        amount * &HpUfixed::<18>::from(10u64) / HpUfixed::from(100u64)
+    };
+    let penalty = ten_percent(&staked);
+    let delegation_penalty = ten_percent(&delegated);
+    assert_eq!(get_staked(&query_runner, &node_pub_key), &staked - &penalty);
+    assert_eq!(
+        query_runner
+            .get_delegation(&node_index, &delegator)
+            .unwrap()
+            .staked,
+        &delegated - &delegation_penalty
+    );
+    let total_penalty = &penalty + &delegation_penalty;
+    let protocol_address =
+        EthAddress::from_str("0x2a8cf657769c264b0c7f88e3a716afdeaec1c318").unwrap();
+    assert_eq!(
+        get_flk_balance(&query_runner, &protocol_address),
+        total_penalty
+    );
+    assert_eq!(
+        query_runner.get_slashes(&node_index).unwrap()[0].amount,
+        total_penalty
+    );
+}
+
+#[tokio::test]
+async fn test_slash_reverts_already_slashed() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    let first = sign_attestation(
+        node_index,
+        0,
+        [0; 32],
+        [1; 32],
+        &keystore[0].node_secret_key,
+    );
+    let second = sign_attestation(
+        node_index,
+        0,
+        [0; 32],
+        [2; 32],
+        &keystore[0].node_secret_key,
+    );
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: first.clone(),
+        second: second.clone(),
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // Reporting the same offense again, even with the attestations swapped, is rejected.
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: second,
+        second: first,
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::AlreadySlashed);
+}
+
+#[tokio::test]
+async fn test_slash_reverts_invalid_proof() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+
+    // Attesting to the same parcel twice is not a misbehavior.
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The attestations have to be signed by the reported node.
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[1].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[1].node_secret_key,
+        ),
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // Attestations for parcels building on different parents do not conflict.
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [3; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 3);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The attestations have to be made by the reported node.
+    let other_index = get_node_index(&query_runner, &keystore[1].node_secret_key.to_pk());
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            other_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            other_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 4);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+}
+
+#[tokio::test]
+async fn test_slash_reverts_misbehavior_not_slashable() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+
+    // Service 1 does not accept any kind of misbehavior.
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let update = prepare_slash_update(proof, 1, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_revert!(
+        update,
+        &update_socket,
+        ExecutionError::MisbehaviorNotSlashable
+    );
+}
+
+#[tokio::test]
+async fn test_slash_reverts_non_existing_service() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, _query_runner) = test_init_app(committee);
+
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(0, 0, [0; 32], [1; 32], &keystore[0].node_secret_key),
+        second: sign_attestation(0, 0, [0; 32], [2; 32], &keystore[0].node_secret_key),
+    };
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_slash_update(
+        proof,
+        1069,
+        &keystore[0].node_secret_key.to_pk(),
+        &reporter_secret_key,
+        1,
+    );
+    expect_tx_revert!(update, &update_socket, ExecutionError::NonExistingService);
+}
+
+#[tokio::test]
+async fn test_jailed_node_is_excluded_from_committee() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let node_pub_key = keystore[0].node_secret_key.to_pk();
+    let node_index = get_node_index(&query_runner, &node_pub_key);
+
+    let proof = ProofOfMisbehavior::ConflictingAttestations {
+        first: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [1; 32],
+            &keystore[0].node_secret_key,
+        ),
+        second: sign_attestation(
+            node_index,
+            0,
+            [0; 32],
+            [2; 32],
+            &keystore[0].node_secret_key,
+        ),
+    };
+    let reporter_secret_key = AccountOwnerSecretKey::generate();
+    let update = prepare_slash_update(proof, 0, &node_pub_key, &reporter_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // The node stays on the committee for the rest of the epoch, so the epoch change still takes
+    // 3 of the 4 members.
+    assert!(
+        query_runner
+            .get_committee_members_by_index()
+            .contains(&node_index)
+    );
+    for node in &keystore[1..3] {
+        let nonce = get_node_nonce(&query_runner, &node.node_secret_key.to_pk()) + 1;
+        let req = prepare_change_epoch_request(0, &node.node_secret_key, nonce);
+        assert!(!run_update!(req, &update_socket).change_epoch);
+    }
+    let nonce = get_node_nonce(&query_runner, &keystore[3].node_secret_key.to_pk()) + 1;
+    let req = prepare_change_epoch_request(0, &keystore[3].node_secret_key, nonce);
+    assert!(run_update!(req, &update_socket).change_epoch);
+
+    // The node is jailed until epoch 3, so it is not part of the committee of epoch 1.
+    assert_eq!(query_runner.get_current_epoch(), 1);
+    assert!(
+        !query_runner
+            .get_committee_members_by_index()
+            .contains(&node_index)
+    );
+    assert_eq!(query_runner.get_jailed_until(&node_index), Some(3));
+}
+
+#[tokio::test]
+async fn test_withdraw_works() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let amount: HpUfixed<18> = 400_u64.into();
+    let update = prepare_withdraw_update(&amount, Tokens::FLK, receiver, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), 600_u64.into());
+    assert_eq!(
+        query_runner.get_pending_withdrawals(0, 10),
+        vec![PendingWithdrawal {
+            id: 0,
+            receiver,
+            token: Tokens::FLK,
+            amount,
+            epoch: 0,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_stables_queues_the_debited_amount() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let mut genesis = test_genesis();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: HpUfixed::<18>::zero(),
+        stables_balance: 100,
+        bandwidth_balance: 0,
+    });
+    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
+
+    // Stables only have 6 decimals, so the rest of the amount is dropped.
+    let amount = HpUfixed::<18>::from(U256::from(1_500_000_000_000_000_001u128));
+    let update = prepare_withdraw_update(&amount, Tokens::USDC, receiver, &owner_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    assert_eq!(
+        get_stables_balance(&query_runner, &owner),
+        HpUfixed::<6>::from(U256::from(98_500_000u64))
+    );
+    assert_eq!(
+        query_runner.get_pending_withdrawals(0, 10),
+        vec![PendingWithdrawal {
+            id: 0,
+            receiver,
+            token: Tokens::USDC,
+            amount: HpUfixed::<18>::from(U256::from(1_500_000_000_000_000_000u128)),
+            epoch: 0,
+        }]
+    );
+
+    // An amount that is zero in the precision of the token can not be withdrawn.
+    let amount = HpUfixed::<18>::from(U256::from(1_000_000_000_000u64 - 1));
+    let update = prepare_withdraw_update(&amount, Tokens::USDC, receiver, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::ZeroAmount);
+    let update = prepare_withdraw_update(
+        &HpUfixed::zero(),
+        Tokens::FLK,
+        receiver,
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_revert!(update, &update_socket, ExecutionError::ZeroAmount);
+    assert_eq!(query_runner.get_pending_withdrawals(0, 10).len(), 1);
+}
+
+#[tokio::test]
+async fn test_withdraw_assigns_sequential_ids() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let update = prepare_withdraw_update(&100_u64.into(), Tokens::FLK, owner, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+    let update = prepare_withdraw_update(&200_u64.into(), Tokens::FLK, owner, &owner_secret_key, 3);
+    expect_tx_success!(update, &update_socket);
+
+    let withdrawals = query_runner.get_pending_withdrawals(0, 10);
+    assert_eq!(withdrawals.len(), 2);
+    assert_eq!(withdrawals[0].id, 0);
+    assert_eq!(withdrawals[0].amount, 100_u64.into());
+    assert_eq!(withdrawals[1].id, 1);
+    assert_eq!(withdrawals[1].amount, 200_u64.into());
+
+    // The pending withdrawals are paged by id.
+    assert_eq!(query_runner.get_pending_withdrawals(0, 1), withdrawals[..1]);
+    assert_eq!(
+        query_runner.get_pending_withdrawals(1, 10),
+        withdrawals[1..]
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_reverts_insufficient_balance() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let update =
+        prepare_withdraw_update(&1_001_u64.into(), Tokens::FLK, owner, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
+
+    // The account has no stables to withdraw.
+    let update = prepare_withdraw_update(&1_u64.into(), Tokens::USDC, owner, &owner_secret_key, 3);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
+
+    // Nothing was withdrawn.
+    assert_eq!(get_flk_balance(&query_runner, &owner), 1_000_u64.into());
+    assert!(query_runner.get_pending_withdrawals(0, 10).is_empty());
+}
+
+#[tokio::test]
+async fn test_withdraw_reverts_only_account_owner() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let update = prepare_update_request_node(
+        UpdateMethod::Withdraw {
+            amount: 1_u64.into(),
+            token: Tokens::FLK,
+            receiving_address: AccountOwnerSecretKey::generate().to_pk().into(),
+        },
+        &keystore[0].node_secret_key,
+        1,
+        None,
+    );
+    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyAccountOwner);
+
+    assert!(query_runner.get_pending_withdrawals(0, 10).is_empty());
+}
+
+#[tokio::test]
+async fn test_settle_withdrawal_works() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let relayer_secret_key = AccountOwnerSecretKey::generate();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let update =
+        prepare_withdraw_update(&300_u64.into(), Tokens::FLK, receiver, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+    let update =
+        prepare_withdraw_update(&200_u64.into(), Tokens::FLK, receiver, &owner_secret_key, 3);
+    expect_tx_success!(update, &update_socket);
+
+    let signers = &BRIDGE_SIGNER_KEYS[..BRIDGE_THRESHOLD as usize];
+    let update = prepare_settle_withdrawal_update(0, signers, &relayer_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // Only the settled withdrawal is removed.
+    let withdrawals = query_runner.get_pending_withdrawals(0, 10);
+    assert_eq!(withdrawals.len(), 1);
+    assert_eq!(withdrawals[0].id, 1);
+    assert_eq!(query_runner.get_pending_withdrawal(&0), None);
+
+    // A withdrawal can only be settled once.
+    let update = prepare_settle_withdrawal_update(0, signers, &relayer_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::WithdrawalNotPending);
+}
+
+#[tokio::test]
+async fn test_settle_withdrawal_reverts_invalid_proof() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let relayer_secret_key = AccountOwnerSecretKey::generate();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let update = prepare_withdraw_update(
+        &300_u64.into(),
+        Tokens::FLK,
+        owner_secret_key.to_pk().into(),
+        &owner_secret_key,
+        2,
+    );
+    expect_tx_success!(update, &update_socket);
+
+    // Not enough bridge signers signed the settlement.
+    let signers = &BRIDGE_SIGNER_KEYS[..BRIDGE_THRESHOLD as usize - 1];
+    let update = prepare_settle_withdrawal_update(0, signers, &relayer_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // Signatures of accounts that are not bridge signers are not counted.
+    let signers = [
+        AccountOwnerSecretKey::generate(),
+        AccountOwnerSecretKey::generate(),
+    ];
+    let update = prepare_settle_withdrawal_update(0, &signers, &relayer_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    assert_eq!(query_runner.get_pending_withdrawals(0, 10).len(), 1);
+}
+
+#[tokio::test]
+async fn test_mock_relayer_settles_pending_withdrawals() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let receiver: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &1_000_u64.into());
+
+    let update =
+        prepare_withdraw_update(&300_u64.into(), Tokens::FLK, receiver, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+
+    let mut relayer = MockRelayer::new();
+    let settled = relayer.settle_pending(&query_runner);
+    assert_eq!(settled.len(), 1);
+    assert!(relayer.is_settled(0));
+    assert_eq!(relayer.l2_balance(&receiver, Tokens::FLK), 300_u64.into());
+
+    // Withdrawals are only settled once.
+    assert!(relayer.settle_pending(&query_runner).is_empty());
+
+    let update =
+        prepare_withdraw_update(&200_u64.into(), Tokens::FLK, receiver, &owner_secret_key, 3);
+    expect_tx_success!(update, &update_socket);
+
+    let settled = relayer.settle_pending(&query_runner);
+    assert_eq!(settled.len(), 1);
+    assert_eq!(settled[0].id, 1);
+    assert_eq!(relayer.l2_balance(&receiver, Tokens::FLK), 500_u64.into());
+}
+
+#[tokio::test]
+async fn test_is_valid_node() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+
+    // Stake minimum required amount.
+    let minimum_stake_amount = query_runner.get_staking_amount().into();
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &minimum_stake_amount,
+        &node_pub_key,
+        [0; 96].into()
+    );
+
+    // Make sure that this node is a valid node.
+    assert!(query_runner.is_valid_node(&node_pub_key));
+
+    // Generate new keys for a different node.
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+
+    // Stake less than the minimum required amount.
+    let less_than_minimum_stake_amount = minimum_stake_amount / HpUfixed::<18>::from(2u16);
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &less_than_minimum_stake_amount,
+        &node_pub_key,
+        [1; 96].into()
+    );
+    // Make sure that this node is not a valid node.
+    assert!(!query_runner.is_valid_node(&node_pub_key));
+}
+
+#[tokio::test]
+async fn test_change_protocol_params() {
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+    let governance_public_key = governance_secret_key.to_pk();
+
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_public_key.into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
+
+    let param = ProtocolParams::LockTime;
+    let new_value = 5;
+    let update =
+        prepare_change_protocol_param_request(&param, &new_value, &governance_secret_key, 1);
+    run_update!(update, &update_socket);
+    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value);
+
+    let new_value = 8;
+    let update =
+        prepare_change_protocol_param_request(&param, &new_value, &governance_secret_key, 2);
+    run_update!(update, &update_socket);
+    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value);
+
+    // Make sure that another private key cannot change protocol parameters.
+    let some_secret_key = AccountOwnerSecretKey::generate();
+    let minimum_stake_amount = query_runner.get_staking_amount().into();
+    deposit!(&update_socket, &some_secret_key, 1, &minimum_stake_amount);
+
+    let malicious_value = 1;
+    let update =
+        prepare_change_protocol_param_request(&param, &malicious_value, &some_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyGovernance);
+    // Lock time should still be 8.
+    assert_eq!(query_runner.get_protocol_param(&param).unwrap(), new_value)
+}
+
+#[tokio::test]
+async fn test_governance_proposals() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let submit = |action, secret_key, nonce| {
+        prepare_update_request_account(UpdateMethod::SubmitProposal { action }, secret_key, nonce)
+    };
+    let vote = |proposal_id, approve, secret_key, nonce| {
+        prepare_update_request_account(
+            UpdateMethod::VoteOnProposal {
+                proposal_id,
+                approve,
+            },
+            secret_key,
+            nonce,
+        )
+    };
+    let execute = |proposal_id, secret_key, nonce| {
+        prepare_update_request_account(
+            UpdateMethod::ExecuteProposal { proposal_id },
+            secret_key,
+            nonce,
+        )
+    };
+    let change_lock_time = ProposalAction::ChangeProtocolParam {
+        param: ProtocolParams::LockTime,
+        value: 10,
+    };
+
+    // Accounts without stake can not submit proposals.
+    let voter_secret_key = AccountOwnerSecretKey::generate();
+    expect_tx_revert!(
+        submit(change_lock_time.clone(), &voter_secret_key, 1),
+        &update_socket,
+        ExecutionError::NoVotingWeight
+    );
+
+    // The voter delegates 10_000 of the 14_000 staked FLK, the owners of the genesis nodes stake
+    // 1_000 each.
+    deposit!(&update_socket, &voter_secret_key, 2, &10_000u64.into());
+    expect_tx_success!(
+        prepare_update_request_account(
+            UpdateMethod::Delegate {
+                node: keystore[1].node_secret_key.to_pk(),
+                amount: 10_000u64.into(),
+            },
+            &voter_secret_key,
+            3,
+        ),
+        &update_socket
+    );
+    let minority_secret_key = &keystore[0].owner_secret_key;
+
+    expect_tx_revert!(
+        submit(
+            ProposalAction::ChangeCommitteeSize { committee_size: 0 },
+            &voter_secret_key,
+            4
+        ),
+        &update_socket,
+        ExecutionError::InvalidProposal
+    );
+    // A proposal can not replace a registered service.
+    expect_tx_revert!(
+        submit(
+            ProposalAction::AddService {
+                service: query_runner.get_service_info(&0).unwrap(),
+                service_id: 0,
+            },
+            &voter_secret_key,
+            5
+        ),
+        &update_socket,
+        ExecutionError::ServiceAlreadyExists
+    );
+    expect_tx_success!(
+        submit(change_lock_time.clone(), &voter_secret_key, 6),
+        &update_socket,
+        ExecutionData::UInt(0)
+    );
+    expect_tx_success!(
+        submit(
+            ProposalAction::ChangeCommitteeSize { committee_size: 3 },
+            &voter_secret_key,
+            7
+        ),
+        &update_socket,
+        ExecutionData::UInt(1)
+    );
+    // Voted down by the majority.
+    expect_tx_success!(
+        submit(
+            ProposalAction::RemoveService { service_id: 0 },
+            minority_secret_key,
+            1
+        ),
+        &update_socket,
+        ExecutionData::UInt(2)
+    );
+    // Does not reach the quorum.
+    expect_tx_success!(
+        submit(
+            ProposalAction::RemoveService { service_id: 1 },
+            minority_secret_key,
+            2
+        ),
+        &update_socket,
+        ExecutionData::UInt(3)
+    );
+    // The minority already has the maximum number of proposals open.
+    expect_tx_revert!(
+        submit(
+            ProposalAction::RemoveService { service_id: 1 },
+            minority_secret_key,
+            3
+        ),
+        &update_socket,
+        ExecutionError::TooManyOpenProposals
+    );
+
+    expect_tx_success!(vote(0, true, &voter_secret_key, 8), &update_socket);
+    expect_tx_revert!(
+        vote(0, true, &voter_secret_key, 9),
+        &update_socket,
+        ExecutionError::AlreadyVoted
+    );
+    expect_tx_success!(vote(0, false, minority_secret_key, 4), &update_socket);
+    expect_tx_success!(vote(1, true, &voter_secret_key, 10), &update_socket);
+    expect_tx_success!(vote(2, true, minority_secret_key, 5), &update_socket);
+    expect_tx_success!(vote(2, false, &voter_secret_key, 11), &update_socket);
+    expect_tx_success!(vote(3, true, minority_secret_key, 6), &update_socket);
+    expect_tx_revert!(
+        vote(4, true, &voter_secret_key, 12),
+        &update_socket,
+        ExecutionError::ProposalDoesNotExist
+    );
+    assert_eq!(
+        query_runner.get_proposal_vote(&0, &voter_secret_key.to_pk().into()),
+        Some(ProposalVote {
+            approve: true,
+            weight: 10_000u64.into(),
+        })
+    );
+    assert_eq!(
+        query_runner.get_proposal(&0).unwrap().total_weight,
+        14_000u64.into()
+    );
+
+    // The weights are taken when the proposal is submitted, stake added afterwards does not
+    // count.
+    let late_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &late_secret_key, 1, &1_000u64.into());
+    expect_tx_success!(
+        prepare_update_request_account(
+            UpdateMethod::Delegate {
+                node: keystore[2].node_secret_key.to_pk(),
+                amount: 1_000u64.into(),
+            },
+            &late_secret_key,
+            2,
+        ),
+        &update_socket
+    );
+    expect_tx_revert!(
+        vote(0, true, &late_secret_key, 3),
+        &update_socket,
+        ExecutionError::NoVotingWeight
+    );
+
+    // The vote is open for 2 epochs, the proposals are tallied upon the change to epoch 2.
+    simple_epoch_change!(&update_socket, &keystore, &query_runner, 0);
+    assert_eq!(
+        query_runner
+            .get_proposal_ids_by_status(&ProposalStatus::Voting)
+            .len(),
+        4
+    );
+    simple_epoch_change!(&update_socket, &keystore, &query_runner, 1);
+    assert_eq!(
+        query_runner.get_proposal_ids_by_status(&ProposalStatus::Passed),
+        BTreeSet::from([0, 1])
+    );
+    assert_eq!(
+        query_runner.get_proposal_ids_by_status(&ProposalStatus::Rejected),
+        BTreeSet::from([2, 3])
+    );
+    expect_tx_revert!(
+        vote(0, true, minority_secret_key, 7),
+        &update_socket,
+        ExecutionError::VotingClosed
+    );
+
+    // The passed proposals can be executed after the timelock of 1 epoch.
+    expect_tx_revert!(
+        execute(0, minority_secret_key, 8),
+        &update_socket,
+        ExecutionError::ProposalNotExecutable
+    );
+    simple_epoch_change!(&update_socket, &keystore, &query_runner, 2);
+    expect_tx_success!(execute(0, minority_secret_key, 9), &update_socket);
+    assert_eq!(
+        query_runner.get_protocol_param(&ProtocolParams::LockTime),
+        Some(10)
+    );
+    expect_tx_revert!(
+        execute(2, minority_secret_key, 10),
+        &update_socket,
+        ExecutionError::ProposalNotExecutable
+    );
+
+    // The proposal that was not executed by hand is executed when the epoch ends.
+    assert_eq!(
+        query_runner.get_protocol_param(&ProtocolParams::CommitteeSize),
+        Some(10)
+    );
+    simple_epoch_change!(&update_socket, &keystore, &query_runner, 3);
+    assert_eq!(
+        query_runner.get_protocol_param(&ProtocolParams::CommitteeSize),
+        Some(3)
+    );
+    assert_eq!(
+        query_runner
+            .get_proposals(Some(ProposalStatus::Executed))
+            .into_iter()
+            .map(|proposal| proposal.id)
+            .collect::<Vec<_>>(),
+        vec![0, 1]
+    );
+    assert_eq!(query_runner.get_proposals(None).len(), 4);
+    assert!(query_runner.get_service_info(&0).is_some());
+    assert!(query_runner.get_service_info(&1).is_some());
+}
+
+#[tokio::test]
+async fn test_change_protocol_params_reverts_not_account_key() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let param = ProtocolParams::LockTime;
+    let initial_value = query_runner.get_protocol_param(&param).unwrap();
+    let new_value = initial_value + 1;
+
+    let change_method = UpdateMethod::ChangeProtocolParam {
+        param: param.clone(),
+        value: new_value,
+    };
+
+    // Assert that reverts for Node Key
+    let update =
+        prepare_update_request_node(change_method.clone(), &keystore[0].node_secret_key, 1, None);
+    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyAccountOwner);
+    assert_eq!(
+        query_runner.get_protocol_param(&param).unwrap(),
         initial_value
     );
 
@@ -2182,6 +3919,101 @@ async fn test_distribute_rewards() {
     }
 }
 
+#[tokio::test]
+async fn test_distribute_rewards_with_delegations() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+
+    let node_part = 80;
+    let (update_socket, query_runner) = init_app_with_params(
+        Params {
+            epoch_time: None,
+            max_inflation: Some(10),
+            protocol_share: Some(10),
+            node_share: Some(node_part),
+            service_builder_share: Some(10),
+            max_boost: Some(4),
+            supply_at_genesis: Some(1_000_000),
+        },
+        Some(committee),
+    );
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    let node_secret_key = NodeSecretKey::generate();
+
+    // The owner stakes 1_000 FLK and the delegator delegates 3_000 FLK to the node.
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000_u64.into(),
+        &node_secret_key.to_pk(),
+        [0; 96].into()
+    );
+    let delegated: HpUfixed<18> = 3_000_u64.into();
+    deposit!(&update_socket, &delegator_secret_key, 1, &delegated);
+    let delegate = prepare_update_request_account(
+        UpdateMethod::Delegate {
+            node: node_secret_key.to_pk(),
+            amount: delegated.clone(),
+        },
+        &delegator_secret_key,
+        2,
+    );
+    expect_tx_success!(delegate, &update_socket);
+    assert_eq!(get_flk_balance(&query_runner, &delegator), HpUfixed::zero());
+
+    let commission_rate = prepare_update_request_account(
+        UpdateMethod::ChangeCommissionRate {
+            node: node_secret_key.to_pk(),
+            commission_rate: 10,
+        },
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_success!(commission_rate, &update_socket);
+
+    let pod = prepare_pod_request(10_000, 0, &node_secret_key, 1);
+    run_updates!(vec![pod], &update_socket);
+    let node_usd = 0.1 * 10_000_f64;
+
+    simple_epoch_change!(&update_socket, &keystore, &query_runner, 0);
+
+    // The delegator receives 3/4 of the rewards of the node minus the 10% commission, the owner
+    // receives the rest.
+    let percentage_divisor: HpUfixed<18> = 100_u16.into();
+    let node_share = HpUfixed::<18>::from(node_part) / &percentage_divisor;
+    let delegator_share = (&delegated / &HpUfixed::<18>::from(4_000_u64))
+        * (HpUfixed::<18>::from(1_u64) - HpUfixed::<18>::from(10_u64) / &percentage_divisor);
+    let owner_share = HpUfixed::<18>::from(1_u64) - &delegator_share;
+
+    let stables_rewards = HpUfixed::<6>::from(node_usd) * node_share.convert_precision();
+    assert_eq!(
+        get_stables_balance(&query_runner, &delegator),
+        &stables_rewards * &delegator_share.convert_precision()
+    );
+    assert_eq!(
+        get_stables_balance(&query_runner, &owner),
+        &stables_rewards * &owner_share.convert_precision()
+    );
+
+    let emissions: HpUfixed<18> = (HpUfixed::<18>::from(10_u64) / &percentage_divisor
+        * HpUfixed::<18>::from(1_000_000_u64))
+        / &365.0.into();
+    let flk_rewards = &emissions * &node_share;
+    assert_eq!(
+        get_flk_balance(&query_runner, &delegator),
+        &flk_rewards * &delegator_share
+    );
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        &flk_rewards * &owner_share
+    );
+}
+
 #[tokio::test]
 async fn test_get_node_registry() {
     let committee_size = 4;
@@ -2412,111 +4244,530 @@ async fn test_revert_self_transfer() {
     let owner_secret_key = AccountOwnerSecretKey::generate();
     let owner: EthAddress = owner_secret_key.to_pk().into();
 
-    let balance = 1_000u64.into();
-
-    deposit!(&update_socket, &owner_secret_key, 1, &balance);
-    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
+    let balance = 1_000u64.into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
+
+    // Check that trying to transfer funds to yourself reverts
+    let update = prepare_transfer_request(&10_u64.into(), &owner, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::CantSendToYourself);
+
+    // Assure that Flk balance has not changed
+    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
+}
+
+#[tokio::test]
+async fn test_revert_transfer_not_account_key() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let amount: HpUfixed<18> = 10_u64.into();
+    let zero_balance = 0u64.into();
+
+    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+
+    let transfer = UpdateMethod::Transfer {
+        amount: amount.clone(),
+        token: Tokens::FLK,
+        to: recipient,
+    };
+
+    // Check that trying to transfer funds with Node Key reverts
+    let node_secret_key = &keystore[0].node_secret_key;
+    let update_node_key = prepare_update_request_node(transfer.clone(), node_secret_key, 1, None);
+    expect_tx_revert!(
+        update_node_key,
+        &update_socket,
+        ExecutionError::OnlyAccountOwner
+    );
+
+    // Check that trying to transfer funds with Consensus Key reverts
+    let consensus_secret_key = &keystore[0].consensus_secret_key;
+    let update_consensus_key = prepare_update_request_consensus(transfer, consensus_secret_key, 2);
+    expect_tx_revert!(
+        update_consensus_key,
+        &update_socket,
+        ExecutionError::OnlyAccountOwner
+    );
+
+    // Assure that Flk balance has not changed
+    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+}
+
+#[tokio::test]
+async fn test_revert_transfer_when_insufficient_balance() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let balance = 10_u64.into();
+    let zero_balance = 0u64.into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+
+    // Check that trying to transfer insufficient funds reverts
+    let update = prepare_transfer_request(&11u64.into(), &recipient, &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
+
+    // Assure that Flk balance has not changed
+    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+}
+
+#[tokio::test]
+async fn test_transfer_works_properly() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let balance = 1_000u64.into();
+    let zero_balance = 0u64.into();
+    let transfer_amount: HpUfixed<18> = 10_u64.into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
+    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+
+    // Check that trying to transfer funds to yourself reverts
+    let update = prepare_transfer_request(&10_u64.into(), &recipient, &owner_secret_key, 2);
+    expect_tx_success!(update, &update_socket);
+
+    // Assure that Flk balance has decreased for sender
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        balance - transfer_amount.clone()
+    );
+    // Assure that Flk balance has increased for recipient
+    assert_eq!(
+        get_flk_balance(&query_runner, &recipient),
+        zero_balance + transfer_amount
+    );
+}
+
+#[tokio::test]
+async fn test_approve_and_transfer_from() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let spender_secret_key = AccountOwnerSecretKey::generate();
+    let spender: EthAddress = spender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let balance: HpUfixed<18> = 1_000u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+
+    // Approve the spender
+    let approve = prepare_update_request_account(
+        UpdateMethod::Approve {
+            token: Tokens::FLK,
+            spender,
+            amount: 100u64.into(),
+        },
+        &owner_secret_key,
+        2,
+    );
+    let response = expect_tx_success!(approve, &update_socket, ExecutionData::None);
+    assert_eq!(
+        response.txn_receipts[0].event,
+        Some(Event::approval(
+            Tokens::FLK.address(),
+            owner,
+            spender,
+            100u64.into()
+        ))
+    );
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::FLK),
+        Some(100u64.into())
+    );
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::USDC),
+        None
+    );
+
+    // The spender transfers part of the allowance
+    let transfer_from = prepare_update_request_account(
+        UpdateMethod::TransferFrom {
+            token: Tokens::FLK,
+            from: owner,
+            to: recipient,
+            amount: 60u64.into(),
+        },
+        &spender_secret_key,
+        1,
+    );
+    let response = expect_tx_success!(transfer_from, &update_socket, ExecutionData::None);
+    assert_eq!(
+        response.txn_receipts[0].event,
+        Some(Event::transfer(
+            Tokens::FLK.address(),
+            owner,
+            recipient,
+            60u64.into()
+        ))
+    );
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        balance - HpUfixed::<18>::from(60u64)
+    );
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 60u64.into());
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::FLK),
+        Some(40u64.into())
+    );
+
+    // The spender can not transfer more than what is left of the allowance
+    let transfer_from = prepare_update_request_account(
+        UpdateMethod::TransferFrom {
+            token: Tokens::FLK,
+            from: owner,
+            to: recipient,
+            amount: 50u64.into(),
+        },
+        &spender_secret_key,
+        2,
+    );
+    expect_tx_revert!(
+        transfer_from,
+        &update_socket,
+        ExecutionError::InsufficientAllowance
+    );
+
+    // Spending the rest of the allowance removes it
+    let transfer_from = prepare_update_request_account(
+        UpdateMethod::TransferFrom {
+            token: Tokens::FLK,
+            from: owner,
+            to: recipient,
+            amount: 40u64.into(),
+        },
+        &spender_secret_key,
+        3,
+    );
+    expect_tx_success!(transfer_from, &update_socket);
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::FLK),
+        None
+    );
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 100u64.into());
+}
+
+#[tokio::test]
+async fn test_revert_transfer_from_when_insufficient_balance() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let spender_secret_key = AccountOwnerSecretKey::generate();
+    let spender: EthAddress = spender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    deposit!(&update_socket, &owner_secret_key, 1, &10u64.into());
+
+    // The allowance can be larger than the balance of the owner
+    let approve = prepare_update_request_account(
+        UpdateMethod::Approve {
+            token: Tokens::FLK,
+            spender,
+            amount: 100u64.into(),
+        },
+        &owner_secret_key,
+        2,
+    );
+    expect_tx_success!(approve, &update_socket);
+
+    let transfer_from = prepare_update_request_account(
+        UpdateMethod::TransferFrom {
+            token: Tokens::FLK,
+            from: owner,
+            to: recipient,
+            amount: 11u64.into(),
+        },
+        &spender_secret_key,
+        1,
+    );
+    expect_tx_revert!(
+        transfer_from,
+        &update_socket,
+        ExecutionError::InsufficientBalance
+    );
+
+    // Assure that neither the balances nor the allowance have changed
+    assert_eq!(get_flk_balance(&query_runner, &owner), 10u64.into());
+    assert_eq!(get_flk_balance(&query_runner, &recipient), 0u64.into());
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::FLK),
+        Some(100u64.into())
+    );
+
+    // Approving zero revokes the allowance
+    let approve = prepare_update_request_account(
+        UpdateMethod::Approve {
+            token: Tokens::FLK,
+            spender,
+            amount: 0u64.into(),
+        },
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_success!(approve, &update_socket);
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::FLK),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_approve_and_transfer_from_with_ethereum_request() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let spender_secret_key = AccountOwnerSecretKey::generate();
+    let spender: EthAddress = spender_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let mut genesis = test_genesis();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 0u64.into(),
+        stables_balance: 10,
+        bandwidth_balance: 0,
+    });
+    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
 
-    // Check that trying to transfer funds to yourself reverts
-    let update = prepare_transfer_request(&10_u64.into(), &owner, &owner_secret_key, 2);
-    expect_tx_revert!(update, &update_socket, ExecutionError::CantSendToYourself);
+    // The allowance is kept in the 6 decimals of the stables
+    let approve = prepare_ethereum_request(
+        ApproveCall {
+            token: "USDC".to_string(),
+            spender: spender.0.into(),
+            amount: U256::from(1_500_000_000_000_000_001u128),
+        },
+        &owner_secret_key,
+        1,
+    );
+    let response = expect_tx_success!(approve, &update_socket, ExecutionData::None);
+    let allowance = HpUfixed::<18>::from(U256::from(1_500_000_000_000_000_000u128));
+    assert_eq!(
+        response.txn_receipts[0].event,
+        Some(Event::approval(
+            Tokens::USDC.address(),
+            owner,
+            spender,
+            allowance.clone()
+        ))
+    );
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::USDC),
+        Some(allowance)
+    );
 
-    // Assure that Flk balance has not changed
-    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
+    // The allowance is spent by exactly the amount that was transferred
+    let transfer_from = prepare_ethereum_request(
+        TransferFromCall {
+            token: "USDC".to_string(),
+            from: owner.0.into(),
+            to: recipient.0.into(),
+            amount: U256::from(1_000_000_000_000_000_001u128),
+        },
+        &spender_secret_key,
+        1,
+    );
+    let response = expect_tx_success!(transfer_from, &update_socket, ExecutionData::None);
+    assert_eq!(
+        response.txn_receipts[0].event,
+        Some(Event::transfer(
+            Tokens::USDC.address(),
+            owner,
+            recipient,
+            1u64.into()
+        ))
+    );
+    assert_eq!(
+        query_runner.get_account_info(&owner, |a| a.stables_balance),
+        Some(9u64.into())
+    );
+    assert_eq!(
+        query_runner.get_account_info(&recipient, |a| a.stables_balance),
+        Some(1u64.into())
+    );
+    assert_eq!(
+        query_runner.get_allowance(&owner, &spender, &Tokens::USDC),
+        Some(HpUfixed::<18>::from(U256::from(
+            500_000_000_000_000_000u128
+        )))
+    );
 }
 
 #[tokio::test]
-async fn test_revert_transfer_not_account_key() {
-    let committee_size = 4;
-    let (committee, keystore) = create_genesis_committee(committee_size);
-    let (update_socket, query_runner) = test_init_app(committee);
+async fn test_transaction_fee_is_paid_in_flk_to_protocol_fund() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
     let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
-
-    let amount: HpUfixed<18> = 10_u64.into();
-    let zero_balance = 0u64.into();
-
-    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
-
-    let transfer = UpdateMethod::Transfer {
-        amount: amount.clone(),
-        token: Tokens::FLK,
-        to: recipient,
+    let balance: HpUfixed<18> = 1_000u64.into();
+    let (update_socket, query_runner) = init_app_with_fees(vec![GenesisAccount {
+        public_key: owner,
+        flk_balance: balance.clone(),
+        stables_balance: 100,
+        bandwidth_balance: 0,
+    }]);
+    let protocol_fund = match query_runner.get_metadata(&Metadata::ProtocolFundAddress) {
+        Some(Value::AccountPublicKey(address)) => address,
+        _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
     };
+    let protocol_fund_balance = get_flk_balance(&query_runner, &protocol_fund);
 
-    // Check that trying to transfer funds with Node Key reverts
-    let node_secret_key = &keystore[0].node_secret_key;
-    let update_node_key = prepare_update_request_node(transfer.clone(), node_secret_key, 1, None);
-    expect_tx_revert!(
-        update_node_key,
-        &update_socket,
-        ExecutionError::OnlyAccountOwner
-    );
+    let transfer_amount: HpUfixed<18> = 10_u64.into();
+    let update = prepare_transfer_request(&transfer_amount, &recipient, &owner_secret_key, 1);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
 
-    // Check that trying to transfer funds with Consensus Key reverts
-    let consensus_secret_key = &keystore[0].consensus_secret_key;
-    let update_consensus_key = prepare_update_request_consensus(transfer, consensus_secret_key, 2);
-    expect_tx_revert!(
-        update_consensus_key,
-        &update_socket,
-        ExecutionError::OnlyAccountOwner
+    assert_eq!(
+        result.txn_receipts[0].fee,
+        Some(TransactionFee {
+            gas: TEST_TRANSFER_GAS as u128,
+            gas_price: TEST_GAS_PRICE as u128,
+            token: Tokens::FLK,
+        })
+    );
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        balance - transfer_amount.clone() - flk_fee(TEST_TRANSFER_GAS)
+    );
+    assert_eq!(get_flk_balance(&query_runner, &recipient), transfer_amount);
+    assert_eq!(
+        get_flk_balance(&query_runner, &protocol_fund),
+        protocol_fund_balance + flk_fee(TEST_TRANSFER_GAS)
+    );
+    // The stables are untouched when the fee can be paid in FLK
+    assert_eq!(
+        get_stables_balance(&query_runner, &owner),
+        HpUfixed::<6>::from(100u64)
     );
-
-    // Assure that Flk balance has not changed
-    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
 }
 
 #[tokio::test]
-async fn test_revert_transfer_when_insufficient_balance() {
-    let (update_socket, query_runner) = init_app(None);
-
+async fn test_transaction_fee_falls_back_to_stables() {
     let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
     let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let stables_balance: HpUfixed<6> = 100u64.into();
+    let (update_socket, query_runner) = init_app_with_fees(vec![GenesisAccount {
+        public_key: owner,
+        flk_balance: HpUfixed::<18>::zero(),
+        stables_balance: 100,
+        bandwidth_balance: 0,
+    }]);
+    let protocol_fund = match query_runner.get_metadata(&Metadata::ProtocolFundAddress) {
+        Some(Value::AccountPublicKey(address)) => address,
+        _ => panic!("ProtocolFundAddress is added at Genesis and should exist"),
+    };
+    let protocol_fund_balance = get_stables_balance(&query_runner, &protocol_fund);
 
-    let balance = 10_u64.into();
-    let zero_balance = 0u64.into();
+    let update =
+        prepare_transfer_request(&HpUfixed::<18>::zero(), &recipient, &owner_secret_key, 1);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
 
-    deposit!(&update_socket, &owner_secret_key, 1, &balance);
-    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+    assert_eq!(
+        result.txn_receipts[0].fee,
+        Some(TransactionFee {
+            gas: TEST_TRANSFER_GAS as u128,
+            gas_price: TEST_STABLES_GAS_PRICE as u128,
+            token: Tokens::USDC,
+        })
+    );
+    assert_eq!(
+        get_stables_balance(&query_runner, &owner),
+        stables_balance - stables_fee(TEST_TRANSFER_GAS)
+    );
+    assert_eq!(
+        get_stables_balance(&query_runner, &protocol_fund),
+        protocol_fund_balance + stables_fee(TEST_TRANSFER_GAS)
+    );
+}
 
-    // Check that trying to transfer insufficient funds reverts
-    let update = prepare_transfer_request(&11u64.into(), &recipient, &owner_secret_key, 2);
+#[tokio::test]
+async fn test_transaction_fee_is_charged_on_revert() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let balance: HpUfixed<18> = 1_000u64.into();
+    let (update_socket, query_runner) = init_app_with_fees(vec![GenesisAccount {
+        public_key: owner,
+        flk_balance: balance.clone(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    }]);
+
+    // The transfer reverts, but the sender still pays for its execution
+    let update = prepare_transfer_request(
+        &2_000u64.into(),
+        &AccountOwnerSecretKey::generate().to_pk().into(),
+        &owner_secret_key,
+        1,
+    );
     expect_tx_revert!(update, &update_socket, ExecutionError::InsufficientBalance);
 
-    // Assure that Flk balance has not changed
-    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        balance - flk_fee(TEST_TRANSFER_GAS)
+    );
+    assert_eq!(get_account_nonce(&query_runner, &owner), 1);
 }
 
 #[tokio::test]
-async fn test_transfer_works_properly() {
-    let (update_socket, query_runner) = init_app(None);
+async fn test_transaction_rejected_when_fee_can_not_be_paid() {
+    let (update_socket, query_runner) = init_app_with_fees(Vec::new());
 
     let owner_secret_key = AccountOwnerSecretKey::generate();
     let owner: EthAddress = owner_secret_key.to_pk().into();
-    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
-
-    let balance = 1_000u64.into();
-    let zero_balance = 0u64.into();
-    let transfer_amount: HpUfixed<18> = 10_u64.into();
-
-    deposit!(&update_socket, &owner_secret_key, 1, &balance);
-
-    assert_eq!(get_flk_balance(&query_runner, &owner), balance);
-    assert_eq!(get_flk_balance(&query_runner, &recipient), zero_balance);
-
-    // Check that trying to transfer funds to yourself reverts
-    let update = prepare_transfer_request(&10_u64.into(), &recipient, &owner_secret_key, 2);
-    expect_tx_success!(update, &update_socket);
+    let update = prepare_transfer_request(
+        &HpUfixed::<18>::zero(),
+        &AccountOwnerSecretKey::generate().to_pk().into(),
+        &owner_secret_key,
+        1,
+    );
 
-    // Assure that Flk balance has decreased for sender
+    // Simulating the transaction shows that the fee can not be paid
     assert_eq!(
-        get_flk_balance(&query_runner, &owner),
-        balance - transfer_amount.clone()
+        query_runner.get_transaction_gas(&update.clone().into()),
+        TEST_TRANSFER_GAS as u128
     );
-    // Assure that Flk balance has increased for recipient
     assert_eq!(
-        get_flk_balance(&query_runner, &recipient),
-        zero_balance + transfer_amount
+        query_runner.simulate_txn(update.clone().into()),
+        TransactionResponse::Revert(ExecutionError::InsufficientBalanceForFee)
+    );
+
+    let result = run_update!(update, &update_socket);
+    assert_eq!(
+        result.txn_receipts[0].response,
+        TransactionResponse::Revert(ExecutionError::InsufficientBalanceForFee)
     );
+    assert_eq!(result.txn_receipts[0].fee, None);
+
+    // The transaction was not executed, so the nonce is unchanged
+    assert_eq!(get_account_nonce(&query_runner, &owner), 0);
+}
+
+#[tokio::test]
+async fn test_deposit_is_free() {
+    let (update_socket, query_runner) = init_app_with_fees(Vec::new());
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let amount: HpUfixed<18> = 1_000u64.into();
+
+    // A fresh account can be funded without holding anything to pay a fee with
+    let update = prepare_deposit_update(&amount, &owner_secret_key, 1);
+    assert_eq!(query_runner.get_transaction_gas(&update.clone().into()), 0);
+    let result = expect_tx_success!(update, &update_socket, ExecutionData::None);
+
+    assert_eq!(result.txn_receipts[0].fee, None);
+    assert_eq!(get_flk_balance(&query_runner, &owner), amount);
 }
 
 #[tokio::test]
@@ -2530,7 +4781,13 @@ async fn test_deposit_flk_works_properly() {
     let intial_balance = get_flk_balance(&query_runner, &owner);
 
     let deposit = UpdateMethod::Deposit {
-        proof: ProofOfConsensus {},
+        proof: prepare_proof_of_consensus(
+            next_deposit_id(),
+            owner,
+            Tokens::FLK,
+            &deposit_amount,
+            &BRIDGE_SIGNER_KEYS,
+        ),
         token: Tokens::FLK,
         amount: deposit_amount.clone(),
     };
@@ -2551,7 +4808,13 @@ async fn test_revert_deposit_not_account_key() {
 
     let amount: HpUfixed<18> = 10_u64.into();
     let deposit = UpdateMethod::Deposit {
-        proof: ProofOfConsensus {},
+        proof: prepare_proof_of_consensus(
+            next_deposit_id(),
+            AccountOwnerSecretKey::generate().to_pk().into(),
+            Tokens::FLK,
+            &amount,
+            &BRIDGE_SIGNER_KEYS,
+        ),
         token: Tokens::FLK,
         amount,
     };
@@ -2575,27 +4838,245 @@ async fn test_revert_deposit_not_account_key() {
     );
 }
 
-#[tokio::test]
-async fn test_deposit_usdc_works_properly() {
-    let (update_socket, query_runner) = init_app(None);
+#[tokio::test]
+async fn test_deposit_usdc_works_properly() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    let intial_balance = get_account_balance(&query_runner, &owner);
+    let deposit_amount = 1_000;
+    let deposit = UpdateMethod::Deposit {
+        proof: prepare_proof_of_consensus(
+            next_deposit_id(),
+            owner,
+            Tokens::USDC,
+            &deposit_amount.into(),
+            &BRIDGE_SIGNER_KEYS,
+        ),
+        token: Tokens::USDC,
+        amount: deposit_amount.into(),
+    };
+    let update = prepare_update_request_account(deposit, &owner_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    assert_eq!(
+        get_account_balance(&query_runner, &owner),
+        intial_balance + deposit_amount
+    );
+}
+
+#[tokio::test]
+async fn test_deposit_reverts_replayed_deposit() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    let amount: HpUfixed<18> = 1_000_u64.into();
+    let deposit = UpdateMethod::Deposit {
+        proof: prepare_proof_of_consensus(
+            next_deposit_id(),
+            owner,
+            Tokens::FLK,
+            &amount,
+            &BRIDGE_SIGNER_KEYS,
+        ),
+        token: Tokens::FLK,
+        amount: amount.clone(),
+    };
+    let update = prepare_update_request_account(deposit.clone(), &owner_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    // Submitting the same deposit again must not mint the tokens twice.
+    let update = prepare_update_request_account(deposit, &owner_secret_key, 2);
+    expect_tx_revert!(
+        update,
+        &update_socket,
+        ExecutionError::DepositAlreadyProcessed
+    );
+
+    assert_eq!(get_flk_balance(&query_runner, &owner), amount);
+}
+
+#[tokio::test]
+async fn test_deposit_reverts_forged_proof() {
+    let (update_socket, query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let amount: HpUfixed<18> = 1_000_u64.into();
+
+    let deposit = |proof: ProofOfConsensus| UpdateMethod::Deposit {
+        proof,
+        token: Tokens::FLK,
+        amount: amount.clone(),
+    };
+
+    // Signed by keys that are not bridge signers.
+    let forgers: Vec<_> = (0..3).map(|_| AccountOwnerSecretKey::generate()).collect();
+    let proof =
+        prepare_proof_of_consensus(next_deposit_id(), owner, Tokens::FLK, &amount, &forgers);
+    let update = prepare_update_request_account(deposit(proof), &owner_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // Signed by fewer bridge signers than the threshold.
+    let proof = prepare_proof_of_consensus(
+        next_deposit_id(),
+        owner,
+        Tokens::FLK,
+        &amount,
+        &BRIDGE_SIGNER_KEYS[..1],
+    );
+    let update = prepare_update_request_account(deposit(proof), &owner_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The same bridge signer signing twice only counts once.
+    let proof = prepare_proof_of_consensus(
+        next_deposit_id(),
+        owner,
+        Tokens::FLK,
+        &amount,
+        &[BRIDGE_SIGNER_KEYS[0].clone(), BRIDGE_SIGNER_KEYS[0].clone()],
+    );
+    let update = prepare_update_request_account(deposit(proof), &owner_secret_key, 3);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // Signed for a smaller amount than the one claimed.
+    let proof = prepare_proof_of_consensus(
+        next_deposit_id(),
+        owner,
+        Tokens::FLK,
+        &10_u64.into(),
+        &BRIDGE_SIGNER_KEYS,
+    );
+    let update = prepare_update_request_account(deposit(proof), &owner_secret_key, 4);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // Signed for another recipient.
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let proof = prepare_proof_of_consensus(
+        next_deposit_id(),
+        recipient,
+        Tokens::FLK,
+        &amount,
+        &BRIDGE_SIGNER_KEYS,
+    );
+    let update = prepare_update_request_account(deposit(proof), &owner_secret_key, 5);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    assert_eq!(
+        get_flk_balance(&query_runner, &owner),
+        HpUfixed::<18>::zero()
+    );
+}
+
+#[tokio::test]
+async fn test_change_bridge_signers() {
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, query_runner) = init_app_with_genesis(&genesis);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // Rotate the bridge signers to a new set.
+    let new_signer_keys: Vec<_> = (0..2).map(|_| AccountOwnerSecretKey::generate()).collect();
+    let new_signers: Vec<EthAddress> = new_signer_keys
+        .iter()
+        .map(|secret_key| secret_key.to_pk().into())
+        .collect();
+    let update =
+        prepare_change_bridge_signers_update(new_signers.clone(), 1, &governance_secret_key, 1);
+    expect_tx_success!(update, &update_socket);
+
+    match query_runner.get_metadata(&Metadata::BridgeSigners) {
+        Some(Value::BridgeSigners(bridge_signers)) => assert_eq!(
+            bridge_signers,
+            BridgeSigners {
+                signers: new_signers.clone(),
+                threshold: 1
+            }
+        ),
+        _ => panic!("Bridge signers are missing from state."),
+    }
+
+    // The old bridge signers can not sign off on deposits anymore.
+    let amount: HpUfixed<18> = 1_000_u64.into();
+    let update = prepare_update_request_account(
+        UpdateMethod::Deposit {
+            proof: prepare_proof_of_consensus(
+                next_deposit_id(),
+                owner,
+                Tokens::FLK,
+                &amount,
+                &BRIDGE_SIGNER_KEYS,
+            ),
+            token: Tokens::FLK,
+            amount: amount.clone(),
+        },
+        &owner_secret_key,
+        1,
+    );
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidProof);
+
+    // The new ones can.
+    let update = prepare_update_request_account(
+        UpdateMethod::Deposit {
+            proof: prepare_proof_of_consensus(
+                next_deposit_id(),
+                owner,
+                Tokens::FLK,
+                &amount,
+                &new_signer_keys[..1],
+            ),
+            token: Tokens::FLK,
+            amount: amount.clone(),
+        },
+        &owner_secret_key,
+        2,
+    );
+    expect_tx_success!(update, &update_socket);
+    assert_eq!(get_flk_balance(&query_runner, &owner), amount);
+
+    // Only governance can change the bridge signers.
+    let update = prepare_change_bridge_signers_update(vec![owner], 1, &owner_secret_key, 3);
+    expect_tx_revert!(update, &update_socket, ExecutionError::OnlyGovernance);
+}
+
+#[tokio::test]
+async fn test_change_bridge_signers_reverts_invalid_set() {
+    let governance_secret_key = AccountOwnerSecretKey::generate();
+
+    let mut genesis = test_genesis();
+    genesis.governance_address = governance_secret_key.to_pk().into();
+
+    let (update_socket, _query_runner) = init_app_with_genesis(&genesis);
+
+    let signers = test_bridge_signers();
 
-    let owner_secret_key = AccountOwnerSecretKey::generate();
-    let owner: EthAddress = owner_secret_key.to_pk().into();
+    // The threshold can not be zero.
+    let update =
+        prepare_change_bridge_signers_update(signers.clone(), 0, &governance_secret_key, 1);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidBridgeSigners);
 
-    let intial_balance = get_account_balance(&query_runner, &owner);
-    let deposit_amount = 1_000;
-    let deposit = UpdateMethod::Deposit {
-        proof: ProofOfConsensus {},
-        token: Tokens::USDC,
-        amount: deposit_amount.into(),
-    };
-    let update = prepare_update_request_account(deposit, &owner_secret_key, 1);
-    expect_tx_success!(update, &update_socket);
+    // The threshold has to be reachable.
+    let update =
+        prepare_change_bridge_signers_update(signers.clone(), 4, &governance_secret_key, 2);
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidBridgeSigners);
 
-    assert_eq!(
-        get_account_balance(&query_runner, &owner),
-        intial_balance + deposit_amount
+    // A signer can not be listed twice.
+    let update = prepare_change_bridge_signers_update(
+        vec![signers[0], signers[0]],
+        2,
+        &governance_secret_key,
+        3,
     );
+    expect_tx_revert!(update, &update_socket, ExecutionError::InvalidBridgeSigners);
 }
 
 #[tokio::test]
@@ -3350,6 +5831,237 @@ async fn test_withdraw_unstaked_works_properly() {
     );
 }
 
+#[tokio::test]
+async fn test_delegation_works_properly() {
+    let committee_size = 4;
+    let (committee, keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    let delegator: EthAddress = delegator_secret_key.to_pk().into();
+    let amount: HpUfixed<18> = 1_000u64.into();
+
+    // Delegating to a node that does not exist reverts.
+    deposit!(&update_socket, &delegator_secret_key, 1, &amount);
+    let delegate = |amount: &HpUfixed<18>, nonce| {
+        prepare_update_request_account(
+            UpdateMethod::Delegate {
+                node: node_pub_key,
+                amount: amount.clone(),
+            },
+            &delegator_secret_key,
+            nonce,
+        )
+    };
+    expect_tx_revert!(
+        delegate(&amount, 2),
+        &update_socket,
+        ExecutionError::NodeDoesNotExist
+    );
+
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &amount,
+        &node_pub_key,
+        [0; 96].into()
+    );
+    let node_index = query_runner.pubkey_to_index(&node_pub_key).unwrap();
+
+    // The owner of the node can not delegate to it.
+    let update = prepare_update_request_account(
+        UpdateMethod::Delegate {
+            node: node_pub_key,
+            amount: amount.clone(),
+        },
+        &owner_secret_key,
+        3,
+    );
+    expect_tx_revert!(
+        update,
+        &update_socket,
+        ExecutionError::CantDelegateToOwnNode
+    );
+
+    // The delegator can not delegate more than it has.
+    expect_tx_revert!(
+        delegate(&(&amount + &amount), 3),
+        &update_socket,
+        ExecutionError::InsufficientBalance
+    );
+    expect_tx_success!(delegate(&amount, 4), &update_socket);
+    assert_eq!(get_flk_balance(&query_runner, &delegator), HpUfixed::zero());
+    assert_eq!(
+        query_runner
+            .get_delegation(&node_index, &delegator)
+            .unwrap(),
+        Delegation {
+            staked: amount.clone(),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        query_runner.get_delegators(&node_index),
+        BTreeSet::from([delegator])
+    );
+    // Delegations do not change the stake of the owner.
+    assert_eq!(get_staked(&query_runner, &node_pub_key), amount);
+
+    // Undelegate
+    let undelegate = |amount: &HpUfixed<18>, nonce| {
+        prepare_update_request_account(
+            UpdateMethod::Undelegate {
+                node: node_pub_key,
+                amount: amount.clone(),
+            },
+            &delegator_secret_key,
+            nonce,
+        )
+    };
+    expect_tx_revert!(
+        undelegate(&(&amount + &amount), 5),
+        &update_socket,
+        ExecutionError::InsufficientBalance
+    );
+    expect_tx_success!(undelegate(&amount, 6), &update_socket);
+    let delegation = query_runner
+        .get_delegation(&node_index, &delegator)
+        .unwrap();
+    assert_eq!(delegation.staked, HpUfixed::zero());
+    assert_eq!(delegation.locked, amount);
+    assert_eq!(delegation.locked_until, 5);
+
+    // The undelegated tokens are locked for the lock time (5).
+    let withdraw = |nonce| {
+        prepare_update_request_account(
+            UpdateMethod::WithdrawDelegation {
+                node: node_pub_key,
+                recipient: None,
+            },
+            &delegator_secret_key,
+            nonce,
+        )
+    };
+    expect_tx_revert!(withdraw(7), &update_socket, ExecutionError::TokensLocked);
+    for epoch in 0..5 {
+        simple_epoch_change!(&update_socket, &keystore, &query_runner, epoch);
+    }
+    expect_tx_success!(withdraw(8), &update_socket);
+    assert_eq!(get_flk_balance(&query_runner, &delegator), amount);
+
+    // The withdrawn delegation is removed.
+    assert!(
+        query_runner
+            .get_delegation(&node_index, &delegator)
+            .is_none()
+    );
+    assert!(query_runner.get_delegators(&node_index).is_empty());
+    expect_tx_revert!(withdraw(9), &update_socket, ExecutionError::NoDelegation);
+}
+
+#[tokio::test]
+async fn test_delegations_count_towards_node_stake() {
+    let committee_size = 4;
+    let (committee, _keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    // The owner stakes less than the minimum stake (1000).
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    let amount: HpUfixed<18> = 500u64.into();
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &amount,
+        &node_pub_key,
+        [0; 96].into()
+    );
+    let node_index = query_runner.pubkey_to_index(&node_pub_key).unwrap();
+    assert!(!query_runner.is_valid_node(&node_pub_key));
+    assert!(
+        !query_runner
+            .get_node_registry(None)
+            .iter()
+            .any(|node| node.index == node_index)
+    );
+
+    // The delegated stake makes up for the rest of the minimum stake.
+    let delegator_secret_key = AccountOwnerSecretKey::generate();
+    deposit!(&update_socket, &delegator_secret_key, 1, &amount);
+    let update = prepare_update_request_account(
+        UpdateMethod::Delegate {
+            node: node_pub_key,
+            amount: amount.clone(),
+        },
+        &delegator_secret_key,
+        2,
+    );
+    expect_tx_success!(update, &update_socket);
+    assert_eq!(
+        query_runner.get_node_stake(&node_index),
+        Some(&amount + &amount)
+    );
+    assert!(query_runner.is_valid_node(&node_pub_key));
+    assert!(
+        query_runner
+            .get_node_registry(None)
+            .iter()
+            .any(|node| node.index == node_index)
+    );
+}
+
+#[tokio::test]
+async fn test_change_commission_rate() {
+    let committee_size = 4;
+    let (committee, _keystore) = create_genesis_committee(committee_size);
+    let (update_socket, query_runner) = test_init_app(committee);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let node_pub_key = NodeSecretKey::generate().to_pk();
+    deposit_and_stake!(
+        &update_socket,
+        &owner_secret_key,
+        1,
+        &1_000u64.into(),
+        &node_pub_key,
+        [0; 96].into()
+    );
+
+    let change_commission_rate = |commission_rate, secret_key, nonce| {
+        prepare_update_request_account(
+            UpdateMethod::ChangeCommissionRate {
+                node: node_pub_key,
+                commission_rate,
+            },
+            secret_key,
+            nonce,
+        )
+    };
+
+    expect_tx_revert!(
+        change_commission_rate(5, &AccountOwnerSecretKey::generate(), 1),
+        &update_socket,
+        ExecutionError::NotNodeOwner
+    );
+    expect_tx_revert!(
+        change_commission_rate(101, &owner_secret_key, 3),
+        &update_socket,
+        ExecutionError::InvalidCommissionRate
+    );
+    expect_tx_success!(
+        change_commission_rate(5, &owner_secret_key, 4),
+        &update_socket
+    );
+    assert_eq!(
+        get_node_info(&query_runner, &node_pub_key).commission_rate,
+        5
+    );
+}
+
 #[tokio::test]
 async fn test_submit_reputation_measurements_reverts_account_key() {
     // Create a genesis committee and seed the application state with it.
@@ -4024,9 +6736,367 @@ fn quick_sort_mock_node_list() -> Vec<(NodeIndex, NodeInfo)> {
                 nonce: 0,
                 ports: Default::default(),
                 secondary_nonce: 0,
+                commission_rate: 0,
             },
         ));
     }
     nodes.shuffle(&mut rand::thread_rng());
     nodes
 }
+
+#[tokio::test]
+async fn test_state_root_and_proofs() {
+    let (update_socket, query_runner) = init_app(None);
+    // The tree is built at startup, so the genesis state can be proven.
+    assert!(query_runner.get_state_root().is_some());
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let balance: HpUfixed<18> = 1_000u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+
+    let transfer = |nonce| {
+        prepare_update_request_account(
+            UpdateMethod::Transfer {
+                token: Tokens::FLK,
+                to: recipient,
+                amount: 10u64.into(),
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+    let first = expect_tx_success!(transfer(2), &update_socket, ExecutionData::None);
+    let response = expect_tx_success!(transfer(3), &update_socket, ExecutionData::None);
+    assert_ne!(response.state_root, first.state_root);
+
+    let verify = |key: &[u8]| {
+        let res = query_runner.get_state_proof("account", key).unwrap();
+        assert_eq!(res.state_root, response.state_root);
+        let proof = StateProof {
+            table_roots: res.proof.table_roots,
+            table: res.proof.table,
+            siblings: res.proof.siblings,
+            leaf: res.proof.leaf.map(|leaf| ProofLeaf {
+                path: leaf.path,
+                value_hash: leaf.value_hash,
+            }),
+        };
+        assert!(proof.verify(&res.state_root, key, res.value.as_deref()));
+        res.value
+            .map(|value| DefaultSerdeBackend::deserialize::<AccountInfo>(&value))
+    };
+
+    let account = verify(&DefaultSerdeBackend::serialize(&recipient)).unwrap();
+    assert_eq!(account.flk_balance, 20u64.into());
+
+    let stranger: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    assert!(verify(&DefaultSerdeBackend::serialize(&stranger)).is_none());
+
+    assert!(query_runner.get_state_proof("unknown", &[0]).is_none());
+}
+
+#[tokio::test]
+async fn test_state_diff_holds_previous_values() {
+    let (update_socket, _query_runner) = init_app(None);
+
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let balance: HpUfixed<18> = 1_000u64.into();
+    deposit!(&update_socket, &owner_secret_key, 1, &balance);
+
+    let transfer = |nonce| {
+        prepare_update_request_account(
+            UpdateMethod::Transfer {
+                token: Tokens::FLK,
+                to: recipient,
+                amount: 10u64.into(),
+            },
+            &owner_secret_key,
+            nonce,
+        )
+    };
+    let previous_value = |response: &BlockExecutionResponse, address: &EthAddress| {
+        response.state_diff.tables["account"]
+            .get(&DefaultSerdeBackend::serialize(address))
+            .cloned()
+            .map(|value| value.map(|value| DefaultSerdeBackend::deserialize::<AccountInfo>(&value)))
+    };
+
+    // The recipient account did not exist before the first transfer.
+    let first = expect_tx_success!(transfer(2), &update_socket, ExecutionData::None);
+    assert_eq!(previous_value(&first, &recipient), Some(None));
+    let owner_info = previous_value(&first, &owner).unwrap().unwrap();
+    assert_eq!(owner_info.flk_balance, balance);
+
+    let second = expect_tx_success!(transfer(3), &update_socket, ExecutionData::None);
+    let recipient_info = previous_value(&second, &recipient).unwrap().unwrap();
+    assert_eq!(recipient_info.flk_balance, 10u64.into());
+    let owner_info = previous_value(&second, &owner).unwrap().unwrap();
+    assert_eq!(
+        owner_info.flk_balance,
+        balance - HpUfixed::<18>::from(10u64)
+    );
+}
+
+#[tokio::test]
+async fn test_migrations_run_in_order_once() {
+    let config = Config::test();
+    let mut env = Env::new(&config, None).unwrap();
+    assert!(env.genesis(&config));
+
+    let schema_version = |env: &Env<UpdatePerm>| {
+        env.inner.query().run(|ctx| {
+            ctx.get_table::<Metadata, Value>("metadata")
+                .get(Metadata::SchemaVersion)
+        })
+    };
+    assert!(matches!(
+        schema_version(&env),
+        Some(Value::SchemaVersion(SCHEMA_VERSION))
+    ));
+
+    // Simulate a state from before versioning, that stored the uptime as a `u16`.
+    env.inner.run(|ctx| {
+        ctx.get_table::<Metadata, Value>("metadata")
+            .remove(Metadata::SchemaVersion);
+        let mut uptime = ctx.get_table_unchecked::<NodeIndex, u16>("uptime");
+        uptime.insert(0, 300);
+        uptime.insert(1, 40);
+    });
+
+    fn uptime_to_u8(ctx: &MigrationContext) {
+        migrate_values::<NodeIndex, u16, u8>(ctx, "uptime", |uptime| uptime.min(100) as u8);
+    }
+    fn noop(_ctx: &MigrationContext) {}
+    let migrations = [
+        Migration {
+            version: 1,
+            description: "Store the uptime as a u8",
+            migrate: uptime_to_u8,
+        },
+        Migration {
+            version: 2,
+            description: "Nothing",
+            migrate: noop,
+        },
+    ];
+
+    let report = migrate(&mut env.inner, &migrations).unwrap();
+    assert_eq!((report.from, report.to), (0, 2));
+    assert_eq!(report.steps.len(), 2);
+    assert_eq!(report.steps[0].changes["uptime"], 2);
+    assert!(!report.steps[1].changes.contains_key("uptime"));
+    assert!(matches!(
+        schema_version(&env),
+        Some(Value::SchemaVersion(2))
+    ));
+
+    let query_runner = env.query_runner();
+    assert_eq!(query_runner.get_node_uptime(&0), Some(100));
+    assert_eq!(query_runner.get_node_uptime(&1), Some(40));
+
+    // The migrations that already ran are skipped.
+    let report = migrate(&mut env.inner, &migrations).unwrap();
+    assert!(report.steps.is_empty());
+
+    // A state of a newer version can not be opened.
+    assert!(migrate(&mut env.inner, &migrations[..1]).is_err());
+}
+
+/// Returns the config of a RocksDb state in an empty directory.
+fn rocks_db_config(name: &str) -> (Config, std::path::PathBuf) {
+    let path = std::env::temp_dir()
+        .join("lightning-application-test")
+        .join(name);
+    if path.exists() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+    let config = Config {
+        storage: StorageConfig::RocksDb,
+        db_path: Some(path.clone().try_into().unwrap()),
+        ..Config::test()
+    };
+    (config, path)
+}
+
+/// Opens the state at the given path with only the given tables declared, the way a previous
+/// version of the application stored it.
+fn open_previous_state(
+    path: &std::path::Path,
+    tables: impl FnOnce(
+        AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>,
+    ) -> AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>,
+) -> Atomo<UpdatePerm, AtomoStorage> {
+    let mut options = Options::default();
+    options.create_if_missing(true);
+    options.create_missing_column_families(true);
+    let storage = AtomoStorageBuilder::new(Some(path)).with_options(options);
+    tables(AtomoBuilder::new(storage).with_table::<Metadata, Value>("metadata"))
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_migrate_node_info_from_v0() {
+    /// The node info as it was stored before the commission rate was added.
+    #[derive(Serialize, Deserialize)]
+    struct NodeInfoV0 {
+        owner: EthAddress,
+        public_key: NodePublicKey,
+        consensus_key: ConsensusPublicKey,
+        staked_since: Epoch,
+        stake: Staking,
+        domain: IpAddr,
+        worker_domain: IpAddr,
+        ports: NodePorts,
+        worker_public_key: NodePublicKey,
+        participation: Participation,
+        nonce: u64,
+        secondary_nonce: u128,
+    }
+
+    let (config, path) = rocks_db_config("migrate-node-info-from-v0");
+    let node = NodeInfo::from(&Genesis::load().unwrap().node_info[0]);
+
+    // Write a state from before versioning, that has no indexes over the nodes yet.
+    let mut atomo = open_previous_state(&path, |builder| {
+        builder.with_table::<NodeIndex, NodeInfoV0>("node")
+    });
+    let previous = node.clone();
+    atomo.run(|ctx| {
+        ctx.get_table::<Metadata, Value>("metadata")
+            .insert(Metadata::Epoch, Value::Epoch(0));
+        ctx.get_table::<NodeIndex, NodeInfoV0>("node").insert(
+            0,
+            NodeInfoV0 {
+                owner: previous.owner,
+                public_key: previous.public_key,
+                consensus_key: previous.consensus_key,
+                staked_since: previous.staked_since,
+                stake: previous.stake,
+                domain: previous.domain,
+                worker_domain: previous.worker_domain,
+                ports: previous.ports,
+                worker_public_key: previous.worker_public_key,
+                participation: previous.participation,
+                nonce: previous.nonce,
+                secondary_nonce: previous.secondary_nonce,
+            },
+        );
+    });
+    drop(atomo);
+
+    // The node table is migrated before the indexes over it are built.
+    let env = Env::new(&config, None).unwrap();
+    let query_runner = env.query_runner();
+    assert_eq!(
+        query_runner.get_node_info(&0, |node| node.commission_rate),
+        Some(0)
+    );
+    assert_eq!(query_runner.pubkey_to_index(&node.public_key), Some(0));
+    assert!(matches!(
+        query_runner.get_metadata(&Metadata::SchemaVersion),
+        Some(Value::SchemaVersion(SCHEMA_VERSION))
+    ));
+    drop(env);
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_migrate_services_and_lookup_tables_from_v2() {
+    /// The service as it was stored before the slashable misbehaviors were added.
+    #[derive(Serialize, Deserialize)]
+    struct ServiceV2 {
+        owner: EthAddress,
+        commodity_type: CommodityTypes,
+        slashing: (),
+    }
+
+    let (config, path) = rocks_db_config("migrate-services-and-lookup-tables-from-v2");
+    let owner: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let node = NodeInfo::from(&Genesis::load().unwrap().node_info[0]);
+
+    let legacy_tables = |builder: AtomoBuilder<AtomoStorageBuilder, DefaultSerdeBackend>| {
+        builder
+            .with_table::<ServiceId, ServiceV2>("service")
+            .with_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
+            .with_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
+            .with_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
+    };
+
+    // Write a state at schema version 2, that still has the lookup tables.
+    let mut atomo = open_previous_state(&path, legacy_tables);
+    atomo.run(|ctx| {
+        let mut metadata = ctx.get_table::<Metadata, Value>("metadata");
+        metadata.insert(Metadata::Epoch, Value::Epoch(0));
+        metadata.insert(Metadata::SchemaVersion, Value::SchemaVersion(2));
+        ctx.get_table::<ServiceId, ServiceV2>("service").insert(
+            0,
+            ServiceV2 {
+                owner,
+                commodity_type: CommodityTypes::Bandwidth,
+                slashing: (),
+            },
+        );
+        ctx.get_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
+            .insert([1; 32], BTreeSet::from([0]));
+        ctx.get_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
+            .insert(node.public_key, 0);
+        ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
+            .insert(node.consensus_key, 0);
+    });
+    drop(atomo);
+
+    let env = Env::new(&config, None).unwrap();
+    let query_runner = env.query_runner();
+    assert_eq!(
+        query_runner.get_service_info(&0),
+        Some(Service {
+            owner,
+            commodity_type: CommodityTypes::Bandwidth,
+            slashing: Vec::new(),
+        })
+    );
+    assert_eq!(
+        query_runner.get_protocol_param(&ProtocolParams::GovernanceMaxOpenProposals),
+        Some(2)
+    );
+    assert!(matches!(
+        query_runner.get_metadata(&Metadata::SchemaVersion),
+        Some(Value::SchemaVersion(SCHEMA_VERSION))
+    ));
+    drop(query_runner);
+    drop(env);
+
+    // The entries of the lookup tables are removed.
+    let atomo = open_previous_state(&path, legacy_tables);
+    atomo.query().run(|ctx| {
+        assert_eq!(
+            ctx.get_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
+                .range(..)
+                .count(),
+            0
+        );
+        assert_eq!(
+            ctx.get_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
+                .range(..)
+                .count(),
+            0
+        );
+        assert_eq!(
+            ctx.get_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
+                .range(..)
+                .count(),
+            0
+        );
+    });
+    drop(atomo);
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
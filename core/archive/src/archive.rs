@@ -1,17 +1,26 @@
-use std::sync::Arc;
+use std::collections::BTreeSet;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
 
 use anyhow::{Context, Result};
-use ethers::types::BlockNumber;
+use atomo_rocks::{chunk_name, CheckpointImporter, CheckpointManifest};
+use ethers::types::{BlockNumber, Filter, FilterBlockOption, Log};
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{
+    filter_topics,
+    log_matches,
     Block,
     BlockExecutionResponse,
     BlockReceipt,
+    Metadata,
+    StateDiff,
     TransactionReceipt,
     TransactionRequest,
+    Value,
 };
+use lru::LruCache;
 use resolved_pathbuf::ResolvedPathBuf;
-use rocksdb::{Options, DB};
+use rocksdb::{Direction, IteratorMode, Options, DB};
 use tokio::pin;
 
 use crate::config::Config;
@@ -20,12 +29,22 @@
 const BLKHASH_TO_BLKNUM: &str = "blkhash_to_blknum";
 const BLKNUM_TO_BLK: &str = "blknum_to_blk";
 const TXHASH_TO_TXRCT: &str = "txhash_to_txrct";
+const ADDRESS_TO_BLKNUM: &str = "address_to_blknum";
+const TOPIC_TO_BLKNUM: &str = "topic_to_blknum";
+const BLKNUM_TO_DIFF: &str = "blknum_to_diff";
+const CHECKPOINT_TO_EPOCH: &str = "checkpoint_to_epoch";
 const MISC: &str = "misc";
 
 // Special keys
 const LATEST: &str = "latest";
 const EARLIEST: &str = "earliest";
 
+/// The maximum number of blocks a single log query may span.
+const MAX_LOG_BLOCK_RANGE: u64 = 10_000;
+
+/// The maximum number of opened historical states kept around for reuse, per kind of query.
+const MAX_CACHED_QUERY_RUNNERS: usize = 16;
+
 pub struct Archive<C: Collection> {
     inner: Option<Arc<ArchiveInner<C>>>,
 }
@@ -35,6 +54,10 @@ struct ArchiveInner<C: Collection> {
     blockstore: c!(C::BlockstoreInterface),
     /// Handles the rocks db storage for each epoch
     historical_state_dir: ResolvedPathBuf,
+    /// Historical states never change once they are available, so the opened query runners are
+    /// cached by epoch and by block number.
+    epoch_query_runners: Mutex<LruCache<u64, c!(C::ApplicationInterface::SyncExecutor)>>,
+    block_query_runners: Mutex<LruCache<u64, c!(C::ApplicationInterface::SyncExecutor)>>,
 }
 
 impl<C: Collection> BuildGraph for Archive<C> {
@@ -59,7 +82,16 @@ pub fn new(
         db_options.create_if_missing(true);
         db_options.create_missing_column_families(true);
 
-        let cf = vec![BLKHASH_TO_BLKNUM, BLKNUM_TO_BLK, TXHASH_TO_TXRCT, MISC];
+        let cf = vec![
+            BLKHASH_TO_BLKNUM,
+            BLKNUM_TO_BLK,
+            TXHASH_TO_TXRCT,
+            ADDRESS_TO_BLKNUM,
+            TOPIC_TO_BLKNUM,
+            BLKNUM_TO_DIFF,
+            CHECKPOINT_TO_EPOCH,
+            MISC,
+        ];
         let db =
             DB::open_cf(&db_options, &config.store_path, cf).expect("Failed to create archive db");
 
@@ -134,6 +166,14 @@ async fn get_block_by_number(&self, number: BlockNumber) -> Option<BlockReceipt>
         })
     }
 
+    async fn get_logs(&self, filter: Filter) -> Result<Vec<Log>> {
+        let inner = self
+            .inner
+            .as_ref()
+            .context("The node is not running as an archive node")?;
+        inner.get_logs(&filter)
+    }
+
     async fn get_historical_epoch_state(
         &self,
         epoch: u64,
@@ -142,6 +182,15 @@ async fn get_historical_epoch_state(
             .as_ref()
             .and_then(|inner| inner.get_historical_query_runner(epoch).ok())
     }
+
+    async fn get_historical_block_state(
+        &self,
+        block_number: u64,
+    ) -> Option<c![C::ApplicationInterface::SyncExecutor]> {
+        self.inner
+            .as_ref()
+            .and_then(|inner| inner.get_historical_block_query_runner(block_number).ok())
+    }
 }
 
 impl<C: Collection> Clone for Archive<C> {
@@ -159,10 +208,13 @@ fn new(
         historical_state_dir: ResolvedPathBuf,
         blockstore: c!(C::BlockstoreInterface),
     ) -> Self {
+        let capacity = NonZeroUsize::new(MAX_CACHED_QUERY_RUNNERS).unwrap();
         Self {
             db,
             historical_state_dir,
             blockstore,
+            epoch_query_runners: Mutex::new(LruCache::new(capacity)),
+            block_query_runners: Mutex::new(LruCache::new(capacity)),
         }
     }
 
@@ -170,10 +222,81 @@ fn get_historical_query_runner(
         &self,
         epoch: u64,
     ) -> Result<c!(C::ApplicationInterface::SyncExecutor)> {
+        if let Some(query_runner) = self.epoch_query_runners.lock().unwrap().get(&epoch) {
+            return Ok(query_runner.clone());
+        }
+
         let path = self.historical_state_dir.join(epoch.to_string());
         tracing::trace!(target: "archive", "Getting historical epoch state from {:?}", path);
         let db = <c!(C::ApplicationInterface::SyncExecutor)>::atomo_from_path(path)?;
         let query_runner = <c!(C::ApplicationInterface::SyncExecutor)>::new(db);
+        self.epoch_query_runners
+            .lock()
+            .unwrap()
+            .put(epoch, query_runner.clone());
+        Ok(query_runner)
+    }
+
+    fn get_historical_block_query_runner(
+        &self,
+        block_number: u64,
+    ) -> Result<c!(C::ApplicationInterface::SyncExecutor)> {
+        if let Some(query_runner) = self.block_query_runners.lock().unwrap().get(&block_number) {
+            return Ok(query_runner.clone());
+        }
+
+        // Find the first epoch checkpoint taken at or after the block.
+        let checkpoint_cf = self
+            .db
+            .cf_handle(CHECKPOINT_TO_EPOCH)
+            .context("Column family `checkpoint_to_epoch` not found in db")?;
+        let start = block_number.to_be_bytes();
+        let (checkpoint_block, epoch) = match self
+            .db
+            .iterator_cf(
+                &checkpoint_cf,
+                IteratorMode::From(&start, Direction::Forward),
+            )
+            .next()
+        {
+            Some(item) => {
+                let (key, value) = item?;
+                (
+                    u64::from_be_bytes(key.as_ref().try_into()?),
+                    u64::from_le_bytes(value.as_ref().try_into()?),
+                )
+            },
+            None => anyhow::bail!("No checkpoint covers block {block_number} yet"),
+        };
+
+        // Walk back from the checkpoint to the block, the reverse diff of an earlier block takes
+        // priority over the ones after it.
+        let diff_cf = self
+            .db
+            .cf_handle(BLKNUM_TO_DIFF)
+            .context("Column family `blknum_to_diff` not found in db")?;
+        let mut diff = StateDiff::default();
+        for number in (block_number + 1..=checkpoint_block).rev() {
+            let bytes = self
+                .db
+                .get_cf(&diff_cf, number.to_le_bytes())?
+                .with_context(|| format!("Missing state diff of block {number}"))?;
+            diff.merge_earlier(bincode::deserialize(&bytes)?);
+        }
+
+        let path = self.historical_state_dir.join(epoch.to_string());
+        tracing::trace!(
+            target: "archive",
+            "Getting historical state of block {block_number} from {:?}",
+            path
+        );
+        let db =
+            <c!(C::ApplicationInterface::SyncExecutor)>::atomo_from_path_with_diff(path, diff)?;
+        let query_runner = <c!(C::ApplicationInterface::SyncExecutor)>::new(db);
+        self.block_query_runners
+            .lock()
+            .unwrap()
+            .put(block_number, query_runner.clone());
         Ok(query_runner)
     }
 
@@ -241,14 +364,154 @@ fn get_block_by_num(&self, blk_num: &[u8]) -> Result<Option<BlockInfo>> {
         }
     }
 
+    fn get_logs(&self, filter: &Filter) -> Result<Vec<Log>> {
+        let (from, to) = match filter.block_option {
+            FilterBlockOption::AtBlockHash(hash) => {
+                let Some(info) = self.get_block_by_hash(&hash.0)? else {
+                    return Ok(Vec::new());
+                };
+                (info.receipt.block_number, info.receipt.block_number)
+            },
+            FilterBlockOption::Range {
+                from_block,
+                to_block,
+            } => {
+                let from = self.resolve_block_number(&from_block.unwrap_or_default())?;
+                let to = self.resolve_block_number(&to_block.unwrap_or_default())?;
+                let (Some(from), Some(to)) = (from, to) else {
+                    return Ok(Vec::new());
+                };
+                (from, to)
+            },
+        };
+        if from > to {
+            return Ok(Vec::new());
+        }
+        if to - from >= MAX_LOG_BLOCK_RANGE {
+            anyhow::bail!("Log queries may span at most {MAX_LOG_BLOCK_RANGE} blocks");
+        }
+
+        // Narrow the blocks down with the address and topic indexes before looking at any logs.
+        let mut candidates: Option<BTreeSet<u64>> = None;
+        if let Some(addresses) = &filter.address {
+            let addresses = match addresses {
+                ValueOrArray::Value(address) => vec![*address],
+                ValueOrArray::Array(addresses) => addresses.clone(),
+            };
+            if !addresses.is_empty() {
+                let mut blocks = BTreeSet::new();
+                for address in addresses {
+                    blocks.extend(self.get_indexed_blocks(
+                        ADDRESS_TO_BLKNUM,
+                        address.as_bytes(),
+                        from,
+                        to,
+                    )?);
+                }
+                candidates = Some(blocks);
+            }
+        }
+        for topics in filter.topics.iter().flatten() {
+            let Some(topics) = filter_topics(topics) else {
+                continue;
+            };
+            let mut blocks = BTreeSet::new();
+            for topic in topics {
+                blocks.extend(self.get_indexed_blocks(
+                    TOPIC_TO_BLKNUM,
+                    topic.as_bytes(),
+                    from,
+                    to,
+                )?);
+            }
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&blocks).copied().collect(),
+                None => blocks,
+            });
+        }
+        let blocks = match candidates {
+            Some(candidates) => candidates,
+            None => (from..=to).collect(),
+        };
+
+        let mut logs = Vec::new();
+        for block_number in blocks {
+            let Some(info) = self.get_block_by_num(&block_number.to_le_bytes())? else {
+                continue;
+            };
+            for hash in &info.receipt.txn_hashes {
+                if let Some(receipt) = self.get_transaction_receipt(hash)? {
+                    logs.extend(
+                        receipt
+                            .logs()
+                            .into_iter()
+                            .filter(|log| log_matches(filter, log)),
+                    );
+                }
+            }
+        }
+        Ok(logs)
+    }
+
+    // Resolves the BlockNumber type from ethers to an actual block number
+    fn resolve_block_number(&self, blk_num: &BlockNumber) -> Result<Option<u64>> {
+        let key = match blk_num {
+            BlockNumber::Number(num) => return Ok(Some(num.as_u64())),
+            BlockNumber::Earliest => EARLIEST,
+            BlockNumber::Latest
+            | BlockNumber::Finalized
+            | BlockNumber::Safe
+            | BlockNumber::Pending => LATEST,
+        };
+        let misc_cf = self
+            .db
+            .cf_handle(MISC)
+            .context("Column family `misc` not found in db")?;
+        let Some(blk_num) = self.db.get_cf(&misc_cf, key)? else {
+            return Ok(None);
+        };
+        Ok(Some(u64::from_le_bytes(blk_num.as_slice().try_into()?)))
+    }
+
+    // Gets the block numbers in the range that an index has an entry for
+    fn get_indexed_blocks(
+        &self,
+        cf_name: &str,
+        prefix: &[u8],
+        from: u64,
+        to: u64,
+    ) -> Result<BTreeSet<u64>> {
+        let cf = self
+            .db
+            .cf_handle(cf_name)
+            .with_context(|| format!("Column family `{cf_name}` not found in db"))?;
+        let start = index_key(prefix, from);
+        let mut blocks = BTreeSet::new();
+        for item in self
+            .db
+            .iterator_cf(&cf, IteratorMode::From(&start, Direction::Forward))
+        {
+            let (key, _) = item?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            let blk_num = u64::from_be_bytes(key[prefix.len()..].try_into()?);
+            if blk_num > to {
+                break;
+            }
+            blocks.insert(blk_num);
+        }
+        Ok(blocks)
+    }
+
     async fn handle_epoch(&self, epoch: u64, hash: [u8; 32]) -> Result<()> {
         let path = self.historical_state_dir.join(epoch.to_string());
 
         // read the checkpoint from the blockstore, at this point application/env::run() has already
         // written this to the blockstore
         tracing::trace!(target: "archive", "Reading checkpoint from blockstore for epoch {}", epoch);
-        let checkpoint = match self.blockstore.read_all_to_vec(&hash).await {
-            Some(checkpoint) => checkpoint,
+        let manifest = match self.blockstore.read_all_to_vec(&hash).await {
+            Some(manifest) => CheckpointManifest::deserialize(&manifest)?,
             None => {
                 return Err(anyhow::anyhow!(
                     "Could not find checkpoint in blockstore for epoch, this is a bug"
@@ -256,20 +519,58 @@ fn get_block_by_num(&self, blk_num: &[u8]) -> Result<Option<BlockInfo>> {
             },
         };
 
-        // create the query runner, this will write the checkpoint to the historical state dir
-        let db = <c!(C::ApplicationInterface::SyncExecutor)>::atomo_from_checkpoint(
-            path,
-            hash,
-            &checkpoint,
-        )?;
+        // import the chunks of the checkpoint next to the historical state dir
+        let import_path = path.with_extension("checkpoint");
+        let mut importer = CheckpointImporter::new(&import_path, manifest, Options::default())?;
+        let missing: Vec<_> = importer
+            .missing()?
+            .into_iter()
+            .map(|chunk| (chunk.table.to_owned(), chunk.index, chunk.hash))
+            .collect();
+        for (table, index, hash) in missing {
+            let content = self
+                .blockstore
+                .read_all_to_vec(&hash)
+                .await
+                .with_context(|| {
+                    format!(
+                        "Could not find checkpoint chunk {} in blockstore",
+                        chunk_name(&table, index)
+                    )
+                })?;
+            importer.import_chunk(&table, index, &content)?;
+        }
+        let (import_path, _) = importer.finish()?;
 
-        // we dont actullay need to do anything with the query runner, so we ignore it explicity
-        let _ = <c!(C::ApplicationInterface::SyncExecutor)>::new(db);
+        // create the query runner, this will move the checkpoint to the historical state dir
+        let db =
+            <c!(C::ApplicationInterface::SyncExecutor)>::atomo_from_checkpoint(path, import_path)?;
+        let query_runner = <c!(C::ApplicationInterface::SyncExecutor)>::new(db);
+
+        // remember the block the checkpoint was taken at, so the state of the blocks before it
+        // can be reconstructed from it
+        let Some(Value::BlockNumber(block_number)) =
+            query_runner.get_metadata(&Metadata::BlockNumber)
+        else {
+            return Err(anyhow::anyhow!(
+                "Checkpoint for epoch {epoch} has no block number"
+            ));
+        };
+        let checkpoint_cf = self
+            .db
+            .cf_handle(CHECKPOINT_TO_EPOCH)
+            .context("Column family `checkpoint_to_epoch` not found in db")?;
+        self.db.put_cf(
+            &checkpoint_cf,
+            block_number.to_be_bytes(),
+            epoch.to_le_bytes(),
+        )?;
 
         Ok(())
     }
 
-    fn handle_block(&self, block: Block, response: BlockExecutionResponse) -> Result<()> {
+    fn handle_block(&self, block: Block, mut response: BlockExecutionResponse) -> Result<()> {
+        let state_diff = std::mem::take(&mut response.state_diff);
         let (blk_receipt, txn_receipts) = response.to_receipts();
         let blk_info = BlockInfo {
             block,
@@ -308,15 +609,45 @@ fn handle_block(&self, block: Block, response: BlockExecutionResponse) -> Result
         self.db
             .put_cf(&blkhash_cf, blk_info.receipt.block_hash, blk_num)?;
 
+        // Store BlockNum => StateDiff
+        let diff_cf = self
+            .db
+            .cf_handle(BLKNUM_TO_DIFF)
+            .context("Column family `blknum_to_diff` not found in db")?;
+        self.db
+            .put_cf(&diff_cf, blk_num, bincode::serialize(&state_diff)?)?;
+
         // Store TxHash => TxReceipt for each tx in the block
         let txhash_cf = self
             .db
             .cf_handle(TXHASH_TO_TXRCT)
             .context("Column family `txhash_to_txrct` not found in db")?;
+        let address_cf = self
+            .db
+            .cf_handle(ADDRESS_TO_BLKNUM)
+            .context("Column family `address_to_blknum` not found in db")?;
+        let topic_cf = self
+            .db
+            .cf_handle(TOPIC_TO_BLKNUM)
+            .context("Column family `topic_to_blknum` not found in db")?;
         for txn_receipt in txn_receipts {
             let txn_receipt_bytes = bincode::serialize(&txn_receipt)?;
             self.db
                 .put_cf(&txhash_cf, txn_receipt.transaction_hash, txn_receipt_bytes)?;
+
+            // Index the logs of the transaction by address and topic
+            for log in txn_receipt.logs() {
+                let block_number = blk_info.receipt.block_number;
+                self.db.put_cf(
+                    &address_cf,
+                    index_key(log.address.as_bytes(), block_number),
+                    b"",
+                )?;
+                for topic in &log.topics {
+                    self.db
+                        .put_cf(&topic_cf, index_key(topic.as_bytes(), block_number), b"")?;
+                }
+            }
         }
         Ok(())
     }
@@ -356,6 +687,15 @@ async fn insertion_task<C: Collection>(
     }
 }
 
+/// Index keys are the indexed value followed by the big endian block number, so that the blocks
+/// of a value are iterated in order.
+fn index_key(prefix: &[u8], blk_num: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(prefix.len() + 8);
+    key.extend_from_slice(prefix);
+    key.extend_from_slice(&blk_num.to_be_bytes());
+    key
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) struct BlockInfo {
     pub block: Block,
@@ -1,12 +1,30 @@
 use std::time::Duration;
 
-use ethers::types::BlockNumber;
+use ethers::types::{Address, BlockNumber, Filter, H256};
+use fleek_crypto::{
+    AccountOwnerSecretKey,
+    ConsensusSecretKey,
+    EthAddress,
+    NodeSecretKey,
+    SecretKey,
+};
+use hp_fixed::unsigned::HpUfixed;
 use lightning_application::app::Application;
-use lightning_application::config::Config as AppConfig;
+use lightning_application::config::{Config as AppConfig, Mode, StorageConfig};
+use lightning_application::genesis::{Genesis, GenesisAccount, GenesisNode};
+use lightning_application::query_runner::QueryRunner;
 use lightning_blockstore::blockstore::Blockstore;
 use lightning_blockstore::config::Config as BlockstoreConfig;
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::{partial, Ref};
+use lightning_interfaces::types::{
+    NodePorts,
+    Staking,
+    Tokens,
+    UpdateMethod,
+    UpdatePayload,
+    UpdateRequest,
+};
+use lightning_interfaces::{partial, Ref, ToDigest};
 use lightning_notifier::Notifier;
 use lightning_test_utils::consensus::{
     Config as MockConsensusConfig,
@@ -31,7 +49,7 @@
     ConfigProviderInterface = JsonConfigProvider;
 });
 
-async fn get_node() -> Node<TestBinding> {
+async fn get_node(app_config: AppConfig) -> Node<TestBinding> {
     let path = std::env::temp_dir().join(std::thread::current().name().unwrap());
 
     if path.exists() {
@@ -42,7 +60,7 @@ async fn get_node() -> Node<TestBinding> {
 
     let node = Node::<TestBinding>::init(
         JsonConfigProvider::default()
-            .with::<Application<TestBinding>>(AppConfig::test())
+            .with::<Application<TestBinding>>(app_config)
             .with::<Archive<TestBinding>>(ArchiveConfig {
                 is_archive: true,
                 store_path: {
@@ -57,6 +75,7 @@ async fn get_node() -> Node<TestBinding> {
                     p.push("store");
                     p
                 },
+                ..Default::default()
             })
             .with::<MockConsensus<TestBinding>>(MockConsensusConfig {
                 min_ordering_time: 0,
@@ -79,7 +98,7 @@ async fn get_node() -> Node<TestBinding> {
 async fn test_archive_api() {
     const NUM_TX: usize = 3;
 
-    let mut node = get_node().await;
+    let mut node = get_node(AppConfig::test()).await;
 
     let archive: Ref<Archive<TestBinding>> = node.provider.get();
 
@@ -188,3 +207,237 @@ async fn test_archive_api() {
 
     node.shutdown().await;
 }
+
+#[tokio::test]
+async fn test_archive_logs() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    let chain_id = genesis.chain_id;
+    let mut node = get_node(AppConfig {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        ..AppConfig::test()
+    })
+    .await;
+
+    let archive: Ref<Archive<TestBinding>> = node.provider.get();
+
+    let notifier: Ref<Notifier<TestBinding>> = node.provider.get();
+    let mut sub = notifier.subscribe_block_executed();
+
+    let forwarder: Ref<MockForwarder<TestBinding>> = node.provider.get();
+    let socket = forwarder.mempool_socket();
+
+    // Transfer some FLK, which emits a transfer event.
+    let payload = UpdatePayload {
+        sender: owner_secret_key.to_pk().into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::Transfer {
+            amount: 10u64.into(),
+            token: Tokens::FLK,
+            to: recipient,
+        },
+        chain_id,
+    };
+    let signature = owner_secret_key.sign(&payload.to_digest());
+    let transfer = UpdateRequest {
+        signature: signature.into(),
+        payload,
+    };
+    socket
+        .run(types::TransactionRequest::UpdateRequest(transfer))
+        .await
+        .unwrap();
+
+    let n = sub.recv().await.unwrap();
+    let logs = n.response.txn_receipts[0].logs();
+    assert_eq!(logs.len(), 1);
+
+    let flk = Address::from(Tokens::FLK.address().0);
+    let mut recipient_topic = H256::zero();
+    recipient_topic.0[12..].copy_from_slice(&recipient.0);
+
+    // The log is found by its token address and topics.
+    let filter = Filter::new()
+        .from_block(BlockNumber::Earliest)
+        .address(flk)
+        .event("Transfer(address,address,uint256)")
+        .topic2(recipient_topic);
+    assert_eq!(archive.get_logs(filter).await.unwrap(), logs);
+
+    // The default range is the latest block.
+    assert_eq!(archive.get_logs(Filter::new()).await.unwrap(), logs);
+
+    // Filters that do not match the log return nothing.
+    let filter = Filter::new()
+        .from_block(BlockNumber::Earliest)
+        .address(Address::from(Tokens::USDC.address().0));
+    assert!(archive.get_logs(filter).await.unwrap().is_empty());
+    let filter = Filter::new()
+        .from_block(BlockNumber::Earliest)
+        .topic1(recipient_topic);
+    assert!(archive.get_logs(filter).await.unwrap().is_empty());
+
+    node.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_archive_historical_block_state() {
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let node_secret_key = NodeSecretKey::generate();
+    let node_public_key = node_secret_key.to_pk();
+
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    // The node is the only member of the committee, so its signal alone changes the epoch.
+    genesis.node_info = vec![GenesisNode::new(
+        owner,
+        node_public_key,
+        "127.0.0.1".parse().unwrap(),
+        ConsensusSecretKey::generate().to_pk(),
+        "127.0.0.1".parse().unwrap(),
+        node_public_key,
+        NodePorts {
+            primary: 48000,
+            worker: 48101,
+            mempool: 48202,
+            rpc: 48300,
+            pool: 48400,
+            pinger: 48600,
+            handshake: Default::default(),
+        },
+        Some(Staking {
+            staked: genesis.min_stake.into(),
+            stake_locked_until: 0,
+            locked: HpUfixed::zero(),
+            locked_until: 0,
+        }),
+        true,
+    )];
+    let chain_id = genesis.chain_id;
+
+    // Checkpoints are only written by the rocks db storage.
+    let db_path = std::env::temp_dir()
+        .join(std::thread::current().name().unwrap())
+        .join("app");
+    let mut node = get_node(AppConfig {
+        genesis: Some(genesis),
+        mode: Mode::Test,
+        storage: StorageConfig::RocksDb,
+        db_path: Some(db_path.try_into().unwrap()),
+        ..AppConfig::test()
+    })
+    .await;
+
+    let archive: Ref<Archive<TestBinding>> = node.provider.get();
+
+    let notifier: Ref<Notifier<TestBinding>> = node.provider.get();
+    let mut sub = notifier.subscribe_block_executed();
+
+    let forwarder: Ref<MockForwarder<TestBinding>> = node.provider.get();
+    let socket = forwarder.mempool_socket();
+
+    // Transfer some FLK twice, each transfer is executed in its own block.
+    let mut blocks = Vec::new();
+    for nonce in 1..=2 {
+        let payload = UpdatePayload {
+            sender: owner_secret_key.to_pk().into(),
+            nonce,
+            secondary_nonce: nonce as u128,
+            method: UpdateMethod::Transfer {
+                amount: 10u64.into(),
+                token: Tokens::FLK,
+                to: recipient,
+            },
+            chain_id,
+        };
+        let signature = owner_secret_key.sign(&payload.to_digest());
+        socket
+            .run(types::TransactionRequest::UpdateRequest(UpdateRequest {
+                signature: signature.into(),
+                payload,
+            }))
+            .await
+            .unwrap();
+        blocks.push(sub.recv().await.unwrap().response.block_number);
+    }
+
+    // Change the epoch, the archive imports the checkpoint that is written at the end of it.
+    let payload = UpdatePayload {
+        sender: node_public_key.into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::ChangeEpoch { epoch: 0 },
+        chain_id,
+    };
+    let signature = node_secret_key.sign(&payload.to_digest());
+    socket
+        .run(types::TransactionRequest::UpdateRequest(UpdateRequest {
+            signature: signature.into(),
+            payload,
+        }))
+        .await
+        .unwrap();
+    let n = sub.recv().await.unwrap();
+    assert!(n.response.change_epoch);
+    let checkpoint_block = n.response.block_number;
+
+    let state = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            if let Some(state) = archive.get_historical_block_state(blocks[0]).await {
+                break state;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("the checkpoint should be imported");
+
+    let balance = |state: &QueryRunner| state.get_account_info(&recipient, |info| info.flk_balance);
+
+    // The state of each block only has the transfers up to it.
+    assert_eq!(balance(&state), Some(HpUfixed::<18>::from(10u64)));
+    let state = archive.get_historical_block_state(blocks[1]).await.unwrap();
+    assert_eq!(balance(&state), Some(HpUfixed::<18>::from(20u64)));
+    let state = archive
+        .get_historical_block_state(checkpoint_block)
+        .await
+        .unwrap();
+    assert_eq!(balance(&state), Some(HpUfixed::<18>::from(20u64)));
+    let state = archive
+        .get_historical_block_state(blocks[0] - 1)
+        .await
+        .unwrap();
+    assert_eq!(balance(&state), None);
+
+    // Opened states are reused and stay the same.
+    let state = archive.get_historical_block_state(blocks[0]).await.unwrap();
+    assert_eq!(balance(&state), Some(HpUfixed::<18>::from(10u64)));
+
+    // Blocks after the last checkpoint are not available yet.
+    assert!(
+        archive
+            .get_historical_block_state(checkpoint_block + 1)
+            .await
+            .is_none()
+    );
+
+    node.shutdown().await;
+}
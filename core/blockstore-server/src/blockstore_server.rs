@@ -5,6 +5,7 @@
 use std::collections::{HashMap, VecDeque};
 use std::mem;
 use std::net::SocketAddr;
+use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -18,9 +19,13 @@
     Blake3Hash,
     CompressionAlgoSet,
     CompressionAlgorithm,
+    ContentPart,
     NodeIndex,
+    PeerBlock,
     PeerRequestError,
     RejectReason,
+    ServerPartRequest,
+    ServerPartResponse,
     ServerRequest,
 };
 use lightning_interfaces::ServiceScope;
@@ -35,18 +40,24 @@
 use crate::config::Config;
 
 type ServerRequestTask = Task<ServerRequest, broadcast::Receiver<Result<(), PeerRequestError>>>;
+type ServerPartTask = Task<ServerPartRequest, Result<ServerPartResponse, PeerRequestError>>;
 
 const REQUEST_TIMEOUT: Duration = Duration::from_millis(1000);
 
 pub struct BlockstoreServer<C: Collection> {
     inner: Option<BlockstoreServerInner<C>>,
     socket: BlockstoreServerSocket,
+    part_socket: BlockstoreServerPartSocket,
 }
 
 impl<C: Collection> BlockstoreServerInterface<C> for BlockstoreServer<C> {
     fn get_socket(&self) -> BlockstoreServerSocket {
         self.socket.clone()
     }
+
+    fn get_part_socket(&self) -> BlockstoreServerPartSocket {
+        self.part_socket.clone()
+    }
 }
 
 impl<C: Collection> BlockstoreServer<C> {
@@ -59,17 +70,24 @@ fn init(
         let config = config.get::<Self>();
         let (pool_requester, pool_responder) = pool.open_req_res(ServiceScope::BlockstoreServer);
         let (socket, request_rx) = Socket::raw_bounded(2048);
+        let (part_socket, part_rx) = Socket::raw_bounded(2048);
         let inner = Some(BlockstoreServerInner::<C>::new(
             blockstore.clone(),
             request_rx,
+            part_rx,
             config.max_conc_req,
             config.max_conc_res,
+            config.compression,
             pool_requester,
             pool_responder,
             rep_aggregator.get_reporter(),
         ));
 
-        Ok(Self { inner, socket })
+        Ok(Self {
+            inner,
+            socket,
+            part_socket,
+        })
     }
 
     /// Start the system, should only be called once
@@ -93,8 +111,10 @@ fn build_graph() -> fdi::DependencyGraph {
 pub struct BlockstoreServerInner<C: Collection> {
     blockstore: C::BlockstoreInterface,
     request_rx: mpsc::Receiver<ServerRequestTask>,
+    part_rx: mpsc::Receiver<ServerPartTask>,
     max_conc_req: usize,
     max_conc_res: usize,
+    compression: CompressionAlgoSet,
     num_responses: Arc<AtomicUsize>,
     pool_requester: c!(C::PoolInterface::Requester),
     pool_responder: c!(C::PoolInterface::Responder),
@@ -106,8 +126,10 @@ impl<C: Collection> BlockstoreServerInner<C> {
     pub fn new(
         blockstore: C::BlockstoreInterface,
         request_rx: mpsc::Receiver<ServerRequestTask>,
+        part_rx: mpsc::Receiver<ServerPartTask>,
         max_conc_req: usize,
         max_conc_res: usize,
+        compression: CompressionAlgoSet,
         pool_requester: c!(C::PoolInterface::Requester),
         pool_responder: c!(C::PoolInterface::Responder),
         rep_reporter: c!(C::ReputationAggregatorInterface::ReputationReporter),
@@ -115,8 +137,10 @@ pub fn new(
         Self {
             blockstore,
             request_rx,
+            part_rx,
             max_conc_req,
             max_conc_res,
+            compression,
             num_responses: AtomicUsize::new(0).into(),
             pool_requester,
             pool_responder,
@@ -179,7 +203,11 @@ pub async fn start(mut self) {
                 }
                 task = self.request_rx.recv() => {
                     if let Some(task) = task {
-                        let peer_request = PeerRequest { hash: task.request.hash };
+                        let peer_request = PeerRequest {
+                            hash: task.request.hash,
+                            compression: self.compression,
+                            part: None,
+                        };
                         let rx = if let Some(tx) = pending_requests.get(&peer_request) {
                             // If a request for this hash is currently pending, subscribe to get
                             // notified about the result.
@@ -226,6 +254,26 @@ pub async fn start(mut self) {
                         break;
                     }
                 }
+                task = self.part_rx.recv() => {
+                    if let Some(task) = task {
+                        let peer_request = PeerRequest {
+                            hash: task.request.hash,
+                            compression: self.compression,
+                            part: Some(task.request.part.clone()),
+                        };
+                        let pool_requester = self.pool_requester.clone();
+                        tokio::spawn(async move {
+                            let res = request_part::<C>(
+                                task.request.peer,
+                                peer_request,
+                                pool_requester,
+                            ).await;
+                            task.respond(res);
+                        });
+                    } else {
+                        break;
+                    }
+                }
                 Some(res) = tasks.join_next() => {
                     match res {
                         Ok(Ok(peer_request)) => {
@@ -261,14 +309,34 @@ enum Message {
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct PeerRequest {
     hash: Blake3Hash,
-    //block_counter: u32,
+    /// The compression algorithms the requester accepts the chunks in. Requests from peers
+    /// that predate compression only contain the hash, they receive uncompressed chunks.
+    /// Requests for uncompressed chunks only contain the hash as well, so that these peers can
+    /// serve them.
+    compression: CompressionAlgoSet,
+    /// The part of the content that is requested, the entire content is requested if this is
+    /// not set.
+    part: Option<ContentPart>,
 }
 
 impl From<PeerRequest> for Bytes {
     fn from(value: PeerRequest) -> Self {
-        let mut buf = BytesMut::with_capacity(value.hash.len());
+        let mut buf = BytesMut::with_capacity(value.hash.len() + 10);
         buf.put_slice(&value.hash);
-        //buf.put_u32(value.block_counter);
+        // Peers that predate compression only accept the hash. The compression is always sent
+        // along with a part, since the length is what tells the part apart from it.
+        if value.compression != CompressionAlgoSet::new() || value.part.is_some() {
+            buf.put_u8(value.compression.into());
+        }
+        match value.part {
+            None => {},
+            Some(ContentPart::Tree) => buf.put_u8(0x00),
+            Some(ContentPart::Blocks(range)) => {
+                buf.put_u8(0x01);
+                buf.put_u32(range.start);
+                buf.put_u32(range.end);
+            },
+        }
         buf.into()
     }
 }
@@ -278,14 +346,27 @@ impl TryFrom<Bytes> for PeerRequest {
 
     fn try_from(mut value: Bytes) -> Result<Self> {
         let hash_len = mem::size_of::<Blake3Hash>();
-        if value.len() != hash_len {
-            return Err(anyhow!("Number of bytes must be {}", hash_len));
+        if ![hash_len, hash_len + 1, hash_len + 2, hash_len + 10].contains(&value.len()) {
+            return Err(anyhow!("Invalid number of bytes {}", value.len()));
         }
         let hash = value.split_to(hash_len);
-        //let block_counter = value.get_u32();
+        let compression = if value.has_remaining() {
+            CompressionAlgoSet::from(value.get_u8())
+        } else {
+            CompressionAlgoSet::new()
+        };
+        let part = match value.remaining() {
+            0 => None,
+            1 if value.get_u8() == 0x00 => Some(ContentPart::Tree),
+            9 if value.get_u8() == 0x01 => {
+                Some(ContentPart::Blocks(value.get_u32()..value.get_u32()))
+            },
+            _ => return Err(anyhow!("Unknown content part")),
+        };
         Ok(Self {
             hash: hash.to_vec().try_into().unwrap(),
-            //block_counter,
+            compression,
+            part,
         })
     }
 }
@@ -293,6 +374,11 @@ fn try_from(mut value: Bytes) -> Result<Self> {
 pub enum Frame<'a> {
     Proof(Cow<'a, [u8]>),
     Chunk(Cow<'a, [u8]>),
+    /// A chunk that is compressed with the given algorithm, which is one the requester
+    /// accepts.
+    CompressedChunk(CompressionAlgorithm, Cow<'a, [u8]>),
+    /// The hash tree of the content, sent in response to a request for the tree.
+    Tree(Cow<'a, [u8]>),
     Eos,
 }
 
@@ -311,6 +397,15 @@ fn from(value: Frame) -> Self {
             Frame::Eos => {
                 b.put_u8(0x02);
             },
+            Frame::CompressedChunk(compression, chunk) => {
+                b.put_u8(0x03);
+                b.put_u8(compression as u8);
+                b.put_slice(&chunk);
+            },
+            Frame::Tree(tree) => {
+                b.put_u8(0x04);
+                b.put_slice(&tree);
+            },
         }
         b.freeze()
     }
@@ -324,6 +419,15 @@ fn try_from(mut value: Bytes) -> Result<Self> {
             0x00 => Ok(Frame::Proof(Cow::Owned(value.to_vec()))),
             0x01 => Ok(Frame::Chunk(Cow::Owned(value.to_vec()))),
             0x02 => Ok(Frame::Eos),
+            0x03 if value.has_remaining() => {
+                let compression = CompressionAlgorithm::try_from(value.get_u8())
+                    .map_err(|algo| anyhow!("Unknown compression algorithm {algo}"))?;
+                Ok(Frame::CompressedChunk(
+                    compression,
+                    Cow::Owned(value.to_vec()),
+                ))
+            },
+            0x04 => Ok(Frame::Tree(Cow::Owned(value.to_vec()))),
             _ => Err(anyhow!("Unknown magic byte")),
         }
     }
@@ -352,16 +456,44 @@ async fn handle_request<C: Collection>(
     if let Some(tree) = blockstore.get_tree(&peer_request.hash).await {
         let mut num_bytes = 0;
         let instant = Instant::now();
-        for block in 0..tree.len() {
-            let compr = CompressionAlgoSet::default(); // rustfmt
+        let hashes: &[[u8; 32]] = tree.as_ref();
+        let blocks = match peer_request.part {
+            Some(ContentPart::Tree) => {
+                // Only the tree is sent, the requester verifies it against the root hash.
+                0..0
+            },
+            Some(ContentPart::Blocks(range)) => {
+                let end = (range.end as usize).min(tree.len());
+                (range.start as usize).min(end)..end
+            },
+            None => 0..tree.len(),
+        };
+
+        if let Some(ContentPart::Tree) = peer_request.part {
+            let tree_bytes = hashes.concat();
+            num_bytes += tree_bytes.len();
+            if let Err(e) = request
+                .send(Bytes::from(Frame::Tree(Cow::Owned(tree_bytes))))
+                .await
+            {
+                error!("Failed to send tree: {e:?}");
+                num_responses.fetch_sub(1, Ordering::Release);
+                return;
+            }
+        }
+
+        for block in blocks.clone() {
+            let compr = peer_request.compression;
             let Some(chunk) = blockstore.get(block as u32, &tree[block], compr).await else {
                 break;
             };
 
-            let proof = if block == 0 {
-                ProofBuf::new(tree.as_ref(), 0)
+            // The proof of the first block of a range has to be self-contained, since the
+            // requester starts verifying from it.
+            let proof = if block == blocks.start {
+                ProofBuf::new(hashes, block)
             } else {
-                ProofBuf::resume(tree.as_ref(), block)
+                ProofBuf::resume(hashes, block)
             };
 
             if !proof.is_empty() {
@@ -377,12 +509,12 @@ async fn handle_request<C: Collection>(
             }
 
             num_bytes += chunk.content.len();
-            if let Err(e) = request
-                .send(Bytes::from(Frame::Chunk(Cow::Borrowed(
-                    chunk.content.as_slice(),
-                ))))
-                .await
-            {
+            let content = Cow::Borrowed(chunk.content.as_slice());
+            let frame = match chunk.compression {
+                CompressionAlgorithm::Uncompressed => Frame::Chunk(content),
+                compression => Frame::CompressedChunk(compression, content),
+            };
+            if let Err(e) = request.send(Bytes::from(frame)).await {
                 error!("Failed to send chunk: {e:?}");
                 num_responses.fetch_sub(1, Ordering::Release);
                 return;
@@ -440,6 +572,20 @@ async fn send_request<C: Collection>(
                             Frame::Chunk(chunk) => putter
                                 .write(&chunk, CompressionAlgorithm::Uncompressed)
                                 .unwrap(),
+                            Frame::CompressedChunk(compression, chunk) => {
+                                if putter.write(&chunk, compression).is_err() {
+                                    return Err(ErrorResponse {
+                                        error: PeerRequestError::Incomplete,
+                                        request,
+                                    });
+                                }
+                            },
+                            Frame::Tree(_) => {
+                                return Err(ErrorResponse {
+                                    error: PeerRequestError::Incomplete,
+                                    request,
+                                });
+                            },
                             Frame::Eos => {
                                 // TODO: Handle premature end of stream errors instead of
                                 // unwrapping here, since we there could be an upstream blockstore
@@ -479,8 +625,92 @@ async fn send_request<C: Collection>(
     }
 }
 
+/// Request a part of a content from a peer. The part is returned as it was received, it is up
+/// to the caller to verify it and to report the peer.
+async fn request_part<C: Collection>(
+    peer: NodeIndex,
+    request: PeerRequest,
+    pool_requester: c!(C::PoolInterface::Requester),
+) -> Result<ServerPartResponse, PeerRequestError> {
+    let response = match timeout(
+        REQUEST_TIMEOUT,
+        pool_requester.request(peer, Bytes::from(request.clone())),
+    )
+    .await
+    {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => return Err(PeerRequestError::Incomplete),
+        Err(_) => return Err(PeerRequestError::Timeout),
+    };
+    response.status_code().map_err(PeerRequestError::Rejected)?;
+
+    let mut body = response.body();
+    let mut tree = None;
+    let mut blocks = Vec::new();
+    let mut proof = Vec::new();
+    while let Some(bytes) = body.next().await {
+        let frame = bytes
+            .ok()
+            .and_then(|bytes| Frame::try_from(bytes).ok())
+            .ok_or(PeerRequestError::Incomplete)?;
+        match frame {
+            Frame::Tree(bytes) => tree = Some(bytes.into_owned()),
+            Frame::Proof(bytes) => proof.extend_from_slice(&bytes),
+            Frame::Chunk(chunk) => blocks.push(PeerBlock {
+                proof: mem::take(&mut proof),
+                compression: CompressionAlgorithm::Uncompressed,
+                content: chunk.into_owned(),
+            }),
+            Frame::CompressedChunk(compression, chunk) => {
+                if !request.compression.contains(compression) {
+                    return Err(PeerRequestError::Incomplete);
+                }
+                blocks.push(PeerBlock {
+                    proof: mem::take(&mut proof),
+                    compression,
+                    content: chunk.into_owned(),
+                });
+            },
+            Frame::Eos => {
+                return match (request.part, tree) {
+                    (Some(ContentPart::Tree), Some(tree)) => Ok(ServerPartResponse::Tree(tree)),
+                    (Some(ContentPart::Blocks(_)), None) => Ok(ServerPartResponse::Blocks(blocks)),
+                    _ => Err(PeerRequestError::Incomplete),
+                };
+            },
+        }
+    }
+    Err(PeerRequestError::Incomplete)
+}
+
 impl<C: Collection> ConfigConsumer for BlockstoreServer<C> {
     const KEY: &'static str = "blockstore-server";
 
     type Config = Config;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_request_round_trip() {
+        let mut compression = CompressionAlgoSet::new();
+        compression.insert(CompressionAlgorithm::Lz4);
+        for (compression, part, len) in [
+            (CompressionAlgoSet::new(), None, 32),
+            (compression, None, 33),
+            (CompressionAlgoSet::new(), Some(ContentPart::Tree), 34),
+            (compression, Some(ContentPart::Blocks(2..5)), 42),
+        ] {
+            let request = PeerRequest {
+                hash: [7; 32],
+                compression,
+                part,
+            };
+            let bytes = Bytes::from(request.clone());
+            assert_eq!(bytes.len(), len);
+            assert_eq!(PeerRequest::try_from(bytes).unwrap(), request);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use lightning_interfaces::types::{CompressionAlgoSet, CompressionAlgorithm};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -6,6 +7,18 @@ pub struct Config {
     pub max_conc_req: usize,
     // Maximum number of concurrent peer requests we respond to.
     pub max_conc_res: usize,
+    // The compression algorithms we accept the content we request from peers in.
+    #[serde(default = "default_compression")]
+    pub compression: CompressionAlgoSet,
+}
+
+fn default_compression() -> CompressionAlgoSet {
+    let mut set = CompressionAlgoSet::new();
+    set.insert(CompressionAlgorithm::Snappy);
+    set.insert(CompressionAlgorithm::Gzip);
+    set.insert(CompressionAlgorithm::Brotli);
+    set.insert(CompressionAlgorithm::Lz4);
+    set
 }
 
 impl Default for Config {
@@ -13,6 +26,7 @@ fn default() -> Self {
         Self {
             max_conc_req: 50,
             max_conc_res: 50,
+            compression: default_compression(),
         }
     }
 }
@@ -3,7 +3,9 @@
 use std::path::PathBuf;
 use std::time::Duration;
 
-use blake3_tree::ProofBuf;
+use blake3_tree::blake3::tree::BlockHasher;
+use blake3_tree::{IncrementalVerifier, ProofBuf};
+use fleek_compression::Codec;
 use fleek_crypto::{AccountOwnerSecretKey, NodePublicKey, SecretKey};
 use lightning_application::app::Application;
 use lightning_application::config::{Config as AppConfig, Mode, StorageConfig};
@@ -15,7 +17,10 @@
 use lightning_interfaces::types::{
     CompressionAlgoSet,
     CompressionAlgorithm,
+    ContentPart,
     NodePorts,
+    ServerPartRequest,
+    ServerPartResponse,
     ServerRequest,
 };
 use lightning_notifier::Notifier;
@@ -138,10 +143,12 @@ async fn get_peers(
                         })
                         .with::<Blockstore<TestBinding>>(BlockstoreConfig {
                             root: path.join(format!("node{i}/blockstore")).try_into().unwrap(),
+                            ..Default::default()
                         })
                         .with::<BlockstoreServer<TestBinding>>(Config {
                             max_conc_req: 10,
                             max_conc_res: 10,
+                            ..Default::default()
                         }),
                 )
                 .with(keystore.clone()),
@@ -204,6 +211,8 @@ async fn test_stream_verified_content() {
             Frame::Chunk(chunk) => putter
                 .write(&chunk, CompressionAlgorithm::Uncompressed)
                 .unwrap(),
+            Frame::CompressedChunk(..) => panic!("unexpected compressed chunk"),
+            Frame::Tree(_) => panic!("unexpected tree"),
             Frame::Eos => {
                 let hash = putter.finalize().await.unwrap();
                 assert_eq!(hash, root_hash);
@@ -223,6 +232,75 @@ async fn test_stream_verified_content() {
     }
 }
 
+#[tokio::test]
+async fn test_stream_compressed_content() {
+    let (peers, path) = get_peers("stream_compressed_content", 49300, 2).await;
+
+    let content = create_content();
+
+    let mut putter = peers[0].blockstore().put(None);
+    putter
+        .write(content.as_slice(), CompressionAlgorithm::Uncompressed)
+        .unwrap();
+    let root_hash = putter.finalize().await.unwrap();
+
+    // The receiver accepts Lz4, which the sender stores its blocks with
+    let mut compr = CompressionAlgoSet::new();
+    compr.insert(CompressionAlgorithm::Lz4);
+
+    let mut network_wire = VecDeque::new();
+    let tree = peers[0].blockstore().get_tree(&root_hash).await.unwrap();
+    for block in 0..tree.len() {
+        let chunk = peers[0]
+            .blockstore()
+            .get(block as u32, &tree[block], compr)
+            .await
+            .expect("failed to get block from store");
+        assert_eq!(chunk.compression, CompressionAlgorithm::Lz4);
+        assert!(chunk.content.len() < BLOCK_SIZE);
+
+        let proof = if block == 0 {
+            ProofBuf::new(tree.as_ref().as_ref(), 0)
+        } else {
+            ProofBuf::resume(tree.as_ref().as_ref(), block)
+        };
+        if !proof.is_empty() {
+            network_wire.push_back(Frame::Proof(Cow::Owned(proof.as_slice().to_vec())));
+        }
+        network_wire.push_back(Frame::CompressedChunk(
+            chunk.compression,
+            Cow::Owned(chunk.content.clone()),
+        ));
+    }
+    network_wire.push_back(Frame::Eos);
+
+    // The frames survive the encoding and the receiver verifies the decompressed content
+    let mut putter = peers[1].blockstore().put(Some(root_hash));
+    while let Some(frame) = network_wire.pop_front() {
+        match Frame::try_from(bytes::Bytes::from(frame)).unwrap() {
+            Frame::Proof(proof) => putter.feed_proof(&proof).unwrap(),
+            Frame::Chunk(_) => panic!("unexpected uncompressed chunk"),
+            Frame::Tree(_) => panic!("unexpected tree"),
+            Frame::CompressedChunk(compression, chunk) => {
+                putter.write(&chunk, compression).unwrap()
+            },
+            Frame::Eos => {
+                let hash = putter.finalize().await.unwrap();
+                assert_eq!(hash, root_hash);
+                break;
+            },
+        }
+    }
+
+    let content1 = peers[1].blockstore().read_all_to_vec(&root_hash).await;
+    assert_eq!(content1, Some(content));
+
+    // Clean up test
+    if path.exists() {
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
+
 #[tokio::test]
 async fn test_send_and_receive() {
     let (peers, path) = get_peers("send_and_receive", 49200, 2).await;
@@ -271,3 +349,81 @@ async fn test_send_and_receive() {
         std::fs::remove_dir_all(path).unwrap();
     }
 }
+
+#[tokio::test]
+async fn test_request_parts() {
+    let (peers, path) = get_peers("request_parts", 49400, 2).await;
+    let query_runner = peers[0].app().sync_query();
+    for peer in &peers {
+        peer.inner.start().await;
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let node_index1 = query_runner
+        .pubkey_to_index(&peers[0].node_public_key)
+        .unwrap();
+
+    let content = create_content();
+    let mut putter = peers[0].blockstore().put(None);
+    putter
+        .write(&content, CompressionAlgorithm::Uncompressed)
+        .unwrap();
+    let hash = putter.finalize().await.unwrap();
+
+    let socket = peers[1].blockstore_server().get_part_socket();
+    let tree = socket
+        .run(ServerPartRequest {
+            hash,
+            peer: node_index1,
+            part: ContentPart::Tree,
+        })
+        .await
+        .expect("Failed to send request");
+    let expected_tree = peers[0].blockstore().get_tree(&hash).await.unwrap();
+    let expected_hashes: &[[u8; 32]] = expected_tree.as_ref();
+    match tree {
+        Ok(ServerPartResponse::Tree(tree)) => assert_eq!(tree, expected_hashes.concat()),
+        res => panic!("Unexpected response: {res:?}"),
+    }
+
+    // Request the blocks in the middle of the content and verify them on their own. They are
+    // sent compressed the way they are stored.
+    let blocks = socket
+        .run(ServerPartRequest {
+            hash,
+            peer: node_index1,
+            part: ContentPart::Blocks(1..3),
+        })
+        .await
+        .expect("Failed to send request");
+    let Ok(ServerPartResponse::Blocks(blocks)) = blocks else {
+        panic!("Unexpected response: {blocks:?}");
+    };
+    assert_eq!(blocks.len(), 2);
+    let mut verifier = IncrementalVerifier::new(hash, 1);
+    for (i, block) in blocks.iter().enumerate() {
+        let counter = i + 1;
+        if !block.proof.is_empty() {
+            verifier.feed_proof(&block.proof).unwrap();
+        }
+        assert_eq!(block.compression, CompressionAlgorithm::Lz4);
+        let block_content = Codec::Lz4.decompress(&block.content, BLOCK_SIZE).unwrap();
+        let mut hasher = BlockHasher::new();
+        hasher.set_block(counter);
+        hasher.update(&block_content);
+        verifier.verify(hasher).unwrap();
+        assert_eq!(
+            block_content,
+            &content[counter * BLOCK_SIZE..(counter + 1) * BLOCK_SIZE]
+        );
+    }
+
+    for mut peer in peers {
+        peer.inner.shutdown().await;
+        drop(peer);
+    }
+
+    if path.exists() {
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
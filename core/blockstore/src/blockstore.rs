@@ -1,19 +1,22 @@
 #![allow(unused)]
 
+use std::collections::HashMap;
 use std::io;
 use std::marker::PhantomData;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 
 use blake3_tree::blake3::tree::{BlockHasher, HashTreeBuilder};
 use blake3_tree::blake3::Hash;
 use blake3_tree::utils::{HashTree, HashVec};
-use blake3_tree::IncrementalVerifier;
+use blake3_tree::{IncrementalVerifier, ProofBuf};
 use bytes::{BufMut, BytesMut};
+use fleek_compression::Codec;
+use futures::Stream;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{Blake3Hash, CompressionAlgoSet, CompressionAlgorithm};
-use lightning_interfaces::ContentChunk;
-use parking_lot::RwLock;
+use lightning_interfaces::{ContentChunk, RangeBlock};
+use parking_lot::{Mutex, RwLock};
 use resolved_pathbuf::ResolvedPathBuf;
 use serde::{Deserialize, Serialize};
 use tempdir::TempDir;
@@ -21,17 +24,38 @@
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use tokio::task::JoinSet;
-use tracing::{error, trace};
+use tracing::{error, trace, warn};
 
-use crate::config::{Config, BLOCK_DIR, INTERNAL_DIR, TMP_DIR};
+use crate::compression::{algorithm, codec, decode_codecs, encode_codecs};
+use crate::config::{
+    Config,
+    ScrubberConfig,
+    BLOCK_DIR,
+    INTERNAL_DIR,
+    PINS_FILE,
+    QUARANTINE_DIR,
+    TMP_DIR,
+};
+use crate::gc::{BlockKey, ContentIndex, StoredBlock};
 use crate::put::Putter;
+use crate::scrubber::Scrubber;
 use crate::store::{Block, Store};
 
 pub const BLOCK_SIZE: usize = 256 << 10;
 
 pub struct Blockstore<C: Collection> {
     root: PathBuf,
+    /// The codec new blocks are compressed with.
+    codec: Option<Codec>,
     indexer: Arc<OnceLock<C::IndexerInterface>>,
+    index: Arc<Mutex<ContentIndex>>,
+    /// Serializes the changes to the tracked content, so that a block file is never removed
+    /// while content that refers to it is being committed.
+    gc_lock: Arc<tokio::sync::Mutex<()>>,
+    scrubber: ScrubberConfig,
+    /// The codecs of the blocks written for the content that is being put, they are recorded
+    /// along with the tree once the content is committed. Every putter gets its own.
+    written: Arc<Mutex<HashMap<BlockKey, Option<Codec>>>>,
     collection: PhantomData<C>,
 }
 
@@ -39,7 +63,12 @@ impl<C: Collection> Clone for Blockstore<C> {
     fn clone(&self) -> Self {
         Self {
             root: self.root.clone(),
+            codec: self.codec,
             indexer: self.indexer.clone(),
+            index: self.index.clone(),
+            gc_lock: self.gc_lock.clone(),
+            scrubber: self.scrubber,
+            written: self.written.clone(),
             collection: PhantomData,
         }
     }
@@ -52,13 +81,17 @@ impl<C: Collection> ConfigConsumer for Blockstore<C> {
 
 impl<C: Collection> BuildGraph for Blockstore<C> {
     fn build_graph() -> fdi::DependencyGraph {
-        fdi::DependencyGraph::new().with(Self::new.on(
-            "_post",
-            |mut this: fdi::RefMut<Self>,
-             fdi::Cloned(indexer): fdi::Cloned<C::IndexerInterface>| {
-                this.provide_indexer(indexer);
-            },
-        ))
+        fdi::DependencyGraph::new().with(
+            Self::new
+                .on(
+                    "_post",
+                    |mut this: fdi::RefMut<Self>,
+                     fdi::Cloned(indexer): fdi::Cloned<C::IndexerInterface>| {
+                        this.provide_indexer(indexer);
+                    },
+                )
+                .on("start", Self::start_scrubber),
+        )
     }
 }
 
@@ -68,6 +101,14 @@ fn new(config_provider: &C::ConfigProviderInterface) -> anyhow::Result<Self> {
     }
 
     pub fn init(config: Config) -> anyhow::Result<Self> {
+        let codec = codec(config.compression);
+        if codec.is_none() && config.compression != CompressionAlgorithm::Uncompressed {
+            anyhow::bail!(
+                "The blockstore does not support {:?} compression",
+                config.compression
+            );
+        }
+
         let root = config.root.to_path_buf();
         let internal_dir = root.join(INTERNAL_DIR);
         let block_dir = root.join(BLOCK_DIR);
@@ -78,18 +119,272 @@ pub fn init(config: Config) -> anyhow::Result<Self> {
         std::fs::create_dir_all(block_dir)?;
         std::fs::create_dir_all(tmp_dir)?;
 
+        let index = load_index(&root, &config)?;
+
         Ok(Self {
             root,
+            codec,
             indexer: Arc::new(OnceLock::new()),
+            index: Arc::new(Mutex::new(index)),
+            gc_lock: Arc::new(tokio::sync::Mutex::new(())),
+            scrubber: config.scrubber,
+            written: Default::default(),
             collection: PhantomData,
         })
     }
 
+    fn start_scrubber(
+        this: fdi::Ref<Self>,
+        fetcher: fdi::Ref<C::FetcherInterface>,
+        fdi::Cloned(waiter): fdi::Cloned<ShutdownWaiter>,
+    ) {
+        let config = this.scrubber;
+        if !config.enabled {
+            return;
+        }
+        let fetcher = config.refetch.then(|| fetcher.get_socket());
+        let scrubber = Scrubber::new(this.clone(), fetcher);
+        tokio::spawn(async move {
+            waiter.run_until_shutdown(scrubber.run(config)).await;
+        });
+    }
+
     /// Provide the blockstore with the indexer after initialization, this function
     /// should only be called once.
     pub fn provide_indexer(&mut self, indexer: C::IndexerInterface) {
         assert!(self.indexer.set(indexer).is_ok());
     }
+
+    /// Returns the number of bytes the content in the blockstore takes up on disk.
+    pub fn used_space(&self) -> u64 {
+        self.index.lock().size()
+    }
+
+    fn path(&self, location: &str, key: &Blake3Hash, tag: Option<usize>) -> PathBuf {
+        self.root.join(location).join(file_name(key, tag))
+    }
+
+    fn block_path(&self, counter: usize, key: &Blake3Hash, codec: Option<Codec>) -> PathBuf {
+        self.root
+            .join(BLOCK_DIR)
+            .join(block_file_name(counter, key, codec))
+    }
+
+    /// Returns a handle to put a content with, which keeps track of the blocks written for it.
+    fn putter_handle(&self) -> Self {
+        Self {
+            written: Default::default(),
+            ..self.clone()
+        }
+    }
+
+    fn codecs_path(&self, root: &Blake3Hash) -> PathBuf {
+        self.root.join(INTERNAL_DIR).join(codecs_file_name(root))
+    }
+
+    /// Reads a block along with the codec it is stored with. Returns `None` for blocks of
+    /// content that is not in the blockstore.
+    async fn fetch_block(
+        &self,
+        counter: usize,
+        key: &Blake3Hash,
+    ) -> Option<(Option<Codec>, Block)> {
+        let codec = self.index.lock().codec(&(counter, *key))?;
+        let path = self.block_path(counter, key, codec);
+        match fs::read(&path).await {
+            Ok(block) => {
+                trace!("Fetch {path:?}");
+                Some((codec, block))
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                error!("Failed to read {path:?}: {e:?}");
+                None
+            },
+        }
+    }
+
+    /// Records the codecs the blocks of a content are stored with next to its tree.
+    async fn save_codecs(&self, root: &Blake3Hash, codecs: &[Option<Codec>]) -> io::Result<()> {
+        let tmp_file_path = self.root.join(TMP_DIR).join(format!(
+            "{}-{}",
+            rand::random::<u64>(),
+            codecs_file_name(root)
+        ));
+        fs::write(&tmp_file_path, encode_codecs(codecs)).await?;
+        fs::rename(tmp_file_path, self.codecs_path(root)).await
+    }
+
+    /// Removes the files of content that is no longer tracked, and tells the indexer the node no
+    /// longer provides it.
+    async fn remove_files(&self, root: &Blake3Hash, orphans: Vec<StoredBlock>) {
+        let tree_path = self.path(INTERNAL_DIR, root, None);
+        if let Err(e) = fs::remove_file(&tree_path).await {
+            warn!("Failed to remove {tree_path:?}: {e:?}");
+        }
+        let codecs_path = self.codecs_path(root);
+        if let Err(e) = remove_file(&codecs_path).await {
+            warn!("Failed to remove {codecs_path:?}: {e:?}");
+        }
+        for ((counter, hash), codec) in orphans {
+            let block_path = self.block_path(counter, &hash, codec);
+            if let Err(e) = remove_file(&block_path).await {
+                warn!("Failed to remove {block_path:?}: {e:?}");
+            }
+        }
+
+        if let Some(indexer) = self.indexer.get() {
+            indexer.unregister(*root).await;
+        }
+    }
+
+    /// Returns the roots of all the content in the blockstore.
+    pub(crate) fn roots(&self) -> Vec<Blake3Hash> {
+        self.index.lock().roots()
+    }
+
+    /// Moves the files of corrupt content out of the blockstore into the quarantine directory,
+    /// so that the content is no longer served. The blocks that are shared with other content
+    /// are left in place. Returns false if the content is not in the blockstore.
+    pub(crate) async fn quarantine(&self, root: &Blake3Hash) -> io::Result<bool> {
+        let _guard = self.gc_lock.lock().await;
+        let Some(orphans) = self.index.lock().remove(root) else {
+            return Ok(false);
+        };
+
+        let dir = self
+            .root
+            .join(QUARANTINE_DIR)
+            .join(Hash::from(*root).to_hex().as_str());
+        fs::create_dir_all(&dir).await?;
+        move_file(&self.path(INTERNAL_DIR, root, None), &dir).await?;
+        move_file(&self.codecs_path(root), &dir).await?;
+        for ((counter, hash), codec) in orphans {
+            move_file(&self.block_path(counter, &hash, codec), &dir).await?;
+        }
+
+        if let Some(indexer) = self.indexer.get() {
+            indexer.unregister(*root).await;
+        }
+        Ok(true)
+    }
+
+    async fn save_pins(&self) -> io::Result<()> {
+        let pins: Vec<Blake3Hash> = self.index.lock().pins().copied().collect();
+        let bytes =
+            bincode::serialize(&pins).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp_file_path = self
+            .root
+            .join(TMP_DIR)
+            .join(format!("{}-{PINS_FILE}", rand::random::<u64>()));
+        fs::write(&tmp_file_path, bytes).await?;
+        fs::rename(tmp_file_path, self.root.join(PINS_FILE)).await
+    }
+}
+
+fn file_name(key: &Blake3Hash, tag: Option<usize>) -> String {
+    match tag {
+        Some(tag) => format!("{tag}-{}", Hash::from(*key).to_hex()),
+        None => format!("{}", Hash::from(*key).to_hex()),
+    }
+}
+
+/// Removes the file, if it exists.
+async fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Moves the file into the directory, if it exists.
+async fn move_file(path: &Path, dir: &Path) -> io::Result<()> {
+    let Some(file_name) = path.file_name() else {
+        return Ok(());
+    };
+    match fs::rename(path, dir.join(file_name)).await {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Compressed blocks have the extension of their codec appended to the file name.
+fn block_file_name(counter: usize, key: &Blake3Hash, codec: Option<Codec>) -> String {
+    let name = file_name(key, Some(counter));
+    match codec {
+        Some(codec) => format!("{name}.{}", codec.extension()),
+        None => name,
+    }
+}
+
+/// The codecs of the blocks of a content are stored next to its tree, one byte per block.
+fn codecs_file_name(root: &Blake3Hash) -> String {
+    format!("{}.codecs", file_name(root, None))
+}
+
+/// Rebuilds the index of the content from the trees and the blocks on disk.
+fn load_index(root: &Path, config: &Config) -> anyhow::Result<ContentIndex> {
+    let pins: Vec<Blake3Hash> = match std::fs::read(root.join(PINS_FILE)) {
+        Ok(bytes) => bincode::deserialize(&bytes)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let mut index = ContentIndex::new(config.eviction_policy, config.max_size, pins);
+
+    // The content is inserted from the oldest to the newest tree, so that the recency order
+    // survives a restart.
+    let mut trees = Vec::new();
+    for entry in std::fs::read_dir(root.join(INTERNAL_DIR))? {
+        let entry = entry?;
+        let Some(hash) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| Hash::from_hex(name).ok())
+        else {
+            continue;
+        };
+        let metadata = entry.metadata()?;
+        trees.push((metadata.modified()?, *hash.as_bytes(), entry.path()));
+    }
+    trees.sort_by_key(|(modified, ..)| *modified);
+
+    for (_, root_hash, path) in trees {
+        let data = std::fs::read(path)?;
+        if data.len() & 31 != 0 {
+            error!("Skipping corrupted proof on disk");
+            continue;
+        }
+        let tree_size = data.len() as u64;
+        let tree = HashTree::from_inner(HashVec::from_inner(data.into_boxed_slice()));
+        // Trees that were written before the blocks were compressed have no codecs.
+        let codecs_path = root.join(INTERNAL_DIR).join(codecs_file_name(&root_hash));
+        let codecs = match std::fs::read(codecs_path) {
+            Ok(bytes) => match decode_codecs(&bytes).filter(|codecs| codecs.len() == tree.len()) {
+                Some(codecs) => codecs,
+                None => {
+                    error!("Skipping content with corrupted block codecs on disk");
+                    continue;
+                },
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![None; tree.len()],
+            Err(e) => return Err(e.into()),
+        };
+        let blocks = codecs
+            .into_iter()
+            .enumerate()
+            .filter_map(|(counter, codec)| {
+                let key = (counter, tree[counter]);
+                let path = root
+                    .join(BLOCK_DIR)
+                    .join(block_file_name(counter, &key.1, codec));
+                let metadata = std::fs::metadata(path).ok()?;
+                Some(((key, codec), metadata.len()))
+            })
+            .collect();
+        index.insert(root_hash, blocks, tree_size);
+    }
+
+    Ok(index)
 }
 
 impl<C: Collection> BlockstoreInterface<C> for Blockstore<C> {
@@ -103,6 +398,7 @@ async fn get_tree(&self, cid: &Blake3Hash) -> Option<Self::SharedPointer<HashTre
             error!("Tried to read corrupted proof from disk");
             return None;
         }
+        self.index.lock().touch(cid);
 
         Some(Arc::new(HashTree::from_inner(HashVec::from_inner(
             data.into_boxed_slice(),
@@ -113,21 +409,82 @@ async fn get(
         &self,
         block_counter: u32,
         block_hash: &Blake3Hash,
-        _compression: CompressionAlgoSet,
+        compression: CompressionAlgoSet,
     ) -> Option<Self::SharedPointer<ContentChunk>> {
-        let block = self
-            .fetch(BLOCK_DIR, block_hash, Some(block_counter as usize))
-            .await?;
-        Some(Arc::new(ContentChunk {
-            compression: CompressionAlgorithm::Uncompressed,
-            content: block,
-        }))
+        let (codec, block) = self.fetch_block(block_counter as usize, block_hash).await?;
+        let chunk = match codec {
+            Some(codec) if !compression.contains(algorithm(Some(codec))) => {
+                let content = codec
+                    .decompress(&block, BLOCK_SIZE)
+                    .map_err(|e| error!("Failed to decompress a block: {e:?}"))
+                    .ok()?;
+                ContentChunk {
+                    compression: CompressionAlgorithm::Uncompressed,
+                    content,
+                }
+            },
+            codec => ContentChunk {
+                compression: algorithm(codec),
+                content: block,
+            },
+        };
+        Some(Arc::new(chunk))
+    }
+
+    fn read_range(
+        &self,
+        cid: &Blake3Hash,
+        offset: u64,
+        len: u64,
+    ) -> impl Stream<Item = RangeBlock<Self::SharedPointer<ContentChunk>>> + Send {
+        let cid = *cid;
+        let end = offset.saturating_add(len);
+        // Every block but the last one is full, so the covering blocks follow from the offset.
+        let first = (offset / BLOCK_SIZE as u64) as usize;
+
+        futures::stream::unfold(
+            (self.clone(), None, first),
+            move |(this, tree, counter)| async move {
+                let tree = match tree {
+                    Some(tree) => tree,
+                    None => this.get_tree(&cid).await?,
+                };
+                let block_offset = counter as u64 * BLOCK_SIZE as u64;
+                if counter >= tree.len() || block_offset >= end {
+                    return None;
+                }
+
+                let block = this
+                    .get(counter as u32, &tree[counter], CompressionAlgoSet::new())
+                    .await?;
+                let start = offset.saturating_sub(block_offset) as usize;
+                let stop = block.content.len().min((end - block_offset) as usize);
+                if start >= stop {
+                    // The offset is past the end of the content.
+                    return None;
+                }
+
+                let hashes = AsRef::<[[u8; 32]]>::as_ref(&*tree);
+                let proof = if counter == first {
+                    ProofBuf::new(hashes, counter)
+                } else {
+                    ProofBuf::resume(hashes, counter)
+                };
+                let range_block = RangeBlock {
+                    counter: counter as u32,
+                    proof: proof.as_slice().to_vec(),
+                    block,
+                    range: start..stop,
+                };
+                Some((range_block, (this, Some(tree), counter + 1)))
+            },
+        )
     }
 
     fn put(&self, root: Option<Blake3Hash>) -> Self::Put {
         match root {
             Some(root) => Putter::verifier(
-                self.clone(),
+                self.putter_handle(),
                 root,
                 self.indexer
                     .get()
@@ -135,7 +492,7 @@ fn put(&self, root: Option<Blake3Hash>) -> Self::Put {
                     .expect("Indexer to have been set"),
             ),
             None => Putter::trust(
-                self.clone(),
+                self.putter_handle(),
                 self.indexer
                     .get()
                     .cloned()
@@ -151,6 +508,40 @@ fn put_dir(&self, root: Option<Blake3Hash>) -> Self::DirPut {
     fn get_root_dir(&self) -> PathBuf {
         self.root.to_path_buf()
     }
+
+    async fn pin(&self, cid: &Blake3Hash) {
+        let _guard = self.gc_lock.lock().await;
+        if self.index.lock().pin(*cid) {
+            if let Err(e) = self.save_pins().await {
+                error!("Failed to persist the pin set: {e:?}");
+            }
+        }
+    }
+
+    async fn unpin(&self, cid: &Blake3Hash) {
+        let _guard = self.gc_lock.lock().await;
+        if self.index.lock().unpin(cid) {
+            if let Err(e) = self.save_pins().await {
+                error!("Failed to persist the pin set: {e:?}");
+            }
+        }
+    }
+
+    async fn remove(&self, cid: &Blake3Hash) -> bool {
+        let _guard = self.gc_lock.lock().await;
+        let orphans = {
+            let mut index = self.index.lock();
+            if index.is_pinned(cid) {
+                return false;
+            }
+            match index.remove(cid) {
+                Some(orphans) => orphans,
+                None => return false,
+            }
+        };
+        self.remove_files(cid, orphans).await;
+        true
+    }
 }
 
 impl<C> Store for Blockstore<C>
@@ -158,11 +549,7 @@ impl<C> Store for Blockstore<C>
     C: Collection,
 {
     async fn fetch(&self, location: &str, key: &Blake3Hash, tag: Option<usize>) -> Option<Block> {
-        let filename = match tag {
-            Some(tag) => format!("{tag}-{}", Hash::from(*key).to_hex()),
-            None => format!("{}", Hash::from(*key).to_hex()),
-        };
-        let path = self.root.to_path_buf().join(location).join(filename);
+        let path = self.path(location, key, tag);
         trace!("Fetch {path:?}");
         fs::read(path).await.ok()
     }
@@ -174,9 +561,33 @@ async fn insert(
         block: &[u8],
         tag: Option<usize>,
     ) -> io::Result<()> {
-        let filename = match tag {
-            Some(tag) => format!("{tag}-{}", Hash::from(key).to_hex()),
-            None => format!("{}", Hash::from(key).to_hex()),
+        let compressed;
+        let (filename, block) = match (location, tag) {
+            (BLOCK_DIR, Some(counter)) => {
+                // A block that is already stored is not written again and keeps its codec.
+                let stored = self.index.lock().codec(&(counter, key));
+                if let Some(codec) = stored {
+                    self.written.lock().insert((counter, key), codec);
+                    return Ok(());
+                }
+
+                // Blocks that do not get smaller when compressed are stored as they are.
+                compressed = match self.codec {
+                    Some(codec) => Some((codec, codec.compress(block)?))
+                        .filter(|(_, compressed)| compressed.len() < block.len()),
+                    None => None,
+                };
+                let codec = compressed.as_ref().map(|(codec, _)| *codec);
+                self.written.lock().insert((counter, key), codec);
+                match &compressed {
+                    Some((codec, compressed)) => (
+                        block_file_name(counter, &key, Some(*codec)),
+                        compressed.as_slice(),
+                    ),
+                    None => (block_file_name(counter, &key, None), block),
+                }
+            },
+            _ => (file_name(&key, tag), block),
         };
         let tmp_file_name = format!("{}-{}", rand::random::<u64>(), filename);
         let tmp_file_path = self.root.to_path_buf().join(TMP_DIR).join(&tmp_file_name);
@@ -194,4 +605,44 @@ async fn insert(
         }
         Ok(())
     }
+
+    async fn commit(&mut self, root: Blake3Hash, tree: &[[u8; 32]]) -> io::Result<()> {
+        let tree = HashTree::from(tree);
+        let _guard = self.gc_lock.lock().await;
+
+        let mut blocks = Vec::with_capacity(tree.len());
+        let mut codecs = Vec::with_capacity(tree.len());
+        for counter in 0..tree.len() {
+            let key = (counter, tree[counter]);
+            let written = self.written.lock().remove(&key);
+            let codec = match written {
+                Some(codec) => codec,
+                None => self
+                    .index
+                    .lock()
+                    .codec(&key)
+                    .ok_or(io::ErrorKind::NotFound)?,
+            };
+            // This fails if a block of this content was written before an eviction removed it,
+            // in which case the content has to be put again.
+            let size = fs::metadata(self.block_path(counter, &key.1, codec))
+                .await?
+                .len();
+            blocks.push(((key, codec), size));
+            codecs.push(codec);
+        }
+        let tree_size = AsRef::<[[u8; 32]]>::as_ref(&tree).len() as u64 * 32;
+        self.save_codecs(&root, &codecs).await?;
+
+        let evicted = {
+            let mut index = self.index.lock();
+            index.insert(root, blocks, tree_size);
+            index.evict(&root)
+        };
+        for (evicted_root, orphans) in evicted {
+            trace!("Evicting {}", Hash::from(evicted_root).to_hex());
+            self.remove_files(&evicted_root, orphans).await;
+        }
+        Ok(())
+    }
 }
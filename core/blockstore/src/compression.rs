@@ -0,0 +1,43 @@
+use fleek_compression::Codec;
+use lightning_interfaces::types::CompressionAlgorithm;
+
+/// Returns the codec of a compression algorithm. Returns [`None`] for uncompressed content and
+/// for the algorithms the blockstore does not support.
+pub fn codec(compression: CompressionAlgorithm) -> Option<Codec> {
+    match compression {
+        CompressionAlgorithm::Snappy => Some(Codec::Snappy),
+        CompressionAlgorithm::Gzip => Some(Codec::Gzip),
+        CompressionAlgorithm::Brotli => Some(Codec::Brotli),
+        CompressionAlgorithm::Lz4 => Some(Codec::Lz4),
+        CompressionAlgorithm::Uncompressed | CompressionAlgorithm::Lzma => None,
+    }
+}
+
+/// Returns the compression algorithm of a codec.
+pub fn algorithm(codec: Option<Codec>) -> CompressionAlgorithm {
+    match codec {
+        None => CompressionAlgorithm::Uncompressed,
+        Some(Codec::Snappy) => CompressionAlgorithm::Snappy,
+        Some(Codec::Gzip) => CompressionAlgorithm::Gzip,
+        Some(Codec::Brotli) => CompressionAlgorithm::Brotli,
+        Some(Codec::Lz4) => CompressionAlgorithm::Lz4,
+    }
+}
+
+/// Encodes the codecs the blocks of a content are stored with, one byte per block.
+pub fn encode_codecs(codecs: &[Option<Codec>]) -> Vec<u8> {
+    codecs.iter().map(|codec| algorithm(*codec) as u8).collect()
+}
+
+/// Decodes the codecs encoded by [`encode_codecs`]. Returns [`None`] if a byte is not the
+/// algorithm of a codec.
+pub fn decode_codecs(bytes: &[u8]) -> Option<Vec<Option<Codec>>> {
+    bytes
+        .iter()
+        .map(|byte| {
+            let compression = CompressionAlgorithm::try_from(*byte).ok()?;
+            let codec = codec(compression);
+            (algorithm(codec) == compression).then_some(codec)
+        })
+        .collect()
+}
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use lightning_interfaces::types::CompressionAlgorithm;
 use resolved_pathbuf::ResolvedPathBuf;
 use serde::{Deserialize, Serialize};
 
@@ -5,16 +8,77 @@
 pub const INTERNAL_DIR: &str = "internal";
 pub const BLOCK_DIR: &str = "block";
 pub const TMP_DIR: &str = "tmp";
+pub const PINS_FILE: &str = "pins";
+pub const QUARANTINE_DIR: &str = "quarantine";
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     pub root: ResolvedPathBuf,
+    /// The maximum number of bytes the content of the blockstore may take up on disk. Once it
+    /// is exceeded unpinned content is evicted according to the eviction policy. The blockstore
+    /// is unbounded if this is not set.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    /// Determines which content is evicted first once the blockstore exceeds its maximum size.
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
+    /// The algorithm the blocks are compressed with on disk, blocks are stored uncompressed
+    /// unless this is set. Blocks that do not get smaller when compressed are stored
+    /// uncompressed. Lzma is not supported.
+    #[serde(default = "default_compression")]
+    pub compression: CompressionAlgorithm,
+    /// Periodically re-verifies the content on disk against its tree. It is disabled unless
+    /// enabled here.
+    #[serde(default)]
+    pub scrubber: ScrubberConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct ScrubberConfig {
+    /// Whether the scrubber runs.
+    pub enabled: bool,
+    /// The time between the starts of two passes over all of the content.
+    pub interval: Duration,
+    /// The time to wait after verifying a content, which bounds the load on the disk.
+    pub pause: Duration,
+    /// Whether to fetch corrupt content again from the network once it is quarantined.
+    pub refetch: bool,
+}
+
+impl Default for ScrubberConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: Duration::from_secs(6 * 60 * 60),
+            pause: Duration::from_millis(10),
+            refetch: false,
+        }
+    }
+}
+
+fn default_compression() -> CompressionAlgorithm {
+    CompressionAlgorithm::Uncompressed
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the content that was read or written the longest time ago.
+    #[default]
+    Lru,
+    /// Evict the content that was read the least number of times, ties are broken by
+    /// recency.
+    Lfu,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             root: ResolvedPathBuf::try_from(ROOT_DIR_DEFAULT).unwrap(),
+            max_size: None,
+            eviction_policy: EvictionPolicy::default(),
+            compression: default_compression(),
+            scrubber: ScrubberConfig::default(),
         }
     }
 }
@@ -0,0 +1,307 @@
+use std::collections::{HashMap, HashSet};
+
+use fleek_compression::Codec;
+use lightning_interfaces::types::Blake3Hash;
+
+use crate::config::EvictionPolicy;
+
+/// A block file is addressed by its block counter and its hash.
+pub type BlockKey = (usize, Blake3Hash);
+
+/// A block file along with the codec it is stored with, which is part of its file name.
+pub type StoredBlock = (BlockKey, Option<Codec>);
+
+/// The bookkeeping the blockstore uses to stay within its size quota.
+///
+/// Every content in the blockstore is tracked by its root hash along with the blocks it is made
+/// of. The same block file can be part of several contents, so blocks are reference counted and
+/// a block file is only removed once no content refers to it anymore. Pinned content is never
+/// evicted.
+pub struct ContentIndex {
+    policy: EvictionPolicy,
+    max_size: Option<u64>,
+    roots: HashMap<Blake3Hash, RootEntry>,
+    blocks: HashMap<BlockKey, BlockEntry>,
+    pins: HashSet<Blake3Hash>,
+    /// The number of bytes of all the tracked blocks and trees.
+    size: u64,
+    /// A logical clock that orders the uses of the content.
+    clock: u64,
+}
+
+struct RootEntry {
+    blocks: Vec<BlockKey>,
+    tree_size: u64,
+    last_used: u64,
+    uses: u64,
+}
+
+struct BlockEntry {
+    refs: usize,
+    size: u64,
+    codec: Option<Codec>,
+}
+
+impl ContentIndex {
+    pub fn new(
+        policy: EvictionPolicy,
+        max_size: Option<u64>,
+        pins: impl IntoIterator<Item = Blake3Hash>,
+    ) -> Self {
+        Self {
+            policy,
+            max_size,
+            roots: HashMap::new(),
+            blocks: HashMap::new(),
+            pins: pins.into_iter().collect(),
+            size: 0,
+            clock: 0,
+        }
+    }
+
+    /// Returns the number of bytes of the tracked content.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Track a content with the given blocks and their sizes. If the content is already tracked
+    /// this only counts as a use of it. A block that is already tracked keeps its codec.
+    pub fn insert(&mut self, root: Blake3Hash, blocks: Vec<(StoredBlock, u64)>, tree_size: u64) {
+        if self.roots.contains_key(&root) {
+            self.touch(&root);
+            return;
+        }
+
+        let mut keys = Vec::with_capacity(blocks.len());
+        for ((key, codec), size) in blocks {
+            let entry = self.blocks.entry(key).or_insert_with(|| {
+                self.size += size;
+                BlockEntry {
+                    refs: 0,
+                    size,
+                    codec,
+                }
+            });
+            entry.refs += 1;
+            keys.push(key);
+        }
+
+        self.size += tree_size;
+        self.clock += 1;
+        self.roots.insert(
+            root,
+            RootEntry {
+                blocks: keys,
+                tree_size,
+                last_used: self.clock,
+                uses: 0,
+            },
+        );
+    }
+
+    /// Returns the codec the block is stored with, or `None` if the block is not tracked.
+    pub fn codec(&self, key: &BlockKey) -> Option<Option<Codec>> {
+        self.blocks.get(key).map(|block| block.codec)
+    }
+
+    /// Record a read of the content.
+    pub fn touch(&mut self, root: &Blake3Hash) {
+        if let Some(entry) = self.roots.get_mut(root) {
+            self.clock += 1;
+            entry.last_used = self.clock;
+            entry.uses += 1;
+        }
+    }
+
+    /// Stop tracking the content and return the blocks that are no longer referred to by any
+    /// other content. Returns `None` if the content is not tracked.
+    pub fn remove(&mut self, root: &Blake3Hash) -> Option<Vec<StoredBlock>> {
+        let entry = self.roots.remove(root)?;
+        self.size -= entry.tree_size;
+
+        let mut orphans = Vec::new();
+        for key in entry.blocks {
+            let Some(block) = self.blocks.get_mut(&key) else {
+                continue;
+            };
+            block.refs -= 1;
+            if block.refs == 0 {
+                self.size -= block.size;
+                orphans.push((key, block.codec));
+                self.blocks.remove(&key);
+            }
+        }
+
+        Some(orphans)
+    }
+
+    /// Returns true if the pin set changed.
+    pub fn pin(&mut self, root: Blake3Hash) -> bool {
+        self.pins.insert(root)
+    }
+
+    /// Returns true if the pin set changed.
+    pub fn unpin(&mut self, root: &Blake3Hash) -> bool {
+        self.pins.remove(root)
+    }
+
+    pub fn is_pinned(&self, root: &Blake3Hash) -> bool {
+        self.pins.contains(root)
+    }
+
+    pub fn pins(&self) -> impl Iterator<Item = &Blake3Hash> {
+        self.pins.iter()
+    }
+
+    /// Returns the roots of all the tracked content.
+    pub fn roots(&self) -> Vec<Blake3Hash> {
+        self.roots.keys().copied().collect()
+    }
+
+    /// Evict unpinned content until the tracked size is within the quota, the content in `keep`
+    /// is never evicted. Returns the evicted roots along with the blocks that are no longer
+    /// referred to, whose files should be removed.
+    pub fn evict(&mut self, keep: &Blake3Hash) -> Vec<(Blake3Hash, Vec<StoredBlock>)> {
+        let Some(max_size) = self.max_size else {
+            return Vec::new();
+        };
+        if self.size <= max_size {
+            return Vec::new();
+        }
+
+        let mut candidates: Vec<_> = self
+            .roots
+            .iter()
+            .filter(|(root, _)| *root != keep && !self.pins.contains(*root))
+            .map(|(root, entry)| {
+                let rank = match self.policy {
+                    EvictionPolicy::Lru => (entry.last_used, 0),
+                    EvictionPolicy::Lfu => (entry.uses, entry.last_used),
+                };
+                (rank, *root)
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        let mut evicted = Vec::new();
+        for (_, root) in candidates {
+            if self.size <= max_size {
+                break;
+            }
+            if let Some(orphans) = self.remove(&root) {
+                evicted.push((root, orphans));
+            }
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(i: u8) -> Blake3Hash {
+        [i; 32]
+    }
+
+    fn block(counter: usize, i: u8) -> StoredBlock {
+        ((counter, [i; 32]), None)
+    }
+
+    /// Inserts a content made of a single block of 100 bytes without a tree.
+    fn insert(index: &mut ContentIndex, i: u8) {
+        index.insert(root(i), vec![(block(0, i), 100)], 0);
+    }
+
+    fn evicted_roots(evicted: Vec<(Blake3Hash, Vec<StoredBlock>)>) -> Vec<Blake3Hash> {
+        evicted.into_iter().map(|(root, _)| root).collect()
+    }
+
+    #[test]
+    fn lru_evicts_the_least_recently_used_content() {
+        let mut index = ContentIndex::new(EvictionPolicy::Lru, Some(300), []);
+        insert(&mut index, 1);
+        insert(&mut index, 2);
+        insert(&mut index, 3);
+        index.touch(&root(1));
+
+        insert(&mut index, 4);
+        assert_eq!(evicted_roots(index.evict(&root(4))), vec![root(2)]);
+        assert_eq!(index.size(), 300);
+
+        // Within the quota nothing is evicted.
+        assert!(index.evict(&root(4)).is_empty());
+    }
+
+    #[test]
+    fn lfu_evicts_the_least_frequently_used_content() {
+        let mut index = ContentIndex::new(EvictionPolicy::Lfu, Some(300), []);
+        insert(&mut index, 1);
+        insert(&mut index, 2);
+        insert(&mut index, 3);
+        index.touch(&root(1));
+        index.touch(&root(1));
+        index.touch(&root(2));
+        index.touch(&root(3));
+
+        // Content 2 and 3 are used as often, the one used the longest time ago goes first.
+        insert(&mut index, 4);
+        assert_eq!(evicted_roots(index.evict(&root(4))), vec![root(2)]);
+        // Content that was never read goes before the content that was.
+        insert(&mut index, 5);
+        assert_eq!(evicted_roots(index.evict(&root(5))), vec![root(4)]);
+    }
+
+    #[test]
+    fn pinned_and_kept_content_is_not_evicted() {
+        let mut index = ContentIndex::new(EvictionPolicy::Lru, Some(100), [root(1)]);
+        insert(&mut index, 1);
+        insert(&mut index, 2);
+        insert(&mut index, 3);
+
+        assert_eq!(evicted_roots(index.evict(&root(3))), vec![root(2)]);
+        // The quota can be exceeded by the content that can not be evicted.
+        assert_eq!(index.size(), 200);
+        let mut roots = index.roots();
+        roots.sort_unstable();
+        assert_eq!(roots, vec![root(1), root(3)]);
+
+        assert!(index.unpin(&root(1)));
+        assert!(!index.unpin(&root(1)));
+        assert_eq!(evicted_roots(index.evict(&root(3))), vec![root(1)]);
+    }
+
+    #[test]
+    fn shared_blocks_are_reference_counted() {
+        let mut index = ContentIndex::new(EvictionPolicy::Lru, None, []);
+        index.insert(root(1), vec![(block(0, 1), 100), (block(1, 3), 50)], 64);
+        index.insert(root(2), vec![(block(0, 2), 100), (block(1, 3), 50)], 64);
+        assert_eq!(index.size(), 100 + 100 + 50 + 64 + 64);
+
+        // Inserting the same content again only counts as a use.
+        index.insert(root(1), vec![(block(0, 1), 100), (block(1, 3), 50)], 64);
+        assert_eq!(index.size(), 100 + 100 + 50 + 64 + 64);
+
+        // Without a quota nothing is evicted.
+        assert!(index.evict(&root(2)).is_empty());
+
+        assert_eq!(index.remove(&root(1)), Some(vec![block(0, 1)]));
+        assert_eq!(index.size(), 100 + 50 + 64);
+        assert_eq!(index.remove(&root(1)), None);
+        assert_eq!(index.remove(&root(2)), Some(vec![block(0, 2), block(1, 3)]));
+        assert_eq!(index.size(), 0);
+    }
+
+    #[test]
+    fn blocks_keep_the_codec_they_were_first_stored_with() {
+        let mut index = ContentIndex::new(EvictionPolicy::Lru, None, []);
+        let compressed = ((0, [1; 32]), Some(Codec::Lz4));
+        index.insert(root(1), vec![(compressed, 100)], 0);
+        index.insert(root(2), vec![(block(0, 1), 100)], 0);
+        assert_eq!(index.codec(&(0, [1; 32])), Some(Some(Codec::Lz4)));
+        assert_eq!(index.codec(&(1, [1; 32])), None);
+
+        assert_eq!(index.remove(&root(1)), Some(vec![]));
+        assert_eq!(index.remove(&root(2)), Some(vec![compressed]));
+    }
+}
@@ -1,20 +1,29 @@
 pub mod blockstore;
+mod compression;
 pub mod config;
+mod gc;
 pub mod put;
+mod scrubber;
 mod store;
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-
-    use blake3_tree::blake3::tree::{HashTree, HashTreeBuilder};
-    use blake3_tree::ProofBuf;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    use blake3_tree::blake3::tree::{BlockHasher, HashTree, HashTreeBuilder};
+    use blake3_tree::blake3::Hash;
+    use blake3_tree::{IncrementalVerifier, ProofBuf};
+    use fleek_compression::Codec;
+    use futures::StreamExt;
     use lightning_interfaces::prelude::*;
-    use lightning_interfaces::types::{Blake3Hash, CompressionAlgorithm};
+    use lightning_interfaces::types::{Blake3Hash, CompressionAlgoSet, CompressionAlgorithm};
+    use lightning_interfaces::PutWriteError;
     use tokio::test;
 
     use crate::blockstore::{Blockstore, BLOCK_SIZE};
-    use crate::config::Config;
+    use crate::config::{Config, EvictionPolicy};
+    use crate::scrubber::Scrubber;
 
     partial!(TestBinding {
         BlockstoreInterface = Blockstore<Self>;
@@ -57,16 +66,61 @@ fn drop(&mut self) {
     async fn make_blockstore(test_name: String) -> BlockStoreCleanOnDrop {
         let path = std::env::temp_dir().join(test_name);
 
+        BlockStoreCleanOnDrop {
+            blockstore: open_blockstore(&path, Config::default()),
+            temp_dir_path: path,
+        }
+    }
+
+    async fn make_blockstore_with_quota(
+        test_name: String,
+        max_size: Option<u64>,
+        eviction_policy: EvictionPolicy,
+    ) -> BlockStoreCleanOnDrop {
+        make_blockstore_with_config(
+            test_name,
+            Config {
+                max_size,
+                eviction_policy,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    async fn make_blockstore_with_config(
+        test_name: String,
+        config: Config,
+    ) -> BlockStoreCleanOnDrop {
+        let path = std::env::temp_dir().join(test_name);
+
+        BlockStoreCleanOnDrop {
+            blockstore: open_blockstore(&path, config),
+            temp_dir_path: path,
+        }
+    }
+
+    fn open_blockstore(path: &Path, config: Config) -> Blockstore<TestBinding> {
         let mut blockstore = Blockstore::<TestBinding>::init(Config {
-            root: path.clone().try_into().unwrap(),
+            root: path.to_path_buf().try_into().unwrap(),
+            ..config
         })
         .unwrap();
         blockstore.provide_indexer(Default::default());
+        blockstore
+    }
 
-        BlockStoreCleanOnDrop {
-            blockstore,
-            temp_dir_path: path,
-        }
+    async fn put_content(blockstore: &Blockstore<TestBinding>, content: &[u8]) -> Blake3Hash {
+        let mut putter = blockstore.put(None);
+        putter
+            .write(content, CompressionAlgorithm::Uncompressed)
+            .unwrap();
+        putter.finalize().await.unwrap()
+    }
+
+    /// Returns content of two blocks filled with the given bytes.
+    fn two_blocks(first: u8, second: u8) -> Vec<u8> {
+        [[first; BLOCK_SIZE], [second; BLOCK_SIZE]].concat()
     }
 
     #[test]
@@ -277,4 +331,274 @@ async fn hash_consistency() {
         let hash = putter.finalize().await.unwrap();
         assert_eq!(&hash, output.hash.as_bytes());
     }
+
+    #[test]
+    async fn test_eviction_keeps_pinned_and_shared_blocks() {
+        // The tree of content with two blocks has three hashes.
+        const TREE_SIZE: u64 = 96;
+        const BLOCK: u64 = BLOCK_SIZE as u64;
+
+        // Given: a blockstore with room for four blocks and three trees.
+        let state = make_blockstore_with_quota(
+            format!("test-{}", std::thread::current().name().unwrap()),
+            Some(4 * BLOCK + 3 * TREE_SIZE),
+            EvictionPolicy::Lru,
+        )
+        .await;
+        let blockstore = &state.blockstore;
+
+        // Given: two contents that share their first block, which is only stored once.
+        let content_a = two_blocks(0, 1);
+        let content_b = two_blocks(0, 2);
+        let cid_a = put_content(blockstore, &content_a).await;
+        let cid_b = put_content(blockstore, &content_b).await;
+        assert_eq!(blockstore.used_space(), 3 * BLOCK + 2 * TREE_SIZE);
+
+        // When: the least recently used content is pinned and the quota is exceeded.
+        blockstore.pin(&cid_a).await;
+        let content_c = two_blocks(3, 4);
+        let cid_c = put_content(blockstore, &content_c).await;
+
+        // Then: the next content is evicted without removing the block it shares.
+        assert!(blockstore.get_tree(&cid_b).await.is_none());
+        assert_eq!(blockstore.read_all_to_vec(&cid_a).await, Some(content_a));
+        assert_eq!(blockstore.read_all_to_vec(&cid_c).await, Some(content_c));
+        assert_eq!(blockstore.used_space(), 4 * BLOCK + 2 * TREE_SIZE);
+
+        // Then: pinned content can not be removed until it is unpinned.
+        assert!(!blockstore.remove(&cid_a).await);
+        blockstore.unpin(&cid_a).await;
+        assert!(blockstore.remove(&cid_a).await);
+        assert!(!blockstore.remove(&cid_a).await);
+        assert!(blockstore.read_all_to_vec(&cid_a).await.is_none());
+        assert_eq!(blockstore.used_space(), 2 * BLOCK + TREE_SIZE);
+
+        // Then: the content and the pins are restored when the blockstore is opened again.
+        blockstore.pin(&cid_c).await;
+        let blockstore = open_blockstore(&state.temp_dir_path, Config::default());
+        assert_eq!(blockstore.used_space(), 2 * BLOCK + TREE_SIZE);
+        assert!(!blockstore.remove(&cid_c).await);
+    }
+
+    #[test]
+    async fn test_lfu_eviction() {
+        const BLOCK: u64 = BLOCK_SIZE as u64;
+
+        // Given: a blockstore with room for four blocks and their trees.
+        let state = make_blockstore_with_quota(
+            format!("test-{}", std::thread::current().name().unwrap()),
+            Some(4 * BLOCK + 1024),
+            EvictionPolicy::Lfu,
+        )
+        .await;
+        let blockstore = &state.blockstore;
+
+        // Given: content that is read twice, followed by content that is read once.
+        let cid_a = put_content(blockstore, &two_blocks(0, 1)).await;
+        blockstore.read_all_to_vec(&cid_a).await.unwrap();
+        blockstore.read_all_to_vec(&cid_a).await.unwrap();
+        let cid_b = put_content(blockstore, &two_blocks(2, 3)).await;
+        blockstore.read_all_to_vec(&cid_b).await.unwrap();
+
+        // When: the quota is exceeded.
+        let cid_c = put_content(blockstore, &two_blocks(4, 5)).await;
+
+        // Then: the least frequently used content is evicted, even though it was used last.
+        assert!(blockstore.get_tree(&cid_b).await.is_none());
+        assert!(blockstore.get_tree(&cid_a).await.is_some());
+        assert!(blockstore.get_tree(&cid_c).await.is_some());
+    }
+
+    #[test]
+    async fn test_compressed_blocks() {
+        // Given: a blockstore that compresses its blocks with brotli.
+        let state = make_blockstore_with_config(
+            format!("test-{}", std::thread::current().name().unwrap()),
+            Config {
+                compression: CompressionAlgorithm::Brotli,
+                ..Default::default()
+            },
+        )
+        .await;
+        let blockstore = &state.blockstore;
+
+        // When: we put some content.
+        let content = create_content();
+        let cid = put_content(blockstore, &content).await;
+
+        // Then: the blocks take up less space than the content.
+        assert!(blockstore.used_space() < content.len() as u64);
+
+        // Then: blocks are decompressed unless the compression is accepted.
+        let tree = blockstore.get_tree(&cid).await.unwrap();
+        let chunk = blockstore
+            .get(0, &tree[0], CompressionAlgoSet::new())
+            .await
+            .unwrap();
+        assert_eq!(chunk.compression, CompressionAlgorithm::Uncompressed);
+        assert_eq!(chunk.content, content[..BLOCK_SIZE]);
+
+        let mut compression = CompressionAlgoSet::new();
+        compression.insert(CompressionAlgorithm::Brotli);
+        let chunk = blockstore.get(0, &tree[0], compression).await.unwrap();
+        assert_eq!(chunk.compression, CompressionAlgorithm::Brotli);
+        assert_eq!(
+            Codec::Brotli
+                .decompress(&chunk.content, BLOCK_SIZE)
+                .unwrap(),
+            content[..BLOCK_SIZE]
+        );
+
+        // Then: the blocks can still be read once the compression is changed.
+        let blockstore = open_blockstore(&state.temp_dir_path, Config::default());
+        assert_eq!(
+            blockstore.read_all_to_vec(&cid).await,
+            Some(content.clone())
+        );
+
+        // Then: putting the content again does not store its blocks a second time.
+        let file_count = |blockstore: &Blockstore<TestBinding>, dir: &str| {
+            std::fs::read_dir(blockstore.get_root_dir().join(dir))
+                .unwrap()
+                .count()
+        };
+        assert_eq!(put_content(&blockstore, &content).await, cid);
+        assert_eq!(file_count(&blockstore, "block"), 4);
+        assert_eq!(blockstore.read_all_to_vec(&cid).await, Some(content));
+
+        // Then: removing the content removes its blocks along with their codecs.
+        assert!(blockstore.remove(&cid).await);
+        assert_eq!(file_count(&blockstore, "block"), 0);
+        assert_eq!(file_count(&blockstore, "internal"), 0);
+    }
+
+    #[test]
+    async fn test_put_verify_compressed() {
+        // Given: some content and its tree.
+        let content = create_content();
+        let hash_tree = hash_tree(content.as_slice());
+        let state =
+            make_blockstore(format!("test-{}", std::thread::current().name().unwrap())).await;
+
+        // When: we put the content by gzip compressed blocks and feed the proof to verify it.
+        let mut putter = state.blockstore.put(Some(Blake3Hash::from(hash_tree.hash)));
+        for (i, block) in content.chunks(BLOCK_SIZE).enumerate() {
+            let proof = new_proof(&hash_tree.tree, i);
+            putter.feed_proof(proof.as_slice()).unwrap();
+            let compressed = Codec::Gzip.compress(block).unwrap();
+            putter
+                .write(&compressed, CompressionAlgorithm::Gzip)
+                .unwrap();
+        }
+
+        // Then: the putter returns the root hash of the decompressed content.
+        let root = putter.finalize().await.unwrap();
+        assert_eq!(root, Blake3Hash::from(hash_tree.hash));
+        assert_eq!(state.blockstore.read_all_to_vec(&root).await, Some(content));
+
+        // Then: content that can not be decompressed is rejected.
+        let mut putter = state.blockstore.put(None);
+        assert!(matches!(
+            putter.write(&[1, 2, 3], CompressionAlgorithm::Lz4),
+            Err(PutWriteError::DecompressionFailure)
+        ));
+        assert!(matches!(
+            putter.write(&[1, 2, 3], CompressionAlgorithm::Lzma),
+            Err(PutWriteError::DecompressionFailure)
+        ));
+    }
+
+    #[test]
+    async fn test_read_range() {
+        // Given: some content in the blockstore.
+        let content = create_content();
+        let hash_tree = hash_tree(content.as_slice());
+        let state =
+            make_blockstore(format!("test-{}", std::thread::current().name().unwrap())).await;
+        let blockstore = &state.blockstore;
+        let cid = put_content(blockstore, &content).await;
+
+        // When: we read a range that starts in the second block and ends in the third.
+        let offset = BLOCK_SIZE + 10;
+        let len = BLOCK_SIZE + 20;
+        let blocks: Vec<_> = blockstore
+            .read_range(&cid, offset as u64, len as u64)
+            .collect()
+            .await;
+
+        // Then: the covering blocks return the bytes of the range.
+        let counters: Vec<_> = blocks.iter().map(|block| block.counter).collect();
+        assert_eq!(counters, vec![1, 2]);
+        let range: Vec<u8> = blocks
+            .iter()
+            .flat_map(|block| block.content().iter().copied())
+            .collect();
+        assert_eq!(range, content[offset..offset + len]);
+
+        // Then: the range can be verified without the rest of the content.
+        let mut verifier = IncrementalVerifier::new(*hash_tree.hash.as_bytes(), 1);
+        for block in &blocks {
+            verifier.feed_proof(&block.proof).unwrap();
+            let mut hasher = BlockHasher::new();
+            hasher.set_block(block.counter as usize);
+            hasher.update(&block.block.content);
+            verifier.verify(hasher).unwrap();
+        }
+
+        // Then: the range is cut off at the end of the content.
+        let offset = 4 * BLOCK_SIZE - 5;
+        let blocks: Vec<_> = blockstore
+            .read_range(&cid, offset as u64, 100)
+            .collect()
+            .await;
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].content(), &content[offset..]);
+        let past_end = blockstore.read_range(&cid, 4 * BLOCK_SIZE as u64, 1);
+        assert_eq!(past_end.count().await, 0);
+    }
+
+    #[test]
+    async fn test_scrubber_quarantines_corrupt_content() {
+        // Given: a blockstore with two contents.
+        let state =
+            make_blockstore(format!("test-{}", std::thread::current().name().unwrap())).await;
+        let blockstore = &state.blockstore;
+        let cid_a = put_content(blockstore, &two_blocks(0, 1)).await;
+        let content_b = two_blocks(2, 3);
+        let cid_b = put_content(blockstore, &content_b).await;
+
+        // When: a block of the first content is corrupted on disk.
+        let tree = blockstore.get_tree(&cid_a).await.unwrap();
+        let block_name = format!("1-{}", Hash::from(tree[1]).to_hex());
+        let block_path = state.temp_dir_path.join("block").join(&block_name);
+        let mut block = std::fs::read(&block_path).unwrap();
+        block[42] ^= 1;
+        std::fs::write(&block_path, block).unwrap();
+
+        // Then: the scrubber moves the corrupt content to the quarantine.
+        let scrubber = Scrubber::new(blockstore.clone(), None);
+        assert_eq!(scrubber.scrub(Duration::ZERO).await, vec![cid_a]);
+        assert!(blockstore.get_tree(&cid_a).await.is_none());
+        assert!(!block_path.exists());
+        let quarantine = state
+            .temp_dir_path
+            .join("quarantine")
+            .join(Hash::from(cid_a).to_hex().as_str());
+        assert!(quarantine.join(&block_name).exists());
+
+        // Then: the intact content is kept.
+        assert_eq!(blockstore.read_all_to_vec(&cid_b).await, Some(content_b));
+        assert_eq!(blockstore.used_space(), 2 * BLOCK_SIZE as u64 + 96);
+        assert!(scrubber.scrub(Duration::ZERO).await.is_empty());
+
+        // Then: a block that can not be read is not taken for corruption.
+        let tree = blockstore.get_tree(&cid_b).await.unwrap();
+        let block_path = state
+            .temp_dir_path
+            .join("block")
+            .join(format!("0-{}", Hash::from(tree[0]).to_hex()));
+        std::fs::remove_file(&block_path).unwrap();
+        assert!(scrubber.scrub(Duration::ZERO).await.is_empty());
+        assert!(blockstore.get_tree(&cid_b).await.is_some());
+    }
 }
@@ -9,6 +9,7 @@
 use tracing::error;
 
 use crate::blockstore::BLOCK_SIZE;
+use crate::compression::codec;
 use crate::config::{BLOCK_DIR, INTERNAL_DIR};
 use crate::store::Store;
 
@@ -138,7 +139,25 @@ fn feed_proof(&mut self, proof: &[u8]) -> Result<(), PutFeedProofError> {
         Ok(())
     }
 
-    fn write(&mut self, content: &[u8], _: CompressionAlgorithm) -> Result<(), PutWriteError> {
+    fn write(
+        &mut self,
+        content: &[u8],
+        compression: CompressionAlgorithm,
+    ) -> Result<(), PutWriteError> {
+        // Compressed content is verified and stored as plain bytes, a compressed write is never
+        // more than one block.
+        let decompressed;
+        let content = match compression {
+            CompressionAlgorithm::Uncompressed => content,
+            compression => {
+                let codec = codec(compression).ok_or(PutWriteError::DecompressionFailure)?;
+                decompressed = codec
+                    .decompress(content, BLOCK_SIZE)
+                    .map_err(|_| PutWriteError::DecompressionFailure)?;
+                &decompressed
+            },
+        };
+
         // For the trusted mode we do write-ahead before the flush, this way
         // when we are running the flush function the hasher has already seen
         // the future bytes of the data.
@@ -232,8 +251,8 @@ async fn finalize(mut self) -> Result<Blake3Hash, PutFinalizeError> {
 
         // In future this can be a no-op/zero-copy when `flatten-slice` is stable in rust.
         let mut encoded_tree = Vec::with_capacity(32 * tree.len());
-        for item in tree {
-            encoded_tree.extend(&item);
+        for item in &tree {
+            encoded_tree.extend(item);
         }
 
         self.store
@@ -244,6 +263,11 @@ async fn finalize(mut self) -> Result<Blake3Hash, PutFinalizeError> {
                 PutFinalizeError::WriteFailed
             })?;
 
+        self.store.commit(hash, &tree).await.map_err(|e| {
+            error!("failed to commit content to store: {e:?}");
+            PutFinalizeError::WriteFailed
+        })?;
+
         self.indexer.register(hash).await;
 
         Ok(hash)
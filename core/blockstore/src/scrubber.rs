@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use blake3_tree::blake3::tree::BlockHasher;
+use blake3_tree::blake3::Hash;
+use blake3_tree::utils::{HashTree, HashVec};
+use blake3_tree::{IncrementalVerifier, ProofBuf};
+use lightning_interfaces::prelude::*;
+use lightning_interfaces::types::{
+    Blake3Hash,
+    CompressionAlgoSet,
+    FetcherRequest,
+    FetcherResponse,
+};
+use lightning_metrics::increment_counter;
+use tracing::{error, info, warn};
+
+use crate::blockstore::Blockstore;
+use crate::config::{ScrubberConfig, INTERNAL_DIR};
+use crate::store::Store;
+
+/// Re-verifies the content on disk against its tree in the background, so that content which
+/// was corrupted after it was written is not served to peers.
+///
+/// Corrupt content is moved to the quarantine directory, and fetched again from the network if
+/// a fetcher socket is provided.
+pub struct Scrubber<C: Collection> {
+    blockstore: Blockstore<C>,
+    fetcher: Option<FetcherSocket>,
+}
+
+impl<C: Collection> Scrubber<C> {
+    pub fn new(blockstore: Blockstore<C>, fetcher: Option<FetcherSocket>) -> Self {
+        Self {
+            blockstore,
+            fetcher,
+        }
+    }
+
+    pub async fn run(self, config: ScrubberConfig) {
+        let mut interval = tokio::time::interval(config.interval);
+        // The first tick completes immediately, we do not want to scan the disk on startup.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let quarantined = self.scrub(config.pause).await;
+            info!(
+                "Blockstore scrub finished, quarantined {} corrupt contents",
+                quarantined.len()
+            );
+        }
+    }
+
+    /// Verify all of the content once. Returns the roots of the content that was quarantined.
+    pub async fn scrub(&self, pause: Duration) -> Vec<Blake3Hash> {
+        let mut quarantined = Vec::new();
+        for root in self.blockstore.roots() {
+            let valid = self.verify(&root).await;
+            increment_counter!(
+                "blockstore_scrubber_verified",
+                Some("Counter for the content verified by the blockstore scrubber")
+            );
+
+            if !valid {
+                error!(
+                    "Found corrupt content {} on disk",
+                    Hash::from(root).to_hex()
+                );
+                increment_counter!(
+                    "blockstore_scrubber_corrupt",
+                    Some("Counter for the corrupt content found by the blockstore scrubber")
+                );
+                match self.blockstore.quarantine(&root).await {
+                    Ok(true) => {
+                        quarantined.push(root);
+                        self.refetch(root);
+                    },
+                    // The content was removed after it was verified.
+                    Ok(false) => {},
+                    Err(e) => error!("Failed to quarantine corrupt content: {e:?}"),
+                }
+            }
+
+            tokio::time::sleep(pause).await;
+        }
+        quarantined
+    }
+
+    /// Returns false if the tree or one of the blocks does not match the root of the content.
+    /// Content that can not be read is not considered corrupt, since it could have been evicted
+    /// or removed while it was verified.
+    async fn verify(&self, root: &Blake3Hash) -> bool {
+        // The tree is read from the disk directly, since a scrub does not count as a use of the
+        // content.
+        let Some(data) = self.blockstore.fetch(INTERNAL_DIR, root, None).await else {
+            // The content was removed after the scrub started.
+            return true;
+        };
+        if data.len() & 31 != 0 {
+            return false;
+        }
+        let tree = HashTree::from_inner(HashVec::from_inner(data.into_boxed_slice()));
+        let hashes: &[[u8; 32]] = tree.as_ref();
+
+        let mut verifier = IncrementalVerifier::new(*root, 0);
+        for counter in 0..tree.len() {
+            let proof = if counter == 0 {
+                ProofBuf::new(hashes, 0)
+            } else {
+                ProofBuf::resume(hashes, counter)
+            };
+            if !proof.is_empty() && verifier.feed_proof(proof.as_slice()).is_err() {
+                return false;
+            }
+
+            let compression = CompressionAlgoSet::new();
+            let Some(block) = self
+                .blockstore
+                .get(counter as u32, &tree[counter], compression)
+                .await
+            else {
+                return true;
+            };
+            let mut hasher = BlockHasher::new();
+            hasher.set_block(counter);
+            hasher.update(&block.content);
+            if verifier.verify(hasher).is_err() {
+                return false;
+            }
+        }
+        verifier.is_done()
+    }
+
+    fn refetch(&self, root: Blake3Hash) {
+        let Some(fetcher) = self.fetcher.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            match fetcher.run(FetcherRequest::Fetch { hash: root }).await {
+                Ok(FetcherResponse::Fetch(Ok(()))) => {
+                    increment_counter!(
+                        "blockstore_scrubber_repaired",
+                        Some("Counter for the corrupt content that was fetched again")
+                    );
+                },
+                res => warn!(
+                    "Failed to fetch corrupt content {} again: {res:?}",
+                    Hash::from(root).to_hex()
+                ),
+            }
+        });
+    }
+}
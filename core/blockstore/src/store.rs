@@ -13,6 +13,8 @@ async fn insert(
         block: &[u8],
         tag: Option<usize>,
     ) -> io::Result<()>;
+    /// Called once all the blocks and the tree of a content were inserted.
+    async fn commit(&mut self, root: Blake3Hash, tree: &[[u8; 32]]) -> io::Result<()>;
 }
 
 pub type Block = Vec<u8>;
@@ -62,6 +62,12 @@ pub enum DevSubCmd {
         /// The Blake3 hash of the content that we want to download.
         hash: String,
     },
+    /// Run the pending migrations of the application state.
+    Migrate {
+        /// Run the migrations on a copy of the database and only report the changes.
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Subcommand, PartialEq, Eq)]
@@ -1,6 +1,10 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use lightning_application::app::Application;
+use lightning_application::config::StorageConfig;
+use lightning_application::env::Env;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{Blake3Hash, NodePorts};
 use lightning_utils::config::TomlConfigProvider;
@@ -20,6 +24,7 @@ pub async fn exec<C>(cmd: DevSubCmd, config_path: ResolvedPathBuf) -> Result<()>
         DevSubCmd::DepGraph => dep_graph::<C>().await,
         DevSubCmd::Store { input } => store(input).await,
         DevSubCmd::Fetch { remote, hash } => fetch::<C>(config_path, hash, remote).await,
+        DevSubCmd::Migrate { dry_run } => migrate::<C>(config_path, dry_run).await,
     }
 }
 
@@ -133,6 +138,75 @@ async fn fetch<C: Collection<ConfigProviderInterface = TomlConfigProvider<C>>>(
     Ok(())
 }
 
+async fn migrate<C: Collection<ConfigProviderInterface = TomlConfigProvider<C>>>(
+    config_path: ResolvedPathBuf,
+    dry_run: bool,
+) -> Result<()> {
+    let config = TomlConfigProvider::<C>::load_or_write_config(config_path).await?;
+    let mut app_config = config.get::<Application<C>>();
+
+    let db_path = match (&app_config.storage, &app_config.db_path) {
+        (StorageConfig::RocksDb, Some(db_path)) => db_path.to_path_buf(),
+        _ => anyhow::bail!("Migrations can only be run on a RocksDb database."),
+    };
+
+    // For a dry run the migrations are run on a copy of the database that is removed afterwards.
+    let dry_run_path = db_path.with_extension("migrate-dry-run");
+    if dry_run {
+        if dry_run_path.exists() {
+            fs::remove_dir_all(&dry_run_path)?;
+        }
+        copy_dir(&db_path, &dry_run_path).context("Failed to copy the database.")?;
+        app_config.db_path = Some(
+            dry_run_path
+                .to_str()
+                .context("Invalid unicode in the database path.")?
+                .try_into()?,
+        );
+    }
+
+    let result = Env::migrate(&app_config, None);
+
+    if dry_run {
+        fs::remove_dir_all(&dry_run_path)?;
+    }
+
+    let report = result.context("Failed to migrate the state.")?;
+    if report.steps.is_empty() {
+        println!("The state is at the latest schema version {}.", report.to);
+        return Ok(());
+    }
+
+    println!(
+        "Migrated the state from schema version {} to {}{}:",
+        report.from,
+        report.to,
+        if dry_run { " (dry run)" } else { "" }
+    );
+    for step in &report.steps {
+        println!("  {}: {}", step.version, step.description);
+        for (table, changes) in &step.changes {
+            println!("    {table}: {changes} entries changed");
+        }
+    }
+
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &path)?;
+        } else {
+            fs::copy(entry.path(), path)?;
+        }
+    }
+    Ok(())
+}
+
 struct ByteBuf<'a>(&'a [u8]);
 
 impl<'a> std::fmt::LowerHex for ByteBuf<'a> {
@@ -41,11 +41,11 @@ pub async fn exec<C>(config_path: ResolvedPathBuf) -> Result<()>
         tokio::select! {
             _ = &mut shutdown_future => break,
             Some(checkpoint_hash) = checkpoint_fut => {
-                // get the checkpoint from the blockstore
-                let checkpoint = node
+                // the checkpoint is read from the blockstore after the node is shut down
+                let blockstore = node
                     .provider()
                     .get::<<C as Collection>::BlockstoreInterface>()
-                    .read_all_to_vec(&checkpoint_hash).await.expect("Failed to read checkpoint from blockstore");
+                    .clone();
 
                 // shutdown the node
                 node.shutdown().await;
@@ -57,8 +57,8 @@ pub async fn exec<C>(config_path: ResolvedPathBuf) -> Result<()>
                 // start local env in checkpoint mode to seed database with the new checkpoint
                 C::ApplicationInterface::load_from_checkpoint(
                     &app_config,
-                    checkpoint,
-                    checkpoint_hash
+                    checkpoint_hash,
+                    &blockstore
                 ).await?;
 
                 let provider = MultiThreadedProvider::default();
@@ -3,7 +3,7 @@
 use std::time::Duration;
 
 use anyhow::{Context, Result};
-use fleek_blake3 as blake3;
+use atomo_rocks::CheckpointManifest;
 use fleek_crypto::{AccountOwnerSecretKey, ConsensusSecretKey, NodeSecretKey, SecretKey};
 use lightning_application::app::Application;
 use lightning_application::config::{Config as AppConfig, Mode, StorageConfig};
@@ -90,6 +90,7 @@ fn build_config(
             .join("data/blockstore")
             .try_into()
             .expect("Failed to resolve path"),
+        ..Default::default()
     });
 
     config.inject::<BlockstoreServer<FinalTypes>>(BlockstoreServerConfig::default());
@@ -136,6 +137,64 @@ fn build_config(
     config
 }
 
+#[tokio::test]
+#[serial]
+async fn checkpoint_hash_roundtrip() -> Result<()> {
+    let path = std::env::temp_dir().join("lightning_test_checkpoint_hash_roundtrip");
+    if path.exists() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+
+    let app_config = |db: &str| AppConfig {
+        mode: Mode::Prod,
+        genesis: Some(Genesis::load().unwrap()),
+        testnet: false,
+        storage: StorageConfig::RocksDb,
+        db_path: Some(path.join(db).try_into().unwrap()),
+        db_options: None,
+    };
+    let blockstore = Blockstore::<FinalTypes>::init(BlockstoreConfig {
+        root: path
+            .join("data/blockstore")
+            .try_into()
+            .expect("Failed to resolve path"),
+        ..Default::default()
+    })?;
+
+    let writer_config = app_config("data/app_db_writer");
+    let mut env = Env::new(&writer_config, None)?;
+    env.genesis(&writer_config);
+    let checkpoint_hash = env
+        .write_checkpoint::<FinalTypes>(&blockstore)
+        .await?
+        .context("Failed to write checkpoint")?;
+    std::mem::drop(env);
+
+    // The hash the writer returns is the one the importers identify the manifest by.
+    let manifest = blockstore
+        .read_all_to_vec(&checkpoint_hash)
+        .await
+        .context("Checkpoint manifest is not in the blockstore")?;
+    let manifest = CheckpointManifest::deserialize(&manifest)?;
+    assert_eq!(manifest.hash(), checkpoint_hash);
+
+    // The imported state records the same hash as the last epoch hash.
+    let importer_config = app_config("data/app_db_importer");
+    <FinalTypes as Collection>::ApplicationInterface::load_from_checkpoint(
+        &importer_config,
+        checkpoint_hash,
+        &blockstore,
+    )
+    .await?;
+    let env = Env::new(&importer_config, None)?;
+    assert_eq!(env.last_epoch_hash(), Some(manifest.hash()));
+    std::mem::drop(env);
+
+    std::fs::remove_dir_all(path).unwrap();
+
+    Ok(())
+}
+
 #[tokio::test]
 #[serial]
 async fn node_checkpointing() -> Result<()> {
@@ -202,9 +261,18 @@ async fn node_checkpointing() -> Result<()> {
     let mut env = Env::new(&app_config_temp, None)?;
     env.genesis(&app_config_temp);
 
-    let storage = env.inner.get_storage_backend_unsafe();
-    let checkpoint = storage.serialize().unwrap();
-    let checkpoint_hash = blake3::hash(&checkpoint);
+    // The checkpoint is written to the blockstore of the node.
+    let blockstore = Blockstore::<FinalTypes>::init(BlockstoreConfig {
+        root: path
+            .join("data/blockstore")
+            .try_into()
+            .expect("Failed to resolve path"),
+        ..Default::default()
+    })?;
+    let checkpoint_hash = env
+        .write_checkpoint::<FinalTypes>(&blockstore)
+        .await?
+        .context("Failed to write checkpoint")?;
     std::mem::drop(env);
 
     // Now that we have a checkpoint, we initialize the node.
@@ -240,7 +308,7 @@ async fn node_checkpointing() -> Result<()> {
 
                 // start local env in checkpoint mode to seed database with the new checkpoint
                 <FinalTypes as Collection>::ApplicationInterface::load_from_checkpoint(
-                    &app_config, checkpoint.clone(), *checkpoint_hash.as_bytes()).await?;
+                    &app_config, checkpoint_hash, &blockstore).await?;
 
                 node = Node::<FinalTypes>::init(config.clone())
                     .map_err(|e| anyhow::anyhow!("Could not start the node: {e:?}"))?;
@@ -4,7 +4,8 @@
 use fastcrypto::hash::HashFunction;
 use fleek_blake3 as blake3;
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::types::{Block, Epoch, Event, Metadata, NodeIndex, TransactionRequest};
+pub use lightning_interfaces::types::CommitteeAttestation;
+use lightning_interfaces::types::{Block, Epoch, Event, Metadata, TransactionRequest};
 use lightning_interfaces::ExecutionEngineSocket;
 use lightning_utils::application::QueryRunnerExt;
 use narwhal_crypto::DefaultHashFunction;
@@ -41,18 +42,6 @@ fn to_digest(&self) -> Digest {
     }
 }
 
-/// A message an authority sends out attest that an Authentic stamp parcel is accurate. When an edge
-/// node gets 2f+1 of these it commits the transactions in the parcel
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct CommitteeAttestation {
-    /// The digest we are attesting is correct
-    pub digest: Digest,
-    /// We send random bytes with this message so it gives it a unique hash and differentiates it
-    /// from the other committee members attestation broadcasts
-    pub node_index: NodeIndex,
-    pub epoch: Epoch,
-}
-
 pub struct Execution<Q: SyncQueryRunnerInterface, NE: Emitter> {
     /// Managing certificates generated by narwhal.
     executor: ExecutionEngineSocket,
@@ -124,8 +124,10 @@ async fn start(mut self) {
                                 if num_dacks >= MAX_DELIVERY_ACKNOWLEDGMENTS {
                                     break;
                                 }
+                                // Only the commodity the client signed off on can be claimed.
+                                let total = commodity.entry(dack.service_id).or_insert(0u128);
+                                *total = total.saturating_add(dack.proof.commodity);
                                 proofs.entry(dack.service_id).or_default().push(dack.proof);
-                                *commodity.entry(dack.service_id).or_insert(0) += dack.commodity;
                                 if let Some(data) = &dack.metadata {
                                     metadata
                                         .entry(dack.service_id)
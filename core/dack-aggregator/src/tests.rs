@@ -2,12 +2,24 @@
 use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 
-use fleek_crypto::{AccountOwnerSecretKey, SecretKey};
+use fleek_crypto::{
+    AccountOwnerSecretKey,
+    ClientPublicKey,
+    ClientSignature,
+    ConsensusSecretKey,
+    EthAddress,
+    SecretKey,
+};
 use lightning_application::app::Application;
 use lightning_application::config::{Config as AppConfig, Mode, StorageConfig};
-use lightning_application::genesis::{Genesis, GenesisNode};
+use lightning_application::genesis::{Genesis, GenesisAccount, GenesisNode};
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::types::{DeliveryAcknowledgment, DeliveryAcknowledgmentProof, NodePorts};
+use lightning_interfaces::types::{
+    DeliveryAcknowledgment,
+    DeliveryAcknowledgmentMessage,
+    DeliveryAcknowledgmentProof,
+    NodePorts,
+};
 use lightning_notifier::Notifier;
 use lightning_signer::Signer;
 use lightning_test_utils::consensus::{Config as ConsensusConfig, MockConsensus, MockForwarder};
@@ -28,7 +40,7 @@
     DeliveryAcknowledgmentAggregatorInterface = DeliveryAcknowledgmentAggregator<Self>;
 });
 
-async fn init_aggregator(path: PathBuf) -> Node<TestBinding> {
+async fn init_aggregator(path: PathBuf, clients: &[ClientPublicKey]) -> Node<TestBinding> {
     let keystore = EphemeralKeystore::<TestBinding>::default();
     let (consensus_secret_key, node_secret_key) =
         (keystore.get_bls_sk(), keystore.get_ed25519_sk());
@@ -65,6 +77,18 @@ async fn init_aggregator(path: PathBuf) -> Node<TestBinding> {
     genesis.epoch_start = epoch_start;
     genesis.epoch_time = 4000; // millis
 
+    // Give every client an account to pay for the acknowledged deliveries.
+    for client in clients {
+        let address: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+        genesis.client.insert(*client, address);
+        genesis.account.push(GenesisAccount {
+            public_key: address,
+            flk_balance: 0u64.into(),
+            stables_balance: 0,
+            bandwidth_balance: 1_000,
+        });
+    }
+
     Node::<TestBinding>::init_with_provider(
         fdi::Provider::default()
             .with(
@@ -102,7 +126,7 @@ async fn test_shutdown_and_start_again() {
         std::fs::remove_file(&path).unwrap();
     }
 
-    let mut node = init_aggregator(path.clone()).await;
+    let mut node = init_aggregator(path.clone(), &[]).await;
 
     node.start().await;
     tokio::time::sleep(Duration::from_secs(2)).await;
@@ -121,7 +145,10 @@ async fn test_submit_dack() {
         std::fs::remove_file(&path).unwrap();
     }
 
-    let mut node = init_aggregator(path.clone()).await;
+    let client_secret_key = ConsensusSecretKey::generate();
+    let client = ClientPublicKey(client_secret_key.to_pk().0);
+
+    let mut node = init_aggregator(path.clone(), &[client]).await;
     node.start().await;
     tokio::time::sleep(Duration::from_secs(1)).await;
 
@@ -134,12 +161,30 @@ async fn test_submit_dack() {
         .get::<DeliveryAcknowledgmentAggregator<TestBinding>>()
         .socket();
 
+    let node_public_key = node
+        .provider
+        .get::<EphemeralKeystore<TestBinding>>()
+        .get_ed25519_pk();
+
     let service_id = 0;
     let commodity = 10;
+    let digest = DeliveryAcknowledgmentMessage {
+        chain_id: query_runner.get_chain_id(),
+        service_id,
+        node: node_public_key,
+        commodity,
+        session_nonce: 1,
+    }
+    .to_digest();
     let dack = DeliveryAcknowledgment {
         service_id,
         commodity,
-        proof: DeliveryAcknowledgmentProof,
+        proof: DeliveryAcknowledgmentProof {
+            client,
+            commodity,
+            session_nonce: 1,
+            signature: ClientSignature(client_secret_key.sign(&digest).0),
+        },
         metadata: None,
     };
     socket.run(dack).await.unwrap();
@@ -449,6 +449,7 @@ fn build_config(
             .join("data/blockstore")
             .try_into()
             .expect("Failed to resolve path"),
+        ..Default::default()
     });
 
     config.inject::<BlockstoreServer<FinalTypes>>(BlockstoreServerConfig::default());
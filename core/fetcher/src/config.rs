@@ -1,15 +1,32 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     // Maximum number of concurrent origin requests we send out.
     pub max_conc_origin_req: usize,
+    /// The maximum number of peers a content is downloaded from at the same time.
+    pub max_peers: usize,
+    /// The number of blocks that are requested from a peer at once.
+    pub blocks_per_request: u32,
+    /// How many blocks past the first block that is not downloaded yet can be requested. The
+    /// blocks are put in order, so this bounds the blocks that are held in memory.
+    pub max_blocks_ahead: u32,
+    /// The time after which a request to a peer is given up on and the blocks are requested
+    /// from another peer.
+    pub request_timeout: Duration,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             max_conc_origin_req: 5,
+            max_peers: 4,
+            blocks_per_request: 8,
+            max_blocks_ahead: 64,
+            request_timeout: Duration::from_secs(10),
         }
     }
 }
@@ -2,20 +2,24 @@
 
 use affair::{AsyncWorkerUnordered, Executor, TokioSpawn};
 use anyhow::{anyhow, Context, Result};
+use fleek_crypto::NodePublicKey;
 use lightning_interfaces::prelude::*;
+use lightning_interfaces::schema::broadcast::ResolvedImmutablePointerRecord;
 use lightning_interfaces::types::{
     Blake3Hash,
     FetcherRequest,
     FetcherResponse,
     ImmutablePointer,
-    ServerRequest,
+    NodeIndex,
 };
-use lightning_interfaces::{BlockstoreServerSocket, FetcherSocket};
+use lightning_interfaces::FetcherSocket;
 use lightning_metrics::increment_counter;
 use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
 
 use crate::config::Config;
 use crate::origin::{OriginFetcher, OriginRequest};
+use crate::swarm::Swarm;
 
 pub(crate) type Uri = Vec<u8>;
 
@@ -30,9 +34,12 @@ pub fn new(
         config: &C::ConfigProviderInterface,
         blockstore_server: &C::BlockstoreServerInterface,
         origin: &C::OriginProviderInterface,
+        keystore: &C::KeystoreInterface,
+        rep_aggregator: &C::ReputationAggregatorInterface,
         fdi::Cloned(waiter): fdi::Cloned<lightning_interfaces::ShutdownWaiter>,
         fdi::Cloned(blockstore): fdi::Cloned<C::BlockstoreInterface>,
         fdi::Cloned(resolver): fdi::Cloned<C::ResolverInterface>,
+        fdi::Cloned(query_runner): fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
     ) -> anyhow::Result<Self> {
         let config = config.get::<Self>();
 
@@ -48,11 +55,21 @@ pub fn new(
             waiter.run_until_shutdown(origin_fetcher.start()).await;
         });
 
+        let swarm = Swarm::<C>::new(
+            &config,
+            blockstore.clone(),
+            blockstore_server.get_part_socket(),
+            rep_aggregator.get_reporter(),
+        );
+
         let worker = FetcherWorker::<C> {
             origin_tx,
             blockstore,
-            blockstore_server_socket: blockstore_server.get_socket(),
+            swarm,
             resolver,
+            query_runner,
+            pk: keystore.get_ed25519_pk(),
+            max_peers: config.max_peers,
         };
 
         let socket = TokioSpawn::spawn_async_unordered(worker);
@@ -73,8 +90,11 @@ fn get_socket(&self) -> FetcherSocket {
 struct FetcherWorker<C: Collection> {
     origin_tx: mpsc::Sender<OriginRequest>,
     blockstore: C::BlockstoreInterface,
-    blockstore_server_socket: BlockstoreServerSocket,
+    swarm: Swarm<C>,
     resolver: C::ResolverInterface,
+    query_runner: c!(C::ApplicationInterface::SyncExecutor),
+    pk: NodePublicKey,
+    max_peers: usize,
 }
 
 impl<C: Collection> FetcherWorker<C> {
@@ -122,9 +142,9 @@ impl<C: Collection> FetcherWorker<C> {
         res
     }
 
-    /// Attempt to fetch the blake3 content. First, we check the blockstore,
-    /// then iterate through the provider records, requesting from the provider,
-    /// then falling back to the record's immutable pointer.
+    /// Attempt to fetch the blake3 content. First, we check the blockstore, then download the
+    /// content from the peers that provide it, falling back to the immutable pointers of the
+    /// provider records.
     #[inline(always)]
     async fn fetch(&self, hash: Blake3Hash) -> Result<()> {
         if self.blockstore.get_tree(&hash).await.is_some() {
@@ -133,55 +153,74 @@ async fn fetch(&self, hash: Blake3Hash) -> Result<()> {
                 Some("Counter for content that was already cached locally")
             );
             return Ok(());
-        } else if let Some(pointers) = self.resolver.get_origins(hash) {
-            for res_pointer in pointers {
-                debug_assert_eq!(res_pointer.hash, hash);
-                if self.blockstore.get_tree(&hash).await.is_some() {
-                    // in case we have the file
-                    increment_counter!(
-                        "fetcher_from_cache",
-                        Some("Counter for content that was already cached locally")
-                    );
-                    return Ok(());
-                }
+        }
 
-                // Try to get the content from the peer that advertised the record.
-                // TODO: Maybe use indexer for getting peers instead
-                let mut res = self
-                    .blockstore_server_socket
-                    .run(ServerRequest {
-                        hash,
-                        peer: res_pointer.originator,
-                    })
-                    .await
-                    .expect("Failed to send request to blockstore server");
-                let res = res
-                    .recv()
-                    .await
-                    .expect("Failed to receive response from blockstore server");
-
-                if res.is_ok() {
+        let pointers = self.resolver.get_origins(hash).unwrap_or_default();
+        let peers = self.providers(&hash, &pointers);
+        if !peers.is_empty() {
+            match self.swarm.download(hash, peers).await {
+                Ok(_) => {
                     increment_counter!(
                         "fetcher_from_peer",
                         Some("Counter for content that was fetched from a peer")
                     );
                     return Ok(());
-                } else {
+                },
+                Err(e) => {
+                    warn!("Failed to download content from peers: {e:?}");
                     increment_counter!(
                         "fetcher_from_peer_failed",
                         Some("Counter for failed attempts to fetch from a peer")
                     );
-                }
+                },
+            }
+        }
 
-                // If not, attempt to pull from the origin. This strikes a balance between trying
-                // to fetch from a bunch of peers vs going to the origin right away.
-                if self.fetch_origin(res_pointer.pointer).await.is_ok() {
-                    return Ok(());
-                }
+        for res_pointer in pointers {
+            debug_assert_eq!(res_pointer.hash, hash);
+            if self.blockstore.get_tree(&hash).await.is_some() {
+                // in case we have the file
+                increment_counter!(
+                    "fetcher_from_cache",
+                    Some("Counter for content that was already cached locally")
+                );
+                return Ok(());
+            }
+
+            if self.fetch_origin(res_pointer.pointer).await.is_ok() {
+                return Ok(());
             }
         }
         Err(anyhow!("Failed to resolve hash"))
     }
+
+    /// Returns the peers to download the content from, which are the originators of the
+    /// provider records followed by the providers on the application state. Our own node is
+    /// left out.
+    fn providers(
+        &self,
+        hash: &Blake3Hash,
+        pointers: &[ResolvedImmutablePointerRecord],
+    ) -> Vec<NodeIndex> {
+        let own_index = self.query_runner.pubkey_to_index(&self.pk);
+        let candidates = pointers.iter().map(|pointer| pointer.originator).chain(
+            self.query_runner
+                .get_cid_providers(hash)
+                .into_iter()
+                .flatten(),
+        );
+
+        let mut peers = Vec::new();
+        for peer in candidates {
+            if peers.len() == self.max_peers {
+                break;
+            }
+            if Some(peer) != own_index && !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+        peers
+    }
 }
 
 impl<C: Collection> ConfigConsumer for Fetcher<C> {
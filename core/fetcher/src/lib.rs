@@ -1,5 +1,6 @@
 pub mod config;
 pub mod fetcher;
 mod origin;
+mod swarm;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,272 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, ensure, Result};
+use blake3_tree::blake3::tree::BlockHasher;
+use blake3_tree::utils::{HashTree, HashVec};
+use blake3_tree::{IncrementalVerifier, ProofBuf};
+use fleek_compression::Codec;
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use lightning_interfaces::prelude::*;
+use lightning_interfaces::types::{
+    Blake3Hash,
+    CompressionAlgorithm,
+    ContentPart,
+    NodeIndex,
+    PeerBlock,
+    ServerPartRequest,
+    ServerPartResponse,
+};
+use lightning_interfaces::{BlockstoreServerPartSocket, Weight};
+use tokio::time::timeout;
+use tracing::warn;
+
+use crate::config::Config;
+
+const BLOCK_SIZE: usize = 256 << 10;
+
+/// Downloads a content from several peers at once.
+///
+/// The hash tree is fetched from the first peer that has it, after which the blocks are split
+/// into ranges that are requested from the peers concurrently. Each range is verified against
+/// the root hash on its own, a range that fails to verify or that takes too long is requested
+/// from another peer and the peer is not used again for the download.
+///
+/// The blocks are put into the blockstore in order, so the ranges are requested in order and
+/// only up to `max_blocks_ahead` blocks past the first missing block.
+pub struct Swarm<C: Collection> {
+    blockstore: C::BlockstoreInterface,
+    socket: BlockstoreServerPartSocket,
+    rep_reporter: c!(C::ReputationAggregatorInterface::ReputationReporter),
+    blocks_per_request: u32,
+    max_blocks_ahead: u32,
+    request_timeout: Duration,
+}
+
+impl<C: Collection> Swarm<C> {
+    pub fn new(
+        config: &Config,
+        blockstore: C::BlockstoreInterface,
+        socket: BlockstoreServerPartSocket,
+        rep_reporter: c!(C::ReputationAggregatorInterface::ReputationReporter),
+    ) -> Self {
+        let blocks_per_request = config.blocks_per_request.max(1);
+        Self {
+            blockstore,
+            socket,
+            rep_reporter,
+            blocks_per_request,
+            max_blocks_ahead: config.max_blocks_ahead.max(blocks_per_request),
+            request_timeout: config.request_timeout,
+        }
+    }
+
+    /// Download the content from the given peers and put it into the blockstore. Returns the
+    /// number of blocks each peer sent.
+    pub async fn download(
+        &self,
+        hash: Blake3Hash,
+        mut peers: Vec<NodeIndex>,
+    ) -> Result<BTreeMap<NodeIndex, u32>> {
+        let tree = self.fetch_tree(hash, &mut peers).await?;
+        let hashes: &[[u8; 32]] = tree.as_ref();
+        let num_blocks = tree.len() as u32;
+
+        // The ranges that are left to request by their first block, a range that failed is
+        // requested again before the ranges after it.
+        let mut queue: BTreeMap<u32, Range<u32>> = (0..num_blocks)
+            .step_by(self.blocks_per_request as usize)
+            .map(|start| {
+                (
+                    start,
+                    start..(start + self.blocks_per_request).min(num_blocks),
+                )
+            })
+            .collect();
+        let mut idle: VecDeque<NodeIndex> = peers.into();
+        let mut requests = FuturesUnordered::new();
+
+        // Ranges can arrive in any order, but the putter expects the blocks in order.
+        let mut received = BTreeMap::new();
+        let mut putter = self.blockstore.put(Some(hash));
+        let mut next_block = 0;
+        let mut served = BTreeMap::new();
+
+        while next_block < num_blocks {
+            while let Some(entry) = queue.first_entry() {
+                if idle.is_empty() || *entry.key() >= next_block + self.max_blocks_ahead {
+                    break;
+                }
+                let range = entry.remove();
+                let peer = idle.pop_front().unwrap();
+                requests.push(self.request_range(hash, peer, range));
+            }
+
+            let Some((peer, range, res)) = requests.next().await else {
+                bail!("No peer left to download the content from");
+            };
+            match res {
+                Ok(blocks) => {
+                    idle.push_back(peer);
+                    *served.entry(peer).or_default() += range.len() as u32;
+                    received.insert(range.start, blocks);
+                },
+                Err(e) => {
+                    warn!("Failed to download blocks {range:?} from peer {peer}: {e:?}");
+                    self.rep_reporter.report_unsat(peer, Weight::Weak);
+                    queue.insert(range.start, range);
+                },
+            }
+
+            while let Some(blocks) = received.remove(&next_block) {
+                for block in blocks {
+                    let proof = if next_block == 0 {
+                        ProofBuf::new(hashes, 0)
+                    } else {
+                        ProofBuf::resume(hashes, next_block as usize)
+                    };
+                    if !proof.is_empty() {
+                        putter.feed_proof(proof.as_slice())?;
+                    }
+                    putter.write(&block, CompressionAlgorithm::Uncompressed)?;
+                    next_block += 1;
+                }
+            }
+        }
+
+        let root = putter.finalize().await?;
+        ensure!(root == hash, "Downloaded content does not match the hash");
+        Ok(served)
+    }
+
+    /// Fetch the hash tree of the content from the first peer that sends a valid one. The peers
+    /// that fail to do so are removed.
+    async fn fetch_tree(&self, hash: Blake3Hash, peers: &mut Vec<NodeIndex>) -> Result<HashTree> {
+        while !peers.is_empty() {
+            let peer = peers[0];
+            let res = timeout(
+                self.request_timeout,
+                self.socket.run(ServerPartRequest {
+                    hash,
+                    peer,
+                    part: ContentPart::Tree,
+                }),
+            )
+            .await;
+            if let Ok(Ok(Ok(ServerPartResponse::Tree(tree)))) = res {
+                if let Some(tree) = verify_tree(hash, tree) {
+                    return Ok(tree);
+                }
+            }
+            warn!("Failed to get the tree of the content from peer {peer}");
+            self.rep_reporter.report_unsat(peer, Weight::Weak);
+            peers.remove(0);
+        }
+        Err(anyhow!("No peer sent the tree of the content"))
+    }
+
+    async fn request_range(
+        &self,
+        hash: Blake3Hash,
+        peer: NodeIndex,
+        range: Range<u32>,
+    ) -> (NodeIndex, Range<u32>, Result<Vec<Vec<u8>>>) {
+        let instant = Instant::now();
+        let request = ServerPartRequest {
+            hash,
+            peer,
+            part: ContentPart::Blocks(range.clone()),
+        };
+        let res = match timeout(self.request_timeout, self.socket.run(request)).await {
+            Ok(Ok(Ok(ServerPartResponse::Blocks(blocks)))) => verify_blocks(hash, &range, blocks),
+            Ok(Ok(Ok(ServerPartResponse::Tree(_)))) => Err(anyhow!("Unexpected response")),
+            Ok(Ok(Err(e))) => Err(anyhow!("Request failed: {e:?}")),
+            Ok(Err(e)) => Err(anyhow!("Blockstore server is not running: {e:?}")),
+            Err(_) => Err(anyhow!("Request timed out")),
+        };
+
+        if let Ok(blocks) = &res {
+            let bytes = blocks.iter().map(|block| block.len() as u64).sum();
+            self.rep_reporter
+                .report_bytes_received(peer, bytes, Some(instant.elapsed()));
+        }
+        (peer, range, res)
+    }
+}
+
+/// Returns the tree if it is well formed and matches the root hash.
+fn verify_tree(hash: Blake3Hash, tree: Vec<u8>) -> Option<HashTree> {
+    // A tree of `n` blocks has `2n - 1` hashes.
+    if tree.is_empty() || tree.len() % 32 != 0 || (tree.len() / 32) % 2 == 0 {
+        return None;
+    }
+    let tree = HashTree::from_inner(HashVec::from_inner(tree.into_boxed_slice()));
+    if *tree.get_root() != hash {
+        return None;
+    }
+
+    let hashes: &[[u8; 32]] = tree.as_ref();
+    let mut verifier = IncrementalVerifier::new(hash, 0);
+    for counter in 0..tree.len() {
+        let proof = if counter == 0 {
+            ProofBuf::new(hashes, 0)
+        } else {
+            ProofBuf::resume(hashes, counter)
+        };
+        if !proof.is_empty() && verifier.feed_proof(proof.as_slice()).is_err() {
+            return None;
+        }
+        if verifier.verify_hash(&tree[counter]).is_err() {
+            return None;
+        }
+    }
+    verifier.is_done().then_some(tree)
+}
+
+/// Verify the blocks of a range against the root hash, and return their uncompressed content.
+fn verify_blocks(
+    hash: Blake3Hash,
+    range: &Range<u32>,
+    blocks: Vec<PeerBlock>,
+) -> Result<Vec<Vec<u8>>> {
+    ensure!(
+        blocks.len() == range.len(),
+        "Expected {} blocks but got {}",
+        range.len(),
+        blocks.len()
+    );
+
+    let mut verifier = IncrementalVerifier::new(hash, range.start as usize);
+    let mut contents = Vec::with_capacity(blocks.len());
+    for (counter, block) in range.clone().zip(blocks) {
+        if !block.proof.is_empty() {
+            verifier
+                .feed_proof(&block.proof)
+                .map_err(|e| anyhow!("Invalid proof for block {counter}: {e:?}"))?;
+        }
+        let content = decompress(block.compression, block.content)
+            .map_err(|e| anyhow!("Failed to decompress block {counter}: {e:?}"))?;
+        let mut hasher = BlockHasher::new();
+        hasher.set_block(counter as usize);
+        hasher.update(&content);
+        verifier
+            .verify(hasher)
+            .map_err(|e| anyhow!("Invalid block {counter}: {e:?}"))?;
+        contents.push(content);
+    }
+    Ok(contents)
+}
+
+fn decompress(compression: CompressionAlgorithm, content: Vec<u8>) -> Result<Vec<u8>> {
+    let codec = match compression {
+        CompressionAlgorithm::Uncompressed => return Ok(content),
+        CompressionAlgorithm::Snappy => Codec::Snappy,
+        CompressionAlgorithm::Gzip => Codec::Gzip,
+        CompressionAlgorithm::Brotli => Codec::Brotli,
+        CompressionAlgorithm::Lz4 => Codec::Lz4,
+        CompressionAlgorithm::Lzma => bail!("Lzma is not supported"),
+    };
+    Ok(codec.decompress(&content, BLOCK_SIZE)?)
+}
@@ -13,6 +13,7 @@
 use lightning_indexer::Indexer;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{
+    CompressionAlgorithm,
     FetcherRequest,
     FetcherResponse,
     ImmutablePointer,
@@ -38,6 +39,7 @@
 
 use crate::config::Config;
 use crate::fetcher::Fetcher;
+use crate::swarm::Swarm;
 
 partial!(TestBinding {
     ConfigProviderInterface = JsonConfigProvider;
@@ -133,6 +135,7 @@ async fn get_fetchers(
                         })
                         .with::<Blockstore<TestBinding>>(BlockstoreConfig {
                             root: path.join(format!("node-{i}/store")).try_into().unwrap(),
+                            ..Default::default()
                         })
                         .with::<OriginDemuxer<TestBinding>>(DemuxerOriginConfig {
                             ipfs: IPFSOriginConfig {
@@ -149,6 +152,8 @@ async fn get_fetchers(
                         })
                         .with::<Fetcher<TestBinding>>(Config {
                             max_conc_origin_req: 3,
+                            blocks_per_request: 1,
+                            ..Default::default()
                         }),
                 ),
             )
@@ -267,3 +272,72 @@ async fn test_fetch_from_peer() {
         std::fs::remove_dir_all(&path).unwrap();
     }
 }
+
+#[tokio::test]
+async fn test_fetch_from_multiple_peers() {
+    let (peers, path) =
+        get_fetchers("lightning-test-fetch-from-multiple-peers", 30501, 40501, 3).await;
+    for peer in &peers {
+        peer.start().await;
+    }
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    // Put the same content that spans several blocks onto the first two peers.
+    let content: Vec<u8> = (0..8 * (256 << 10)).map(|i| (i % 251) as u8).collect();
+    let mut hashes = Vec::new();
+    for peer in &peers[..2] {
+        let mut putter = peer.provider.get::<Blockstore<TestBinding>>().put(None);
+        putter
+            .write(&content, CompressionAlgorithm::Uncompressed)
+            .unwrap();
+        hashes.push(putter.finalize().await.unwrap());
+    }
+    assert_eq!(hashes[0], hashes[1]);
+    let hash = hashes[0];
+
+    let query_runner = peers[2]
+        .provider
+        .get::<Application<TestBinding>>()
+        .sync_query();
+    let indices: Vec<_> = peers[..2]
+        .iter()
+        .map(|peer| {
+            let pk = peer
+                .provider
+                .get::<EphemeralKeystore<TestBinding>>()
+                .get_ed25519_pk();
+            query_runner.pubkey_to_index(&pk).unwrap()
+        })
+        .collect();
+
+    // Download the content on the third peer one block per request, so that the blocks are
+    // requested from both peers.
+    let blockstore3 = peers[2].provider.get::<Blockstore<TestBinding>>().clone();
+    let swarm = Swarm::<TestBinding>::new(
+        &Config {
+            blocks_per_request: 1,
+            ..Default::default()
+        },
+        blockstore3.clone(),
+        peers[2]
+            .provider
+            .get::<BlockstoreServer<TestBinding>>()
+            .get_part_socket(),
+        peers[2]
+            .provider
+            .get::<ReputationAggregator<TestBinding>>()
+            .get_reporter(),
+    );
+    let served = swarm.download(hash, indices).await.unwrap();
+    assert!(served.len() > 1, "Blocks were served by {served:?}");
+    assert_eq!(served.values().sum::<u32>(), 8);
+    assert_eq!(blockstore3.read_all_to_vec(&hash).await.unwrap(), content);
+
+    for mut peer in peers {
+        peer.shutdown().await;
+    }
+
+    if path.exists() {
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
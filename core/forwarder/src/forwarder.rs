@@ -2,12 +2,18 @@
 
 use affair::{Executor, TokioSpawn};
 use lightning_interfaces::prelude::*;
+use lightning_interfaces::types::TransactionRequest;
+use tokio::sync::broadcast;
 
 use crate::config::ForwarderConfig;
 use crate::worker::Worker;
 
+/// The number of forwarded transactions that are buffered for slow subscribers.
+const PENDING_TRANSACTIONS_CAPACITY: usize = 1024;
+
 pub struct Forwarder<C> {
     socket: MempoolSocket,
+    pending_tx: broadcast::Sender<TransactionRequest>,
     _p: PhantomData<C>,
 }
 
@@ -15,6 +21,10 @@ impl<C: Collection> ForwarderInterface<C> for Forwarder<C> {
     fn mempool_socket(&self) -> MempoolSocket {
         self.socket.clone()
     }
+
+    fn subscribe_pending_transactions(&self) -> broadcast::Receiver<TransactionRequest> {
+        self.pending_tx.subscribe()
+    }
 }
 
 impl<C> ConfigConsumer for Forwarder<C> {
@@ -28,10 +38,16 @@ fn build_graph() -> fdi::DependencyGraph {
             |keystore: &C::KeystoreInterface, app: &C::ApplicationInterface| {
                 let consensus_key = keystore.get_bls_pk();
                 let query_runner = app.sync_query();
-                let socket = TokioSpawn::spawn_async(Worker::new(consensus_key, query_runner));
+                let (pending_tx, _) = broadcast::channel(PENDING_TRANSACTIONS_CAPACITY);
+                let socket = TokioSpawn::spawn_async(Worker::new(
+                    consensus_key,
+                    query_runner,
+                    pending_tx.clone(),
+                ));
 
                 Self {
                     socket,
+                    pending_tx,
                     _p: PhantomData,
                 }
             },
@@ -15,6 +15,7 @@
 use lightning_utils::application::QueryRunnerExt;
 use narwhal_types::{TransactionProto, TransactionsClient};
 use rand::seq::SliceRandom;
+use tokio::sync::broadcast;
 use tokio::time::{timeout, Duration};
 use tonic::transport::channel::Channel;
 use tracing::error;
@@ -40,10 +41,16 @@ pub struct Worker<Q: SyncQueryRunnerInterface> {
     min_connections: usize,
     /// Open connections to committee workers
     active_connections: HashMap<usize, TransactionsClient<Channel>>,
+    /// Sender for the transactions that were forwarded successfully
+    pending_tx: broadcast::Sender<TransactionRequest>,
 }
 
 impl<Q: SyncQueryRunnerInterface> Worker<Q> {
-    pub fn new(primary_name: ConsensusPublicKey, query_runner: Q) -> Self {
+    pub fn new(
+        primary_name: ConsensusPublicKey,
+        query_runner: Q,
+        pending_tx: broadcast::Sender<TransactionRequest>,
+    ) -> Self {
         Self {
             query_runner,
             primary_name,
@@ -53,6 +60,7 @@ pub fn new(primary_name: ConsensusPublicKey, query_runner: Q) -> Self {
             max_connections: 0,
             min_connections: 0,
             active_connections: HashMap::with_capacity(TARGETED_CONNECTION_NUM),
+            pending_tx,
         }
     }
 
@@ -184,6 +192,8 @@ async fn handle(&mut self, req: Self::Request) -> Self::Response {
                 error!("Failed to send transaction to a worker: {e}");
                 retried += 1;
             } else {
+                // It is fine if nobody is subscribed to the pending transactions.
+                let _ = self.pending_tx.send(req);
                 break;
             }
         }
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests;
 
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::{Arc, OnceLock};
 
@@ -8,9 +9,14 @@
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{Blake3Hash, ContentUpdate, NodeIndex, UpdateMethod};
 use lightning_interfaces::SubmitTxSocket;
+use tokio::sync::Mutex;
+
 pub struct Indexer<C: Collection> {
     pk: NodePublicKey,
     local_index: Arc<OnceLock<NodeIndex>>,
+    /// The content registry updates that were submitted but are not reflected in the state yet,
+    /// mapped to whether the content is registered once the update is executed.
+    pending: Arc<Mutex<HashMap<Blake3Hash, bool>>>,
     submit_tx: SubmitTxSocket,
     query_runner: c![C::ApplicationInterface::SyncExecutor],
     _marker: PhantomData<C>,
@@ -21,6 +27,7 @@ fn clone(&self) -> Self {
         Self {
             pk: self.pk,
             local_index: self.local_index.clone(),
+            pending: self.pending.clone(),
             submit_tx: self.submit_tx.clone(),
             query_runner: self.query_runner.clone(),
             _marker: PhantomData,
@@ -43,6 +50,7 @@ fn init(
         Ok(Self {
             pk,
             local_index: Arc::new(local_index),
+            pending: Default::default(),
             submit_tx: signer.get_socket(),
             query_runner,
             _marker: PhantomData,
@@ -62,6 +70,47 @@ fn get_index(&self) -> Option<NodeIndex> {
             Some(index) => Some(*index),
         }
     }
+
+    /// Submit an update of the content registry of the node, unless the content is already in
+    /// the requested state once the updates that were submitted before are executed. This way
+    /// content that is removed right after it was put is not left registered by a registration
+    /// that was still pending.
+    async fn update(&self, cid: Blake3Hash, register: bool) {
+        let Some(index) = self.get_index() else {
+            return;
+        };
+        let registry = self
+            .query_runner
+            .get_content_registry(&index)
+            .unwrap_or_default();
+
+        // The lock is held until the update is submitted, so that the updates are submitted in
+        // the order they are decided in.
+        let mut pending = self.pending.lock().await;
+        pending.retain(|cid, registered| registry.contains(cid) != *registered);
+        let registered = pending
+            .get(&cid)
+            .copied()
+            .unwrap_or_else(|| registry.contains(&cid));
+        if registered == register {
+            return;
+        }
+
+        let updates = vec![ContentUpdate {
+            cid,
+            remove: !register,
+        }];
+        match self
+            .submit_tx
+            .enqueue(UpdateMethod::UpdateContentRegistry { updates })
+            .await
+        {
+            Ok(()) => {
+                pending.insert(cid, register);
+            },
+            Err(e) => tracing::error!("Submitting content registry update failed: {e:?}"),
+        }
+    }
 }
 
 impl<C: Collection> BuildGraph for Indexer<C> {
@@ -72,43 +121,10 @@ fn build_graph() -> fdi::DependencyGraph {
 
 impl<C: Collection> IndexerInterface<C> for Indexer<C> {
     async fn register(&self, cid: Blake3Hash) {
-        if let Some(index) = self.get_index() {
-            if self
-                .query_runner
-                .get_content_registry(&index)
-                .map(|registry| !registry.contains(&cid))
-                .unwrap_or(true)
-            {
-                let updates = vec![ContentUpdate { cid, remove: false }];
-                if let Err(e) = self
-                    .submit_tx
-                    .enqueue(UpdateMethod::UpdateContentRegistry { updates })
-                    .await
-                {
-                    tracing::error!("Submitting content registry update failed: {e:?}");
-                }
-            }
-        }
+        self.update(cid, true).await;
     }
 
     async fn unregister(&self, cid: Blake3Hash) {
-        if let Some(index) = self.get_index() {
-            if self
-                .query_runner
-                .get_content_registry(&index)
-                .map(|registry| registry.contains(&cid))
-                .unwrap_or(false)
-            {
-                let updates = vec![ContentUpdate { cid, remove: true }];
-
-                if let Err(e) = self
-                    .submit_tx
-                    .enqueue(UpdateMethod::UpdateContentRegistry { updates })
-                    .await
-                {
-                    tracing::error!("Submitting content registry update failed: {e:?}");
-                }
-            }
-        }
+        self.update(cid, false).await;
     }
 }
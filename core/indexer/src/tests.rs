@@ -159,5 +159,31 @@ async fn test_submission() {
         }
     }
 
+    // When: we unregister a cid while its registration is still pending, as happens when content
+    // is evicted right after it was put.
+    let evicted = [1u8; 32];
+    indexer.register(evicted).await;
+    indexer.unregister(evicted).await;
+    let marker = [2u8; 32];
+    indexer.register(marker).await;
+
+    // Then: once the later updates are executed we are not a provider of the evicted cid.
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let providers = query_runner.get_cid_providers(&marker).unwrap_or_default();
+                if !providers.is_empty() {
+                        break;
+                }
+            }
+        }
+    }
+    assert!(
+        query_runner
+            .get_cid_providers(&evicted)
+            .unwrap_or_default()
+            .is_empty()
+    );
+
     node.shutdown().await;
 }
@@ -7,6 +7,7 @@
 use atomo::{
     Atomo,
     AtomoBuilder,
+    AtomoSnapshot,
     InMemoryStorage,
     KeyIterator,
     QueryPerm,
@@ -23,12 +24,22 @@
     ChainId,
     Committee,
     CommodityTypes,
+    Delegation,
     Metadata,
     NodeIndex,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
+    ProposalStatus,
+    ProposalVote,
     ServiceRevenue,
+    SlashRecord,
+    StateDiff,
+    Tokens,
     TransactionRequest,
     TxHash,
     Value,
+    ValueWithProof,
 };
 use serde::{Deserialize, Serialize};
 
@@ -82,11 +93,12 @@ pub trait ApplicationInterface<C: Collection>:
     /// without slowing down the system.
     fn sync_query(&self) -> Self::SyncExecutor;
 
-    /// Will seed its underlying database with the checkpoint provided
+    /// Will seed its underlying database with the checkpoint with the given hash. The manifest
+    /// of the checkpoint and all of its chunks must already be in the blockstore.
     async fn load_from_checkpoint(
         config: &Self::Config,
-        checkpoint: Vec<u8>,
         checkpoint_hash: [u8; 32],
+        blockstore: &C::BlockstoreInterface,
     ) -> Result<()>;
 
     /// Used to get the chain id from the genesis file instead of state
@@ -103,27 +115,50 @@ pub trait SyncQueryRunnerInterface: Clone + Send + Sync + 'static {
 
     fn new(atomo: Atomo<QueryPerm, Self::Backend>) -> Self;
 
+    /// Opens the state at the given path, replacing it with a checkpoint that was imported at the
+    /// `checkpoint` path.
     fn atomo_from_checkpoint(
         path: impl AsRef<Path>,
-        hash: [u8; 32],
-        checkpoint: &[u8],
+        checkpoint: impl AsRef<Path>,
     ) -> Result<Atomo<QueryPerm, Self::Backend>>;
 
     fn atomo_from_path(path: impl AsRef<Path>) -> Result<Atomo<QueryPerm, Self::Backend>>;
 
+    /// Opens the state at the given path as it was before the changes of the reverse diff, see
+    /// [`StateDiff`]. The database is not modified.
+    fn atomo_from_path_with_diff(
+        path: impl AsRef<Path>,
+        diff: StateDiff,
+    ) -> Result<Atomo<QueryPerm, Self::Backend>>;
+
     fn register_tables<B: StorageBackendConstructor, S: SerdeBackend>(
         builder: AtomoBuilder<B, S>,
     ) -> AtomoBuilder<B, S> {
         builder
             .with_table::<Metadata, Value>("metadata")
             .with_table::<EthAddress, AccountInfo>("account")
+            .with_table::<(EthAddress, EthAddress, Tokens), HpUfixed<18>>("allowances")
             .with_table::<ClientPublicKey, EthAddress>("client_keys")
             .with_table::<NodeIndex, NodeInfo>("node")
-            .with_table::<ConsensusPublicKey, NodeIndex>("consensus_key_to_index")
-            .with_table::<NodePublicKey, NodeIndex>("pub_key_to_index")
+            .with_index::<NodeIndex, NodeInfo, ConsensusPublicKey, _>(
+                "nodes_by_consensus_key",
+                "node",
+                |_, node| vec![node.consensus_key],
+            )
+            .with_index::<NodeIndex, NodeInfo, NodePublicKey, _>(
+                "nodes_by_pub_key",
+                "node",
+                |_, node| vec![node.public_key],
+            )
+            .with_index::<NodeIndex, NodeInfo, EthAddress, _>(
+                "nodes_by_owner",
+                "node",
+                |_, node| vec![node.owner],
+            )
             .with_table::<(NodeIndex, NodeIndex), Duration>("latencies")
             .with_table::<Epoch, Committee>("committee")
             .with_table::<ServiceId, Service>("service")
+            .with_table::<ServiceId, HpUfixed<18>>("service_bonds")
             .with_table::<ProtocolParams, u128>("parameter")
             .with_table::<NodeIndex, Vec<ReportedReputationMeasurements>>("rep_measurements")
             .with_table::<NodeIndex, u8>("rep_scores")
@@ -135,8 +170,41 @@ fn register_tables<B: StorageBackendConstructor, S: SerdeBackend>(
             .with_table::<ServiceId, ServiceRevenue>("service_revenue")
             .with_table::<TxHash, ()>("executed_digests")
             .with_table::<NodeIndex, u8>("uptime")
-            .with_table::<Blake3Hash, BTreeSet<NodeIndex>>("cid_to_node")
             .with_table::<NodeIndex, BTreeSet<Blake3Hash>>("node_to_cid")
+            .with_index::<NodeIndex, BTreeSet<Blake3Hash>, Blake3Hash, _>(
+                "cid_providers",
+                "node_to_cid",
+                |_, cids| cids.iter().copied().collect(),
+            )
+            .with_table::<NodeIndex, Vec<SlashRecord>>("slashes")
+            .with_table::<NodeIndex, Epoch>("jailed_nodes")
+            .with_table::<u64, PendingWithdrawal>("pending_withdrawals")
+            .with_table::<u64, Epoch>("processed_deposits")
+            .with_table::<(ClientPublicKey, NodeIndex), u64>("client_session_nonces")
+            .with_table::<(NodeIndex, EthAddress), Delegation>("delegations")
+            .with_index::<(NodeIndex, EthAddress), Delegation, NodeIndex, _>(
+                "delegators",
+                "delegations",
+                |(node, _), _| vec![*node],
+            )
+            .with_index::<(NodeIndex, EthAddress), Delegation, EthAddress, _>(
+                "delegations_by_delegator",
+                "delegations",
+                |(_, delegator), _| vec![*delegator],
+            )
+            .with_table::<ProposalId, Proposal>("proposals")
+            .with_index::<ProposalId, Proposal, ProposalStatus, _>(
+                "proposals_by_status",
+                "proposals",
+                |_, proposal| vec![proposal.status],
+            )
+            .with_table::<(ProposalId, EthAddress), ProposalVote>("proposal_votes")
+            .with_table::<(ProposalId, EthAddress), HpUfixed<18>>("proposal_weights")
+            .with_index::<(ProposalId, EthAddress), HpUfixed<18>, ProposalId, _>(
+                "proposal_weights_by_proposal",
+                "proposal_weights",
+                |(id, _), _| vec![*id],
+            )
     }
 
     /// Query Metadata Table
@@ -150,6 +218,15 @@ fn get_account_info<V>(
         selector: impl FnOnce(AccountInfo) -> V,
     ) -> Option<V>;
 
+    /// Query Allowances Table
+    /// Returns how much of the owner's token the spender is allowed to transfer.
+    fn get_allowance(
+        &self,
+        owner: &EthAddress,
+        spender: &EthAddress,
+        token: &Tokens,
+    ) -> Option<HpUfixed<18>>;
+
     /// Query Client Table
     fn client_key_to_account_key(&self, pub_key: &ClientPublicKey) -> Option<EthAddress>;
 
@@ -212,9 +289,12 @@ fn get_latencies_iter<V>(
     /// Get Node's Public Key based on the Node's Index
     fn index_to_pubkey(&self, node_index: &NodeIndex) -> Option<NodePublicKey>;
 
-    /// Simulate Transaction
+    /// Simulate Transaction, including charging the sender for its fee
     fn simulate_txn(&self, txn: TransactionRequest) -> TransactionResponse;
 
+    /// Returns the gas a transaction is charged for, `0` if it is free
+    fn get_transaction_gas(&self, txn: &TransactionRequest) -> u128;
+
     /// Returns the uptime for a node from the past epoch.
     fn get_node_uptime(&self, node_index: &NodeIndex) -> Option<u8>;
 
@@ -223,6 +303,54 @@ fn get_latencies_iter<V>(
 
     /// Returns the node's content registry.
     fn get_content_registry(&self, node_index: &NodeIndex) -> Option<BTreeSet<Blake3Hash>>;
+
+    /// Query Slashes Table
+    /// Returns the slashes a node received, oldest first.
+    fn get_slashes(&self, node_index: &NodeIndex) -> Option<Vec<SlashRecord>>;
+
+    /// Query Jailed Nodes Table
+    /// Returns the first epoch a jailed node is eligible for the committee again.
+    fn get_jailed_until(&self, node_index: &NodeIndex) -> Option<Epoch>;
+
+    /// Query Pending Withdrawals Table
+    /// Returns the withdrawal with the given id.
+    fn get_pending_withdrawal(&self, id: &u64) -> Option<PendingWithdrawal>;
+
+    /// Returns an Iterator to Pending Withdrawals Table
+    fn get_pending_withdrawals_iter<V>(&self, closure: impl FnOnce(KeyIterator<u64>) -> V) -> V;
+
+    /// Query Delegations Table
+    /// Returns the FLK the delegator has delegated to the node.
+    fn get_delegation(&self, node: &NodeIndex, delegator: &EthAddress) -> Option<Delegation>;
+
+    /// Returns the accounts that have delegated to the node.
+    fn get_delegators(&self, node: &NodeIndex) -> BTreeSet<EthAddress>;
+
+    /// Query Proposals Table
+    /// Returns the governance proposal with the given id.
+    fn get_proposal(&self, id: &ProposalId) -> Option<Proposal>;
+
+    /// Returns an Iterator to Proposals Table
+    fn get_proposals_iter<V>(&self, closure: impl FnOnce(KeyIterator<ProposalId>) -> V) -> V;
+
+    /// Returns the ids of the governance proposals with the given status.
+    fn get_proposal_ids_by_status(&self, status: &ProposalStatus) -> BTreeSet<ProposalId>;
+
+    /// Query Proposal Votes Table
+    /// Returns the vote of the account on the governance proposal.
+    fn get_proposal_vote(&self, id: &ProposalId, voter: &EthAddress) -> Option<ProposalVote>;
+
+    /// Returns the state root, or `None` if the state tree is not kept for this state.
+    fn get_state_root(&self) -> Option<[u8; 32]>;
+
+    /// Returns the raw value of a raw key in the given table along with a proof for it against
+    /// the state root. Returns `None` if the table does not exist or if the state tree is not
+    /// kept for this state.
+    fn get_state_proof(&self, table: &str, key: &[u8]) -> Option<ValueWithProof>;
+
+    /// Returns a handle that pins the current version of the state, a query runner that reads
+    /// the pinned version can be created from it with [`SyncQueryRunnerInterface::new`].
+    fn snapshot(&self) -> AtomoSnapshot<Self::Backend>;
 }
 
 #[derive(Clone, Debug)]
@@ -248,4 +376,9 @@ pub struct PagingParams {
     pub ignore_stake: bool,
     pub start: NodeIndex,
     pub limit: usize,
+    /// A paging cursor from `flk_open_paging_cursor`. When set, every page is read from the
+    /// version of the state that was pinned when the cursor was opened, so that pages stay
+    /// consistent with each other while the state changes.
+    #[serde(default)]
+    pub cursor: Option<u64>,
 }
@@ -1,4 +1,4 @@
-use ethers::types::BlockNumber;
+use ethers::types::{BlockNumber, Filter, Log};
 use fdi::BuildGraph;
 
 use crate::collection::Collection;
@@ -18,8 +18,19 @@ pub trait ArchiveInterface<C: Collection>: BuildGraph + Clone + Send + Sync {
 
     async fn get_transaction(&self, hash: [u8; 32]) -> Option<TransactionRequest>;
 
+    /// Returns the logs of the events emitted in the blocks that match the filter.
+    async fn get_logs(&self, filter: Filter) -> anyhow::Result<Vec<Log>>;
+
     async fn get_historical_epoch_state(
         &self,
         epoch: u64,
     ) -> Option<c![C::ApplicationInterface::SyncExecutor]>;
+
+    /// Returns the state right after the given block was executed. The state is reconstructed
+    /// from the checkpoint of the epoch the block belongs to, so blocks of the current epoch are
+    /// not available until the epoch has ended.
+    async fn get_historical_block_state(
+        &self,
+        block_number: u64,
+    ) -> Option<c![C::ApplicationInterface::SyncExecutor]>;
 }
@@ -1,11 +1,12 @@
 use std::future::Future;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use blake3_tree::directory::DirectoryEntry;
 use blake3_tree::utils::HashTree;
 use fdi::BuildGraph;
+use futures::Stream;
 use thiserror::Error;
 
 use crate::collection::Collection;
@@ -19,6 +20,27 @@ pub struct ContentChunk {
     pub content: Vec<u8>,
 }
 
+/// A block that covers part of a byte range read from the block store, see
+/// [`BlockstoreInterface::read_range`].
+pub struct RangeBlock<P> {
+    /// The counter of the block in the content.
+    pub counter: u32,
+    /// The proof to feed to an incremental verifier before the block. The verifier has to
+    /// start at the first block of the range. Empty if no proof is needed.
+    pub proof: Vec<u8>,
+    /// The entire decompressed block, which is what the proof verifies.
+    pub block: P,
+    /// The part of the block that is in the requested range.
+    pub range: Range<usize>,
+}
+
+impl<P: Deref<Target = ContentChunk>> RangeBlock<P> {
+    /// Returns the bytes of the block that are in the requested range.
+    pub fn content(&self) -> &[u8] {
+        &self.block.content[self.range.clone()]
+    }
+}
+
 /// The block store is the local unit on a single node responsible for storing a file, each file in
 /// Fleek Network is determined and addressed by its Blake3 hash, we have made this choice to allow
 /// us to perform incremental verification over an stream of the content, along with performance
@@ -127,6 +149,22 @@ fn get(
         async { None }
     }
 
+    /// Returns a stream of the blocks that cover `len` bytes of the content starting at the
+    /// byte `offset`, the range is cut off at the end of the content. Each block comes with
+    /// the part of it that is in the range and the proof that lets a client verify the range
+    /// without the rest of the content.
+    ///
+    /// The stream is empty if the content is not present or the offset is past its end, and
+    /// ends early if one of the blocks is missing.
+    fn read_range(
+        &self,
+        _cid: &Blake3Hash,
+        _offset: u64,
+        _len: u64,
+    ) -> impl Stream<Item = RangeBlock<Self::SharedPointer<ContentChunk>>> + Send {
+        futures::stream::empty()
+    }
+
     /// Create a putter that can be used to write a content into the block store.
     fn put(&self, cid: Option<Blake3Hash>) -> Self::Put;
 
@@ -149,6 +187,27 @@ fn get(
     /// The `block` directory maps each `content-hash` (or leaf) to the actual content.
     fn get_root_dir(&self) -> PathBuf;
 
+    /// Pin the content with the given root hash, pinned content is never evicted when the
+    /// block store exceeds its size quota. Content can be pinned before it is inserted.
+    fn pin(&self, _cid: &Blake3Hash) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Unpin the content with the given root hash, so that it can be evicted again.
+    fn unpin(&self, _cid: &Blake3Hash) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+
+    /// Remove the content with the given root hash from the block store, the blocks that are
+    /// shared with other content are kept. Pinned content is not removed. Returns true if the
+    /// content was removed.
+    ///
+    /// The node stops advertising the removed content through the indexer, which also applies
+    /// to the content that is evicted to stay within the size quota.
+    fn remove(&self, _cid: &Blake3Hash) -> impl Future<Output = bool> + Send {
+        async { false }
+    }
+
     /// Utility function to read an entire file to a vec.
     fn read_all_to_vec(&self, hash: &Blake3Hash) -> impl Future<Output = Option<Vec<u8>>> + Send {
         async {
@@ -177,6 +236,10 @@ pub trait IncrementalPutInterface: Send {
 
     /// Write the content. If there has been a call to `feed_proof`, an incremental
     /// validation will happen.
+    ///
+    /// Compressed content is decompressed before it is validated, and must not be larger than
+    /// a single block once it is decompressed. The block store decides on its own how the
+    /// content is compressed on disk.
     fn write(
         &mut self,
         content: &[u8],
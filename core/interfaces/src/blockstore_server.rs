@@ -1,7 +1,7 @@
 use affair::Socket;
 use anyhow::Result;
 use fdi::BuildGraph;
-use lightning_types::{PeerRequestError, ServerRequest};
+use lightning_types::{PeerRequestError, ServerPartRequest, ServerPartResponse, ServerRequest};
 use tokio::sync::broadcast;
 
 use crate::collection::Collection;
@@ -10,10 +10,17 @@
 pub type BlockstoreServerSocket =
     Socket<ServerRequest, broadcast::Receiver<Result<(), PeerRequestError>>>;
 
+pub type BlockstoreServerPartSocket =
+    Socket<ServerPartRequest, Result<ServerPartResponse, PeerRequestError>>;
+
 #[interfaces_proc::blank]
 pub trait BlockstoreServerInterface<C: Collection>:
     BuildGraph + Sized + Send + Sync + ConfigConsumer
 {
     #[socket]
     fn get_socket(&self) -> BlockstoreServerSocket;
+
+    /// Returns a socket that can be used to request parts of a content from a peer.
+    #[socket]
+    fn get_part_socket(&self) -> BlockstoreServerPartSocket;
 }
@@ -1,6 +1,7 @@
 use affair::Socket;
 use fdi::BuildGraph;
 use lightning_types::TransactionRequest;
+use tokio::sync::broadcast;
 
 use crate::collection::Collection;
 
@@ -19,4 +20,8 @@ pub trait ForwarderInterface<C: Collection>: BuildGraph + Sized + Send + 'static
     /// Get the socket for forwarding new transaction requests to the mempool.
     #[socket]
     fn mempool_socket(&self) -> MempoolSocket;
+
+    /// Subscribe to the transactions that are forwarded to the mempool by this node.
+    #[blank = broadcast::channel(1).1]
+    fn subscribe_pending_transactions(&self) -> broadcast::Receiver<TransactionRequest>;
 }
@@ -26,7 +26,8 @@
     FetcherSocket,
     DeliveryAcknowledgmentSocket,
     MempoolSocket,
-    BlockstoreServerSocket
+    BlockstoreServerSocket,
+    BlockstoreServerPartSocket
 };
 
 // Re-export all of the pub traits defined in our source code. Except the ones from our hack file.
@@ -123,6 +123,7 @@ async fn create_app_state(test_name: String) -> AppState {
                 JsonConfigProvider::default()
                     .with::<Blockstore<TestBinding>>(BlockstoreConfig {
                         root: path.clone().try_into().unwrap(),
+                        ..Default::default()
                     })
                     .with::<Application<TestBinding>>(AppConfig {
                         genesis: Some(genesis),
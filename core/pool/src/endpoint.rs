@@ -387,7 +387,7 @@ fn validate_stake(&self, peer: NodePublicKey) -> bool {
                 HpUfixed::from(self.query_runner.get_staking_amount())
                     <= self
                         .query_runner
-                        .get_node_info::<HpUfixed<18>>(node_idx, |n| n.stake.staked)
+                        .get_node_stake(node_idx)
                         .unwrap_or(HpUfixed::<18>::zero())
             },
         }
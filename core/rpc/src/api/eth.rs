@@ -4,16 +4,18 @@
     Block,
     BlockNumber,
     Bytes,
+    Filter,
+    Log,
     TransactionReceipt,
     TransactionRequest,
     H256,
     U256,
 };
 use fleek_crypto::EthAddress;
-use jsonrpsee::core::RpcResult;
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
 use jsonrpsee::proc_macros::rpc;
 
-use crate::api_types::{CallRequest, StateOverride};
+use crate::api_types::{CallRequest, StateOverride, SubscriptionKind};
 
 #[rpc(client, server, namespace = "eth")]
 pub trait EthApi {
@@ -129,4 +131,32 @@ async fn call(
 
     #[method(name = "getTransactionReceipt")]
     async fn transaction_receipt(&self, hash: H256) -> RpcResult<Option<TransactionReceipt>>;
+
+    #[method(name = "getLogs")]
+    async fn logs(&self, filter: Filter) -> RpcResult<Vec<Log>>;
+
+    #[method(name = "newFilter")]
+    async fn new_filter(&self, filter: Filter) -> RpcResult<U256>;
+
+    #[method(name = "getFilterChanges")]
+    async fn filter_changes(&self, id: U256) -> RpcResult<Vec<Log>>;
+
+    #[method(name = "getFilterLogs")]
+    async fn filter_logs(&self, id: U256) -> RpcResult<Vec<Log>>;
+
+    #[method(name = "uninstallFilter")]
+    async fn uninstall_filter(&self, id: U256) -> RpcResult<bool>;
+
+    /// Streams block headers, matching logs or pending transaction hashes depending on the kind
+    /// of the subscription. The filter is only used by `logs` subscriptions.
+    #[subscription(
+        name = "subscribe" => "subscription",
+        unsubscribe = "unsubscribe",
+        item = serde_json::Value
+    )]
+    async fn handle_subscription(
+        &self,
+        kind: SubscriptionKind,
+        filter: Option<Filter>,
+    ) -> SubscriptionResult;
 }
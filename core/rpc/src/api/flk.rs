@@ -15,11 +15,17 @@
     NodeInfo,
     NodeInfoWithIndex,
     NodeServed,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
+    ProposalStatus,
     ProtocolParams,
     PublicKeys,
     ReportedReputationMeasurements,
+    Tokens,
     TotalServed,
     TransactionRequest,
+    ValueWithProof,
 };
 use lightning_interfaces::PagingParams;
 use lightning_openrpc_macros::open_rpc;
@@ -67,6 +73,15 @@ async fn get_stables_balance(
         epoch: Option<u64>,
     ) -> RpcResult<HpUfixed<6>>;
 
+    #[method(name = "get_allowance")]
+    async fn get_allowance(
+        &self,
+        owner: EthAddress,
+        spender: EthAddress,
+        token: Tokens,
+        epoch: Option<u64>,
+    ) -> RpcResult<HpUfixed<18>>;
+
     #[method(name = "get_stake_locked_until")]
     async fn get_stake_locked_until(
         &self,
@@ -150,6 +165,11 @@ async fn get_node_served(
     #[method(name = "is_valid_node_epoch")]
     async fn is_valid_node_epoch(&self, public_key: NodePublicKey) -> RpcResult<(bool, Epoch)>;
 
+    /// Open a paging cursor that pins the current state, pass it in the paging params to read
+    /// every page of a registry from the same state.
+    #[method(name = "open_paging_cursor")]
+    async fn open_paging_cursor(&self) -> RpcResult<u64>;
+
     #[method(name = "get_node_registry")]
     async fn get_node_registry(&self, paging: Option<PagingParams>) -> RpcResult<Vec<NodeInfo>>;
 
@@ -182,6 +202,40 @@ async fn get_latencies(
     #[method(name = "get_last_epoch_hash")]
     async fn get_last_epoch_hash(&self) -> RpcResult<([u8; 32], Epoch)>;
 
+    /// Returns a page of the withdrawals waiting to be settled on the L2, ordered by id and
+    /// starting from the withdrawal with id `start`. At most 100 withdrawals are returned.
+    #[method(name = "get_pending_withdrawals")]
+    async fn get_pending_withdrawals(
+        &self,
+        start: Option<u64>,
+        limit: Option<usize>,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<PendingWithdrawal>>;
+
+    #[method(name = "get_proposal")]
+    async fn get_proposal(&self, id: ProposalId, epoch: Option<u64>)
+    -> RpcResult<Option<Proposal>>;
+
+    /// Returns the governance proposals ordered by id, optionally only the ones with the given
+    /// status.
+    #[method(name = "get_proposals")]
+    async fn get_proposals(
+        &self,
+        status: Option<ProposalStatus>,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<Proposal>>;
+
+    /// Returns the raw value of a raw key in the given table along with a proof for it against
+    /// the state root. Only the live node keeps the state tree in memory, so the state of a past
+    /// epoch can not be proven and querying it returns an error.
+    #[method(name = "get_proof")]
+    async fn get_proof(
+        &self,
+        table: String,
+        key: Vec<u8>,
+        epoch: Option<u64>,
+    ) -> RpcResult<ValueWithProof>;
+
     #[method(name = "send_txn")]
     async fn send_txn(&self, tx: TransactionRequest) -> RpcResult<()>;
 
@@ -196,4 +250,12 @@ async fn get_latencies(
 
     #[subscription(name = "subscribe", item = Event)]
     async fn handle_subscription(&self, event_type: Option<EventType>) -> SubscriptionResult;
+
+    /// Streams the epoch info every time the epoch changes.
+    #[subscription(
+        name = "subscribeEpochChanges",
+        unsubscribe = "unsubscribeEpochChanges",
+        item = EpochInfo
+    )]
+    async fn subscribe_epoch_changes(&self) -> SubscriptionResult;
 }
@@ -45,3 +45,15 @@ pub struct CallRequest {
     pub data: Option<Bytes>,
     pub value: Option<U256>,
 }
+
+/// The kind of an `eth_subscribe` subscription.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionKind {
+    /// A block header for every new block.
+    NewHeads,
+    /// The logs of every new block that match the filter of the subscription.
+    Logs,
+    /// The hash of every transaction this node forwards to the mempool.
+    NewPendingTransactions,
+}
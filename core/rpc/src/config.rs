@@ -2,10 +2,60 @@
 
 use serde::{Deserialize, Serialize};
 
+/// The default number of subscriptions a single connection may open.
+const DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION: u32 = 64;
+/// The default number of messages buffered for a connection before subscriptions wait on it.
+const DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY: u32 = 1024;
+/// The default number of seconds a paging cursor stays usable after it is opened.
+const DEFAULT_PAGING_CURSOR_TTL_SECS: u64 = 60;
+/// The default number of paging cursors that may be open at the same time.
+const DEFAULT_MAX_PAGING_CURSORS: usize = 1024;
+/// The default number of log filters that may be installed at the same time.
+const DEFAULT_MAX_FILTERS: usize = 1024;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     addr: SocketAddr,
     rpc_selection: RPCSelection,
+    /// The maximum number of subscriptions a single connection may open.
+    #[serde(default = "default_max_subscriptions_per_connection")]
+    max_subscriptions_per_connection: u32,
+    /// The number of messages buffered for a connection. Once the buffer is full subscriptions
+    /// on that connection wait for the client to catch up.
+    #[serde(default = "default_subscription_buffer_capacity")]
+    subscription_buffer_capacity: u32,
+    /// The number of seconds a paging cursor stays usable after it is opened. The state changes
+    /// that happen while a cursor is open are kept in memory until it expires.
+    #[serde(default = "default_paging_cursor_ttl_secs")]
+    paging_cursor_ttl_secs: u64,
+    /// The maximum number of paging cursors that may be open at the same time, opening another
+    /// one fails until one of them expires.
+    #[serde(default = "default_max_paging_cursors")]
+    max_paging_cursors: usize,
+    /// The maximum number of log filters that may be installed at the same time, installing
+    /// another one fails until one of them is uninstalled or expires.
+    #[serde(default = "default_max_filters")]
+    max_filters: usize,
+}
+
+fn default_max_subscriptions_per_connection() -> u32 {
+    DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION
+}
+
+fn default_subscription_buffer_capacity() -> u32 {
+    DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY
+}
+
+fn default_paging_cursor_ttl_secs() -> u64 {
+    DEFAULT_PAGING_CURSOR_TTL_SECS
+}
+
+fn default_max_paging_cursors() -> usize {
+    DEFAULT_MAX_PAGING_CURSORS
+}
+
+fn default_max_filters() -> usize {
+    DEFAULT_MAX_FILTERS
 }
 
 impl Config {
@@ -13,6 +63,11 @@ pub fn new(addr: SocketAddr, rpc_selection: RPCSelection) -> Self {
         Self {
             addr,
             rpc_selection,
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            subscription_buffer_capacity: DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY,
+            paging_cursor_ttl_secs: DEFAULT_PAGING_CURSOR_TTL_SECS,
+            max_paging_cursors: DEFAULT_MAX_PAGING_CURSORS,
+            max_filters: DEFAULT_MAX_FILTERS,
         }
     }
 
@@ -22,6 +77,11 @@ pub fn default_with_port_and_addr(addr: String, port: u16) -> Self {
                 .parse()
                 .expect("RPC Socket Addr to parse"),
             rpc_selection: Default::default(),
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            subscription_buffer_capacity: DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY,
+            paging_cursor_ttl_secs: DEFAULT_PAGING_CURSOR_TTL_SECS,
+            max_paging_cursors: DEFAULT_MAX_PAGING_CURSORS,
+            max_filters: DEFAULT_MAX_FILTERS,
         }
     }
 
@@ -31,6 +91,11 @@ pub fn default_with_port(port: u16) -> Self {
                 .parse()
                 .expect("RPC Socket Addr to parse"),
             rpc_selection: Default::default(),
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            subscription_buffer_capacity: DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY,
+            paging_cursor_ttl_secs: DEFAULT_PAGING_CURSOR_TTL_SECS,
+            max_paging_cursors: DEFAULT_MAX_PAGING_CURSORS,
+            max_filters: DEFAULT_MAX_FILTERS,
         }
     }
 
@@ -41,6 +106,26 @@ pub fn port(&self) -> u16 {
     pub fn addr(&self) -> SocketAddr {
         self.addr
     }
+
+    pub fn max_subscriptions_per_connection(&self) -> u32 {
+        self.max_subscriptions_per_connection
+    }
+
+    pub fn subscription_buffer_capacity(&self) -> u32 {
+        self.subscription_buffer_capacity
+    }
+
+    pub fn paging_cursor_ttl_secs(&self) -> u64 {
+        self.paging_cursor_ttl_secs
+    }
+
+    pub fn max_paging_cursors(&self) -> usize {
+        self.max_paging_cursors
+    }
+
+    pub fn max_filters(&self) -> usize {
+        self.max_filters
+    }
 }
 
 impl Default for Config {
@@ -48,6 +133,11 @@ fn default() -> Self {
         Self {
             addr: "0.0.0.0:4230".parse().expect("RPC Socket Addr to parse"),
             rpc_selection: Default::default(),
+            max_subscriptions_per_connection: DEFAULT_MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            subscription_buffer_capacity: DEFAULT_SUBSCRIPTION_BUFFER_CAPACITY,
+            paging_cursor_ttl_secs: DEFAULT_PAGING_CURSOR_TTL_SECS,
+            max_paging_cursors: DEFAULT_MAX_PAGING_CURSORS,
+            max_filters: DEFAULT_MAX_FILTERS,
         }
     }
 }
@@ -50,6 +50,12 @@ pub enum RPCError {
     #[error("Not an archive node")]
     NotArchiveNode,
 
+    #[error("Unknown or expired paging cursor")]
+    BadPagingCursor,
+
+    #[error("Too many paging cursors are open")]
+    TooManyPagingCursors,
+
     #[error("Error: ")]
     Anyhow(#[from] anyhow::Error),
 }
@@ -92,6 +98,8 @@ fn from(e: RPCError) -> Self {
             RPCError::BadEpoch => internal_err_from_string("Bad Epoch".to_string()),
             RPCError::Anyhow(e) => internal_err_from_string(e.to_string()),
             RPCError::NotArchiveNode => internal_err_from_string(e.to_string()),
+            RPCError::BadPagingCursor => internal_err_from_string(e.to_string()),
+            RPCError::TooManyPagingCursors => internal_err_from_string(e.to_string()),
         }
     }
 }
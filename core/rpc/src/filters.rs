@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use ethers::types::{Filter, U256};
+
+/// Filters that are not polled for this long are uninstalled.
+pub(crate) const FILTER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A log filter installed with `eth_newFilter`.
+struct InstalledFilter {
+    filter: Filter,
+    /// The first block whose logs have not been returned by `eth_getFilterChanges` yet.
+    next_block: u64,
+    last_poll: Instant,
+}
+
+impl InstalledFilter {
+    fn is_expired(&self, timeout: Duration) -> bool {
+        self.last_poll.elapsed() >= timeout
+    }
+}
+
+/// The log filters that are installed on the node, each one is uninstalled once it has not been
+/// polled for the timeout.
+pub(crate) struct LogFilters {
+    timeout: Duration,
+    max_filters: usize,
+    next_id: AtomicU64,
+    filters: Mutex<HashMap<U256, InstalledFilter>>,
+}
+
+impl LogFilters {
+    pub fn new(timeout: Duration, max_filters: usize) -> Self {
+        Self {
+            timeout,
+            max_filters,
+            next_id: AtomicU64::new(1),
+            filters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Install a filter whose changes are reported from the given block and return its id, or
+    /// `None` if the maximum number of filters is already installed.
+    pub fn install(&self, filter: Filter, next_block: u64) -> Option<U256> {
+        let mut filters = self.filters.lock().unwrap();
+        filters.retain(|_, installed| !installed.is_expired(self.timeout));
+        if filters.len() >= self.max_filters {
+            return None;
+        }
+        let id = U256::from(self.next_id.fetch_add(1, Ordering::Relaxed));
+        filters.insert(
+            id,
+            InstalledFilter {
+                filter,
+                next_block,
+                last_poll: Instant::now(),
+            },
+        );
+        Some(id)
+    }
+
+    /// Returns the filter with the given id along with the first block whose logs have not been
+    /// returned yet, and marks the blocks up to `latest` as returned.
+    pub fn changes(&self, id: U256, latest: u64) -> Option<(Filter, u64)> {
+        let mut filters = self.filters.lock().unwrap();
+        let installed = filters
+            .get_mut(&id)
+            .filter(|installed| !installed.is_expired(self.timeout))?;
+        let next_block = installed.next_block;
+        installed.next_block = installed.next_block.max(latest + 1);
+        installed.last_poll = Instant::now();
+        Some((installed.filter.clone(), next_block))
+    }
+
+    /// Returns the filter with the given id.
+    pub fn get(&self, id: U256) -> Option<Filter> {
+        let mut filters = self.filters.lock().unwrap();
+        let installed = filters
+            .get_mut(&id)
+            .filter(|installed| !installed.is_expired(self.timeout))?;
+        installed.last_poll = Instant::now();
+        Some(installed.filter.clone())
+    }
+
+    /// Uninstall the filter with the given id, returns whether it was installed.
+    pub fn uninstall(&self, id: U256) -> bool {
+        self.filters.lock().unwrap().remove(&id).is_some()
+    }
+
+    /// Drop the filters that have not been polled for the timeout, so that they do not take up
+    /// memory when no other filter is installed.
+    pub fn sweep(&self) {
+        let mut filters = self.filters.lock().unwrap();
+        filters.retain(|_, installed| !installed.is_expired(self.timeout));
+    }
+
+    /// Returns the number of filters that are held, including the expired ones that have not
+    /// been swept yet.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.filters.lock().unwrap().len()
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use fleek_crypto::{ConsensusPublicKey, NodePublicKey};
 use hyper::server::conn::AddrStream;
@@ -6,9 +7,10 @@
 use jsonrpsee::server::{stop_channel, Server as JSONRPCServer};
 use jsonrpsee::{Methods, RpcModule};
 use lightning_interfaces::prelude::*;
-use lightning_interfaces::types::Event;
-use lightning_interfaces::{FetcherSocket, MempoolSocket};
+use lightning_interfaces::types::{Event, TransactionRequest};
+use lightning_interfaces::{FetcherSocket, MempoolSocket, PagingParams};
 use reqwest::StatusCode;
+use tokio::sync::broadcast;
 use tower::Service;
 
 use crate::api::AdminApiServer;
@@ -16,15 +18,19 @@
 pub use crate::config::Config;
 use crate::error::RPCError;
 use crate::event::EventDistributor;
+use crate::filters::{LogFilters, FILTER_TIMEOUT};
 use crate::logic::AdminApi;
 pub use crate::logic::{EthApi, FleekApi, NetApi};
+use crate::paging::PagingCursors;
 
 pub mod api;
 pub mod api_types;
 pub mod config;
 pub mod error;
 pub mod event;
+mod filters;
 mod logic;
+mod paging;
 
 #[cfg(test)]
 mod tests;
@@ -37,7 +43,13 @@ pub(crate) struct Data<C: Collection> {
     pub node_public_key: NodePublicKey,
     pub consensus_public_key: ConsensusPublicKey,
     pub archive: C::ArchiveInterface,
+    pub notifier: C::NotifierInterface,
+    pub pending_transactions: broadcast::Receiver<TransactionRequest>,
     pub event_handler: EventDistributor,
+    pub paging_cursors: PagingCursors<
+        <c!(C::ApplicationInterface::SyncExecutor) as SyncQueryRunnerInterface>::Backend,
+    >,
+    pub filters: LogFilters,
 }
 
 impl<C: Collection> Data<C> {
@@ -55,6 +67,23 @@ pub(crate) async fn query_runner(
             Ok(self.query_runner.clone())
         }
     }
+
+    /// Returns the query runner to read a page from, which reads the snapshot of the paging
+    /// cursor if one is given.
+    pub(crate) fn paging_query_runner(
+        &self,
+        paging: &Option<PagingParams>,
+    ) -> Result<c!(C::ApplicationInterface::SyncExecutor), RPCError> {
+        let Some(id) = paging.as_ref().and_then(|paging| paging.cursor) else {
+            return Ok(self.query_runner.clone());
+        };
+
+        self.paging_cursors
+            .get(id)
+            .and_then(|snapshot| snapshot.query().ok())
+            .map(SyncQueryRunnerInterface::new)
+            .ok_or(RPCError::BadPagingCursor)
+    }
 }
 
 pub struct Rpc<C: Collection> {
@@ -85,6 +114,7 @@ fn init(
         blockstore: &C::BlockstoreInterface,
         fetcher: &C::FetcherInterface,
         keystore: &C::KeystoreInterface,
+        fdi::Cloned(notifier): fdi::Cloned<c!(C::NotifierInterface)>,
         fdi::Cloned(archive): fdi::Cloned<c!(C::ArchiveInterface)>,
         fdi::Cloned(query_runner): fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
     ) -> anyhow::Result<Self> {
@@ -97,7 +127,14 @@ fn init(
             node_public_key: keystore.get_ed25519_pk(),
             consensus_public_key: keystore.get_bls_pk(),
             archive,
+            notifier,
+            pending_transactions: forwarder.subscribe_pending_transactions(),
             event_handler: EventDistributor::spawn(),
+            paging_cursors: PagingCursors::new(
+                Duration::from_secs(config.paging_cursor_ttl_secs()),
+                config.max_paging_cursors(),
+            ),
+            filters: LogFilters::new(FILTER_TIMEOUT, config.max_filters()),
         });
         let module = Self::create_modules_from_config(&config, data.clone())?;
         let admin_module = Self::create_admin_module_from_config(&config, data.clone())?;
@@ -110,9 +147,11 @@ fn init(
         })
     }
 
-    fn start(&self, shutdown: fdi::Cloned<ShutdownWaiter>) {
+    fn start(&self, fdi::Cloned(shutdown): fdi::Cloned<ShutdownWaiter>) {
         let (stop, server_handle) = stop_channel();
         let json_rpc_service = JSONRPCServer::builder()
+            .max_subscriptions_per_connection(self.config.max_subscriptions_per_connection())
+            .set_message_buffer_capacity(self.config.subscription_buffer_capacity())
             .to_service_builder()
             .build(Methods::from(self.module.clone()), stop.clone());
 
@@ -189,6 +228,24 @@ fn start(&self, shutdown: fdi::Cloned<ShutdownWaiter>) {
             graceful.await.expect("Rpc Server to start");
         });
 
+        // Expired paging cursors and filters are swept periodically, so they do not take up
+        // memory when they are not used anymore.
+        let data = self.data.clone();
+        let waiter = shutdown.clone();
+        tokio::spawn(async move {
+            let period = data.paging_cursors.ttl().max(Duration::from_secs(1));
+            let mut interval = tokio::time::interval(period);
+            waiter
+                .run_until_shutdown(async move {
+                    loop {
+                        interval.tick().await;
+                        data.paging_cursors.sweep();
+                        data.filters.sweep();
+                    }
+                })
+                .await;
+        });
+
         tokio::spawn(async move {
             shutdown.wait_for_shutdown().await;
             server_handle.stop().unwrap();
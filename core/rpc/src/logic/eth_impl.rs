@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
 use alloy_primitives::U64;
+use ethers::abi::{AbiDecode, AbiEncode};
 use ethers::types::{
     Address,
     Block,
     BlockNumber,
     Bytes,
+    Filter,
+    Log,
+    NameOrAddress,
     Transaction,
     TransactionReceipt,
     TransactionRequest,
@@ -14,22 +18,45 @@
 };
 use ethers::utils::rlp;
 use fleek_crypto::EthAddress;
+use futures::StreamExt;
 use hp_fixed::unsigned::HpUfixed;
-use jsonrpsee::core::RpcResult;
+use jsonrpsee::core::{RpcResult, SubscriptionResult};
+use jsonrpsee::PendingSubscriptionSink;
 use lightning_interfaces::prelude::*;
 use lightning_utils::application::QueryRunnerExt;
+use lightning_utils::eth::fleek_contract::FleekContractCalls;
+use lightning_utils::eth::{
+    GetBalanceCall,
+    GetBalanceReturn,
+    GetEpochCall,
+    GetEpochReturn,
+    GetNodeInfoCall,
+    GetNodeInfoReturn,
+    GetProtocolParamCall,
+    GetProtocolParamReturn,
+    GetStakeCall,
+    GetStakeReturn,
+};
+use num_traits::FromPrimitive;
 use tracing::trace;
 
 use crate::api::EthApiServer;
-use crate::api_types::{CallRequest, StateOverride};
+use crate::api_types::{CallRequest, StateOverride, SubscriptionKind};
 use crate::error::RPCError;
+use crate::logic::subscription::{broadcast_stream, pipe_from_stream, subscriber_stream};
 use crate::Data;
 
-const FLEEK_CONTRACT: EthAddress = EthAddress([6; 20]);
-
 const FLEEK_CONTRACT_BYTES: &[u8; 172] = b"73000000000000000000000000000000000000000030146080604052600080fdfea264697066735822122012d3570051ca11eb882745693b7b2af91a10ad5074b3486da80280731d9af73164736f6c63430008120033";
 
-use lightning_interfaces::types::{Metadata, Value};
+use lightning_interfaces::types::{
+    log_matches,
+    Metadata,
+    ProtocolParams,
+    TransactionRequest as FleekTransactionRequest,
+    TransactionResponse,
+    Value,
+    FLEEK_CONTRACT_ADDRESS,
+};
 
 pub struct EthApi<C: Collection> {
     data: Arc<Data<C>>,
@@ -39,6 +66,57 @@ impl<C: Collection> EthApi<C> {
     pub(crate) fn new(data: Arc<Data<C>>) -> Self {
         Self { data }
     }
+
+    /// Returns the number of the latest block the archive has stored.
+    async fn latest_archived_block(&self) -> Option<u64> {
+        self.data
+            .archive
+            .get_block_by_number(BlockNumber::Latest)
+            .await
+            .map(|block| block.block_number)
+    }
+
+    /// Returns the state to read from for a block number. The state of a past block is
+    /// reconstructed by the archive from the checkpoint of its epoch, so it is only available
+    /// on archive nodes once that epoch has ended. When `epoch` is provided it takes priority.
+    async fn query_runner_at(
+        &self,
+        block_number: Option<BlockNumber>,
+        epoch: Option<u64>,
+    ) -> Result<c!(C::ApplicationInterface::SyncExecutor), RPCError> {
+        let latest = match self.data.query_runner.get_metadata(&Metadata::BlockNumber) {
+            Some(Value::BlockNumber(num)) => num,
+            _ => 0,
+        };
+        match block_number {
+            None
+            | Some(BlockNumber::Latest)
+            | Some(BlockNumber::Pending)
+            | Some(BlockNumber::Safe)
+            | Some(BlockNumber::Finalized) => self.data.query_runner(epoch).await,
+            _ if epoch.is_some() => self.data.query_runner(epoch).await,
+            Some(BlockNumber::Number(number)) if number.as_u64() == latest => {
+                self.data.query_runner(epoch).await
+            },
+            Some(BlockNumber::Number(number)) if number.as_u64() < latest => {
+                if !self.data.archive.is_active() {
+                    return Err(RPCError::custom(
+                        "Historical state is only available on archive nodes".to_string(),
+                    ));
+                }
+                self.data
+                    .archive
+                    .get_historical_block_state(number.as_u64())
+                    .await
+                    .ok_or_else(|| {
+                        RPCError::custom(format!("State at block {number} is not available"))
+                    })
+            },
+            Some(block_number) => Err(RPCError::custom(format!(
+                "State at block {block_number} is not available"
+            ))),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -82,8 +160,7 @@ async fn balance(
         trace!(target: "rpc::eth", ?address, ?block_number, "Serving eth_getBalance");
         // Todo(dalton) direct safe conversion from hpfixed => u128
         Ok(self
-            .data
-            .query_runner(epoch)
+            .query_runner_at(block_number, epoch)
             .await?
             .get_account_info::<HpUfixed<18>>(&address, |a| a.flk_balance)
             .unwrap_or(HpUfixed::<18>::zero())
@@ -174,16 +251,43 @@ async fn send_raw_transaction(&self, tx: Bytes) -> RpcResult<H256> {
         Ok(hash)
     }
 
-    /// Todo(n)
     async fn gas_price(&self) -> RpcResult<U256> {
         trace!(target: "rpc::eth", "Serving eth_gasPrice");
-        Ok(U256::zero())
+        Ok(U256::from(
+            self.data
+                .query_runner
+                .get_protocol_param(&ProtocolParams::GasPrice)
+                .unwrap_or(0),
+        ))
     }
 
-    /// todo(n)
+    /// Simulates the transaction and returns the gas it would be charged for. Errors if the
+    /// transaction would revert, including when the sender can not pay for the fee.
     async fn estimate_gas(&self, tx: CallRequest) -> RpcResult<U256> {
         trace!(target: "rpc::eth", ?tx, "Serving eth_estimateGas");
-        Ok(U256::from(1000000))
+
+        let chain_id = match self.data.query_runner.get_metadata(&Metadata::ChainId) {
+            Some(Value::ChainId(chain_id)) => chain_id,
+            _ => 0,
+        };
+        let txn: FleekTransactionRequest = Transaction {
+            from: tx.from.unwrap_or_default(),
+            to: tx.to,
+            input: tx.input.or(tx.data).unwrap_or_default(),
+            value: tx.value.unwrap_or_default(),
+            chain_id: Some(chain_id.into()),
+            ..Default::default()
+        }
+        .into();
+
+        match self.data.query_runner.simulate_txn(txn.clone()) {
+            TransactionResponse::Success(_) => {
+                Ok(U256::from(self.data.query_runner.get_transaction_gas(&txn)))
+            },
+            TransactionResponse::Revert(err) => {
+                Err(RPCError::custom(format!("Transaction would revert: {err:?}")).into())
+            },
+        }
     }
 
     /// todo(n)
@@ -194,13 +298,15 @@ async fn code(
     ) -> RpcResult<Bytes> {
         trace!(target: "rpc::eth", ?address, ?block_number, "Serving eth_getCode");
 
-        if address == FLEEK_CONTRACT {
+        if address == FLEEK_CONTRACT_ADDRESS {
             Ok(Bytes::from(FLEEK_CONTRACT_BYTES))
         } else {
             Ok(Bytes::new())
         }
     }
 
+    /// Fees are fixed by the protocol and go to the protocol fund, so there is no tip to pay on
+    /// top of the gas price.
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
         trace!(target: "rpc::eth", "Serving eth_maxPriorityFeePerGas");
         Ok(U256::zero())
@@ -210,14 +316,20 @@ async fn fee_history(&self) -> RpcResult<String> {
         Err(RPCError::unimplemented().into())
     }
 
-    /// todo(n)
+    /// Lightning state does not live in EVM storage slots. Contract state is exposed through the
+    /// view functions of the Fleek contract instead, see `call`.
     async fn storage_at(
         &self,
-        _address: EthAddress,
-        _index: U256,
-        _block_number: Option<BlockNumber>,
+        address: EthAddress,
+        index: U256,
+        block_number: Option<BlockNumber>,
     ) -> RpcResult<Bytes> {
-        Err(RPCError::unimplemented().into())
+        trace!(target: "rpc::eth", ?address, ?index, ?block_number, "Serving eth_getStorageAt");
+        Err(RPCError::custom(
+            "Storage slots are not supported, use the view functions of the Fleek contract"
+                .to_string(),
+        )
+        .into())
     }
 
     async fn transaction_by_hash(&self, _hash: H256) -> RpcResult<String> {
@@ -256,14 +368,92 @@ async fn accounts(&self) -> RpcResult<Vec<Address>> {
         Err(RPCError::unimplemented().into())
     }
 
-    /// todo(n)
+    /// Answers calls to the view functions of the Fleek contract from the application state.
+    /// State overrides are not supported, since there is no EVM state to override.
     async fn call(
         &self,
-        _tx: TransactionRequest,
-        _block_number: Option<BlockNumber>,
+        tx: TransactionRequest,
+        block_number: Option<BlockNumber>,
         _state_overrides: Option<StateOverride>,
     ) -> RpcResult<Bytes> {
-        Err(RPCError::unimplemented().into())
+        trace!(target: "rpc::eth", ?tx, ?block_number, "Serving eth_call");
+
+        // Calls to anything other than the Fleek contract behave like calls to an account
+        // without code.
+        if tx.to != Some(NameOrAddress::Address(FLEEK_CONTRACT_ADDRESS.0.into())) {
+            return Ok(Bytes::new());
+        }
+
+        let query_runner = self.query_runner_at(block_number, None).await?;
+        let data = tx.data.unwrap_or_default();
+        let output = match FleekContractCalls::decode(&data) {
+            Ok(FleekContractCalls::GetBalance(GetBalanceCall { account })) => {
+                let account = query_runner
+                    .get_account_info(&EthAddress(account.0), |a| a)
+                    .unwrap_or_default();
+                GetBalanceReturn {
+                    flk: account.flk_balance.into(),
+                    stables: account.stables_balance.into(),
+                    bandwidth: account.bandwidth_balance.into(),
+                }
+                .encode()
+            },
+            Ok(FleekContractCalls::GetStake(GetStakeCall { node_public_key })) => {
+                let stake = query_runner
+                    .pubkey_to_index(&node_public_key.into())
+                    .and_then(|index| query_runner.get_node_info(&index, |n| n.stake))
+                    .unwrap_or_default();
+                GetStakeReturn {
+                    staked: stake.staked.into(),
+                    stake_locked_until: stake.stake_locked_until,
+                    locked: stake.locked.into(),
+                    locked_until: stake.locked_until,
+                }
+                .encode()
+            },
+            Ok(FleekContractCalls::GetNodeInfo(GetNodeInfoCall { node_public_key })) => {
+                let node = query_runner
+                    .pubkey_to_index(&node_public_key.into())
+                    .and_then(|index| query_runner.get_node_info(&index, |n| n))
+                    .ok_or_else(|| RPCError::custom("Node does not exist".to_string()))?;
+                GetNodeInfoReturn {
+                    owner: node.owner.0.into(),
+                    consensus_key: node.consensus_key.0.to_vec().into(),
+                    domain: node.domain.to_string(),
+                    worker_public_key: node.worker_public_key.0,
+                    worker_domain: node.worker_domain.to_string(),
+                    staked_since: node.staked_since,
+                    nonce: node.nonce,
+                }
+                .encode()
+            },
+            Ok(FleekContractCalls::GetEpoch(GetEpochCall)) => {
+                let epoch_info = query_runner.get_epoch_info();
+                GetEpochReturn {
+                    epoch: epoch_info.epoch,
+                    epoch_end: epoch_info.epoch_end,
+                }
+                .encode()
+            },
+            Ok(FleekContractCalls::GetProtocolParam(GetProtocolParamCall { param })) => {
+                let param = ProtocolParams::from_u8(param)
+                    .ok_or_else(|| RPCError::custom(format!("Invalid protocol param: {param}")))?;
+                GetProtocolParamReturn {
+                    value: query_runner.get_protocol_param(&param).unwrap_or(0).into(),
+                }
+                .encode()
+            },
+            Ok(_) => {
+                return Err(RPCError::custom(
+                    "Only view functions can be called, state changes require a transaction"
+                        .to_string(),
+                )
+                .into());
+            },
+            Err(e) => return Err(RPCError::custom(e.to_string()).into()),
+        };
+
+        Ok(output.into())
     }
 
     async fn sign(&self, _address: EthAddress, _data: Bytes) -> RpcResult<Bytes> {
@@ -273,4 +463,124 @@ async fn sign(&self, _address: EthAddress, _data: Bytes) -> RpcResult<Bytes> {
     async fn sign_transaction(&self, _tx: TransactionRequest) -> RpcResult<Bytes> {
         Err(RPCError::unimplemented().into())
     }
+
+    async fn logs(&self, filter: Filter) -> RpcResult<Vec<Log>> {
+        trace!(target: "rpc::eth", ?filter, "Serving eth_getLogs");
+
+        if !self.data.archive.is_active() {
+            return Err(RPCError::custom("Archive socket not initialized".to_string()).into());
+        }
+
+        Ok(self
+            .data
+            .archive
+            .get_logs(filter)
+            .await
+            .map_err(|e| RPCError::custom(e.to_string()))?)
+    }
+
+    async fn new_filter(&self, filter: Filter) -> RpcResult<U256> {
+        trace!(target: "rpc::eth", ?filter, "Serving eth_newFilter");
+
+        if !self.data.archive.is_active() {
+            return Err(RPCError::custom("Archive socket not initialized".to_string()).into());
+        }
+
+        // Changes are reported from the first block after the filter was installed.
+        let next_block = self.latest_archived_block().await.map_or(0, |num| num + 1);
+        self.data
+            .filters
+            .install(filter, next_block)
+            .ok_or_else(|| RPCError::custom("Too many filters are installed".to_string()).into())
+    }
+
+    async fn filter_changes(&self, id: U256) -> RpcResult<Vec<Log>> {
+        trace!(target: "rpc::eth", ?id, "Serving eth_getFilterChanges");
+
+        if !self.data.archive.is_active() {
+            return Err(RPCError::custom("Archive socket not initialized".to_string()).into());
+        }
+        let Some(latest) = self.latest_archived_block().await else {
+            return Ok(Vec::new());
+        };
+        let (filter, next_block) = self
+            .data
+            .filters
+            .changes(id, latest)
+            .ok_or_else(|| RPCError::custom("Filter not found".to_string()))?;
+        if next_block > latest {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .data
+            .archive
+            .get_logs(filter.from_block(next_block).to_block(latest))
+            .await
+            .map_err(|e| RPCError::custom(e.to_string()))?)
+    }
+
+    async fn filter_logs(&self, id: U256) -> RpcResult<Vec<Log>> {
+        trace!(target: "rpc::eth", ?id, "Serving eth_getFilterLogs");
+
+        let filter = self
+            .data
+            .filters
+            .get(id)
+            .ok_or_else(|| RPCError::custom("Filter not found".to_string()))?;
+        self.logs(filter).await
+    }
+
+    async fn uninstall_filter(&self, id: U256) -> RpcResult<bool> {
+        trace!(target: "rpc::eth", ?id, "Serving eth_uninstallFilter");
+        Ok(self.data.filters.uninstall(id))
+    }
+
+    async fn handle_subscription(
+        &self,
+        pending: PendingSubscriptionSink,
+        kind: SubscriptionKind,
+        filter: Option<Filter>,
+    ) -> SubscriptionResult {
+        trace!(target: "rpc::eth", ?kind, ?filter, "Serving eth_subscribe");
+
+        if filter.is_some() && kind != SubscriptionKind::Logs {
+            pending
+                .reject(RPCError::custom(
+                    "Only logs subscriptions take a filter".to_string(),
+                ))
+                .await;
+            return Ok(());
+        }
+
+        match kind {
+            SubscriptionKind::NewHeads => {
+                let blocks = subscriber_stream(self.data.notifier.subscribe_block_executed());
+                let heads = blocks.map(|notification| {
+                    let (receipt, _) = notification.response.to_receipts();
+                    vec![Block::<H256>::from(receipt)]
+                });
+                pipe_from_stream(pending, heads).await
+            },
+            SubscriptionKind::Logs => {
+                let filter = filter.unwrap_or_default();
+                let blocks = subscriber_stream(self.data.notifier.subscribe_block_executed());
+                let logs = blocks.map(move |notification| {
+                    notification
+                        .response
+                        .txn_receipts
+                        .iter()
+                        .flat_map(|receipt| receipt.logs())
+                        .filter(|log| log_matches(&filter, log))
+                        .collect::<Vec<_>>()
+                });
+                pipe_from_stream(pending, logs).await
+            },
+            SubscriptionKind::NewPendingTransactions => {
+                let txns = broadcast_stream(self.data.pending_transactions.resubscribe());
+                let hashes = txns.map(|txn| vec![H256::from(txn.hash())]);
+                pipe_from_stream(pending, hashes).await
+            },
+        }
+    }
 }
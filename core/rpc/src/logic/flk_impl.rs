@@ -2,6 +2,7 @@
 use std::time::Duration;
 
 use fleek_crypto::{EthAddress, NodePublicKey};
+use futures::StreamExt;
 use hp_fixed::unsigned::HpUfixed;
 use jsonrpsee::core::{RpcResult, SubscriptionResult};
 use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
@@ -21,20 +22,30 @@
     NodeInfoWithIndex,
     NodeServed,
     OriginProvider,
+    PendingWithdrawal,
+    Proposal,
+    ProposalId,
+    ProposalStatus,
     ProtocolParams,
     PublicKeys,
     ReportedReputationMeasurements,
+    Tokens,
     TotalServed,
     TransactionRequest,
     Value,
+    ValueWithProof,
 };
 use lightning_interfaces::PagingParams;
 use lightning_utils::application::QueryRunnerExt;
 
 use crate::api::FleekApiServer;
 use crate::error::RPCError;
+use crate::logic::subscription::{pipe_from_stream, subscriber_stream};
 use crate::Data;
 
+/// The maximum number of pending withdrawals returned in one page.
+const MAX_PENDING_WITHDRAWALS_PAGE: usize = 100;
+
 pub struct FleekApi<C: Collection> {
     data: Arc<Data<C>>,
 }
@@ -110,6 +121,21 @@ async fn get_stables_balance(
             .unwrap_or(HpUfixed::<6>::zero()))
     }
 
+    async fn get_allowance(
+        &self,
+        owner: EthAddress,
+        spender: EthAddress,
+        token: Tokens,
+        epoch: Option<u64>,
+    ) -> RpcResult<HpUfixed<18>> {
+        Ok(self
+            .data
+            .query_runner(epoch)
+            .await?
+            .get_allowance(&owner, &spender, &token)
+            .unwrap_or(HpUfixed::zero()))
+    }
+
     async fn get_stake_locked_until(
         &self,
         pk: NodePublicKey,
@@ -306,10 +332,18 @@ async fn is_valid_node_epoch(&self, pk: NodePublicKey) -> RpcResult<(bool, Epoch
         ))
     }
 
+    async fn open_paging_cursor(&self) -> RpcResult<u64> {
+        Ok(self
+            .data
+            .paging_cursors
+            .open(self.data.query_runner.snapshot())
+            .ok_or(RPCError::TooManyPagingCursors)?)
+    }
+
     async fn get_node_registry(&self, paging: Option<PagingParams>) -> RpcResult<Vec<NodeInfo>> {
         Ok(self
             .data
-            .query_runner
+            .paging_query_runner(&paging)?
             .get_node_registry(paging)
             .into_iter()
             .map(|n| n.info)
@@ -320,7 +354,10 @@ async fn get_node_registry_index(
         &self,
         paging: Option<PagingParams>,
     ) -> RpcResult<Vec<NodeInfoWithIndex>> {
-        Ok(self.data.query_runner.get_node_registry(paging))
+        Ok(self
+            .data
+            .paging_query_runner(&paging)?
+            .get_node_registry(paging))
     }
 
     async fn get_reputation(&self, pk: NodePublicKey, epoch: Option<u64>) -> RpcResult<Option<u8>> {
@@ -379,6 +416,56 @@ async fn get_latencies(
         ))
     }
 
+    async fn get_pending_withdrawals(
+        &self,
+        start: Option<u64>,
+        limit: Option<usize>,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<PendingWithdrawal>> {
+        let limit = limit
+            .unwrap_or(MAX_PENDING_WITHDRAWALS_PAGE)
+            .min(MAX_PENDING_WITHDRAWALS_PAGE);
+        Ok(self
+            .data
+            .query_runner(epoch)
+            .await?
+            .get_pending_withdrawals(start.unwrap_or(0), limit))
+    }
+
+    async fn get_proposal(
+        &self,
+        id: ProposalId,
+        epoch: Option<u64>,
+    ) -> RpcResult<Option<Proposal>> {
+        Ok(self.data.query_runner(epoch).await?.get_proposal(&id))
+    }
+
+    async fn get_proposals(
+        &self,
+        status: Option<ProposalStatus>,
+        epoch: Option<u64>,
+    ) -> RpcResult<Vec<Proposal>> {
+        Ok(self.data.query_runner(epoch).await?.get_proposals(status))
+    }
+
+    async fn get_proof(
+        &self,
+        table: String,
+        key: Vec<u8>,
+        epoch: Option<u64>,
+    ) -> RpcResult<ValueWithProof> {
+        let query_runner = self.data.query_runner(epoch).await?;
+        if query_runner.get_state_root().is_none() {
+            return Err(RPCError::custom(
+                "The state tree is not available for this state".to_string(),
+            )
+            .into());
+        }
+        query_runner
+            .get_state_proof(&table, &key)
+            .ok_or_else(|| RPCError::custom(format!("Table {table} does not exist")).into())
+    }
+
     async fn send_txn(&self, tx: TransactionRequest) -> RpcResult<()> {
         Ok(self
             .data
@@ -455,4 +542,15 @@ async fn handle_subscription(
 
         Ok(())
     }
+
+    async fn subscribe_epoch_changes(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let query_runner = self.data.query_runner.clone();
+        let epochs = subscriber_stream(self.data.notifier.subscribe_epoch_changed())
+            .map(move |_| vec![query_runner.get_epoch_info()]);
+
+        pipe_from_stream(pending, epochs).await
+    }
 }
@@ -2,6 +2,7 @@
 mod eth_impl;
 mod flk_impl;
 mod net_impl;
+mod subscription;
 
 pub use admin_impl::AdminApi;
 pub use eth_impl::EthApi;
@@ -0,0 +1,80 @@
+use futures::{Stream, StreamExt};
+use jsonrpsee::core::SubscriptionResult;
+use jsonrpsee::{PendingSubscriptionSink, SubscriptionMessage};
+use lightning_interfaces::prelude::*;
+use serde::Serialize;
+use tokio::pin;
+use tokio::sync::broadcast;
+
+/// Accepts the subscription and sends every item of the stream to it, until either the stream
+/// ends or the client goes away.
+///
+/// Sending waits while the message buffer of the connection is full, so a slow client only holds
+/// up its own subscriptions. The sources are broadcast channels which skip over the items a
+/// lagging subscription missed.
+pub(crate) async fn pipe_from_stream<S, T>(
+    pending: PendingSubscriptionSink,
+    stream: S,
+) -> SubscriptionResult
+where
+    S: Stream<Item = Vec<T>>,
+    T: Serialize,
+{
+    let sink = pending.accept().await?;
+    pin!(stream);
+
+    loop {
+        let items = tokio::select! {
+            _ = sink.closed() => break,
+            items = stream.next() => match items {
+                Some(items) => items,
+                None => break,
+            },
+        };
+
+        for item in items {
+            if sink
+                .send(SubscriptionMessage::from_json(&item)?)
+                .await
+                .is_err()
+            {
+                tracing::trace!("subscription closed");
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Turns a notifier subscriber into a stream.
+pub(crate) fn subscriber_stream<T>(
+    subscriber: impl Subscriber<T>,
+) -> impl Stream<Item = T> + Send + 'static
+where
+    T: Send + 'static,
+{
+    futures::stream::unfold(subscriber, |mut subscriber| async move {
+        subscriber.recv().await.map(|item| (item, subscriber))
+    })
+}
+
+/// Turns a broadcast receiver into a stream, skipping over the items it lagged behind on.
+pub(crate) fn broadcast_stream<T>(
+    rx: broadcast::Receiver<T>,
+) -> impl Stream<Item = T> + Send + 'static
+where
+    T: Clone + Send + 'static,
+{
+    futures::stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(item) => return Some((item, rx)),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::trace!("subscription lagged behind by {skipped} items");
+                },
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+}
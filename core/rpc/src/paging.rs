@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use atomo::{AtomoSnapshot, StorageBackend};
+
+/// The paging cursors that are open on the node, each one is bound to a snapshot of the
+/// application state that is dropped once its time to live has passed.
+pub(crate) struct PagingCursors<B: StorageBackend> {
+    ttl: Duration,
+    max_cursors: usize,
+    next_id: AtomicU64,
+    snapshots: Mutex<HashMap<u64, AtomoSnapshot<B>>>,
+}
+
+impl<B: StorageBackend> PagingCursors<B> {
+    pub fn new(ttl: Duration, max_cursors: usize) -> Self {
+        Self {
+            ttl,
+            max_cursors,
+            next_id: AtomicU64::new(1),
+            snapshots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the time to live of the cursors.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Open a new cursor bound to the given snapshot and return its id, or `None` if the maximum
+    /// number of cursors is already open.
+    pub fn open(&self, snapshot: AtomoSnapshot<B>) -> Option<u64> {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        snapshots.retain(|_, snapshot| !snapshot.is_expired());
+        if snapshots.len() >= self.max_cursors {
+            return None;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        snapshots.insert(id, snapshot.with_ttl(self.ttl));
+        Some(id)
+    }
+
+    /// Returns the snapshot of the cursor with the given id, or `None` if there is no such cursor
+    /// or it has expired.
+    pub fn get(&self, id: u64) -> Option<AtomoSnapshot<B>> {
+        let snapshots = self.snapshots.lock().unwrap();
+        snapshots
+            .get(&id)
+            .filter(|snapshot| !snapshot.is_expired())
+            .cloned()
+    }
+
+    /// Drop the cursors whose time to live has passed, which releases the state they pin even
+    /// when no other cursor is opened.
+    pub fn sweep(&self) {
+        let mut snapshots = self.snapshots.lock().unwrap();
+        snapshots.retain(|_, snapshot| !snapshot.is_expired());
+    }
+
+    /// Returns the number of cursors that are held, including the expired ones that have not
+    /// been swept yet.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.snapshots.lock().unwrap().len()
+    }
+}
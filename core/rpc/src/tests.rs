@@ -2,6 +2,9 @@
 use std::time::Duration;
 
 use anyhow::Result;
+use atomo::{AtomoBuilder, DefaultSerdeBackend, InMemoryStorage, SerdeBackend, StateProof};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::types::{Address, Filter, Log, TransactionRequest, U256};
 use fleek_crypto::{
     AccountOwnerSecretKey,
     ConsensusSecretKey,
@@ -22,17 +25,25 @@
 use lightning_indexer::Indexer;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{
+    AccountInfo,
     Blake3Hash,
+    Block,
     EpochInfo,
     Event,
     Metadata,
     NodeInfo,
     NodePorts,
     NodeServed,
+    PendingWithdrawal,
     ProtocolParams,
     Staking,
+    Tokens,
     TotalServed,
+    UpdateMethod,
+    UpdatePayload,
+    UpdateRequest,
     Value,
+    FLEEK_CONTRACT_ADDRESS,
 };
 use lightning_interfaces::PagingParams;
 use lightning_notifier::Notifier;
@@ -43,13 +54,25 @@
 use lightning_test_utils::json_config::JsonConfigProvider;
 use lightning_test_utils::keys::EphemeralKeystore;
 use lightning_utils::application::QueryRunnerExt;
+use lightning_utils::eth::{
+    GetBalanceCall,
+    GetBalanceReturn,
+    GetEpochCall,
+    GetEpochReturn,
+    GetProtocolParamCall,
+    GetProtocolParamReturn,
+    WithdrawCall,
+};
 use lightning_utils::rpc as utils;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::api::FleekApiClient;
+use crate::api::{EthApiClient, FleekApiClient};
+use crate::api_types::{CallRequest, SubscriptionKind};
 use crate::config::Config as RpcConfig;
+use crate::filters::LogFilters;
+use crate::paging::PagingCursors;
 use crate::Rpc;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -92,6 +115,12 @@ fn query_runner(&self) -> fdi::Ref<QueryRunner> {
     fn blockstore(&self) -> fdi::Ref<Blockstore<TestBinding>> {
         self.inner.provider.get()
     }
+    fn app(&self) -> fdi::Ref<Application<TestBinding>> {
+        self.inner.provider.get()
+    }
+    fn notifier(&self) -> fdi::Ref<Notifier<TestBinding>> {
+        self.inner.provider.get()
+    }
 }
 
 async fn init_rpc(genesis: Option<Genesis>, rpc_port: u16) -> TestNode {
@@ -1185,7 +1214,7 @@ async fn test_rpc_get_node_registry() -> Result<()> {
     let req = json!({
         "jsonrpc": "2.0",
         "method":"flk_get_node_registry",
-        "params": PagingParams { ignore_stake: true, start: committee_size as u32, limit: 10 },
+        "params": PagingParams { ignore_stake: true, start: committee_size as u32, limit: 10, cursor: None },
         "id":1,
     });
 
@@ -1284,3 +1313,615 @@ async fn test_rpc_events() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_get_pending_withdrawals() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    let chain_id = genesis.chain_id;
+
+    let port = 30024;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    // Withdraw some FLK to the L2
+    let payload = UpdatePayload {
+        sender: owner_secret_key.to_pk().into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::Withdraw {
+            amount: 100u64.into(),
+            token: Tokens::FLK,
+            receiving_address: owner,
+        },
+        chain_id,
+    };
+    let signature = owner_secret_key.sign(&payload.to_digest());
+    let update = UpdateRequest {
+        signature: signature.into(),
+        payload,
+    };
+    node.app()
+        .transaction_executor()
+        .run(Block {
+            transactions: vec![update.into()],
+            digest: [0; 32],
+        })
+        .await
+        .unwrap();
+
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_pending_withdrawals",
+        "params":[],
+        "id":1,
+    });
+
+    let client = Client::new();
+    let response = utils::rpc_request::<Vec<PendingWithdrawal>>(
+        &client,
+        format!("http://127.0.0.1:{port}/rpc/v0"),
+        req.to_string(),
+    )
+    .await?;
+
+    assert_eq!(
+        response.result,
+        vec![PendingWithdrawal {
+            id: 0,
+            receiver: owner,
+            token: Tokens::FLK,
+            amount: 100u64.into(),
+            epoch: 0,
+        }]
+    );
+
+    // Pages start at the given withdrawal id.
+    let req = json!({
+        "jsonrpc": "2.0",
+        "method":"flk_get_pending_withdrawals",
+        "params":[1, 10],
+        "id":1,
+    });
+    let response = utils::rpc_request::<Vec<PendingWithdrawal>>(
+        &client,
+        format!("http://127.0.0.1:{port}/rpc/v0"),
+        req.to_string(),
+    )
+    .await?;
+    assert!(response.result.is_empty());
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_eth_gas_price_and_estimate_gas() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    let gas_price = genesis.gas_price;
+    let transfer_gas = genesis.transfer_gas;
+
+    let port = 30025;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    let client = client(node.rpc().config.addr());
+    let recipient: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+    let unfunded_sender: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    assert_eq!(
+        EthApiClient::gas_price(&client).await?,
+        U256::from(gas_price)
+    );
+
+    // A transfer from a funded account is charged the transfer gas
+    let transfer = CallRequest {
+        from: Some(owner.0.into()),
+        to: Some(recipient.0.into()),
+        value: Some(U256::from(10)),
+        ..Default::default()
+    };
+    assert_eq!(
+        EthApiClient::estimate_gas(&client, transfer.clone()).await?,
+        U256::from(transfer_gas)
+    );
+
+    // An account that can not pay for the fee can not send the transaction
+    let unfunded = CallRequest {
+        from: Some(unfunded_sender.0.into()),
+        ..transfer
+    };
+    assert!(EthApiClient::estimate_gas(&client, unfunded).await.is_err());
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_eth_call_fleek_contract() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 200,
+        bandwidth_balance: 300,
+    });
+
+    let port = 30026;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    let client = client(node.rpc().config.addr());
+    let fleek_contract = Address::from(FLEEK_CONTRACT_ADDRESS.0);
+
+    let call = TransactionRequest::new().to(fleek_contract).data(
+        GetBalanceCall {
+            account: owner.0.into(),
+        }
+        .encode(),
+    );
+    let output = EthApiClient::call(&client, call, None, None).await?;
+    let balance = GetBalanceReturn::decode(output)?;
+    assert_eq!(balance.flk, HpUfixed::<18>::from(1000u64).into());
+    assert_eq!(balance.stables, HpUfixed::<6>::from(200u64).into());
+    assert_eq!(balance.bandwidth, U256::from(300));
+
+    let call = TransactionRequest::new().to(fleek_contract).data(
+        GetProtocolParamCall {
+            param: ProtocolParams::LockTime as u8,
+        }
+        .encode(),
+    );
+    let output = EthApiClient::call(&client, call, None, None).await?;
+    assert_eq!(
+        GetProtocolParamReturn::decode(output)?.value,
+        U256::from(
+            node.query_runner()
+                .get_protocol_param(&ProtocolParams::LockTime)
+                .unwrap()
+        )
+    );
+
+    let output = EthApiClient::call(
+        &client,
+        TransactionRequest::new()
+            .to(fleek_contract)
+            .data(GetEpochCall.encode()),
+        None,
+        None,
+    )
+    .await?;
+    assert_eq!(
+        GetEpochReturn::decode(output)?.epoch,
+        node.query_runner().get_current_epoch()
+    );
+
+    // State changing functions have to be sent as transactions
+    let call = TransactionRequest::new().to(fleek_contract).data(
+        WithdrawCall {
+            amount: U256::from(1),
+            token: "FLK".to_string(),
+            recipient: owner.0.into(),
+        }
+        .encode(),
+    );
+    assert!(EthApiClient::call(&client, call, None, None).await.is_err());
+
+    // There is no EVM storage
+    assert!(
+        EthApiClient::storage_at(&client, FLEEK_CONTRACT_ADDRESS, U256::zero(), None)
+            .await
+            .is_err()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_eth_subscribe_new_heads_and_logs() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    let chain_id = genesis.chain_id;
+
+    let port = 30027;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    let client = jsonrpsee::ws_client::WsClientBuilder::default()
+        .build(&format!("ws://127.0.0.1:{port}/rpc/v0"))
+        .await?;
+
+    let mut heads =
+        EthApiClient::handle_subscription(&client, SubscriptionKind::NewHeads, None).await?;
+    let flk_logs = Filter::new().address(Address::from(Tokens::FLK.address().0));
+    let mut logs =
+        EthApiClient::handle_subscription(&client, SubscriptionKind::Logs, Some(flk_logs)).await?;
+    let stables_logs = Filter::new().address(Address::from(Tokens::USDC.address().0));
+    let mut other_logs =
+        EthApiClient::handle_subscription(&client, SubscriptionKind::Logs, Some(stables_logs))
+            .await?;
+
+    // Subscriptions other than logs do not take a filter.
+    assert!(
+        EthApiClient::handle_subscription(&client, SubscriptionKind::NewHeads, Some(Filter::new()))
+            .await
+            .is_err()
+    );
+
+    // Transfer some FLK, which emits a transfer log.
+    let payload = UpdatePayload {
+        sender: owner_secret_key.to_pk().into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::Transfer {
+            amount: 10u64.into(),
+            token: Tokens::FLK,
+            to: EthAddress::from([9; 20]),
+        },
+        chain_id,
+    };
+    let signature = owner_secret_key.sign(&payload.to_digest());
+    let update = UpdateRequest {
+        signature: signature.into(),
+        payload,
+    };
+    let block = Block {
+        transactions: vec![update.into()],
+        digest: [0; 32],
+    };
+    let response = node
+        .app()
+        .transaction_executor()
+        .run(block.clone())
+        .await
+        .unwrap();
+    let expected_logs = response.txn_receipts[0].logs();
+    let block_number = response.block_number;
+    node.notifier().get_emitter().new_block(block, response);
+
+    let head = heads.next().await.expect("A head from the sub")?;
+    assert_eq!(
+        head["number"],
+        serde_json::json!(format!("{block_number:#x}"))
+    );
+
+    let log: Log = serde_json::from_value(logs.next().await.expect("A log from the sub")?)?;
+    assert_eq!(vec![log], expected_logs);
+
+    // The log does not match the filter of the other subscription.
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), other_logs.next())
+            .await
+            .is_err()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_subscribe_epoch_changes() -> Result<()> {
+    let port = 30028;
+    let node = init_rpc(None, port).await;
+
+    wait_for_server_start(port).await?;
+
+    let client = jsonrpsee::ws_client::WsClientBuilder::default()
+        .build(&format!("ws://127.0.0.1:{port}/rpc/v0"))
+        .await?;
+
+    let mut sub = FleekApiClient::subscribe_epoch_changes(&client).await?;
+
+    node.notifier().get_emitter().epoch_changed(1, [0; 32]);
+
+    assert_eq!(
+        sub.next().await.expect("An epoch from the sub")?,
+        node.query_runner().get_epoch_info()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_get_allowance() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let spender: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+    let chain_id = genesis.chain_id;
+
+    let port = 30029;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    // Approve the spender
+    let payload = UpdatePayload {
+        sender: owner_secret_key.to_pk().into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::Approve {
+            token: Tokens::FLK,
+            spender,
+            amount: 100u64.into(),
+        },
+        chain_id,
+    };
+    let signature = owner_secret_key.sign(&payload.to_digest());
+    let update = UpdateRequest {
+        signature: signature.into(),
+        payload,
+    };
+    node.app()
+        .transaction_executor()
+        .run(Block {
+            transactions: vec![update.into()],
+            digest: [0; 32],
+        })
+        .await
+        .unwrap();
+
+    let client = client(node.rpc().config.addr());
+    assert_eq!(
+        FleekApiClient::get_allowance(&client, owner, spender, Tokens::FLK, None).await?,
+        HpUfixed::<18>::from(100u64)
+    );
+    assert_eq!(
+        FleekApiClient::get_allowance(&client, owner, spender, Tokens::USDC, None).await?,
+        HpUfixed::<18>::zero()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rpc_get_proof() -> Result<()> {
+    // Create keys
+    let owner_secret_key = AccountOwnerSecretKey::generate();
+    let owner: EthAddress = owner_secret_key.to_pk().into();
+    let stranger: EthAddress = AccountOwnerSecretKey::generate().to_pk().into();
+
+    // Init application service
+    let mut genesis = Genesis::load().unwrap();
+    genesis.account.push(GenesisAccount {
+        public_key: owner,
+        flk_balance: 1000u64.into(),
+        stables_balance: 0,
+        bandwidth_balance: 0,
+    });
+
+    let port = 30030;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    // Execute an empty block to learn the current state root.
+    let response = node
+        .app()
+        .transaction_executor()
+        .run(Block {
+            transactions: vec![],
+            digest: [0; 32],
+        })
+        .await
+        .unwrap();
+
+    let client = client(node.rpc().config.addr());
+
+    // Inclusion proof for an existing account.
+    let key = DefaultSerdeBackend::serialize(&owner);
+    let res = FleekApiClient::get_proof(&client, "account".to_string(), key.clone(), None).await?;
+    assert_eq!(res.state_root, response.state_root);
+    let value = res.value.expect("account should exist");
+    let account: AccountInfo = DefaultSerdeBackend::deserialize(&value);
+    assert_eq!(account.flk_balance, HpUfixed::<18>::from(1000u64));
+    let proof: StateProof = serde_json::from_value(serde_json::to_value(res.proof)?)?;
+    assert!(proof.verify(&response.state_root, &key, Some(&value)));
+
+    // Exclusion proof for an account that does not exist.
+    let key = DefaultSerdeBackend::serialize(&stranger);
+    let res = FleekApiClient::get_proof(&client, "account".to_string(), key.clone(), None).await?;
+    assert_eq!(res.value, None);
+    let proof: StateProof = serde_json::from_value(serde_json::to_value(res.proof)?)?;
+    assert!(proof.verify(&response.state_root, &key, None));
+
+    assert!(
+        FleekApiClient::get_proof(&client, "unknown".to_string(), key, None)
+            .await
+            .is_err()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_rpc_paging_cursor() -> Result<()> {
+    let owner_public_key = AccountOwnerSecretKey::generate().to_pk();
+    let node_secret_key = NodeSecretKey::generate();
+    let node_public_key = node_secret_key.to_pk();
+    let consensus_public_key = ConsensusSecretKey::generate().to_pk();
+
+    // Init application service with a node that is not in the committee.
+    let mut genesis = Genesis::load().unwrap();
+    let staking = Staking {
+        staked: genesis.min_stake.into(),
+        stake_locked_until: 0,
+        locked: 0_u32.into(),
+        locked_until: 0,
+    };
+    genesis.node_info.push(GenesisNode::new(
+        owner_public_key.into(),
+        node_public_key,
+        "127.0.0.1".parse().unwrap(),
+        consensus_public_key,
+        "127.0.0.1".parse().unwrap(),
+        node_public_key,
+        NodePorts::default(),
+        Some(staking),
+        false,
+    ));
+    let chain_id = genesis.chain_id;
+
+    let port = 30031;
+    let node = init_rpc(Some(genesis), port).await;
+
+    wait_for_server_start(port).await?;
+
+    let client = client(node.rpc().config.addr());
+    let cursor = FleekApiClient::open_paging_cursor(&client).await?;
+
+    // Opting out changes the nonce of the node after the cursor was opened.
+    let payload = UpdatePayload {
+        sender: node_public_key.into(),
+        nonce: 1,
+        secondary_nonce: 1,
+        method: UpdateMethod::OptOut {},
+        chain_id,
+    };
+    let update = UpdateRequest {
+        signature: node_secret_key.sign(&payload.to_digest()).into(),
+        payload,
+    };
+    node.app()
+        .transaction_executor()
+        .run(Block {
+            transactions: vec![update.into()],
+            digest: [0; 32],
+        })
+        .await
+        .unwrap();
+
+    let node_nonce = |nodes: Vec<NodeInfo>| {
+        nodes
+            .into_iter()
+            .find(|info| info.public_key == node_public_key)
+            .map(|info| info.nonce)
+    };
+    let paging = |cursor| PagingParams {
+        ignore_stake: true,
+        start: 0,
+        limit: 100,
+        cursor,
+    };
+
+    let nodes = FleekApiClient::get_node_registry(&client, Some(paging(Some(cursor)))).await?;
+    assert_eq!(node_nonce(nodes), Some(0));
+
+    let nodes = FleekApiClient::get_node_registry(&client, Some(paging(None))).await?;
+    assert_eq!(node_nonce(nodes), Some(1));
+
+    assert!(
+        FleekApiClient::get_node_registry(&client, Some(paging(Some(cursor + 1))))
+            .await
+            .is_err()
+    );
+
+    node.shutdown().await;
+
+    Ok(())
+}
+
+#[test]
+fn test_paging_cursors_limit_and_sweep() {
+    let db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+        .build()
+        .unwrap()
+        .query();
+    let cursors = PagingCursors::new(Duration::from_millis(50), 2);
+
+    let first = cursors.open(db.snapshot()).unwrap();
+    let second = cursors.open(db.snapshot()).unwrap();
+    assert!(cursors.open(db.snapshot()).is_none());
+    assert!(cursors.get(first).is_some());
+
+    // Expired cursors can not be read, and are dropped by a sweep.
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(cursors.get(second).is_none());
+    assert_eq!(cursors.len(), 2);
+    cursors.sweep();
+    assert_eq!(cursors.len(), 0);
+    assert!(cursors.open(db.snapshot()).is_some());
+}
+
+#[test]
+fn test_filters_limit_and_sweep() {
+    let filters = LogFilters::new(Duration::from_millis(50), 2);
+
+    let first = filters.install(Filter::new(), 3).unwrap();
+    let second = filters.install(Filter::new(), 3).unwrap();
+    assert!(filters.install(Filter::new(), 3).is_none());
+
+    // The changes are reported from the block the filter was installed at.
+    assert_eq!(filters.changes(first, 5).unwrap().1, 3);
+    assert_eq!(filters.changes(first, 5).unwrap().1, 6);
+
+    // Expired filters can not be polled, and are dropped by a sweep.
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(filters.get(second).is_none());
+    assert_eq!(filters.len(), 2);
+    filters.sweep();
+    assert_eq!(filters.len(), 0);
+    assert!(filters.install(Filter::new(), 3).is_some());
+
+    assert!(!filters.uninstall(first));
+}
@@ -52,6 +52,7 @@ async fn init_service_executor(
             JsonConfigProvider::default()
                 .with::<Blockstore<TestBinding>>(BlockstoreConfig {
                     root: path.join("dummy_blockstore").try_into().unwrap(),
+                    ..Default::default()
                 })
                 .with::<Application<TestBinding>>(AppConfig {
                     genesis: Some(genesis),
@@ -1,6 +1,7 @@
 use std::time::{Duration, SystemTime};
 
 use anyhow::{anyhow, bail, Context, Result};
+use atomo_rocks::CheckpointManifest;
 use fleek_crypto::NodePublicKey;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{
@@ -34,6 +35,7 @@ struct SyncronizerInner<C: Collection> {
     our_public_key: NodePublicKey,
     query_runner: c![C::ApplicationInterface::SyncExecutor],
     notifier: C::NotifierInterface,
+    blockstore: C::BlockstoreInterface,
     blockstore_server_socket: BlockstoreServerSocket,
     genesis_committee: Vec<(NodeIndex, NodeInfo)>,
     rpc_client: reqwest::Client,
@@ -45,6 +47,7 @@ impl<C: Collection> Syncronizer<C> {
     fn init(
         config: &C::ConfigProviderInterface,
         keystore: &C::KeystoreInterface,
+        blockstore: &C::BlockstoreInterface,
         blockstore_server: &C::BlockstoreServerInterface,
         notifier: &C::NotifierInterface,
         query_runner: fdi::Cloned<c!(C::ApplicationInterface::SyncExecutor)>,
@@ -73,6 +76,7 @@ fn init(
             genesis_committee,
             query_runner.clone(),
             notifier.clone(),
+            blockstore.clone(),
             blockstore_server,
             config.epoch_change_delta,
             rpc_client,
@@ -182,6 +186,7 @@ fn new(
         genesis_committee: Vec<(NodeIndex, NodeInfo)>,
         query_runner: c![C::ApplicationInterface::SyncExecutor],
         notifier: C::NotifierInterface,
+        blockstore: C::BlockstoreInterface,
         blockstore_server: &C::BlockstoreServerInterface,
         epoch_change_delta: Duration,
         rpc_client: reqwest::Client,
@@ -189,6 +194,7 @@ fn new(
         Ok(Self {
             our_public_key,
             query_runner,
+            blockstore,
             blockstore_server_socket: blockstore_server.get_socket(),
             notifier,
             genesis_committee,
@@ -301,12 +307,35 @@ async fn ask_bootstrap_nodes<T: DeserializeOwned>(&self, req: String) -> Result<
         rpc::ask_nodes(req, &self.genesis_committee, &self.rpc_client).await
     }
 
+    /// Downloads the manifest of the checkpoint and then every chunk it lists that is not
+    /// already in our blockstore, so an interrupted download resumes where it stopped.
     async fn download_checkpoint_from_bootstrap(&self, checkpoint_hash: [u8; 32]) -> Result<()> {
+        if self.blockstore.get_tree(&checkpoint_hash).await.is_none() {
+            self.download_from_bootstrap(checkpoint_hash).await?;
+        }
+
+        let manifest = self
+            .blockstore
+            .read_all_to_vec(&checkpoint_hash)
+            .await
+            .context("Failed to read checkpoint manifest from blockstore")?;
+        let manifest = CheckpointManifest::deserialize(&manifest)?;
+
+        for chunk in manifest.chunks() {
+            if self.blockstore.get_tree(&chunk.hash).await.is_none() {
+                self.download_from_bootstrap(chunk.hash).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn download_from_bootstrap(&self, hash: Blake3Hash) -> Result<()> {
         for (node_index, _) in &self.genesis_committee {
             let mut res = self
                 .blockstore_server_socket
                 .run(ServerRequest {
-                    hash: checkpoint_hash,
+                    hash,
                     peer: *node_index,
                 })
                 .await
@@ -68,21 +68,28 @@ fn default() -> Self {
 
 pub struct MockForwarder<C> {
     socket: MempoolSocket,
+    pending_tx: broadcast::Sender<TransactionRequest>,
     c: PhantomData<C>,
 }
 
 impl<C: Collection> MockForwarder<C> {
     fn new(sender: mpsc::Sender<TransactionRequest>) -> Self {
-        struct ProxyWorker(mpsc::Sender<TransactionRequest>);
+        struct ProxyWorker(
+            mpsc::Sender<TransactionRequest>,
+            broadcast::Sender<TransactionRequest>,
+        );
         impl AsyncWorkerUnordered for ProxyWorker {
             type Request = TransactionRequest;
             type Response = ();
             async fn handle(&self, req: Self::Request) {
+                let _ = self.1.send(req.clone());
                 self.0.send(req).await.expect("Failed to send transaction.")
             }
         }
+        let (pending_tx, _) = broadcast::channel(1024);
         Self {
-            socket: TokioSpawn::spawn_async_unordered(ProxyWorker(sender)),
+            socket: TokioSpawn::spawn_async_unordered(ProxyWorker(sender, pending_tx.clone())),
+            pending_tx,
             c: PhantomData,
         }
     }
@@ -100,6 +107,10 @@ impl<C: Collection> ForwarderInterface<C> for MockForwarder<C> {
     fn mempool_socket(&self) -> MempoolSocket {
         self.socket.clone()
     }
+
+    fn subscribe_pending_transactions(&self) -> broadcast::Receiver<TransactionRequest> {
+        self.pending_tx.subscribe()
+    }
 }
 
 /// Provides a controlled and mocked version of the consensus. Should be used in a collection with
@@ -5,6 +5,7 @@
 pub mod logging;
 pub mod plotting;
 pub mod random;
+pub mod relayer;
 pub mod reputation;
 pub mod server;
 pub mod statistics;
@@ -0,0 +1,72 @@
+use std::collections::{BTreeMap, HashMap};
+
+use fleek_crypto::EthAddress;
+use hp_fixed::unsigned::HpUfixed;
+use lightning_interfaces::types::{PendingWithdrawal, Tokens};
+use lightning_interfaces::SyncQueryRunnerInterface;
+
+/// A local stand-in for the bridge relayer. It picks up the pending withdrawals from the
+/// application state and settles them on a mocked L2 ledger.
+#[derive(Default)]
+pub struct MockRelayer {
+    settled: BTreeMap<u64, PendingWithdrawal>,
+    balances: HashMap<(EthAddress, Tokens), HpUfixed<18>>,
+}
+
+impl MockRelayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Settle every pending withdrawal in the application state that was not settled before.
+    /// Returns the newly settled withdrawals ordered by id.
+    pub fn settle_pending<Q: SyncQueryRunnerInterface>(
+        &mut self,
+        query_runner: &Q,
+    ) -> Vec<PendingWithdrawal> {
+        let mut pending = query_runner.get_pending_withdrawals_iter(|ids| {
+            ids.filter(|id| !self.settled.contains_key(id))
+                .filter_map(|id| query_runner.get_pending_withdrawal(&id))
+                .collect::<Vec<_>>()
+        });
+        pending.sort_by_key(|withdrawal| withdrawal.id);
+
+        for withdrawal in &pending {
+            self.settle(withdrawal.clone());
+        }
+        pending
+    }
+
+    /// Mark a single withdrawal as settled and credit the receiver on the L2. Settling the same
+    /// withdrawal twice has no effect.
+    pub fn settle(&mut self, withdrawal: PendingWithdrawal) {
+        if self.settled.contains_key(&withdrawal.id) {
+            return;
+        }
+
+        let balance = self
+            .balances
+            .entry((withdrawal.receiver, withdrawal.token.clone()))
+            .or_default();
+        *balance += withdrawal.amount.clone();
+        self.settled.insert(withdrawal.id, withdrawal);
+    }
+
+    /// Returns true if the withdrawal with the given id has been settled.
+    pub fn is_settled(&self, id: u64) -> bool {
+        self.settled.contains_key(&id)
+    }
+
+    /// Returns the settled withdrawals ordered by id.
+    pub fn settled(&self) -> Vec<PendingWithdrawal> {
+        self.settled.values().cloned().collect()
+    }
+
+    /// Returns the balance of an address on the mocked L2.
+    pub fn l2_balance(&self, address: &EthAddress, token: Tokens) -> HpUfixed<18> {
+        self.balances
+            .get(&(*address, token))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
@@ -1,11 +1,15 @@
 //! The types used by the Application interface.
 
-use ethers::types::{Block as EthersBlock, H256, U64};
+use std::collections::BTreeMap;
+
+use ethers::abi::{encode, Token};
+use ethers::types::{Block as EthersBlock, H256, U256, U64};
+use ethers::utils::keccak256;
 use fleek_crypto::{EthAddress, NodePublicKey};
 use hp_fixed::unsigned::HpUfixed;
 use serde::{Deserialize, Serialize};
 
-use crate::TransactionReceipt;
+use crate::{CommodityTypes, Tokens, TransactionReceipt};
 
 /// Max number of updates allowed in a content registry update transaction.
 pub const MAX_UPDATES_CONTENT_REGISTRY: usize = 100;
@@ -13,6 +17,10 @@
 /// Max number of delivery acknowledgements allowed per transaction.
 pub const MAX_DELIVERY_ACKNOWLEDGMENTS: usize = 1000;
 
+/// The address Ethereum transactions call state functions on. Events that do not belong to a
+/// token are logged under this address.
+pub const FLEEK_CONTRACT_ADDRESS: EthAddress = EthAddress([6; 20]);
+
 macro_rules! create_events {
     (
         pub enum Event {
@@ -63,10 +71,30 @@ pub enum Event {
             to: EthAddress,
             amount: HpUfixed<18>,
         },
+        Approval {
+            token: EthAddress,
+            owner: EthAddress,
+            spender: EthAddress,
+            amount: HpUfixed<18>,
+        },
         ServiceEvent {
             service_id: u32,
             event: Vec<u8>,
         },
+        ServiceAdded {
+            service_id: u32,
+            owner: EthAddress,
+            commodity_type: CommodityTypes,
+        },
+        ServiceUpdated {
+            service_id: u32,
+            owner: EthAddress,
+            commodity_type: CommodityTypes,
+        },
+        ServiceRemoved {
+            service_id: u32,
+            owner: EthAddress,
+        },
     }
 }
 
@@ -85,9 +113,138 @@ pub fn transfer(
         }
     }
 
+    pub fn approval(
+        token: EthAddress,
+        owner: EthAddress,
+        spender: EthAddress,
+        amount: HpUfixed<18>,
+    ) -> Self {
+        Self::Approval {
+            token,
+            owner,
+            spender,
+            amount,
+        }
+    }
+
     pub fn service_event(service_id: u32, event: Vec<u8>) -> Self {
         Self::ServiceEvent { service_id, event }
     }
+
+    pub fn service_added(
+        service_id: u32,
+        owner: EthAddress,
+        commodity_type: CommodityTypes,
+    ) -> Self {
+        Self::ServiceAdded {
+            service_id,
+            owner,
+            commodity_type,
+        }
+    }
+
+    pub fn service_updated(
+        service_id: u32,
+        owner: EthAddress,
+        commodity_type: CommodityTypes,
+    ) -> Self {
+        Self::ServiceUpdated {
+            service_id,
+            owner,
+            commodity_type,
+        }
+    }
+
+    pub fn service_removed(service_id: u32, owner: EthAddress) -> Self {
+        Self::ServiceRemoved { service_id, owner }
+    }
+
+    /// The address of the Ethereum log for this event. Transfers and approvals are logged by
+    /// the token, like ERC-20 events, every other event by the Fleek contract.
+    pub fn log_address(&self) -> EthAddress {
+        match self {
+            Self::Transfer { token, .. } | Self::Approval { token, .. } => *token,
+            _ => FLEEK_CONTRACT_ADDRESS,
+        }
+    }
+
+    /// The topics of the Ethereum log for this event. The first topic is the hash of the event
+    /// signature, the rest are the indexed fields.
+    pub fn log_topics(&self) -> Vec<H256> {
+        match self {
+            Self::Transfer { from, to, .. } => vec![
+                signature_topic("Transfer(address,address,uint256)"),
+                address_topic(from),
+                address_topic(to),
+            ],
+            Self::Approval { owner, spender, .. } => vec![
+                signature_topic("Approval(address,address,uint256)"),
+                address_topic(owner),
+                address_topic(spender),
+            ],
+            Self::ServiceEvent { service_id, .. } => vec![
+                signature_topic("ServiceEvent(uint32,bytes)"),
+                uint_topic(*service_id),
+            ],
+            Self::ServiceAdded {
+                service_id, owner, ..
+            } => vec![
+                signature_topic("ServiceAdded(uint32,address,uint8)"),
+                uint_topic(*service_id),
+                address_topic(owner),
+            ],
+            Self::ServiceUpdated {
+                service_id, owner, ..
+            } => vec![
+                signature_topic("ServiceUpdated(uint32,address,uint8)"),
+                uint_topic(*service_id),
+                address_topic(owner),
+            ],
+            Self::ServiceRemoved { service_id, owner } => vec![
+                signature_topic("ServiceRemoved(uint32,address)"),
+                uint_topic(*service_id),
+                address_topic(owner),
+            ],
+        }
+    }
+
+    /// The ABI encoded fields of this event that are not indexed, the data of its Ethereum log.
+    pub fn log_data(&self) -> Vec<u8> {
+        match self {
+            Self::Transfer { token, amount, .. } | Self::Approval { token, amount, .. } => {
+                // Stables are accounted with 6 decimals, which is what an ERC-20 indexer expects
+                // for them.
+                let amount: U256 = if *token == Tokens::USDC.address() {
+                    amount.convert_precision::<6>().into()
+                } else {
+                    amount.clone().into()
+                };
+                encode(&[Token::Uint(amount)])
+            },
+            Self::ServiceEvent { event, .. } => encode(&[Token::Bytes(event.clone())]),
+            Self::ServiceAdded { commodity_type, .. }
+            | Self::ServiceUpdated { commodity_type, .. } => {
+                encode(&[Token::Uint((*commodity_type as u8).into())])
+            },
+            Self::ServiceRemoved { .. } => Vec::new(),
+        }
+    }
+}
+
+fn signature_topic(signature: &str) -> H256 {
+    keccak256(signature).into()
+}
+
+fn address_topic(address: &EthAddress) -> H256 {
+    let mut topic = [0; 32];
+    topic[12..].copy_from_slice(&address.0);
+    topic.into()
+}
+
+fn uint_topic(value: u32) -> H256 {
+    let mut topic = [0; 32];
+    topic[28..].copy_from_slice(&value.to_be_bytes());
+    topic.into()
 }
 
 /// The response generated from executing an entire batch of transactions (aka a block).
@@ -106,6 +263,35 @@ pub struct BlockExecutionResponse {
     pub node_registry_delta: Vec<(NodePublicKey, NodeRegistryChange)>,
     /// Receipts of all executed transactions
     pub txn_receipts: Vec<TransactionReceipt>,
+    /// The root of the application state after executing the block.
+    pub state_root: [u8; 32],
+    /// The changes that revert the application state to what it was before executing the block.
+    pub state_diff: StateDiff,
+}
+
+/// A reverse diff of the application state. For every modified table, it maps the raw key of
+/// each changed entry to its previous raw value, or `None` if the entry did not exist before.
+///
+/// Applying the reverse diff of a block on top of the state after that block gives the state
+/// before it.
+#[derive(Serialize, Deserialize, Debug, Default, Hash, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    pub tables: BTreeMap<String, BTreeMap<Vec<u8>, Option<Vec<u8>>>>,
+}
+
+impl StateDiff {
+    /// Merges the reverse diff of an earlier block into this one. The previous values of the
+    /// earlier block take priority, so the result reverts the changes of both blocks.
+    pub fn merge_earlier(&mut self, earlier: StateDiff) {
+        for (table, changes) in earlier.tables {
+            self.tables.entry(table).or_default().extend(changes);
+        }
+    }
+
+    /// Returns true if the diff does not contain any changes.
+    pub fn is_empty(&self) -> bool {
+        self.tables.values().all(|changes| changes.is_empty())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
@@ -122,6 +308,8 @@ pub struct BlockReceipt {
     pub node_registry_delta: Vec<(NodePublicKey, NodeRegistryChange)>,
     /// The hashes of the transactions included in the block
     pub txn_hashes: Vec<[u8; 32]>,
+    /// The root of the application state after executing the block.
+    pub state_root: [u8; 32],
 }
 
 impl BlockExecutionResponse {
@@ -138,6 +326,7 @@ pub fn to_receipts(self) -> (BlockReceipt, Vec<TransactionReceipt>) {
                 .iter()
                 .map(|txn| txn.transaction_hash)
                 .collect(),
+            state_root: self.state_root,
         };
 
         let txn_receipts = self.txn_receipts;
@@ -153,6 +342,7 @@ fn from(value: BlockReceipt) -> Self {
             parent_hash: value.parent_hash.into(),
             number: Some(U64::from(value.block_number)),
             transactions: value.txn_hashes.iter().map(|t| H256(*t)).collect(),
+            state_root: value.state_root.into(),
             ..Default::default()
         }
     }
@@ -1,4 +1,6 @@
-use crate::{Blake3Hash, NodeIndex, RejectReason};
+use std::ops::Range;
+
+use crate::{Blake3Hash, CompressionAlgorithm, NodeIndex, RejectReason};
 
 #[derive(Clone, Debug)]
 pub struct ServerRequest {
@@ -6,6 +8,41 @@ pub struct ServerRequest {
     pub peer: NodeIndex,
 }
 
+/// A request for a part of some content from a peer. Unlike a [`ServerRequest`] the part is
+/// returned to the requester unverified instead of being put into the blockstore, so that
+/// content can be assembled from the parts of several peers.
+#[derive(Clone, Debug)]
+pub struct ServerPartRequest {
+    pub hash: Blake3Hash,
+    pub peer: NodeIndex,
+    pub part: ContentPart,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ContentPart {
+    /// The hash tree of the content.
+    Tree,
+    /// The blocks with the counters in the range. The range is cut off at the end of the content.
+    Blocks(Range<u32>),
+}
+
+#[derive(Debug)]
+pub enum ServerPartResponse {
+    Tree(Vec<u8>),
+    Blocks(Vec<PeerBlock>),
+}
+
+/// A block received from a peer.
+#[derive(Debug)]
+pub struct PeerBlock {
+    /// The proof to feed to an incremental verifier before the block, the verifier has to start
+    /// at the first block of the requested range. Empty if no proof is needed.
+    pub proof: Vec<u8>,
+    /// The algorithm the content is compressed with, which is one the requester accepts.
+    pub compression: CompressionAlgorithm,
+    pub content: Vec<u8>,
+}
+
 #[derive(Debug, Clone, thiserror::Error)]
 #[error("Failed to fetch data from other peers")]
 pub enum PeerRequestError {
@@ -1,8 +1,129 @@
 //! Types that are and will be used for the bridge functionality.
 
+use fleek_crypto::{AccountOwnerSignature, EthAddress};
+use hp_fixed::unsigned::HpUfixed;
+use ink_quill::{ToDigest, TranscriptBuilder, TranscriptBuilderInput};
 use serde::{Deserialize, Serialize};
 
-/// Placeholder
-/// This is the proof used to operate our PoC bridges
+use crate::transaction::HpUfixedWrapper;
+use crate::{ChainId, Epoch, Tokens};
+
+const FN_BRIDGE_DEPOSIT_DOMAIN: &str = "fleek_network_bridge_deposit";
+const FN_BRIDGE_WITHDRAWAL_DOMAIN: &str = "fleek_network_bridge_withdrawal";
+
+/// This is the proof used to operate our PoC bridges. A deposit is accepted once enough of the
+/// bridge signers stored in the application state signed off on it.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct ProofOfConsensus {
+    /// The id of the deposit on the L2 bridge, every deposit can only be claimed once
+    pub deposit_id: u64,
+    /// The signatures of the bridge signers over the [`DepositAttestation`]
+    pub signatures: Vec<BridgeSignature>,
+}
+
+impl TranscriptBuilderInput for ProofOfConsensus {
+    const TYPE: &'static str = "proof_of_consensus";
+
+    fn to_transcript_builder_input(&self) -> Vec<u8> {
+        let mut input = self.deposit_id.to_le_bytes().to_vec();
+        for signature in &self.signatures {
+            input.extend(signature.signer.0);
+            input.extend(signature.signature.0);
+        }
+        input
+    }
+}
+
+/// The signature of a single bridge signer.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct BridgeSignature {
+    /// The address of the bridge signer
+    pub signer: EthAddress,
+    /// The signature over the digest of the [`DepositAttestation`] or the
+    /// [`WithdrawalSettlement`]
+    pub signature: AccountOwnerSignature,
+}
+
+/// The set of L2 bridge signers whose signatures are accepted for deposits.
+#[derive(
+    Clone, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema,
+)]
+pub struct BridgeSigners {
+    /// The addresses of the bridge signers
+    pub signers: Vec<EthAddress>,
+    /// The number of distinct signers that have to sign a deposit
+    pub threshold: u16,
+}
+
+impl BridgeSigners {
+    /// Returns true if the set can ever accept a deposit: the threshold is not zero, it can be
+    /// reached and no signer is listed twice.
+    pub fn is_valid(&self) -> bool {
+        let mut signers = self.signers.clone();
+        signers.sort();
+        signers.dedup();
+        self.threshold > 0
+            && signers.len() == self.signers.len()
+            && self.threshold as usize <= signers.len()
+    }
+}
+
+/// The message the bridge signers sign for a deposit made on the L2.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct DepositAttestation {
+    /// The chain id of the network the deposit is made to
+    pub chain_id: ChainId,
+    /// The id of the deposit on the L2 bridge
+    pub deposit_id: u64,
+    /// The account receiving the tokens in the network
+    pub recipient: EthAddress,
+    /// The token being deposited
+    pub token: Tokens,
+    /// The amount being deposited
+    pub amount: HpUfixed<18>,
+}
+
+impl ToDigest for DepositAttestation {
+    fn transcript(&self) -> TranscriptBuilder {
+        TranscriptBuilder::empty(FN_BRIDGE_DEPOSIT_DOMAIN)
+            .with("chain_id", &self.chain_id)
+            .with("deposit_id", &self.deposit_id)
+            .with("recipient", &self.recipient.0)
+            .with("token", &self.token)
+            .with("amount", &HpUfixedWrapper(self.amount.clone()))
+    }
+}
+
+/// A withdrawal out of the network that is waiting to be picked up by the bridge relayer and
+/// settled on the L2.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct PendingWithdrawal {
+    /// The id of the withdrawal, ids are assigned incrementally
+    pub id: u64,
+    /// The address receiving the tokens on the L2
+    pub receiver: EthAddress,
+    /// The token being withdrawn
+    pub token: Tokens,
+    /// The amount being withdrawn
+    pub amount: HpUfixed<18>,
+    /// The epoch the withdrawal was made in
+    pub epoch: Epoch,
+}
+
+/// The message the bridge signers sign once a withdrawal was paid out on the L2. It removes the
+/// withdrawal from the pending withdrawals.
 #[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
-pub struct ProofOfConsensus {}
+pub struct WithdrawalSettlement {
+    /// The chain id of the network the withdrawal was made from
+    pub chain_id: ChainId,
+    /// The id of the settled withdrawal
+    pub withdrawal_id: u64,
+}
+
+impl ToDigest for WithdrawalSettlement {
+    fn transcript(&self) -> TranscriptBuilder {
+        TranscriptBuilder::empty(FN_BRIDGE_WITHDRAWAL_DOMAIN)
+            .with("chain_id", &self.chain_id)
+            .with("withdrawal_id", &self.withdrawal_id)
+    }
+}
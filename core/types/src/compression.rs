@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Hash, Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum CompressionAlgorithm {
     Uncompressed = 0,
@@ -11,6 +11,22 @@ pub enum CompressionAlgorithm {
     Lzma = 0x01 << 4,
 }
 
+impl TryFrom<u8> for CompressionAlgorithm {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Uncompressed),
+            0x01 => Ok(Self::Snappy),
+            0x02 => Ok(Self::Gzip),
+            0x04 => Ok(Self::Brotli),
+            0x08 => Ok(Self::Lz4),
+            0x10 => Ok(Self::Lzma),
+            _ => Err(value),
+        }
+    }
+}
+
 /// A set of [`CompressionAlgorithm`] values. The [`CompressionAlgorithm::Uncompressed`]
 /// is a special case
 #[derive(
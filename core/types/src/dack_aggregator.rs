@@ -1,12 +1,16 @@
+use fleek_crypto::{ClientPublicKey, ClientSignature, NodePublicKey};
+use ink_quill::{ToDigest, TranscriptBuilder};
 use serde::{Deserialize, Serialize};
 
+use crate::ChainId;
+
+const FN_DELIVERY_ACKNOWLEDGMENT_DOMAIN: &str = "fleek_network_delivery_acknowledgment";
+
 /// A batch of delivery acknowledgments.
 #[derive(Serialize, Deserialize, Debug, Hash)]
 pub struct DeliveryAcknowledgmentBatch;
 
-#[derive(
-    Serialize, Deserialize, Debug, Hash, Clone, Default, Eq, PartialEq, schemars::JsonSchema,
-)]
+#[derive(Serialize, Deserialize, Debug, Hash, Clone, Eq, PartialEq, schemars::JsonSchema)]
 pub struct DeliveryAcknowledgment {
     /// The service id of the service this was provided through(CDN, compute, ect.)
     pub service_id: u32,
@@ -18,7 +22,42 @@ pub struct DeliveryAcknowledgment {
     pub metadata: Option<Vec<u8>>,
 }
 
-#[derive(
-    Serialize, Deserialize, Debug, Hash, Clone, Default, Eq, PartialEq, schemars::JsonSchema,
-)]
-pub struct DeliveryAcknowledgmentProof;
+/// The acknowledgment of a client that it received some amount of a commodity from a node.
+#[derive(Serialize, Deserialize, Debug, Hash, Clone, Eq, PartialEq, schemars::JsonSchema)]
+pub struct DeliveryAcknowledgmentProof {
+    /// The client that received the commodity
+    pub client: ClientPublicKey,
+    /// How much of the commodity the client acknowledges to have received
+    pub commodity: u128,
+    /// The nonce of the session the commodity was served in. The nonces a client uses with a node
+    /// have to increase with every acknowledgment.
+    pub session_nonce: u64,
+    /// The signature of the client over the [`DeliveryAcknowledgmentMessage`]
+    pub signature: ClientSignature,
+}
+
+/// The message a client signs to acknowledge a delivery.
+#[derive(Serialize, Deserialize, Debug, Hash, Clone, Eq, PartialEq)]
+pub struct DeliveryAcknowledgmentMessage {
+    /// The chain id of the network the acknowledgment is submitted to
+    pub chain_id: ChainId,
+    /// The service id of the service the commodity was provided through
+    pub service_id: u32,
+    /// The node that served the commodity
+    pub node: NodePublicKey,
+    /// How much of the commodity was served
+    pub commodity: u128,
+    /// The nonce of the session the commodity was served in
+    pub session_nonce: u64,
+}
+
+impl ToDigest for DeliveryAcknowledgmentMessage {
+    fn transcript(&self) -> TranscriptBuilder {
+        TranscriptBuilder::empty(FN_DELIVERY_ACKNOWLEDGMENT_DOMAIN)
+            .with("chain_id", &self.chain_id)
+            .with("service_id", &self.service_id)
+            .with("node", &self.node.0)
+            .with("commodity", &self.commodity)
+            .with("session_nonce", &self.session_nonce)
+    }
+}
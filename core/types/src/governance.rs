@@ -0,0 +1,114 @@
+//! Types of the on-chain governance of the protocol.
+
+use fleek_crypto::EthAddress;
+use hp_fixed::unsigned::HpUfixed;
+use ink_quill::TranscriptBuilderInput;
+use serde::{Deserialize, Serialize};
+
+use crate::{Epoch, ProtocolParams, Service, ServiceId};
+
+/// The id of a governance proposal, ids are assigned incrementally.
+pub type ProposalId = u64;
+
+/// The change a governance proposal makes to the protocol once it is executed.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub enum ProposalAction {
+    /// Change a protocol parameter
+    ChangeProtocolParam { param: ProtocolParams, value: u128 },
+    /// Register a service, the service id must not be taken. Services added through governance
+    /// are not bonded
+    AddService {
+        service: Service,
+        service_id: ServiceId,
+    },
+    /// Remove a service, its bond is released to its owner
+    RemoveService { service_id: ServiceId },
+    /// Change the size of the committee, must not be zero or larger than the node count
+    ChangeCommitteeSize { committee_size: u64 },
+}
+
+/// The stage of a governance proposal.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub enum ProposalStatus {
+    /// The proposal is open for votes
+    Voting,
+    /// The proposal reached the quorum and a majority, it is executed once its timelock is over
+    Passed,
+    /// The proposal did not reach the quorum or a majority
+    Rejected,
+    /// The proposal was executed
+    Executed,
+    /// The proposal passed but its action could not be applied to the state it was executed on
+    Failed,
+}
+
+/// A governance proposal.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct Proposal {
+    /// The id of the proposal
+    pub id: ProposalId,
+    /// The account that submitted the proposal
+    pub proposer: EthAddress,
+    /// The change the proposal makes once it is executed
+    pub action: ProposalAction,
+    /// The epoch the proposal was submitted in
+    pub created_at: Epoch,
+    /// The first epoch votes are no longer accepted in, the votes are tallied upon the change to
+    /// this epoch
+    pub voting_ends: Epoch,
+    /// The first epoch a passed proposal can be executed in, it is executed when this epoch ends
+    /// at the latest
+    pub executable_at: Epoch,
+    /// The voting weight in favor of the proposal
+    pub votes_for: HpUfixed<18>,
+    /// The voting weight against the proposal
+    pub votes_against: HpUfixed<18>,
+    /// The voting weight of all the accounts when the proposal was submitted, the quorum is a
+    /// share of it
+    pub total_weight: HpUfixed<18>,
+    /// The stage of the proposal
+    pub status: ProposalStatus,
+}
+
+/// The vote of an account on a governance proposal.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct ProposalVote {
+    /// Whether the vote is in favor of the proposal
+    pub approve: bool,
+    /// The voting weight of the account when the proposal was submitted, which is the FLK it had
+    /// staked on its nodes and delegated to other nodes
+    pub weight: HpUfixed<18>,
+}
+
+impl TranscriptBuilderInput for ProposalAction {
+    const TYPE: &'static str = "proposal_action";
+
+    fn to_transcript_builder_input(&self) -> Vec<u8> {
+        match self {
+            ProposalAction::ChangeProtocolParam { param, value } => {
+                let mut input = vec![0, param.clone() as u8];
+                input.extend(value.to_le_bytes());
+                input
+            },
+            ProposalAction::AddService {
+                service,
+                service_id,
+            } => {
+                let mut input = vec![1];
+                input.extend(service_id.to_le_bytes());
+                input.extend(service.to_transcript_builder_input());
+                input
+            },
+            ProposalAction::RemoveService { service_id } => {
+                let mut input = vec![2];
+                input.extend(service_id.to_le_bytes());
+                input
+            },
+            ProposalAction::ChangeCommitteeSize { committee_size } => {
+                let mut input = vec![3];
+                input.extend(committee_size.to_le_bytes());
+                input
+            },
+        }
+    }
+}
@@ -1,11 +1,149 @@
 //! Types related to the proof of misbehavior.
 
+use fleek_crypto::NodeSignature;
+use hp_fixed::unsigned::HpUfixed;
+use ink_quill::{ToDigest, TranscriptBuilder, TranscriptBuilderInput};
 use serde::{Deserialize, Serialize};
 
-/// Placeholder
+use crate::{Epoch, NodeIndex, ServiceId};
+
+const FN_MISBEHAVIOR_DOMAIN: &str = "fleek_network_misbehavior";
+
+/// The kinds of misbehavior the protocol knows how to verify. A service lists the kinds of
+/// misbehavior a node can be slashed for while serving it.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Serialize,
+    Deserialize,
+    Hash,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    schemars::JsonSchema,
+)]
+#[repr(u8)]
+pub enum MisbehaviorType {
+    /// The node attested to two different parcels building on the same parent.
+    ConflictingAttestations = 0,
+}
+
 /// This is the proof presented to the slashing function that proves a node misbehaved and should be
 /// slashed
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
 pub enum ProofOfMisbehavior {
-    Placeholder,
+    /// Two attestations broadcast by the node that can not both be honest.
+    ConflictingAttestations {
+        first: AttestationEvidence,
+        second: AttestationEvidence,
+    },
+}
+
+impl ProofOfMisbehavior {
+    /// Returns the kind of misbehavior this proof is for.
+    pub fn misbehavior_type(&self) -> MisbehaviorType {
+        match self {
+            ProofOfMisbehavior::ConflictingAttestations { .. } => {
+                MisbehaviorType::ConflictingAttestations
+            },
+        }
+    }
+}
+
+impl ToDigest for ProofOfMisbehavior {
+    /// The digest identifies the offense rather than the exact proof, so that the same
+    /// misbehavior can not be used to slash a node more than once.
+    fn transcript(&self) -> TranscriptBuilder {
+        match self {
+            ProofOfMisbehavior::ConflictingAttestations { first, .. } => {
+                TranscriptBuilder::empty(FN_MISBEHAVIOR_DOMAIN)
+                    .with(
+                        "misbehavior",
+                        &(MisbehaviorType::ConflictingAttestations as u8),
+                    )
+                    .with("parent", &first.parcel.last_executed)
+            },
+        }
+    }
+}
+
+impl TranscriptBuilderInput for ProofOfMisbehavior {
+    const TYPE: &'static str = "proof_of_misbehavior";
+
+    fn to_transcript_builder_input(&self) -> Vec<u8> {
+        match self {
+            ProofOfMisbehavior::ConflictingAttestations { first, second } => {
+                let mut input = vec![MisbehaviorType::ConflictingAttestations as u8];
+                first.write_transcript_input(&mut input);
+                second.write_transcript_input(&mut input);
+                input
+            },
+        }
+    }
+}
+
+/// The message a committee member broadcasts to attest that a parcel is accurate. When an edge
+/// node gets 2f+1 of these it commits the transactions in the parcel.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct CommitteeAttestation {
+    /// The digest we are attesting is correct
+    pub digest: [u8; 32],
+    /// We send random bytes with this message so it gives it a unique hash and differentiates it
+    /// from the other committee members attestation broadcasts
+    pub node_index: NodeIndex,
+    pub epoch: Epoch,
+}
+
+/// A committee attestation as it was received from the broadcast, together with the header of
+/// the parcel it attests to.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct AttestationEvidence {
+    /// The payload of the broadcast message, the encoded consensus message carrying the
+    /// attestation
+    pub payload: Vec<u8>,
+    /// The signature of the attesting node over the broadcast message
+    pub signature: NodeSignature,
+    /// The header of the attested parcel
+    pub parcel: ParcelHeader,
+}
+
+impl AttestationEvidence {
+    fn write_transcript_input(&self, input: &mut Vec<u8>) {
+        input.extend((self.payload.len() as u32).to_le_bytes());
+        input.extend(&self.payload);
+        input.extend(self.signature.0);
+        input.extend(self.parcel.num_transactions.to_le_bytes());
+        input.extend(self.parcel.batch_digest);
+        input.extend(self.parcel.last_executed);
+    }
+}
+
+/// The part of a consensus parcel that the digest of the parcel commits to.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct ParcelHeader {
+    /// The number of transactions in the parcel
+    pub num_transactions: u32,
+    /// The narwhal batch digest of the transactions in the parcel
+    pub batch_digest: [u8; 32],
+    /// The digest of the last executed parcel this parcel builds on
+    pub last_executed: [u8; 32],
+}
+
+/// A record of a node being slashed.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+pub struct SlashRecord {
+    /// The epoch the node was slashed in
+    pub epoch: Epoch,
+    /// The service the node misbehaved in
+    pub service_id: ServiceId,
+    /// The kind of misbehavior the node was slashed for
+    pub misbehavior: MisbehaviorType,
+    /// The digest identifying the offense, see [`ProofOfMisbehavior`]
+    pub offense: [u8; 32],
+    /// The amount of staked FLK that was taken from the node
+    pub amount: HpUfixed<18>,
+    /// The first epoch the node is allowed back on the committee
+    pub jailed_until: Epoch,
 }
@@ -1,9 +1,17 @@
-use ethers::types::{TransactionReceipt as EthersTxnReceipt, U256, U64};
+use ethers::types::{
+    Filter,
+    Log,
+    TransactionReceipt as EthersTxnReceipt,
+    ValueOrArray,
+    H256,
+    U256,
+    U64,
+};
 use fleek_crypto::{EthAddress, TransactionSender};
 use serde::{Deserialize, Serialize};
 
 use super::{Epoch, NodeInfo};
-use crate::{Event, UpdateMethod};
+use crate::{Event, Tokens, UpdateMethod};
 
 /// Info on a Narwhal epoch
 #[derive(
@@ -18,6 +26,40 @@ pub struct EpochInfo {
     pub epoch_end: u64,
 }
 
+/// The value of a key in one of the application tables along with a proof for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ValueWithProof {
+    /// The state root the proof is against.
+    pub state_root: [u8; 32],
+    /// The serialized value, `None` if the key is not present in the table.
+    pub value: Option<Vec<u8>>,
+    /// The inclusion or exclusion proof for the key.
+    pub proof: StateProof,
+}
+
+/// A Merkle proof against the application state root. This has the same representation as
+/// `atomo::StateProof` which can be used to verify it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StateProof {
+    /// The roots of all of the tables.
+    pub table_roots: Vec<[u8; 32]>,
+    /// The index of the table the proof is for.
+    pub table: u8,
+    /// The sibling hashes on the path to the key, starting from the table root.
+    pub siblings: Vec<[u8; 32]>,
+    /// The leaf at the end of the path.
+    pub leaf: Option<ProofLeaf>,
+}
+
+/// The leaf at the end of the path in a [`StateProof`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ProofLeaf {
+    /// The hash of the serialized key.
+    pub path: [u8; 32],
+    /// The hash of the serialized value.
+    pub value_hash: [u8; 32],
+}
+
 #[derive(Clone, Debug, PartialEq, PartialOrd, Hash, Eq, Serialize, Deserialize)]
 pub enum TransactionResponse {
     Success(ExecutionData),
@@ -52,6 +94,75 @@ pub struct TransactionReceipt {
     pub response: TransactionResponse,
     /// The event that was emitted by the transaction
     pub event: Option<Event>,
+    /// The fee the sender paid for the transaction, `None` if it was free
+    pub fee: Option<TransactionFee>,
+}
+
+/// The fee charged for a transaction. The amount paid is `gas * gas_price` in the smallest
+/// denomination of `token`.
+#[derive(Clone, Debug, Hash, Serialize, Deserialize, Eq, PartialEq)]
+pub struct TransactionFee {
+    /// The gas the transaction was charged for
+    pub gas: u128,
+    /// The price of a unit of gas in the smallest denomination of `token`
+    pub gas_price: u128,
+    /// The token the fee was paid in
+    pub token: Tokens,
+}
+
+impl TransactionReceipt {
+    /// The Ethereum logs of this transaction. A transaction emits at most one event, so the
+    /// index of its log within the block is the index of the transaction.
+    pub fn logs(&self) -> Vec<Log> {
+        self.event
+            .iter()
+            .map(|event| Log {
+                address: event.log_address().0.into(),
+                topics: event.log_topics(),
+                data: event.log_data().into(),
+                block_hash: Some(self.block_hash.into()),
+                block_number: Some(self.block_number.into()),
+                transaction_hash: Some(self.transaction_hash.into()),
+                transaction_index: Some(self.transaction_index.into()),
+                log_index: Some(self.transaction_index.into()),
+                transaction_log_index: Some(U256::zero()),
+                removed: Some(false),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Returns the topics accepted at one position of a log filter, or `None` if any topic is
+/// accepted.
+pub fn filter_topics(topics: &ValueOrArray<Option<H256>>) -> Option<Vec<H256>> {
+    match topics {
+        ValueOrArray::Value(topic) => topic.map(|topic| vec![topic]),
+        ValueOrArray::Array(topics) if !topics.is_empty() => topics.iter().copied().collect(),
+        ValueOrArray::Array(_) => None,
+    }
+}
+
+/// Returns true if the log matches the addresses and topics of the filter. The block range of
+/// the filter is not checked.
+pub fn log_matches(filter: &Filter, log: &Log) -> bool {
+    let address_matches = match &filter.address {
+        Some(ValueOrArray::Value(address)) => log.address == *address,
+        Some(ValueOrArray::Array(addresses)) if !addresses.is_empty() => {
+            addresses.contains(&log.address)
+        },
+        _ => true,
+    };
+    address_matches
+        && filter.topics.iter().enumerate().all(|(i, topics)| {
+            match topics.as_ref().and_then(filter_topics) {
+                Some(topics) => log
+                    .topics
+                    .get(i)
+                    .map_or(false, |topic| topics.contains(topic)),
+                None => true,
+            }
+        })
 }
 
 /// What state function a transaction was calling. If an ethereum transaction it will either be
@@ -79,6 +190,11 @@ fn from(value: TransactionReceipt) -> Self {
         } else {
             [0u8; 20].into()
         };
+        let (gas_used, gas_price) = value
+            .fee
+            .map(|fee| (U256::from(fee.gas), U256::from(fee.gas_price)))
+            .unwrap_or_default();
+        let logs = value.logs();
         Self {
             transaction_hash: value.transaction_hash.into(),
             transaction_index: value.transaction_index.into(),
@@ -86,9 +202,10 @@ fn from(value: TransactionReceipt) -> Self {
             block_number: Some((value.block_number).into()),
             from: sender,
             to: Some(value.to.to_eth_address().0.into()),
-            gas_used: Some(U256::zero()),
+            gas_used: Some(gas_used),
             transaction_type: Some(U64::one()),
-            effective_gas_price: Some(U256::zero()),
+            effective_gas_price: Some(gas_price),
+            logs,
             status: Some(if value.response.is_success() {
                 U64::one()
             } else {
@@ -112,15 +229,23 @@ pub enum ExecutionData {
 #[derive(Clone, Debug, PartialEq, PartialOrd, Hash, Eq, Serialize, Deserialize)]
 pub enum ExecutionError {
     InsufficientBalance,
+    InsufficientBalanceForFee,
+    InsufficientAllowance,
     InvalidChainId,
     InvalidSignature,
     InvalidNonce,
     InvalidProof,
+    InvalidSessionNonce,
+    UnknownClient,
     InvalidInternetAddress,
     InsufficientNodeDetails,
     InvalidStateFunction,
     InvalidConsensusKey,
     InvalidToken,
+    DepositAlreadyProcessed,
+    WithdrawalNotPending,
+    ZeroAmount,
+    InvalidBridgeSigners,
     InvalidStateForContentRemoval,
     InvalidContentRemoval,
     NoLockedTokens,
@@ -132,13 +257,28 @@ pub enum ExecutionError {
     AlreadySignaled,
     SubmittedTooManyTransactions,
     NonExistingService,
+    ServiceAlreadyExists,
+    NotServiceOwner,
+    InvalidCommodityType,
     OnlyAccountOwner,
     OnlyNode,
     OnlyGovernance,
     InvalidServiceId,
+    MisbehaviorNotSlashable,
+    AlreadySlashed,
     InsufficientStake,
     LockExceededMaxStakeLockTime,
     LockedTokensUnstakeForbidden,
+    NoDelegation,
+    CantDelegateToOwnNode,
+    InvalidCommissionRate,
+    ProposalDoesNotExist,
+    InvalidProposal,
+    NoVotingWeight,
+    TooManyOpenProposals,
+    VotingClosed,
+    AlreadyVoted,
+    ProposalNotExecutable,
     EpochAlreadyChanged,
     EpochHasNotStarted,
     ConsensusKeyAlreadyIndexed,
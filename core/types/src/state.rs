@@ -9,7 +9,7 @@
 use num_derive::FromPrimitive;
 use serde::{Deserialize, Serialize};
 
-use super::ReputationMeasurements;
+use super::{BridgeSigners, MisbehaviorType, ReputationMeasurements};
 
 /// The Id of a Service
 pub type ServiceId = u32;
@@ -94,6 +94,10 @@ pub enum Metadata {
     LastEpochHash,
     LastBlockHash,
     GenesisCommittee,
+    NextWithdrawalId,
+    BridgeSigners,
+    SchemaVersion,
+    NextProposalId,
 }
 
 /// The Value enum is a data type used to represent values in a key-value pair for a metadata table
@@ -108,6 +112,10 @@ pub enum Value {
     NextNodeIndex(u32),
     Hash([u8; 32]),
     GenesisCommittee(Vec<NodeIndex>),
+    NextWithdrawalId(u64),
+    BridgeSigners(BridgeSigners),
+    SchemaVersion(u32),
+    NextProposalId(u64),
 }
 
 impl Value {
@@ -150,6 +158,7 @@ pub enum Participation {
     Serialize,
     Deserialize,
     Debug,
+    FromPrimitive,
     schemars::JsonSchema
 )]
 #[repr(u8)]
@@ -178,6 +187,35 @@ pub enum ProtocolParams {
     MaxBoost = 10,
     /// The max amount of time tokens can be locked
     MaxStakeLockTime = 11,
+    /// The amount of FLK a service builder has to bond in order to register a service
+    ServiceBond = 12,
+    /// The percentage of a node's stake that is taken when it is slashed
+    SlashPercentage = 13,
+    /// The amount of epochs a slashed node is kept off the committee
+    JailDuration = 14,
+    /// The price of a unit of gas in the smallest denomination of FLK
+    GasPrice = 15,
+    /// The price of a unit of gas in the smallest denomination of stables, used when the sender
+    /// can not cover the fee in FLK
+    StablesGasPrice = 16,
+    /// The gas charged for account transactions without a more specific cost
+    BaseGas = 17,
+    /// The gas charged for a transfer
+    TransferGas = 18,
+    /// The gas charged for staking, locking, unstaking and withdrawing unstaked tokens
+    StakingGas = 19,
+    /// The gas charged for a withdrawal to the bridge
+    BridgeGas = 20,
+    /// The gas charged for registering and removing services and for reporting misbehavior
+    ServiceGas = 21,
+    /// The percentage of the staked FLK that has to vote on a governance proposal for it to pass
+    GovernanceQuorum = 22,
+    /// The time in epochs a governance proposal is open for votes
+    GovernanceVotingPeriod = 23,
+    /// The time in epochs between the end of the vote on a governance proposal and its execution
+    GovernanceTimelock = 24,
+    /// The number of governance proposals an account can have open for votes at the same time
+    GovernanceMaxOpenProposals = 25,
 }
 
 #[rustfmt::skip]
@@ -221,6 +259,8 @@ pub struct NodeInfo {
     /// The secondary nonce. This nonce is used to invalidate transactions that we already sent to
     /// the mempool in case we have to resent a transaction with an updated nonce.
     pub secondary_nonce: u128,
+    /// The percentage of the rewards of its delegators that the owner of the node keeps.
+    pub commission_rate: u8,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema)]
@@ -322,6 +362,29 @@ pub struct Staking {
     pub locked_until: u64,
 }
 
+/// The FLK an account has delegated to a node.
+#[derive(
+    Debug,
+    Hash,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Eq,
+    Serialize,
+    Deserialize,
+    Clone,
+    Default,
+    schemars::JsonSchema,
+)]
+pub struct Delegation {
+    /// How much FLK is currently delegated
+    pub staked: HpUfixed<18>,
+    /// How much FLK is undelegated and locked pending withdraw
+    pub locked: HpUfixed<18>,
+    /// The epoch the locked FLK is eligible to be withdrawn
+    pub locked_until: u64,
+}
+
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone)]
 pub struct Worker {
     /// The public key of the worker
@@ -334,15 +397,15 @@ pub struct Worker {
 
 /// Placeholder
 /// Information about the services
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, schemars::JsonSchema)]
 pub struct Service {
     /// the owner address that deploys the service and also recieves reward share
     pub owner: EthAddress,
     // TODO: can there be multiple types of commodity per service
     /// the commodity that service is going to serve
     pub commodity_type: CommodityTypes,
-    /// TODO: List of circuits to prove a node should be slashed
-    pub slashing: (),
+    /// The kinds of misbehavior a node can be slashed for while serving this service
+    pub slashing: Vec<MisbehaviorType>,
 }
 
 #[derive(Debug, Hash, PartialEq, PartialOrd, Ord, Eq, Serialize, Deserialize, Clone, Default)]
@@ -357,8 +420,10 @@ impl TranscriptBuilderInput for Service {
     const TYPE: &'static str = "service";
 
     fn to_transcript_builder_input(&self) -> Vec<u8> {
-        self.commodity_type.to_transcript_builder_input()
-        // todo: check if implementation needs to change when slashing is implemented
+        let mut input = self.owner.0.to_vec();
+        input.extend(self.commodity_type.to_transcript_builder_input());
+        input.extend(self.slashing.iter().map(|misbehavior| *misbehavior as u8));
+        input
     }
 }
 
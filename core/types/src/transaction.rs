@@ -16,10 +16,13 @@
 use serde::{Deserialize, Serialize};
 
 use super::{
+    BridgeSignature,
     Epoch,
     Event,
     ProofOfConsensus,
     ProofOfMisbehavior,
+    ProposalAction,
+    ProposalId,
     ProtocolParams,
     ReputationMeasurements,
     Service,
@@ -181,6 +184,9 @@ pub fn event(&self) -> Option<Event> {
                     } => event
                         .to_owned()
                         .map(|e| Event::service_event(*service_id, e)),
+                    UpdateMethod::RemoveService { service_id } => {
+                        Some(Event::service_removed(*service_id, sender))
+                    },
                     _ => None,
                 },
                 _ => None,
@@ -301,7 +307,8 @@ pub enum UpdateMethod {
     /// node will submit this transaction to get paid.
     /// Revisit the naming of this transaction.
     SubmitDeliveryAcknowledgmentAggregation {
-        /// How much of the commodity was served
+        /// How much of the commodity was served. The node is only credited with what the valid
+        /// acknowledgments in `proofs` add up to.
         commodity: u128,
         /// The service id of the service this was provided through(CDN, compute, ect.)
         service_id: u32,
@@ -328,6 +335,14 @@ pub enum UpdateMethod {
         /// Amount bridged
         amount: HpUfixed<18>,
     },
+    /// Submitted by the bridge relayer once a withdrawal was paid out on the L2, removes it from
+    /// the pending withdrawals
+    SettleWithdrawal {
+        /// The id of the settled withdrawal
+        withdrawal_id: u64,
+        /// The signatures of the bridge signers over the [`crate::WithdrawalSettlement`]
+        signatures: Vec<BridgeSignature>,
+    },
     /// Transfer tokens to another address
     Transfer {
         /// The amount to transfer
@@ -337,6 +352,28 @@ pub enum UpdateMethod {
         /// The address to transfer to
         to: EthAddress,
     },
+    /// Allow another address to transfer up to an amount of the sender's tokens. Approving
+    /// again replaces the previous allowance.
+    Approve {
+        /// Which token the allowance is for
+        token: Tokens,
+        /// The address that is allowed to spend the tokens
+        spender: EthAddress,
+        /// The maximum amount the spender can transfer
+        amount: HpUfixed<18>,
+    },
+    /// Transfer tokens on behalf of another address, spending from the allowance it approved
+    /// for the sender.
+    TransferFrom {
+        /// Which token to transfer
+        token: Tokens,
+        /// The address to transfer from
+        from: EthAddress,
+        /// The address to transfer to
+        to: EthAddress,
+        /// The amount to transfer
+        amount: HpUfixed<18>,
+    },
     /// Stake FLK in network
     Stake {
         /// Amount to stake
@@ -373,6 +410,29 @@ pub enum UpdateMethod {
         node: NodePublicKey,
         recipient: Option<EthAddress>,
     },
+    /// Delegate FLK to a node operated by someone else, the delegator receives a share of the
+    /// rewards of the node pro-rata to the delegated amount, minus the commission of the node
+    Delegate {
+        node: NodePublicKey,
+        amount: HpUfixed<18>,
+    },
+    /// Undelegate FLK from a node, the tokens will be locked for a set amount of
+    /// time(ProtocolParameter::LockTime) before they can be withdrawn
+    Undelegate {
+        node: NodePublicKey,
+        amount: HpUfixed<18>,
+    },
+    /// Withdraw undelegated tokens from a node after the lock period has passed, optionally to a
+    /// different address than the delegator
+    WithdrawDelegation {
+        node: NodePublicKey,
+        recipient: Option<EthAddress>,
+    },
+    /// Change the percentage of the rewards of its delegators that the owner of a node keeps
+    ChangeCommissionRate {
+        node: NodePublicKey,
+        commission_rate: u8,
+    },
     /// Sent by committee member to signal he is ready to change epoch
     ChangeEpoch { epoch: Epoch },
     /// Adding a new service to the protocol
@@ -391,7 +451,7 @@ pub enum UpdateMethod {
         service_id: ServiceId,
         /// The public key of the node that misbehaved
         node: NodePublicKey,
-        /// Zk proof to be provided to the slash circuit
+        /// The proof of misbehavior, checked by the verifier registered for its type
         proof_of_misbehavior: ProofOfMisbehavior,
     },
     /// Report reputation measurements
@@ -400,6 +460,25 @@ pub enum UpdateMethod {
     },
     /// Change protocol parameters
     ChangeProtocolParam { param: ProtocolParams, value: u128 },
+    /// Submit a governance proposal, it is open for votes for
+    /// ProtocolParams::GovernanceVotingPeriod epochs
+    SubmitProposal { action: ProposalAction },
+    /// Vote on a governance proposal with the FLK staked on the nodes of the sender and delegated
+    /// by it
+    VoteOnProposal {
+        proposal_id: ProposalId,
+        approve: bool,
+    },
+    /// Execute a governance proposal that passed and whose timelock is over, otherwise it is
+    /// executed at the end of the epoch
+    ExecuteProposal { proposal_id: ProposalId },
+    /// Change the set of L2 bridge signers that sign off on deposits
+    ChangeBridgeSigners {
+        /// The addresses of the bridge signers
+        signers: Vec<EthAddress>,
+        /// The number of distinct signers that have to sign a deposit
+        threshold: u16,
+    },
     /// Opt out of participating in the network.
     OptOut {},
     /// Opt into participating in the network.
@@ -442,22 +521,37 @@ fn transcript(&self) -> TranscriptBuilder {
         // insert method fields
         match &self.method {
             UpdateMethod::Deposit {
-                proof: _,
+                proof,
                 token,
                 amount,
             } => {
                 transcript_builder = transcript_builder
                     .with("transaction_name", &"deposit")
                     .with_prefix("input".to_owned())
+                    .with("proof", proof)
                     .with("token", token)
                     .with("amount", &HpUfixedWrapper(amount.clone()));
-                //.with("method.proof", proof);
+            },
+            UpdateMethod::SettleWithdrawal {
+                withdrawal_id,
+                signatures,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"settle_withdrawal")
+                    .with_prefix("input".to_owned())
+                    .with("withdrawal_id", withdrawal_id);
+                for (idx, signature) in signatures.iter().enumerate() {
+                    transcript_builder = transcript_builder
+                        .with_prefix(idx.to_string())
+                        .with("signer", &signature.signer.0)
+                        .with("signature", &signature.signature.0);
+                }
             },
 
             UpdateMethod::SubmitDeliveryAcknowledgmentAggregation {
                 commodity,
                 service_id,
-                proofs: _,
+                proofs,
                 metadata,
             } => {
                 transcript_builder = transcript_builder
@@ -469,7 +563,14 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with("commodity", commodity)
                     .with("service_id", service_id)
                     .with("metadata", metadata);
-                //.with("method.proof", proof);
+                for (idx, proof) in proofs.iter().enumerate() {
+                    transcript_builder = transcript_builder
+                        .with_prefix(idx.to_string())
+                        .with("client", &proof.client.0)
+                        .with("commodity", &proof.commodity)
+                        .with("session_nonce", &proof.session_nonce)
+                        .with("signature", &proof.signature.0);
+                }
             },
             UpdateMethod::Withdraw {
                 amount,
@@ -491,6 +592,32 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with("amount", &HpUfixedWrapper(amount.clone()))
                     .with("to", &to.0);
             },
+            UpdateMethod::Approve {
+                token,
+                spender,
+                amount,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"approve")
+                    .with_prefix("input".to_owned())
+                    .with("token", token)
+                    .with("spender", &spender.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
+            UpdateMethod::TransferFrom {
+                token,
+                from,
+                to,
+                amount,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"transfer_from")
+                    .with_prefix("input".to_owned())
+                    .with("token", token)
+                    .with("from", &from.0)
+                    .with("to", &to.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
             UpdateMethod::Stake {
                 amount,
                 node_public_key,
@@ -555,6 +682,37 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with("node", &node.0)
                     .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
             },
+            UpdateMethod::Delegate { node, amount } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"delegate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
+            UpdateMethod::Undelegate { node, amount } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"undelegate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("amount", &HpUfixedWrapper(amount.clone()));
+            },
+            UpdateMethod::WithdrawDelegation { node, recipient } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"withdraw_delegation")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("recipient", &recipient.map_or([0u8; 20], |key| key.0));
+            },
+            UpdateMethod::ChangeCommissionRate {
+                node,
+                commission_rate,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"change_commission_rate")
+                    .with_prefix("input".to_owned())
+                    .with("node", &node.0)
+                    .with("commission_rate", commission_rate);
+            },
             UpdateMethod::ChangeEpoch { epoch } => {
                 transcript_builder = transcript_builder
                     .with("transaction_name", &"change_epoch")
@@ -580,15 +738,14 @@ fn transcript(&self) -> TranscriptBuilder {
             UpdateMethod::Slash {
                 service_id,
                 node,
-                proof_of_misbehavior: _,
+                proof_of_misbehavior,
             } => {
                 transcript_builder = transcript_builder
                     .with("transaction_name", &"slash")
                     .with_prefix("input".to_owned())
                     .with("service_id", service_id)
-                .with("node", &node.0)
-                // .with("proof_of_misbehavior", proof_of_misbehavior)
-                ;
+                    .with("node", &node.0)
+                    .with("proof_of_misbehavior", proof_of_misbehavior);
             },
             UpdateMethod::SubmitReputationMeasurements { measurements } => {
                 transcript_builder =
@@ -612,6 +769,39 @@ fn transcript(&self) -> TranscriptBuilder {
                     .with("param", &(param.clone() as u8))
                     .with("value", value);
             },
+            UpdateMethod::SubmitProposal { action } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"submit_proposal")
+                    .with_prefix("input".to_owned())
+                    .with("action", action);
+            },
+            UpdateMethod::VoteOnProposal {
+                proposal_id,
+                approve,
+            } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"vote_on_proposal")
+                    .with_prefix("input".to_owned())
+                    .with("proposal_id", proposal_id)
+                    .with("approve", &(*approve as u8));
+            },
+            UpdateMethod::ExecuteProposal { proposal_id } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"execute_proposal")
+                    .with_prefix("input".to_owned())
+                    .with("proposal_id", proposal_id);
+            },
+            UpdateMethod::ChangeBridgeSigners { signers, threshold } => {
+                transcript_builder = transcript_builder
+                    .with("transaction_name", &"change_bridge_signers")
+                    .with_prefix("input".to_owned())
+                    .with("threshold", threshold);
+                for (idx, signer) in signers.iter().enumerate() {
+                    transcript_builder = transcript_builder
+                        .with_prefix(idx.to_string())
+                        .with("signer", &signer.0);
+                }
+            },
             UpdateMethod::OptIn {} => {
                 transcript_builder = transcript_builder.with("transaction_name", &"opt_in");
             },
@@ -634,7 +824,7 @@ fn transcript(&self) -> TranscriptBuilder {
     }
 }
 
-struct HpUfixedWrapper<const T: usize>(HpUfixed<T>);
+pub(crate) struct HpUfixedWrapper<const T: usize>(pub HpUfixed<T>);
 
 impl<const T: usize> HpUfixedWrapper<T> {
     #[inline]
@@ -3,6 +3,7 @@
 
 use autometrics::autometrics;
 use fleek_crypto::NodePublicKey;
+use hp_fixed::unsigned::HpUfixed;
 use lightning_interfaces::prelude::*;
 use lightning_interfaces::types::{
     Epoch,
@@ -11,6 +12,9 @@
     NodeIndex,
     NodeInfo,
     NodeInfoWithIndex,
+    PendingWithdrawal,
+    Proposal,
+    ProposalStatus,
     ProtocolParams,
     Value,
 };
@@ -128,15 +132,22 @@ fn get_node_registry(&self, paging: Option<PagingParams>) -> Vec<NodeInfoWithInd
             });
             match paging {
                 None => nodes
-                    .filter(|node| node.info.stake.staked >= staking_amount)
+                    .filter(|node| {
+                        self.get_node_stake(&node.index).unwrap_or_default() >= staking_amount
+                    })
                     .collect(),
                 Some(PagingParams {
                     ignore_stake,
                     limit,
                     start,
+                    ..
                 }) => {
                     let mut nodes = nodes
-                        .filter(|node| ignore_stake || node.info.stake.staked >= staking_amount)
+                        .filter(|node| {
+                            ignore_stake
+                                || self.get_node_stake(&node.index).unwrap_or_default()
+                                    >= staking_amount
+                        })
                         .collect::<Vec<NodeInfoWithIndex>>();
 
                     nodes.sort_by_key(|info| info.index);
@@ -177,14 +188,57 @@ fn get_staking_amount(&self) -> u128 {
             .unwrap_or(0)
     }
 
+    /// Returns the FLK staked on a node, which is the stake of its owner plus the stake
+    /// delegated to it.
+    fn get_node_stake(&self, node: &NodeIndex) -> Option<HpUfixed<18>> {
+        let staked = self.get_node_info(node, |n| n.stake.staked)?;
+        Some(
+            self.get_delegators(node)
+                .iter()
+                .filter_map(|delegator| self.get_delegation(node, delegator))
+                .fold(staked, |total, delegation| total + delegation.staked),
+        )
+    }
+
     /// Returns true if the node is a valid node in the network, with enough stake.
     fn is_valid_node(&self, id: &NodePublicKey) -> bool {
         let minimum_stake_amount = self.get_staking_amount().into();
         self.pubkey_to_index(id).is_some_and(|node_idx| {
-            self.get_node_info(&node_idx, |n| n.stake.staked)
+            self.get_node_stake(&node_idx)
                 .is_some_and(|node_stake| node_stake >= minimum_stake_amount)
         })
     }
+
+    /// Returns up to `limit` of the withdrawals waiting to be settled on the L2, starting from
+    /// the withdrawal with id `start`, ordered by id.
+    fn get_pending_withdrawals(&self, start: u64, limit: usize) -> Vec<PendingWithdrawal> {
+        let mut ids = self.get_pending_withdrawals_iter::<Vec<u64>>(|ids| {
+            ids.filter(|id| *id >= start).collect()
+        });
+        ids.sort_unstable();
+        ids.into_iter()
+            .take(limit)
+            .filter_map(|id| self.get_pending_withdrawal(&id))
+            .collect()
+    }
+
+    /// Returns the governance proposals, or only the ones with the given status, ordered by id.
+    fn get_proposals(&self, status: Option<ProposalStatus>) -> Vec<Proposal> {
+        match status {
+            Some(status) => self
+                .get_proposal_ids_by_status(&status)
+                .into_iter()
+                .filter_map(|id| self.get_proposal(&id))
+                .collect(),
+            None => {
+                let mut proposals = self.get_proposals_iter::<Vec<Proposal>>(|ids| {
+                    ids.filter_map(|id| self.get_proposal(&id)).collect()
+                });
+                proposals.sort_by_key(|proposal| proposal.id);
+                proposals
+            },
+        }
+    }
 }
 
 impl<T: SyncQueryRunnerInterface> QueryRunnerExt for T {}
@@ -8,9 +8,16 @@
     r"[
         function withdraw(uint256 amount, string token, address recipient)
         function stake(uint256 amount, bytes32 nodePublicKey, bytes consensusKey, string domain, bytes32 workerPublicKey, string workerDomain)
-        function deposit(string token, uint256 amount)
+        function deposit(string token, uint256 amount, uint64 depositId, address[] signers, bytes[] signatures)
         function unstake(uint256 amount, bytes32 node_public_key)
         function withdrawUnstaked(bytes32 node_public_key, address recipient)
+        function approve(string token, address spender, uint256 amount)
+        function transferFrom(string token, address from, address to, uint256 amount)
+        function getBalance(address account) view returns (uint256 flk, uint256 stables, uint256 bandwidth)
+        function getStake(bytes32 nodePublicKey) view returns (uint256 staked, uint64 stakeLockedUntil, uint256 locked, uint64 lockedUntil)
+        function getNodeInfo(bytes32 nodePublicKey) view returns (address owner, bytes consensusKey, string domain, bytes32 workerPublicKey, string workerDomain, uint64 stakedSince, uint64 nonce)
+        function getEpoch() view returns (uint64 epoch, uint64 epochEnd)
+        function getProtocolParam(uint8 param) view returns (uint256 value)
     ]"
 );
 
@@ -0,0 +1,623 @@
+//! Incremental, chunked checkpoints of a RocksDB database.
+//!
+//! A checkpoint is made of a list of chunks and a [`CheckpointManifest`] that references them by
+//! their blake3 hash. Every chunk holds a contiguous range of entries of a single table, tables
+//! are visited in alphabetic order and entries in key order, so the same database state always
+//! produces the same chunks and the same manifest.
+//!
+//! Chunks are produced one at a time by a [`CheckpointWriter`] and ingested one at a time by a
+//! [`CheckpointImporter`], so neither side ever holds more than a single chunk in memory. The
+//! importer records its progress in the database it is building, which allows a partially
+//! imported checkpoint to be resumed.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use blake3_tree::directory::{Directory, DirectoryEntry, Link};
+use fleek_blake3 as blake3;
+use rocksdb::{
+    ColumnFamilyDescriptor,
+    IteratorMode,
+    Options,
+    ReadOptions,
+    Snapshot,
+    WriteBatch,
+    DB,
+};
+
+use crate::serialization::{deserialize_table, read_bytes, read_u64, serialize_table};
+
+/// The default target size of a single checkpoint chunk.
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// The column family used by the [`CheckpointImporter`] to keep track of the imported chunks.
+const PROGRESS_TABLE: &str = "__checkpoint_progress";
+
+/// The key in the progress table that holds the hash of the manifest being imported.
+const MANIFEST_KEY: &[u8] = b"manifest";
+
+/// Returns the name of a chunk in the manifest directory.
+pub fn chunk_name(table: &str, index: u32) -> String {
+    format!("{table}/{index:010}")
+}
+
+/// A single chunk of a checkpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckpointChunk {
+    /// The table the entries of this chunk belong to.
+    pub table: String,
+    /// The position of this chunk among the chunks of the same table.
+    pub index: u32,
+    /// The serialized entries.
+    pub content: Vec<u8>,
+}
+
+/// A reference to a chunk listed in a [`CheckpointManifest`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkRef<'a> {
+    pub table: &'a str,
+    pub index: u32,
+    pub hash: [u8; 32],
+}
+
+impl<'a> ChunkRef<'a> {
+    /// Returns the name of this chunk in the manifest directory.
+    pub fn name(&self) -> String {
+        chunk_name(self.table, self.index)
+    }
+}
+
+/// The manifest of a checkpoint, listing the hashes of the chunks of every table.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CheckpointManifest {
+    tables: BTreeMap<String, Vec<[u8; 32]>>,
+}
+
+impl CheckpointManifest {
+    /// Append the hash of the next chunk of the given table.
+    pub fn push(&mut self, table: &str, hash: [u8; 32]) {
+        self.tables.entry(table.to_owned()).or_default().push(hash);
+    }
+
+    /// Returns the names of the tables in this checkpoint.
+    pub fn tables(&self) -> impl Iterator<Item = &str> {
+        self.tables.keys().map(String::as_str)
+    }
+
+    /// Returns every chunk of this checkpoint in order.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkRef<'_>> {
+        self.tables.iter().flat_map(|(table, hashes)| {
+            hashes.iter().enumerate().map(|(index, hash)| ChunkRef {
+                table,
+                index: index as u32,
+                hash: *hash,
+            })
+        })
+    }
+
+    /// Returns the hash of a chunk.
+    pub fn chunk_hash(&self, table: &str, index: u32) -> Option<[u8; 32]> {
+        self.tables.get(table)?.get(index as usize).copied()
+    }
+
+    /// Returns the total number of chunks.
+    pub fn len(&self) -> usize {
+        self.tables.values().map(Vec::len).sum()
+    }
+
+    /// Returns true if the manifest has no chunks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the manifest as a blake3 directory with one file entry per chunk, which can be
+    /// used to prove that a chunk is part of the checkpoint.
+    pub fn directory(&self) -> Directory {
+        let entries = self
+            .chunks()
+            .map(|chunk| DirectoryEntry::new(chunk.name().into(), Link::file(chunk.hash)))
+            .collect();
+        Directory::new(entries, false)
+    }
+
+    /// Returns the hash that identifies the checkpoint, the blake3 root of the serialized
+    /// manifest. This is the hash the manifest is stored under in the blockstore, so the
+    /// checkpoint can be fetched by it.
+    pub fn hash(&self) -> [u8; 32] {
+        *blake3::hash(&self.serialize()).as_bytes()
+    }
+
+    /// Serializes the manifest into a stream of bytes.
+    /// The serialization format is:
+    /// [num tables][table1 name length][table1 name bytes][table1 num chunks][table1 chunk1
+    /// hash][table1 chunk2 hash]...[table2 name length]...
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend((self.tables.len() as u64).to_le_bytes());
+        for (table, hashes) in &self.tables {
+            bytes.extend((table.len() as u64).to_le_bytes());
+            bytes.extend(table.as_bytes());
+            bytes.extend((hashes.len() as u64).to_le_bytes());
+            for hash in hashes {
+                bytes.extend(hash);
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes a manifest from a stream of bytes.
+    pub fn deserialize(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        let mut tables = BTreeMap::new();
+        let num_tables = read_u64(&mut reader)?;
+        for _ in 0..num_tables {
+            let table_name_len = read_u64(&mut reader)? as usize;
+            let table_name = String::from_utf8(read_bytes(&mut reader, table_name_len)?.to_vec())?;
+            let num_chunks = read_u64(&mut reader)?;
+            let mut hashes = Vec::new();
+            for _ in 0..num_chunks {
+                hashes.push(read_bytes(&mut reader, 32)?.try_into().unwrap());
+            }
+            tables.insert(table_name, hashes);
+        }
+        if !reader.is_empty() {
+            return Err(anyhow!("Unexpected trailing bytes in manifest"));
+        }
+        Ok(Self { tables })
+    }
+}
+
+/// Produces the chunks of a checkpoint from a consistent snapshot of a database.
+///
+/// Every call to `next` reads at most one chunk worth of entries, so the writer can be held
+/// across await points and driven at the pace of the consumer.
+pub struct CheckpointWriter<'a> {
+    db: &'a DB,
+    snapshot: Snapshot<'a>,
+    tables: Vec<String>,
+    table: usize,
+    index: u32,
+    cursor: Option<Box<[u8]>>,
+    chunk_size: usize,
+}
+
+impl<'a> CheckpointWriter<'a> {
+    /// Create a writer over the given tables of the database.
+    pub fn new(db: &'a DB, table_names: &[String]) -> Self {
+        let mut tables = table_names.to_vec();
+        tables.sort();
+
+        Self {
+            db,
+            snapshot: db.snapshot(),
+            tables,
+            table: 0,
+            index: 0,
+            cursor: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Set the target size of a chunk. A chunk is closed as soon as it reaches this size, so the
+    /// last entry can make it slightly larger.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<CheckpointChunk>> {
+        while self.table < self.tables.len() {
+            let table = self.tables[self.table].clone();
+            let cf = self
+                .db
+                .cf_handle(&table)
+                .ok_or(anyhow!("Unknown table name"))?;
+
+            // Resume right after the last key of the previous chunk.
+            let mut options = ReadOptions::default();
+            if let Some(cursor) = &self.cursor {
+                let mut lower = cursor.to_vec();
+                lower.push(0);
+                options.set_iterate_lower_bound(lower);
+            }
+
+            let mut entries = Vec::new();
+            let mut size = 8;
+            let mut exhausted = true;
+            for entry in self
+                .snapshot
+                .iterator_cf_opt(cf, options, IteratorMode::Start)
+            {
+                let (key, value) = entry?;
+                size += 16 + key.len() + value.len();
+                entries.push((key, value));
+                if size >= self.chunk_size {
+                    exhausted = false;
+                    break;
+                }
+            }
+
+            let index = self.index;
+            if exhausted {
+                self.table += 1;
+                self.index = 0;
+                self.cursor = None;
+            } else {
+                self.index += 1;
+                self.cursor = entries.last().map(|(key, _)| key.clone());
+            }
+
+            // Empty tables are still written as a single empty chunk so that they are created
+            // when the checkpoint is imported.
+            if entries.is_empty() && index > 0 {
+                continue;
+            }
+
+            return Ok(Some(CheckpointChunk {
+                table,
+                index,
+                content: serialize_table(entries.into_iter()),
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+impl<'a> Iterator for CheckpointWriter<'a> {
+    type Item = Result<CheckpointChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().transpose()
+    }
+}
+
+/// Builds a database from the chunks of a checkpoint.
+///
+/// Chunks can be imported in any order, each chunk is verified against the manifest before it is
+/// written. Opening an importer on a path that holds a partial import of the same checkpoint
+/// resumes it, anything else at the path is removed.
+pub struct CheckpointImporter {
+    db: DB,
+    path: PathBuf,
+    manifest: CheckpointManifest,
+}
+
+impl CheckpointImporter {
+    /// Open an importer for the given manifest at the given path.
+    pub fn new<P: Into<PathBuf>>(
+        path: P,
+        manifest: CheckpointManifest,
+        mut options: Options,
+    ) -> Result<Self> {
+        let path = path.into();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let manifest_hash = manifest.hash();
+        if path.exists() && !Self::is_resumable(&path, &manifest_hash, &options) {
+            // This is either a partial import of another checkpoint or something that is not an
+            // import at all, start over.
+            fs::remove_dir_all(&path)?;
+        }
+
+        let db = Self::open(&path, &manifest, &options)?;
+        let progress = db
+            .cf_handle(PROGRESS_TABLE)
+            .context("Missing progress table")?;
+        db.put_cf(progress, MANIFEST_KEY, manifest_hash)?;
+
+        Ok(Self { db, path, manifest })
+    }
+
+    /// Returns true if the database at the path is a partial import of the checkpoint with the
+    /// given hash.
+    fn is_resumable(path: &Path, manifest_hash: &[u8; 32], options: &Options) -> bool {
+        let Ok(columns) = DB::list_cf(options, path) else {
+            return false;
+        };
+        if !columns.iter().any(|name| name == PROGRESS_TABLE) {
+            return false;
+        }
+        let Ok(db) = DB::open_cf_for_read_only(options, path, columns, false) else {
+            return false;
+        };
+        let stored_hash = db
+            .cf_handle(PROGRESS_TABLE)
+            .and_then(|progress| db.get_cf(progress, MANIFEST_KEY).ok().flatten());
+        stored_hash.as_deref() == Some(&manifest_hash[..])
+    }
+
+    fn open(path: &Path, manifest: &CheckpointManifest, options: &Options) -> Result<DB> {
+        let mut columns: Vec<String> = manifest.tables().map(String::from).collect();
+        columns.push(PROGRESS_TABLE.to_owned());
+        // All of the existing column families have to be opened.
+        if path.exists() {
+            for name in DB::list_cf(options, path)? {
+                if !columns.contains(&name) {
+                    columns.push(name);
+                }
+            }
+        }
+
+        let cf_iter: Vec<_> = columns
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(name, options.clone()))
+            .collect();
+        Ok(DB::open_cf_descriptors(options, path, cf_iter)?)
+    }
+
+    /// Returns the manifest of the checkpoint being imported.
+    pub fn manifest(&self) -> &CheckpointManifest {
+        &self.manifest
+    }
+
+    /// Returns true if the given chunk was already imported.
+    pub fn is_imported(&self, table: &str, index: u32) -> Result<bool> {
+        let progress = self
+            .db
+            .cf_handle(PROGRESS_TABLE)
+            .context("Missing progress table")?;
+        Ok(self
+            .db
+            .get_cf(progress, chunk_name(table, index))?
+            .is_some())
+    }
+
+    /// Returns the chunks that still have to be imported.
+    pub fn missing(&self) -> Result<Vec<ChunkRef<'_>>> {
+        let mut missing = Vec::new();
+        for chunk in self.manifest.chunks() {
+            if !self.is_imported(chunk.table, chunk.index)? {
+                missing.push(chunk);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// Verify a chunk against the manifest and write its entries to the database.
+    pub fn import_chunk(&mut self, table: &str, index: u32, content: &[u8]) -> Result<()> {
+        let hash = self.manifest.chunk_hash(table, index).ok_or(anyhow!(
+            "Chunk {} is not in the manifest",
+            chunk_name(table, index)
+        ))?;
+        if &hash != blake3::hash(content).as_bytes() {
+            return Err(anyhow!("Failed to verify hash"));
+        }
+
+        let (entries, len) = deserialize_table(content)?;
+        if len != content.len() {
+            return Err(anyhow!("Unexpected trailing bytes in chunk"));
+        }
+
+        let cf = self.db.cf_handle(table).context("Unknown table name")?;
+        let progress = self
+            .db
+            .cf_handle(PROGRESS_TABLE)
+            .context("Missing progress table")?;
+        let mut batch = WriteBatch::default();
+        for (key, value) in entries {
+            batch.put_cf(cf, key, value);
+        }
+        batch.put_cf(progress, chunk_name(table, index), b"");
+        self.db.write(batch)?;
+
+        Ok(())
+    }
+
+    /// Finish the import once every chunk was imported, and return the path of the database and
+    /// the names of its tables.
+    pub fn finish(mut self) -> Result<(PathBuf, Vec<String>)> {
+        if !self.missing()?.is_empty() {
+            return Err(anyhow!("Checkpoint is incomplete"));
+        }
+
+        self.db.drop_cf(PROGRESS_TABLE)?;
+        self.db.flush()?;
+
+        let table_names = self.manifest.tables().map(String::from).collect();
+        Ok((self.path, table_names))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use fleek_blake3 as blake3;
+    use rand::Rng;
+    use rocksdb::{ColumnFamilyDescriptor, IteratorMode, Options, DB};
+
+    use super::{CheckpointImporter, CheckpointManifest, CheckpointWriter};
+    use crate::serialization::Entry;
+
+    fn generate_random_bytes(length: usize) -> Box<[u8]> {
+        let mut rng = rand::thread_rng();
+        (0..length).map(|_| rng.gen_range(0..255)).collect()
+    }
+
+    fn options() -> Options {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        options
+    }
+
+    fn build_random_db(path: &std::path::Path, columns: &[String]) -> DB {
+        if path.exists() {
+            std::fs::remove_dir_all(path).unwrap();
+        }
+        let options = options();
+        let cf_iter: Vec<_> = columns
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(name.to_owned(), options.clone()))
+            .collect();
+        let db = DB::open_cf_descriptors(&options, path, cf_iter).unwrap();
+        // The last table is left empty.
+        for col in &columns[..columns.len() - 1] {
+            let cf = db.cf_handle(col).expect("Unknown table name");
+            let num_entries = rand::thread_rng().gen_range(100..1000);
+            for _ in 0..num_entries {
+                let key_length = rand::thread_rng().gen_range(4..16);
+                let key = generate_random_bytes(key_length);
+                let value_length = rand::thread_rng().gen_range(4..32);
+                let value = generate_random_bytes(value_length);
+                db.put_cf(&cf, key, value).unwrap();
+            }
+        }
+        db
+    }
+
+    fn read_tables(db: &DB, columns: &[String]) -> BTreeMap<String, Vec<Entry>> {
+        columns
+            .iter()
+            .map(|col| {
+                let cf = db.cf_handle(col).expect("Unknown table name");
+                let entries = db
+                    .iterator_cf(&cf, IteratorMode::Start)
+                    .map(|entry| entry.unwrap())
+                    .collect();
+                (col.clone(), entries)
+            })
+            .collect()
+    }
+
+    fn write_checkpoint(db: &DB, columns: &[String]) -> (CheckpointManifest, Vec<Vec<u8>>) {
+        let mut manifest = CheckpointManifest::default();
+        let mut chunks = Vec::new();
+        for chunk in CheckpointWriter::new(db, columns).with_chunk_size(1024) {
+            let chunk = chunk.unwrap();
+            assert!(chunk.content.len() < 1024 + 64);
+            manifest.push(&chunk.table, *blake3::hash(&chunk.content).as_bytes());
+            chunks.push(chunk.content);
+        }
+        (manifest, chunks)
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let path = std::env::temp_dir().join("lightning_test_checkpoint_1");
+        let new_path = std::env::temp_dir().join("lightning_test_checkpoint_2");
+        let columns = vec![
+            "table2".to_owned(),
+            "table1".to_owned(),
+            "table3".to_owned(),
+            "table4".to_owned(),
+        ];
+        let db = build_random_db(&path, &columns);
+
+        let (manifest, chunks) = write_checkpoint(&db, &columns);
+        assert_eq!(manifest.len(), chunks.len());
+        assert!(manifest.len() > columns.len());
+        assert_eq!(
+            manifest.tables().collect::<Vec<_>>(),
+            vec!["table1", "table2", "table3", "table4"]
+        );
+
+        // The same state always produces the same checkpoint.
+        assert_eq!(write_checkpoint(&db, &columns).0, manifest);
+
+        // The manifest survives a roundtrip and matches its directory.
+        let decoded = CheckpointManifest::deserialize(&manifest.serialize()).unwrap();
+        assert_eq!(decoded, manifest);
+        let directory = manifest.directory();
+        assert_eq!(directory.entries.len(), manifest.len());
+        assert_eq!(directory.root_hash(), decoded.directory().root_hash());
+        assert_eq!(
+            manifest.hash(),
+            *blake3::hash(&decoded.serialize()).as_bytes()
+        );
+
+        // A database at the path that is not an import of this checkpoint is removed.
+        drop(build_random_db(&new_path, &["stale".to_owned()]));
+        let mut importer = CheckpointImporter::new(&new_path, manifest.clone(), options()).unwrap();
+        let pairs: Vec<_> = manifest.chunks().zip(chunks.iter()).collect();
+        for (chunk, content) in pairs.into_iter().rev() {
+            importer
+                .import_chunk(chunk.table, chunk.index, content)
+                .unwrap();
+        }
+        let (new_path, table_names) = importer.finish().unwrap();
+        assert_eq!(table_names.len(), columns.len());
+        assert!(
+            !DB::list_cf(&Options::default(), &new_path)
+                .unwrap()
+                .contains(&"stale".to_owned())
+        );
+
+        let cf_iter: Vec<_> = table_names
+            .iter()
+            .map(|name| ColumnFamilyDescriptor::new(name.to_owned(), Options::default()))
+            .collect();
+        let new_db = DB::open_cf_descriptors(&Options::default(), &new_path, cf_iter).unwrap();
+        assert_eq!(read_tables(&db, &columns), read_tables(&new_db, &columns));
+
+        drop(db);
+        drop(new_db);
+        std::fs::remove_dir_all(path).unwrap();
+        std::fs::remove_dir_all(new_path).unwrap();
+    }
+
+    #[test]
+    fn test_checkpoint_import_resumes() {
+        let path = std::env::temp_dir().join("lightning_test_checkpoint_3");
+        let new_path = std::env::temp_dir().join("lightning_test_checkpoint_4");
+        let columns = vec!["table1".to_owned(), "table2".to_owned()];
+        let db = build_random_db(&path, &columns);
+        let (manifest, chunks) = write_checkpoint(&db, &columns);
+
+        if new_path.exists() {
+            std::fs::remove_dir_all(&new_path).unwrap();
+        }
+        let mut importer = CheckpointImporter::new(&new_path, manifest.clone(), options()).unwrap();
+
+        // A chunk that does not match the manifest is rejected.
+        let first = manifest.chunks().next().unwrap();
+        assert!(
+            importer
+                .import_chunk(first.table, first.index, &chunks[1])
+                .is_err()
+        );
+
+        let half = chunks.len() / 2;
+        for (chunk, content) in manifest.chunks().zip(chunks.iter()).take(half) {
+            importer
+                .import_chunk(chunk.table, chunk.index, content)
+                .unwrap();
+        }
+        drop(importer);
+
+        // Reopening the importer only asks for the remaining chunks.
+        let mut importer = CheckpointImporter::new(&new_path, manifest.clone(), options()).unwrap();
+        let missing = importer
+            .missing()
+            .unwrap()
+            .into_iter()
+            .map(|chunk| (chunk.table.to_owned(), chunk.index))
+            .collect::<Vec<_>>();
+        assert_eq!(missing.len(), chunks.len() - half);
+        for (table, index) in missing {
+            let position = manifest
+                .chunks()
+                .position(|chunk| chunk.table == table && chunk.index == index)
+                .unwrap();
+            importer
+                .import_chunk(&table, index, &chunks[position])
+                .unwrap();
+        }
+        importer.finish().unwrap();
+
+        // Importing another checkpoint at the same path starts over.
+        let mut other = CheckpointManifest::default();
+        other.push("table1", *blake3::hash(&chunks[0]).as_bytes());
+        let importer = CheckpointImporter::new(&new_path, other, options()).unwrap();
+        assert_eq!(importer.missing().unwrap().len(), 1);
+        drop(importer);
+
+        drop(db);
+        std::fs::remove_dir_all(path).unwrap();
+        std::fs::remove_dir_all(new_path).unwrap();
+    }
+}
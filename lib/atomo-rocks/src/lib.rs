@@ -1,22 +1,38 @@
 //! A [`rocksdb`] storage backend implementation for [`atomo`].
 
+mod checkpoint;
 mod serialization;
 use std::fs::{self};
+use std::ops::Bound;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use atomo::batch::Operation;
-use atomo::{AtomoBuilder, DefaultSerdeBackend, StorageBackend, StorageBackendConstructor};
+use atomo::storage::RawIterator;
+use atomo::{
+    AtomoBuilder,
+    DefaultSerdeBackend,
+    Direction,
+    StorageBackend,
+    StorageBackendConstructor,
+};
+pub use checkpoint::{
+    chunk_name,
+    CheckpointChunk,
+    CheckpointImporter,
+    CheckpointManifest,
+    CheckpointWriter,
+    ChunkRef,
+    DEFAULT_CHUNK_SIZE,
+};
 use fxhash::FxHashMap;
 /// Re-export of [`rocksdb::Options`].
 pub use rocksdb::Options;
 pub use rocksdb::{Cache, Env, DB};
-use rocksdb::{ColumnFamilyDescriptor, WriteBatch};
-pub use serialization::{build_db_from_checkpoint, serialize_db};
+use rocksdb::{ColumnFamilyDescriptor, IteratorMode, ReadOptions, WriteBatch};
 
 /// Helper alias for an [`atomo::AtomoBuilder`] using a [`RocksBackendBuilder`].
-pub type AtomoBuilderWithRocks<'a, S = DefaultSerdeBackend> =
-    AtomoBuilder<RocksBackendBuilder<'a>, S>;
+pub type AtomoBuilderWithRocks<S = DefaultSerdeBackend> = AtomoBuilder<RocksBackendBuilder, S>;
 
 /// Builder for a new [`rocksdb::DB`] backend.
 ///
@@ -42,16 +58,16 @@
 /// drop(atomo);
 /// std::fs::remove_dir_all(path).unwrap();
 /// ```
-pub struct RocksBackendBuilder<'a> {
+pub struct RocksBackendBuilder {
     path: PathBuf,
     options: Options,
     columns: Vec<String>,
     column_options: FxHashMap<String, Options>,
-    checkpoint: Option<([u8; 32], &'a [u8])>,
+    checkpoint: Option<PathBuf>,
     read_only: bool,
 }
 
-impl<'a> RocksBackendBuilder<'a> {
+impl RocksBackendBuilder {
     /// Create a new builder at the given path.
     #[inline(always)]
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
@@ -80,12 +96,13 @@ pub fn with_table_option(mut self, name: &str, opts: Options) -> Self {
         self
     }
 
-    /// Provide a checkpoint from which the database will be built.
+    /// Provide the path of a checkpoint that was imported with a [`CheckpointImporter`], from
+    /// which the database will be built.
     /// Warning: providing a checkpoint will overwrite the existing database at the specified path,
     /// if there is one.
     #[inline(always)]
-    pub fn from_checkpoint(mut self, hash: [u8; 32], checkpoint: &'a [u8]) -> Self {
-        self.checkpoint = Some((hash, checkpoint));
+    pub fn from_checkpoint<P: Into<PathBuf>>(mut self, checkpoint: P) -> Self {
+        self.checkpoint = Some(checkpoint.into());
         self
     }
 
@@ -97,7 +114,7 @@ pub fn read_only(mut self) -> Self {
     }
 }
 
-impl<'a> StorageBackendConstructor for RocksBackendBuilder<'a> {
+impl StorageBackendConstructor for RocksBackendBuilder {
     type Storage = RocksBackend;
 
     type Error = anyhow::Error;
@@ -107,8 +124,26 @@ fn open_table(&mut self, name: String) {
     }
 
     fn build(mut self) -> Result<Self::Storage, Self::Error> {
-        let cf_iter: Vec<_> = self
-            .columns
+        let mut options = self.options;
+        if let Some(checkpoint) = self.checkpoint {
+            // The checkpoint was fully imported, we move it over to the actual directory.
+            if self.path.exists() {
+                fs::remove_dir_all(&self.path)?;
+            }
+            fs::rename(&checkpoint, &self.path)?;
+            // The database should exist at this point.
+            options.create_if_missing(false);
+        }
+
+        // All of the column families of an existing database have to be opened, along with the
+        // ones that were added since. The ones that are no longer used are left untouched.
+        let mut column_names = DB::list_cf(&options, &self.path).unwrap_or_default();
+        for name in &self.columns {
+            if !column_names.contains(name) {
+                column_names.push(name.clone());
+            }
+        }
+        let cf_iter: Vec<_> = column_names
             .iter()
             .map(|name| {
                 ColumnFamilyDescriptor::new(
@@ -117,56 +152,16 @@ fn build(mut self) -> Result<Self::Storage, Self::Error> {
                 )
             })
             .collect();
-        let db = match self.checkpoint {
-            Some((hash, checkpoint)) => {
-                // We try to build the db from a checkpoint in a temporary dir.
-                let mut tmp_path = self.path.clone();
-                tmp_path.pop();
-                tmp_path.push("tmp");
-                if tmp_path.exists() {
-                    fs::remove_dir_all(&tmp_path)?;
-                }
-                fs::create_dir_all(&tmp_path)?;
-                let (_db, column_names) =
-                    build_db_from_checkpoint(&tmp_path, hash, checkpoint, self.options.clone())?;
-                // If the build was successful, we move the db over to the actual directory.
-                if self.path.exists() {
-                    fs::remove_dir_all(&self.path)?;
-                }
-                fs::rename(&tmp_path, &self.path)?;
-                if tmp_path.exists() {
-                    fs::remove_dir_all(&tmp_path)?;
-                }
-                let cf_iter: Vec<_> = column_names
-                    .iter()
-                    .map(|name| {
-                        ColumnFamilyDescriptor::new(
-                            name,
-                            self.column_options.remove(name).unwrap_or_default(),
-                        )
-                    })
-                    .collect();
-                let mut options = self.options;
-                // The database should exist at this point.
-                options.create_if_missing(false);
-                if self.read_only {
-                    DB::open_cf_descriptors_read_only(&options, &self.path, cf_iter, false)?
-                } else {
-                    DB::open_cf_descriptors(&options, &self.path, cf_iter)?
-                }
-            },
-            None => {
-                if self.read_only {
-                    DB::open_cf_descriptors_read_only(&self.options, self.path, cf_iter, false)?
-                } else {
-                    DB::open_cf_descriptors(&self.options, self.path, cf_iter)?
-                }
-            },
+        let db = if self.read_only {
+            DB::open_cf_descriptors_read_only(&options, &self.path, cf_iter, false)?
+        } else {
+            DB::open_cf_descriptors(&options, &self.path, cf_iter)?
         };
 
         Ok(RocksBackend {
             columns: self.columns,
             db,
+            read_only: self.read_only,
         })
     }
 }
@@ -175,14 +170,13 @@ fn build(mut self) -> Result<Self::Storage, Self::Error> {
 pub struct RocksBackend {
     db: rocksdb::DB,
     columns: Vec<String>,
+    read_only: bool,
 }
 
 impl RocksBackend {
-    pub fn serialize(&self) -> Vec<u8> {
-        // We can safely unwrap here.
-        // This will only panic if the table names in `columns` are not consistent with the
-        // database.
-        serialize_db(&self.db, &self.columns).unwrap()
+    /// Returns a writer that produces the chunks of a checkpoint of the current state.
+    pub fn checkpoint(&self) -> CheckpointWriter<'_> {
+        CheckpointWriter::new(&self.db, &self.columns)
     }
 }
 
@@ -207,6 +201,10 @@ fn commit(&self, batch: atomo::batch::VerticalBatch) {
             .expect("failed to commit batch to rocksdb");
     }
 
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     fn keys(&self, tid: u8) -> Vec<atomo::batch::BoxedVec> {
         let cf = self.db.cf_handle(&self.columns[tid as usize]).unwrap();
         self.db
@@ -236,6 +234,42 @@ fn contains(&self, tid: u8, key: &[u8]) -> bool {
             false
         }
     }
+
+    fn range(
+        &self,
+        tid: u8,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_> {
+        let cf = self.db.cf_handle(&self.columns[tid as usize]).unwrap();
+
+        // RocksDB takes an inclusive lower bound and an exclusive upper bound, the key right
+        // after `key` is `key || 0x00`.
+        let successor = |key: &[u8]| [key, &[0]].concat();
+        let mut options = ReadOptions::default();
+        match start {
+            Bound::Included(key) => options.set_iterate_lower_bound(key),
+            Bound::Excluded(key) => options.set_iterate_lower_bound(successor(key)),
+            Bound::Unbounded => {},
+        }
+        match end {
+            Bound::Included(key) => options.set_iterate_upper_bound(successor(key)),
+            Bound::Excluded(key) => options.set_iterate_upper_bound(key),
+            Bound::Unbounded => {},
+        }
+
+        let mode = match direction {
+            Direction::Forward => IteratorMode::Start,
+            Direction::Reverse => IteratorMode::End,
+        };
+
+        Box::new(
+            self.db
+                .iterator_cf_opt(&cf, options, mode)
+                .map(|res| res.expect("failed to get entry from column family iterator")),
+        )
+    }
 }
 
 #[cfg(test)]
@@ -319,4 +353,69 @@ fn create_insert_and_query() {
         // cleanup
         std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
     }
+
+    #[test]
+    fn range_and_prefix_scans() {
+        let path: PathBuf = "test-rocksdb-range".parse().unwrap();
+        if path.exists() {
+            std::fs::remove_dir_all(path.clone()).expect("failed to remove old rocksdb");
+        }
+
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+        let rocksdb = RocksBackendBuilder::new(path.clone()).with_options(options);
+
+        let mut db = AtomoBuilderWithRocks::new(rocksdb)
+            .with_table::<(u8, u8), u8>("test")
+            .build()
+            .unwrap();
+        let table_res = db.resolve::<(u8, u8), u8>("test");
+
+        db.run(|ctx: _| {
+            let mut table_ref = table_res.get(ctx);
+            for a in 0..4 {
+                for b in 0..4 {
+                    table_ref.insert((a, b), a * 4 + b);
+                }
+            }
+        });
+
+        db.run(|ctx: _| {
+            let mut table_ref = table_res.get(ctx);
+            table_ref.remove((2, 1));
+            table_ref.insert((2, 9), 0);
+
+            let keys = |iter: atomo::RangeIterator<(u8, u8), u8, atomo::BincodeSerde>| {
+                iter.map(|(key, _)| key).collect::<Vec<_>>()
+            };
+            assert_eq!(
+                keys(table_ref.prefix(&2u8)),
+                vec![(2, 0), (2, 2), (2, 3), (2, 9)]
+            );
+            assert_eq!(
+                keys(table_ref.prefix_rev(&3u8)),
+                vec![(3, 3), (3, 2), (3, 1), (3, 0)]
+            );
+            assert_eq!(
+                keys(table_ref.range((0, 2)..=(1, 0))),
+                vec![(0, 2), (0, 3), (1, 0)]
+            );
+            assert_eq!(
+                keys(table_ref.range_rev((1, 2)..(2, 1))),
+                vec![(2, 0), (1, 3), (1, 2)]
+            );
+        });
+
+        let query_runner = db.query();
+        query_runner.run(|ctx: _| {
+            let table_ref = table_res.get(ctx);
+            assert_eq!(table_ref.range(..).count(), 16);
+            assert_eq!(table_ref.prefix(&2u8).last(), Some(((2, 9), 0)));
+        });
+
+        // cleanup
+        drop(db);
+        std::fs::remove_dir_all(path).expect("failed to remove old rocksdb");
+    }
 }
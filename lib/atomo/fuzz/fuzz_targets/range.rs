@@ -0,0 +1,103 @@
+#![no_main]
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: Input| {
+    fuzz(data);
+});
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    runs: Vec<Vec<Op>>,
+}
+
+// With bincode a tuple of bytes is encoded as the bytes themselves, so the order of the
+// serialized keys is the same as the order of the keys.
+type Key = (u8, u8);
+
+#[derive(Arbitrary, Debug, Copy, Clone)]
+enum Op {
+    Insert { key: Key, value: u64 },
+    Remove { key: Key },
+    Range { start: Key, end: Key, reverse: bool },
+    Prefix { prefix: u8, reverse: bool },
+}
+
+fn fuzz(input: Input) {
+    let mut db =
+        atomo::AtomoBuilder::<atomo::InMemoryStorage, atomo::DefaultSerdeBackend>::default()
+            .with_table::<Key, u64>("TABLE")
+            .build()
+            .unwrap();
+
+    let table = db.resolve::<Key, u64>("TABLE");
+    let query_runner = db.query();
+    let mut committed = BTreeMap::<Key, u64>::new();
+
+    for ops in input.runs {
+        // Start a query before running the update, it should not observe the update.
+        query_runner.run(|ctx| {
+            let next = db.run(|ctx| {
+                let mut table = table.get(ctx);
+                let mut local = committed.clone();
+
+                for op in ops {
+                    match op {
+                        Op::Insert { key, value } => {
+                            local.insert(key, value);
+                            table.insert(key, value);
+                        },
+                        Op::Remove { key } => {
+                            local.remove(&key);
+                            table.remove(key);
+                        },
+                        Op::Range {
+                            start,
+                            end,
+                            reverse,
+                        } => {
+                            let bounds = (Bound::Included(start), Bound::Excluded(end));
+                            if start > end {
+                                assert_eq!(table.range(bounds).next(), None);
+                                continue;
+                            }
+                            let expected = local.range(bounds).map(|(k, v)| (*k, *v));
+                            if reverse {
+                                assert!(table.range_rev(bounds).eq(expected.rev()));
+                            } else {
+                                assert!(table.range(bounds).eq(expected));
+                            }
+                        },
+                        Op::Prefix { prefix, reverse } => {
+                            let expected = local
+                                .range((prefix, 0)..=(prefix, u8::MAX))
+                                .map(|(k, v)| (*k, *v));
+                            if reverse {
+                                assert!(table.prefix_rev(&prefix).eq(expected.rev()));
+                            } else {
+                                assert!(table.prefix(&prefix).eq(expected));
+                            }
+                        },
+                    }
+                }
+
+                local
+            });
+
+            let table = table.get(ctx);
+            assert!(table.range(..).eq(committed.clone().into_iter()));
+            assert!(table.range_rev(..).eq(committed.clone().into_iter().rev()));
+
+            committed = next;
+        });
+    }
+
+    query_runner.run(|ctx| {
+        let table = table.get(ctx);
+        assert!(table.range(..).eq(committed.into_iter()));
+    });
+}
@@ -0,0 +1,285 @@
+use std::any::Any;
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use rkyv::ser::serializers::AllocSerializer;
+use rkyv::validation::validators::DefaultValidator;
+use rkyv::{AlignedVec, Archive, CheckBytes, Infallible};
+use serde::de::{DeserializeOwned, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::serder::{BincodeSerde, SerdeBackend};
+use crate::table::TableRef;
+use crate::StorageBackend;
+
+/// A value that is stored as an rkyv archive, so that it can be read with
+/// [`TableRef::get_archived`] instead of being deserialized.
+///
+/// The archive is written as a byte string of the serde backend, so this can be used with any
+/// backend, but only a backend that implements [`ArchiveBackend`] can read it in place.
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Rkyv<T>(pub T);
+
+impl<T> Serialize for Rkyv<T>
+where
+    T: rkyv::Serialize<AllocSerializer<256>>,
+{
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        let bytes = rkyv::to_bytes::<_, 256>(&self.0)
+            .map_err(|_| serde::ser::Error::custom("Failed to archive the value."))?;
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Rkyv<T>
+where
+    T: Archive,
+    T::Archived: for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<T, Infallible>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArchiveVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for ArchiveVisitor<T>
+        where
+            T: Archive,
+            T::Archived:
+                for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<T, Infallible>,
+        {
+            type Value = Rkyv<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an rkyv archive")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                ArchivedValue::<T>::try_new(bytes)
+                    .map(|archived| Rkyv(archived.deserialize()))
+                    .map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_bytes(ArchiveVisitor(PhantomData))
+    }
+}
+
+/// A serde backend that can locate the archive of an [`Rkyv`] value in its serialized form.
+pub trait ArchiveBackend: SerdeBackend {
+    /// Returns the bytes of the archive from a serialized [`Rkyv`] value, or an error if the
+    /// slice is not a serialized byte string.
+    fn archive_bytes(slice: &[u8]) -> Result<&[u8], InvalidArchive>;
+}
+
+/// The error returned when a stored value is not a valid archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidArchive;
+
+/// The serde backend for databases whose hot values are read with rkyv.
+///
+/// Keys and the values that are not wrapped in [`Rkyv`] are encoded exactly like
+/// [`BincodeSerde`], so the keys keep their order and a table can be moved to this backend
+/// without rewriting the ones that are not archived.
+pub struct RkyvSerde;
+
+impl SerdeBackend for RkyvSerde {
+    fn serialize<T>(value: &T) -> Vec<u8>
+    where
+        T: Serialize,
+    {
+        BincodeSerde::serialize(value)
+    }
+
+    fn deserialize<T>(slice: &[u8]) -> T
+    where
+        T: DeserializeOwned,
+    {
+        BincodeSerde::deserialize(slice)
+    }
+}
+
+impl ArchiveBackend for RkyvSerde {
+    fn archive_bytes(slice: &[u8]) -> Result<&[u8], InvalidArchive> {
+        // Bincode writes a byte string after its length as a `u64`.
+        if slice.len() < 8 {
+            return Err(InvalidArchive);
+        }
+        let (len, bytes) = slice.split_at(8);
+        if u64::from_le_bytes(len.try_into().unwrap()) != bytes.len() as u64 {
+            return Err(InvalidArchive);
+        }
+        Ok(bytes)
+    }
+}
+
+impl fmt::Display for InvalidArchive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The value is not a valid archive.")
+    }
+}
+
+impl std::error::Error for InvalidArchive {}
+
+/// An owned archived value, which dereferences to the archived type without deserializing it.
+///
+/// The archive is copied to an aligned buffer and validated once when the value is created, so
+/// reading it is cheaper than deserializing it, but it is not free.
+pub struct ArchivedValue<T: Archive> {
+    bytes: AlignedVec,
+    value: PhantomData<T>,
+}
+
+impl<T> ArchivedValue<T>
+where
+    T: Archive,
+    T::Archived: for<'a> CheckBytes<DefaultValidator<'a>>,
+{
+    /// Copy the archive to an aligned buffer and validate it, returns an error if the bytes are
+    /// not a valid archive of `T`.
+    fn try_new(bytes: &[u8]) -> Result<Self, InvalidArchive> {
+        let mut aligned = AlignedVec::with_capacity(bytes.len());
+        aligned.extend_from_slice(bytes);
+        rkyv::check_archived_root::<T>(&aligned).map_err(|_| InvalidArchive)?;
+        Ok(Self {
+            bytes: aligned,
+            value: PhantomData,
+        })
+    }
+
+    /// Deserialize the archived value.
+    pub fn deserialize(&self) -> T
+    where
+        T::Archived: rkyv::Deserialize<T, Infallible>,
+    {
+        rkyv::Deserialize::deserialize(self.deref(), &mut Infallible).unwrap()
+    }
+}
+
+impl<T: Archive> Deref for ArchivedValue<T> {
+    type Target = T::Archived;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: The archive was validated when this value was created.
+        unsafe { rkyv::archived_root::<T>(&self.bytes) }
+    }
+}
+
+impl<'selector, K, T, B: StorageBackend, S: ArchiveBackend> TableRef<'selector, K, Rkyv<T>, B, S>
+where
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    T: Archive + rkyv::Serialize<AllocSerializer<256>> + Any,
+    T::Archived: for<'a> CheckBytes<DefaultValidator<'a>> + rkyv::Deserialize<T, Infallible>,
+{
+    /// Returns the archived value associated with the provided key, which can be read without
+    /// deserializing the entire value. If the key doesn't exits in the table [`None`] is
+    /// returned, and if the stored value is not a valid archive of `T` an error is returned.
+    pub fn get_archived(
+        &self,
+        key: impl Borrow<K>,
+    ) -> Result<Option<ArchivedValue<T>>, InvalidArchive> {
+        let k = S::serialize(key.borrow()).into_boxed_slice();
+        self.get_raw(&k)
+            .map(|value| ArchivedValue::try_new(S::archive_bytes(&value)?))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rkyv::{Archive, Deserialize, Serialize};
+
+    use super::*;
+    use crate::batch::{Operation, VerticalBatch};
+    use crate::{AtomoBuilder, InMemoryStorage, StorageBackendConstructor};
+
+    #[derive(Archive, Serialize, Deserialize, Debug, Clone, PartialEq)]
+    #[archive(check_bytes)]
+    struct Info {
+        nonce: u64,
+        stake: u128,
+        domain: String,
+    }
+
+    fn info(nonce: u64) -> Info {
+        Info {
+            nonce,
+            stake: nonce as u128 * 1000,
+            domain: format!("node-{nonce}.example"),
+        }
+    }
+
+    #[test]
+    fn archived_values_should_be_readable_in_place() {
+        let mut db = AtomoBuilder::<InMemoryStorage, RkyvSerde>::default()
+            .with_table::<u32, Rkyv<Info>>("INFO")
+            .with_table::<u32, u64>("PLAIN")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u32, Rkyv<Info>>("INFO");
+            table.insert(1, Rkyv(info(1)));
+            table.insert(2, Rkyv(info(2)));
+            ctx.get_table::<u32, u64>("PLAIN").insert(1, 17);
+
+            // Uncommitted values are readable as well.
+            assert_eq!(table.get_archived(1).unwrap().unwrap().nonce, 1);
+        });
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u32, Rkyv<Info>>("INFO");
+            let archived = table.get_archived(2).unwrap().unwrap();
+            assert_eq!(archived.nonce, 2);
+            assert_eq!(archived.stake, 2000);
+            assert_eq!(archived.domain.as_str(), "node-2.example");
+            assert_eq!(archived.deserialize(), info(2));
+            assert!(table.get_archived(3).unwrap().is_none());
+
+            assert_eq!(table.get(1), Some(Rkyv(info(1))));
+            assert_eq!(ctx.get_table::<u32, u64>("PLAIN").get(1), Some(17));
+        });
+    }
+
+    #[test]
+    fn invalid_archives_should_be_an_error() {
+        let raw = |value: &[u8]| value.to_vec().into_boxed_slice();
+        let mut storage = InMemoryStorage::default();
+        storage.open_table("INFO".into());
+        let mut batch = VerticalBatch::new(1);
+        let entries = batch.get_mut(0);
+        // Shorter than the length of a byte string.
+        entries.insert(
+            raw(&RkyvSerde::serialize(&1u32)),
+            Operation::Insert(raw(&[1, 2])),
+        );
+        // A byte string whose length does not match.
+        let mut value = RkyvSerde::serialize(&vec![0u8; 4]);
+        value.pop();
+        entries.insert(
+            raw(&RkyvSerde::serialize(&2u32)),
+            Operation::Insert(raw(&value)),
+        );
+        // A byte string that is not an archive.
+        let value = RkyvSerde::serialize(&vec![0xffu8; 3]);
+        entries.insert(
+            raw(&RkyvSerde::serialize(&3u32)),
+            Operation::Insert(raw(&value)),
+        );
+        storage.commit(batch);
+
+        let db = AtomoBuilder::<_, RkyvSerde>::new(storage)
+            .with_table::<u32, Rkyv<Info>>("INFO")
+            .build()
+            .unwrap()
+            .query();
+
+        db.run(|ctx| {
+            let table = ctx.get_table::<u32, Rkyv<Info>>("INFO");
+            for key in 1..=3 {
+                assert_eq!(table.get_archived(key).err(), Some(InvalidArchive));
+            }
+            assert!(table.get_archived(4).unwrap().is_none());
+        });
+    }
+}
@@ -1,185 +1,147 @@
-// use std::{
-//     hint::black_box,
-//     sync::atomic::{AtomicBool, AtomicUsize},
-//     time::Duration,
-// };
-//
-// use atomo::{Atomo, Context, SerdeBackend};
-// use rand::Rng;
-//
-// type UserId = u8;
-//
-// fn mint<S: SerdeBackend>(ctx: &mut Context<UserId, u128, S>, user: UserId, amount: u128) -> u128
-// {     let balance = ctx.get(&user).map(|s| *s).unwrap_or(0);
-//     ctx.insert(user, balance + amount);
-//     balance + amount
-// }
-//
-// fn balance<S: SerdeBackend>(ctx: &mut Context<UserId, u128, S>, user: UserId) -> u128 {
-//     ctx.get(&user).map(|s| *s).unwrap_or(0)
-// }
-//
-// fn main() {
-//     let (mut u, q) = Atomo::<UserId, u128>::new().split();
-//
-//     let num_query_threads: usize = get_arg("-t").unwrap_or(4);
-//     let num_query_per_thread: usize = get_arg("-q").unwrap_or(1_000_000);
-//     let num_mint_per_update: usize = get_arg("-u").unwrap_or(16);
-//
-//     println!("To configure use `-t [num threads] -q [num query per thread] -u [num tx per
-// update]");     println!("STARTING BENCHMARK");
-//     println!("NUM THREADS      = {}", num_query_threads);
-//     println!("NUM QUERY/THREAD = {}", num_query_per_thread);
-//     println!("NUM TX/UPDATE = {}", num_mint_per_update);
-//     println!("\n");
-//
-//     static PENDING: AtomicUsize = AtomicUsize::new(0);
-//     static START: AtomicBool = AtomicBool::new(false);
-//
-//     PENDING.store(num_query_threads, std::sync::atomic::Ordering::Relaxed);
-//
-//     let mut handles = Vec::<std::thread::JoinHandle<Duration>>::new();
-//
-//     for _ in 0..num_query_threads {
-//         let q = q.clone();
-//         let handle = std::thread::spawn(move || {
-//             while !START.load(std::sync::atomic::Ordering::Relaxed) {
-//                 std::hint::spin_loop()
-//             }
-//
-//             let mut rng: u32 = rand::thread_rng().gen();
-//
-//             let now = std::time::Instant::now();
-//
-//             for _ in 0..num_query_per_thread {
-//                 rng = rng.wrapping_mul(48271) % 0x7fffffff;
-//                 q.run(|ctx| black_box(balance(ctx, rng as u8)));
-//             }
-//
-//             let duration = now.elapsed();
-//             PENDING.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
-//             duration
-//         });
-//
-//         handles.push(handle);
-//     }
-//
-//     let mut rng: u32 = rand::thread_rng().gen();
-//
-//     // signal every threads that's waiting to start.
-//     START.store(true, std::sync::atomic::Ordering::Relaxed);
-//
-//     let now = std::time::Instant::now();
-//     let updates = if num_query_threads == 0 {
-//         for _ in 0..num_query_per_thread {
-//             let r = rng;
-//             rng = u.run(move |ctx| {
-//                 let mut rng = r;
-//                 for _ in 0..num_mint_per_update {
-//                     rng = rng.wrapping_mul(48271) % 0x7fffffff;
-//                     black_box(mint(ctx, rng as u8, 17));
-//                 }
-//                 rng
-//             });
-//         }
-//
-//         num_query_per_thread
-//     } else {
-//         let mut updates = 0;
-//         loop {
-//             let r = rng;
-//             rng = u.run(move |ctx| {
-//                 let mut rng = r;
-//                 for _ in 0..num_mint_per_update {
-//                     rng = rng.wrapping_mul(48271) % 0x7fffffff;
-//                     black_box(mint(ctx, rng as u8, 17));
-//                 }
-//                 rng
-//             });
-//             updates += 1;
-//             if updates % 10 == 0 && PENDING.load(std::sync::atomic::Ordering::Relaxed) == 0 {
-//                 break;
-//             }
-//         }
-//         updates
-//     };
-//     let updates_duration = now.elapsed();
-//
-//     // Compute stuff
-//     let query_times: Vec<Duration> = handles.into_iter().map(|q| q.join().unwrap()).collect();
-//     let total_query_time: Duration = query_times.iter().sum();
-//
-//     println!("<-UPDATES--------------------------------->");
-//     print_report(updates_duration, updates);
-//
-//     println!("<-TOTAL NUM OF MINTS---------------------->");
-//     print_report(updates_duration, updates * num_mint_per_update);
-//
-//     if num_query_threads > 0 {
-//         println!("<-QUERIES [REAL TIME]--------------------->");
-//         print_report(updates_duration, num_query_threads * num_query_per_thread);
-//     }
-//
-//     if num_query_threads > 1 {
-//         println!("<-QUERIES [CPU TIME]---------------------->");
-//         print_report(total_query_time, num_query_threads * num_query_per_thread);
-//
-//         println!("<-QUERIES [EACH THREAD]------------------->");
-//         print_report_vec(&query_times, num_query_per_thread);
-//     }
-// }
-//
-// fn print_report(duration: Duration, count: usize) {
-//     print_report_vec(&[duration], count);
-// }
-//
-// fn print_report_vec(duration: &[Duration], count: usize) {
-//     println!(
-//         "  Count = {}",
-//         format_vec(duration.iter(), |_| format!("{count}"))
-//     );
-//     println!(
-//         "  Took  = {}",
-//         format_vec(duration.iter(), |d| format!("{:.2}s", d.as_secs_f64()))
-//     );
-//     println!(
-//         "  Op/s  = {}",
-//         format_vec(duration.iter(), |d| format!("{:.2}", op_per_sec(d, count)))
-//     );
-//     println!(
-//         "  t/Op  = {}",
-//         format_vec(duration.iter(), |d| format!(
-//             "{:.3}μs",
-//             (d.as_micros() as f64) / (count as f64)
-//         ))
-//     );
-// }
-//
-// fn format_vec<T>(v: impl Iterator<Item = T>, f: impl Fn(&T) -> String) -> String {
-//     let mut result = String::new();
-//     for item in v {
-//         result.push_str(&format!("{: ^12}", f(&item)));
-//     }
-//     result
-// }
-//
-// fn op_per_sec(duration: &Duration, count: usize) -> usize {
-//     let duration_sec = duration.as_secs_f64();
-//     ((count as f64) / duration_sec) as usize
-// }
-//
-// fn get_arg(name: &str) -> Option<usize> {
-//     let mut args = std::env::args();
-//
-//     while let Some(arg) = args.next() {
-//         if arg == name {
-//             let value = args.next().expect("invalid cmd");
-//             let number = value.parse::<usize>().expect("invalid arg");
-//             return Some(number);
-//         }
-//     }
-//
-//     None
-// }
-//
-fn main() {}
+//! Compares the cost of reading values with the bincode and the rkyv serde backends.
+//!
+//! Run with `cargo run --release --features rkyv --bin bench -- [-n entries] [-q queries]`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use atomo::{
+    AtomoBuilder,
+    BincodeSerde,
+    InMemoryStorage,
+    QueryPerm,
+    Rkyv,
+    RkyvSerde,
+    SerdeBackend,
+};
+use serde::{Deserialize, Serialize};
+
+type Atomo<S> = atomo::Atomo<QueryPerm, InMemoryStorage, S>;
+
+/// A value shaped like the node info of the application state.
+#[derive(Serialize, Deserialize, rkyv::Archive, rkyv::Serialize, rkyv::Deserialize, Clone)]
+#[archive(check_bytes)]
+struct Node {
+    owner: [u8; 20],
+    public_key: [u8; 32],
+    consensus_key: Vec<u8>,
+    staked_since: u64,
+    stake: u128,
+    locked: u128,
+    domain: String,
+    ports: [u16; 8],
+    nonce: u64,
+}
+
+fn node(index: u32) -> Node {
+    Node {
+        owner: [index as u8; 20],
+        public_key: [index as u8; 32],
+        consensus_key: vec![index as u8; 96],
+        staked_since: index as u64,
+        stake: index as u128 * 1000,
+        locked: 0,
+        domain: format!("node-{index}.example.com"),
+        ports: [4000; 8],
+        nonce: index as u64,
+    }
+}
+
+fn main() {
+    let num_entries: u32 = get_arg("-n").unwrap_or(10_000) as u32;
+    let num_queries: usize = get_arg("-q").unwrap_or(1_000_000);
+
+    println!("To configure use `-n [num entries] -q [num queries]`");
+    println!("STARTING BENCHMARK");
+    println!("NUM ENTRIES = {}", num_entries);
+    println!("NUM QUERIES = {}", num_queries);
+    println!("\n");
+
+    let bincode = build::<BincodeSerde, Node>(num_entries, node);
+    let rkyv = build::<RkyvSerde, Rkyv<Node>>(num_entries, |index| Rkyv(node(index)));
+
+    println!("<-BINCODE [GET]--------------------------->");
+    let duration = bench(num_entries, num_queries, |key| {
+        bincode.run(|ctx| {
+            let table = ctx.get_table::<u32, Node>("NODE");
+            table.get(key).map(|node| node.nonce)
+        })
+    });
+    print_report(duration, num_queries);
+
+    println!("<-RKYV [GET]------------------------------>");
+    let duration = bench(num_entries, num_queries, |key| {
+        rkyv.run(|ctx| {
+            let table = ctx.get_table::<u32, Rkyv<Node>>("NODE");
+            table.get(key).map(|node| node.0.nonce)
+        })
+    });
+    print_report(duration, num_queries);
+
+    println!("<-RKYV [GET ARCHIVED]--------------------->");
+    let duration = bench(num_entries, num_queries, |key| {
+        rkyv.run(|ctx| {
+            let table = ctx.get_table::<u32, Rkyv<Node>>("NODE");
+            table.get_archived(key).unwrap().map(|node| node.nonce)
+        })
+    });
+    print_report(duration, num_queries);
+}
+
+fn build<S: SerdeBackend, V>(num_entries: u32, f: impl Fn(u32) -> V) -> Atomo<S>
+where
+    V: Serialize + serde::de::DeserializeOwned + 'static,
+{
+    let mut db = AtomoBuilder::<InMemoryStorage, S>::default()
+        .with_table::<u32, V>("NODE")
+        .build()
+        .unwrap();
+
+    db.run(|ctx| {
+        let mut table = ctx.get_table::<u32, V>("NODE");
+        for index in 0..num_entries {
+            table.insert(index, f(index));
+        }
+    });
+
+    db.query()
+}
+
+fn bench(num_entries: u32, num_queries: usize, f: impl Fn(u32) -> Option<u64>) -> Duration {
+    let mut rng: u32 = rand::random();
+    let now = Instant::now();
+    for _ in 0..num_queries {
+        rng = rng.wrapping_mul(48271) % 0x7fffffff;
+        black_box(f(rng % num_entries));
+    }
+    now.elapsed()
+}
+
+fn print_report(duration: Duration, count: usize) {
+    println!("  Count = {count}");
+    println!("  Took  = {:.2}s", duration.as_secs_f64());
+    println!("  Op/s  = {}", op_per_sec(&duration, count));
+    println!(
+        "  t/Op  = {:.3}μs",
+        (duration.as_nanos() as f64) / (count as f64) / 1000.0
+    );
+}
+
+fn op_per_sec(duration: &Duration, count: usize) -> usize {
+    let duration_sec = duration.as_secs_f64();
+    ((count as f64) / duration_sec) as usize
+}
+
+fn get_arg(name: &str) -> Option<usize> {
+    let mut args = std::env::args();
+
+    while let Some(arg) = args.next() {
+        if arg == name {
+            let value = args.next().expect("invalid cmd");
+            let number = value.parse::<usize>().expect("invalid arg");
+            return Some(number);
+        }
+    }
+
+    None
+}
@@ -1,15 +1,18 @@
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::hash::Hash;
 use std::iter::Extend;
+use std::ops::Bound;
 use std::sync::Arc;
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::batch::{Operation, VerticalBatch};
 use crate::db::{Atomo, TableId, UpdatePerm};
+use crate::index::IndexMeta;
 use crate::inner::AtomoInner;
 use crate::serder::SerdeBackend;
-use crate::storage::{InMemoryStorage, StorageBackendConstructor};
+use crate::storage::{Direction, InMemoryStorage, StorageBackendConstructor};
 use crate::table::TableMeta;
 use crate::{DefaultSerdeBackend, StorageBackend};
 
@@ -50,6 +53,48 @@ pub fn with_table<K, V>(mut self, name: impl ToString) -> Self
         self
     }
 
+    /// Declare a secondary index with the given name over the entries of a table, which maps
+    /// every index key returned by `extract` for an entry to the key of that entry.
+    ///
+    /// The index is stored in its own table and is updated in the same batch as every change to
+    /// the indexed table, so it can never get out of sync with it. It can be read using
+    /// [`crate::TableSelector::get_index`] but not modified directly. If the index is declared on
+    /// a database that already has entries in the table, it is built upon opening, unless the
+    /// storage is read-only.
+    ///
+    /// # Panics
+    ///
+    /// If the table is not already defined with the same key-value types using a prior call to
+    /// `with_table`, or another table with the name of the index is already defined.
+    #[must_use = "Builder is incomplete."]
+    pub fn with_index<K, V, I, F>(mut self, name: impl ToString, table: &str, extract: F) -> Self
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Any,
+        V: Serialize + DeserializeOwned + Any,
+        I: Hash + Eq + Serialize + DeserializeOwned + Any,
+        F: Fn(&K, &V) -> Vec<I> + Send + Sync + 'static,
+    {
+        let tid = *self
+            .atomo
+            .table_name_to_id
+            .get(table)
+            .unwrap_or_else(|| panic!("Table {table} is not defined."));
+        let meta = &self.atomo.tables[tid as usize];
+        assert!(
+            meta.k_id == TypeId::of::<K>() && meta.v_id == TypeId::of::<V>(),
+            "Could not index table '{table}' with mismatched key-value types."
+        );
+
+        let name = name.to_string();
+        let index = self.atomo.tables.len() as TableId;
+        self.with_table_internal_non_generic_part(name.clone());
+        self.atomo.tables.push(TableMeta::new::<(I, K), ()>(name));
+        self.atomo
+            .indexes
+            .push(IndexMeta::new::<K, V, I, S, F>(tid, index, extract));
+        self
+    }
+
     /// Performs the common operation for opening a table that is not depended
     /// on generic types to produce smaller code when `with_table` is inlined.
     fn with_table_internal_non_generic_part(&mut self, name: String) {
@@ -88,6 +133,7 @@ pub fn enable_iter(mut self, name: &str) -> Self {
             self.atomo
                 .snapshot_list
                 .get_metadata_mut()
+                .keys
                 .enable(*index as usize);
             return self;
         }
@@ -95,6 +141,18 @@ pub fn enable_iter(mut self, name: &str) -> Self {
         panic!("Table {name} is not defined.");
     }
 
+    /// Enable the state tree, which maintains a Merkle tree over the content of every table
+    /// and allows querying the state root and generating [`crate::StateProof`]s for the keys.
+    ///
+    /// The tree is built from every key in the storage when the database is opened, and every
+    /// update from there on recomputes the path of the changed keys. Building it takes a full scan
+    /// of the storage, so it should not be enabled on a database that is opened only to be read.
+    #[must_use = "Builder is incomplete."]
+    pub fn enable_state_tree(mut self) -> Self {
+        self.atomo.state_tree = true;
+        self
+    }
+
     /// Finish the construction and returns an [`Atomo`] with [`UpdatePerm`] permission.
     #[must_use = "Creating a Atomo without using it is probably a mistake."]
     pub fn build(self) -> Result<Atomo<UpdatePerm, B::Storage, S>, B::Error> {
@@ -105,6 +163,31 @@ pub fn build(self) -> Result<Atomo<UpdatePerm, B::Storage, S>, B::Error> {
     pub(crate) fn build_inner(mut self) -> Result<AtomoInner<B::Storage, S>, B::Error> {
         let storage = self.constructor.build()?;
 
+        // Build the indexes that were declared on a table which already has entries. A read-only
+        // storage can not be written to, so its indexes are left as they are.
+        let count = self.atomo.tables.len();
+        let mut batch = VerticalBatch::new(count);
+        for index in &self.atomo.indexes {
+            let all =
+                |tid| storage.range(tid, Bound::Unbounded, Bound::Unbounded, Direction::Forward);
+            if storage.is_read_only() || all(index.index).next().is_some() {
+                continue;
+            }
+
+            let entries = batch.get_mut(index.index as usize);
+            for (key, value) in all(index.table) {
+                for entry in (index.extract)(&key, &value) {
+                    entries.insert(
+                        entry,
+                        Operation::Insert(S::serialize(&()).into_boxed_slice()),
+                    );
+                }
+            }
+        }
+        if (0..count).any(|tid| !batch.get(tid).is_empty()) {
+            storage.commit(batch);
+        }
+
         // Upon opening read every key for the tables that have enabled the
         // iterator.
         //
@@ -114,12 +197,12 @@ pub(crate) fn build_inner(mut self) -> Result<AtomoInner<B::Storage, S>, B::Erro
         // So we just iterate through every table and attempt to *update* the
         // list of keys if present.
 
-        let vertical_keys = self.atomo.snapshot_list.get_metadata_mut();
+        let metadata = self.atomo.snapshot_list.get_metadata_mut();
 
         let count = self.atomo.tables.len() as u8;
 
         for tid in 0..count {
-            vertical_keys.update(tid, |value| {
+            metadata.keys.update(tid, |value| {
                 // TODO(qti3e): The extend method here does not do anything smart and just does
                 // several inserts and each insert is O(log n). And we know this is the initial
                 // change and nothing is referring to this im instance. So.. we can do better.
@@ -127,7 +210,13 @@ pub(crate) fn build_inner(mut self) -> Result<AtomoInner<B::Storage, S>, B::Erro
             });
         }
 
-        Ok(self.atomo.swap_persistance(storage))
+        let mut atomo = self.atomo.swap_persistance(storage);
+        if atomo.state_tree {
+            let tree = atomo.build_state_tree();
+            atomo.snapshot_list.get_metadata_mut().tree = Some(tree);
+        }
+
+        Ok(atomo)
     }
 }
 
@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::collections::BTreeMap;
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -6,8 +7,12 @@
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::inner::AtomoInner;
+use crate::batch::{Operation, VerticalBatch};
+use crate::inner::{AtomoInner, SnapshotMetadata};
+use crate::pinned::AtomoSnapshot;
+use crate::range::Overlay;
 use crate::serder::SerdeBackend;
+use crate::snapshot::Snapshot;
 use crate::storage::{InMemoryStorage, StorageBackend};
 use crate::table::{ResolvedTableReference, TableSelector};
 use crate::DefaultSerdeBackend;
@@ -38,13 +43,15 @@
 /// you can clone it anytime (and the clone implementation is rather cheap.)
 pub struct Atomo<O, B: StorageBackend = InMemoryStorage, S: SerdeBackend = DefaultSerdeBackend> {
     inner: Arc<AtomoInner<B, S>>,
+    /// The version this instance is pinned to, see [`Atomo::snapshot`].
+    pinned: Option<Snapshot<VerticalBatch, SnapshotMetadata>>,
     ownership: PhantomData<O>,
 }
 
 // only implement the clone for the query permission.
 impl<B: StorageBackend, S: SerdeBackend> Clone for Atomo<QueryPerm, B, S> {
     fn clone(&self) -> Self {
-        Self::new(self.inner.clone())
+        self.query()
     }
 }
 
@@ -53,13 +60,38 @@ impl<O, B: StorageBackend, S: SerdeBackend> Atomo<O, B, S> {
     pub(crate) fn new(inner: Arc<AtomoInner<B, S>>) -> Self {
         Self {
             inner,
+            pinned: None,
             ownership: PhantomData,
         }
     }
 
-    /// Returns a query end for this table.
+    /// Returns a query end for this table. If this instance is pinned to a version the query end
+    /// is pinned to the same version.
     pub fn query(&self) -> Atomo<QueryPerm, B, S> {
-        Atomo::new(self.inner.clone())
+        Atomo {
+            inner: self.inner.clone(),
+            pinned: self.pinned.clone(),
+            ownership: PhantomData,
+        }
+    }
+
+    /// Returns a handle that pins the current version of the data, queries that are run through
+    /// it do not see the updates that are committed afterwards. See [`AtomoSnapshot`].
+    pub fn snapshot(&self) -> AtomoSnapshot<B, S> {
+        AtomoSnapshot::new(Atomo {
+            inner: self.inner.clone(),
+            pinned: Some(self.current()),
+            ownership: PhantomData,
+        })
+    }
+
+    /// Returns the version of the data that this instance reads.
+    #[inline]
+    fn current(&self) -> Snapshot<VerticalBatch, SnapshotMetadata> {
+        match &self.pinned {
+            Some(snapshot) => snapshot.clone(),
+            None => self.inner.snapshot_list.current(),
+        }
     }
 
     /// Resolve a table with the given name and key-value types.
@@ -76,6 +108,16 @@ pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K,
     {
         self.inner.resolve::<K, V>(name)
     }
+
+    /// Returns the current state root, or `None` if the state tree is not enabled. See
+    /// [`crate::AtomoBuilder::enable_state_tree`].
+    pub fn state_root(&self) -> Option<[u8; 32]> {
+        self.current()
+            .get_metadata()
+            .tree
+            .as_ref()
+            .map(|tree| tree.root())
+    }
 }
 
 impl<B: StorageBackend, S: SerdeBackend> Atomo<QueryPerm, B, S> {
@@ -84,7 +126,7 @@ pub fn run<F, R>(&self, query: F) -> R
     where
         F: FnOnce(&mut TableSelector<B, S>) -> R,
     {
-        let mut selector = TableSelector::new(self.inner.clone());
+        let mut selector = TableSelector::with_snapshot(self.inner.clone(), self.current());
         query(&mut selector)
     }
 }
@@ -97,14 +139,67 @@ pub fn run<F, R>(&mut self, mutation: F) -> R
     {
         let mut selector = TableSelector::new(self.inner.clone());
         let response = mutation(&mut selector);
+        self.commit(selector, |_, _| ());
+        response
+    }
 
+    /// Run an update on the database and return the changes that revert it alongside the
+    /// response of the mutation.
+    ///
+    /// The reverse changes map the name of every modified table to the previous value of each
+    /// key that was changed, or `None` if the key did not exist before this update. They can be
+    /// served on top of the new state using [`crate::OverlayStorage`] to look at the previous
+    /// state.
+    pub fn run_with_inverse<F, R>(&mut self, mutation: F) -> (R, BTreeMap<String, Overlay>)
+    where
+        F: FnOnce(&mut TableSelector<B, S>) -> R,
+    {
+        let mut selector = TableSelector::new(self.inner.clone());
+        let response = mutation(&mut selector);
+        let changes = self.commit(selector, |inner, inverse| {
+            let mut changes = BTreeMap::new();
+            for (tid, meta) in inner.tables.iter().enumerate() {
+                let table = inverse.get(tid);
+                if table.is_empty() {
+                    continue;
+                }
+
+                let overlay = table
+                    .iter()
+                    .map(|(key, op)| match op {
+                        Operation::Insert(value) => (key.clone(), Some(value.clone())),
+                        Operation::Remove => (key.clone(), None),
+                    })
+                    .collect::<Overlay>();
+                changes.insert(meta.name.clone(), overlay);
+            }
+            changes
+        });
+        (response, changes)
+    }
+
+    /// Commit the changes of the given selector, the provided closure is called with the inverse
+    /// of the changes before they are applied.
+    fn commit<F, T>(&mut self, selector: TableSelector<B, S>, inspect: F) -> T
+    where
+        F: FnOnce(&AtomoInner<B, S>, &VerticalBatch) -> T,
+    {
         let (batch, keys) = selector.into_raw();
+        let tree = self
+            .inner
+            .snapshot_list
+            .current()
+            .get_metadata()
+            .tree
+            .as_ref()
+            .map(|tree| tree.apply(&batch));
         let inverse = self.inner.compute_inverse(&batch);
-        self.inner.snapshot_list.push(inverse, keys, || {
+        let output = inspect(&self.inner, &inverse);
+        let metadata = SnapshotMetadata { keys, tree };
+        self.inner.snapshot_list.push(inverse, metadata, || {
             self.inner.perform_batch(batch);
         });
-
-        response
+        output
     }
 
     /// Return a reference to the storage backend. Modifying the state directly and going behind
@@ -0,0 +1,194 @@
+use std::any::Any;
+use std::hash::Hash;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::batch::BoxedVec;
+use crate::db::TableId;
+use crate::serder::SerdeBackend;
+use crate::table::TableRef;
+use crate::StorageBackend;
+
+/// The function that computes the raw keys of the index entries of a raw key-value pair.
+pub(crate) type RawExtractor = Box<dyn Fn(&[u8], &[u8]) -> Vec<BoxedVec> + Send + Sync>;
+
+/// A secondary index derived from the entries of a table. See
+/// [`crate::AtomoBuilder::with_index`].
+pub struct IndexMeta {
+    /// The table the index is derived from.
+    pub table: TableId,
+    /// The table the entries of the index are stored in.
+    pub index: TableId,
+    /// Computes the raw keys of the entries of the index for an entry of the table.
+    pub(crate) extract: RawExtractor,
+}
+
+impl IndexMeta {
+    /// Create the metadata of an index whose entries are derived from the typed key-value pairs
+    /// of the table by the given function. Each index entry is stored under the serialized
+    /// `(I, K)` pair with an empty value.
+    pub(crate) fn new<K, V, I, S, F>(table: TableId, index: TableId, extract: F) -> Self
+    where
+        K: Serialize + DeserializeOwned,
+        V: DeserializeOwned,
+        I: Serialize,
+        S: SerdeBackend,
+        F: Fn(&K, &V) -> Vec<I> + Send + Sync + 'static,
+    {
+        let extract = move |key: &[u8], value: &[u8]| {
+            let key = S::deserialize::<K>(key);
+            let value = S::deserialize::<V>(value);
+            extract(&key, &value)
+                .into_iter()
+                .map(|entry| S::serialize(&(entry, &key)).into_boxed_slice())
+                .collect()
+        };
+
+        Self {
+            table,
+            index,
+            extract: Box::new(extract),
+        }
+    }
+}
+
+/// A reference to a secondary index inside an execution context (i.e
+/// [`crate::TableSelector`]). An index maps each index key `I` to the keys `K` of the entries of
+/// the table it is derived from.
+///
+/// An index can only be read, it is updated as part of every change to its table.
+pub struct IndexRef<
+    'selector,
+    I: Hash + Eq + Serialize + DeserializeOwned + Any,
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    B: StorageBackend,
+    S: SerdeBackend,
+> {
+    pub(crate) table: TableRef<'selector, (I, K), (), B, S>,
+}
+
+impl<'selector, I, K, B: StorageBackend, S: SerdeBackend> IndexRef<'selector, I, K, B, S>
+where
+    I: Hash + Eq + Serialize + DeserializeOwned + Any,
+    K: Hash + Eq + Serialize + DeserializeOwned + Any,
+{
+    /// Returns the keys of the table entries that have the given index key, in the order of
+    /// their serialized keys. The changes made in this run are also visible.
+    ///
+    /// This relies on the serialized `I` being a prefix of the serialized `(I, K)` pair, which
+    /// holds for bincode.
+    pub fn get(&self, key: &I) -> Vec<K> {
+        self.table.prefix(key).map(|((_, key), ())| key).collect()
+    }
+
+    /// Returns true if the table entry with the key `primary` has the given index key.
+    pub fn contains(&self, key: I, primary: K) -> bool {
+        self.table.contains_key((key, primary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AtomoBuilder, DefaultSerdeBackend, InMemoryStorage, OverlayStorageBuilder};
+
+    fn builder(storage: InMemoryStorage) -> AtomoBuilder<InMemoryStorage, DefaultSerdeBackend> {
+        AtomoBuilder::new(storage)
+            .with_table::<u8, Vec<u32>>("TABLE")
+            .with_index::<u8, Vec<u32>, u32, _>("INDEX", "TABLE", |_, value| value.clone())
+    }
+
+    #[test]
+    fn index_should_follow_table_changes() {
+        let mut db = builder(InMemoryStorage::default()).build().unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, Vec<u32>>("TABLE");
+            table.insert(1, vec![10, 20]);
+            table.insert(2, vec![20, 30]);
+
+            // Changes of this run are visible in the index.
+            let index = ctx.get_index::<u32, u8>("INDEX");
+            assert_eq!(index.get(&20), vec![1, 2]);
+
+            table.insert(1, vec![30]);
+            assert_eq!(index.get(&10), Vec::<u8>::new());
+            assert_eq!(index.get(&20), vec![2]);
+            assert_eq!(index.get(&30), vec![1, 2]);
+        });
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, Vec<u32>>("TABLE");
+            table.remove(2);
+        });
+
+        db.query().run(|ctx| {
+            let index = ctx.get_index::<u32, u8>("INDEX");
+            assert_eq!(index.get(&20), Vec::<u8>::new());
+            assert_eq!(index.get(&30), vec![1]);
+            assert!(index.contains(30, 1));
+            assert!(!index.contains(30, 2));
+        });
+    }
+
+    #[test]
+    fn index_should_be_built_on_open() {
+        let mut db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+            .with_table::<u8, Vec<u32>>("TABLE")
+            .build()
+            .unwrap();
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, Vec<u32>>("TABLE");
+            table.insert(1, vec![10]);
+            table.insert(2, vec![10, 20]);
+        });
+
+        // The in-memory storage opens a new slot for every table, so the index is stored in a
+        // slot that is still empty.
+        let storage = db.get_storage_backend_unsafe().clone();
+        let db = builder(storage).build().unwrap();
+
+        db.query().run(|ctx| {
+            let index = ctx.get_index::<u32, u8>("INDEX");
+            assert_eq!(index.get(&10), vec![1, 2]);
+            assert_eq!(index.get(&20), vec![2]);
+        });
+    }
+
+    #[test]
+    fn index_should_not_be_built_on_read_only_storage() {
+        let mut db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+            .with_table::<u8, Vec<u32>>("TABLE")
+            .build()
+            .unwrap();
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, Vec<u32>>("TABLE");
+            table.insert(1, vec![10]);
+        });
+
+        // Opening an overlay must not write the missing index to the storage.
+        let storage = db.get_storage_backend_unsafe().clone();
+        let overlay = OverlayStorageBuilder::new(storage, Default::default());
+        let db = AtomoBuilder::<_, DefaultSerdeBackend>::new(overlay)
+            .with_table::<u8, Vec<u32>>("TABLE")
+            .with_index::<u8, Vec<u32>, u32, _>("INDEX", "TABLE", |_, value| value.clone())
+            .build()
+            .unwrap();
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u8, Vec<u32>>("TABLE");
+            assert_eq!(table.get(1), Some(vec![10]));
+            let index = ctx.get_index::<u32, u8>("INDEX");
+            assert_eq!(index.get(&10), Vec::<u8>::new());
+        });
+    }
+
+    #[test]
+    #[should_panic]
+    fn index_should_not_be_modified_directly() {
+        let db = builder(InMemoryStorage::default()).build().unwrap();
+        db.query().run(|ctx| {
+            ctx.get_table::<(u32, u8), ()>("INDEX");
+        });
+    }
+}
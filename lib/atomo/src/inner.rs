@@ -1,6 +1,7 @@
 use std::any::{Any, TypeId};
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::Bound;
 use std::sync::atomic::AtomicUsize;
 
 use fxhash::FxHashMap;
@@ -9,14 +10,25 @@
 
 use crate::batch::{Operation, VerticalBatch};
 use crate::db::TableId;
+use crate::index::IndexMeta;
 use crate::keys::VerticalKeys;
+use crate::merkle::StateTree;
 use crate::serder::SerdeBackend;
 use crate::snapshot::SnapshotList;
-use crate::storage::StorageBackend;
+use crate::storage::{Direction, RawIterator, StorageBackend};
 use crate::table::{ResolvedTableReference, TableMeta};
 
 static INSTANCE_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+/// The metadata attached to each snapshot.
+#[derive(Default, Clone)]
+pub struct SnapshotMetadata {
+    /// The keys of the tables with the iterator functionality enabled.
+    pub keys: VerticalKeys,
+    /// The state tree, only present if it is enabled. See [`AtomoInner::state_tree`].
+    pub tree: Option<StateTree>,
+}
+
 pub struct AtomoInner<B, S: SerdeBackend> {
     /// The unique id of this instance in the entire program.
     pub id: usize,
@@ -26,8 +38,13 @@ pub struct AtomoInner<B, S: SerdeBackend> {
     pub tables: Vec<TableMeta>,
     /// Map each table name to its index.
     pub table_name_to_id: FxHashMap<String, TableId>,
+    /// The secondary indexes derived from the tables.
+    pub indexes: Vec<IndexMeta>,
     /// The linked list of the old-snapshots.
-    pub snapshot_list: SnapshotList<VerticalBatch, VerticalKeys>,
+    pub snapshot_list: SnapshotList<VerticalBatch, SnapshotMetadata>,
+    /// Whether the state tree is enabled. The tree is built from the storage when the database is
+    /// opened.
+    pub state_tree: bool,
     serde: PhantomData<S>,
 }
 
@@ -39,7 +56,9 @@ pub fn empty() -> Self {
             persistence: (),
             tables: Vec::new(),
             table_name_to_id: FxHashMap::default(),
+            indexes: Vec::new(),
             snapshot_list: SnapshotList::default(),
+            state_tree: false,
             serde: PhantomData,
         }
     }
@@ -50,7 +69,9 @@ pub fn swap_persistance<P: StorageBackend>(self, persistence: P) -> AtomoInner<P
             persistence,
             tables: self.tables,
             table_name_to_id: self.table_name_to_id,
+            indexes: self.indexes,
             snapshot_list: self.snapshot_list,
+            state_tree: self.state_tree,
             serde: PhantomData,
         }
     }
@@ -62,6 +83,22 @@ pub fn perform_batch(&self, batch: VerticalBatch) {
         self.persistence.commit(batch);
     }
 
+    /// Build the state tree from a full scan of every table in the persistence layer.
+    pub fn build_state_tree(&self) -> StateTree {
+        let count = self.tables.len();
+        let mut tree = StateTree::new(count);
+
+        for tid in 0..count as TableId {
+            for key in self.persistence.keys(tid) {
+                let value = self.persistence.get(tid, &key);
+                tree.update(tid, &key, value.as_deref());
+            }
+        }
+
+        tree.update_root();
+        tree
+    }
+
     /// Given the name of a table as an input string returns a [`ResolvedTableReference`].
     ///
     /// # Panics
@@ -71,6 +108,7 @@ pub fn perform_batch(&self, batch: VerticalBatch) {
     /// 1. The table with the given name does not exists.
     /// 2. The generic types passed for the key-value pair mismatch from the type that was used when
     ///    constructing atomo.
+    /// 3. The table is an index, which can not be modified directly.
     #[inline]
     pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K, V>
     where
@@ -83,6 +121,11 @@ pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K,
             .get(name)
             .unwrap_or_else(|| panic!("Table {name} not found."));
 
+        assert!(
+            !self.is_index(index),
+            "Table '{name}' is an index and can not be modified directly."
+        );
+
         let info = &self.tables[index as usize];
         let k_id = TypeId::of::<K>();
         let v_id = TypeId::of::<V>();
@@ -102,6 +145,43 @@ pub fn resolve<K, V>(&self, name: impl AsRef<str>) -> ResolvedTableReference<K,
         ResolvedTableReference::<K, V>::new(self.id, index)
     }
 
+    /// Given the name of an index returns the id of the table it is stored in.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the index does not exist or the generic types mismatch the types
+    /// that were used when declaring the index.
+    pub fn resolve_index<I, K>(&self, name: impl AsRef<str>) -> TableId
+    where
+        I: Any,
+        K: Any,
+    {
+        let name = name.as_ref();
+        let index = *self
+            .table_name_to_id
+            .get(name)
+            .unwrap_or_else(|| panic!("Index {name} not found."));
+
+        assert!(self.is_index(index), "Table '{name}' is not an index.");
+
+        let info = &self.tables[index as usize];
+        let i_str = std::any::type_name::<I>();
+        let k_str = std::any::type_name::<K>();
+        assert_eq!(
+            info.k_id,
+            TypeId::of::<(I, K)>(),
+            "Could not resolve index '{name}' with types '{i_str}' and '{k_str}'."
+        );
+
+        index
+    }
+
+    /// Returns true if the table with the given id holds the entries of an index.
+    #[inline]
+    pub fn is_index(&self, tid: TableId) -> bool {
+        self.indexes.iter().any(|index| index.index == tid)
+    }
+
     /// Given a vertical batch (which we intend to commit) compute the inverse of the batch. The
     /// inverse of a batch is another batch that when executed reverts the changes.
     #[inline]
@@ -162,6 +242,17 @@ pub fn get<V>(&self, tid: TableId, key: &[u8]) -> Option<V>
     pub fn contains_key(&self, tid: TableId, key: &[u8]) -> bool {
         self.persistence.contains(tid, key)
     }
+
+    /// Returns the raw key-value pairs within the given bounds in the provided direction.
+    pub fn range_raw(
+        &self,
+        tid: TableId,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_> {
+        self.persistence.range(tid, start, end, direction)
+    }
 }
 
 #[cfg(test)]
@@ -213,6 +304,29 @@ fn resolve_undefined_table_should_panic() {
         inner.resolve::<String, usize>("TABLE-X");
     }
 
+    #[test]
+    fn get_table_unchecked_should_read_previous_format() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<u8, u64>("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table_unchecked::<u8, u32>("TABLE");
+            table.insert(1, 7);
+        });
+
+        db.run(|ctx| {
+            let value = ctx.get_table_unchecked::<u8, u32>("TABLE").get(1).unwrap();
+            let mut table = ctx.get_table::<u8, u64>("TABLE");
+            table.insert(1, value as u64 * 2);
+        });
+
+        db.query().run(|ctx| {
+            assert_eq!(ctx.get_table::<u8, u64>("TABLE").get(1), Some(14));
+        });
+    }
+
     #[test]
     fn perform_batch() {
         let inner = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
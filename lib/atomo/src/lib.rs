@@ -2,12 +2,19 @@
 //! wrapper that enhances any backend storage engine with an optimized snapshot
 //! functionality.
 
+#[cfg(feature = "rkyv")]
+mod archive;
 pub mod batch;
 mod builder;
 mod db;
+mod index;
 mod inner;
 mod key_iterator;
 mod keys;
+mod merkle;
+mod overlay;
+mod pinned;
+mod range;
 mod serder;
 mod snapshot;
 pub mod storage;
@@ -15,9 +22,16 @@
 
 pub type DefaultSerdeBackend = serder::BincodeSerde;
 
+#[cfg(feature = "rkyv")]
+pub use archive::{ArchiveBackend, ArchivedValue, InvalidArchive, Rkyv, RkyvSerde};
 pub use builder::AtomoBuilder;
 pub use db::{Atomo, QueryPerm, UpdatePerm};
+pub use index::IndexRef;
 pub use key_iterator::KeyIterator;
+pub use merkle::{ProofLeaf, StateProof, EMPTY_HASH};
+pub use overlay::{OverlayStorage, OverlayStorageBuilder};
+pub use pinned::{AtomoSnapshot, SnapshotExpired};
+pub use range::{Overlay, RangeIterator};
 pub use serder::{BincodeSerde, SerdeBackend};
-pub use storage::{InMemoryStorage, StorageBackend, StorageBackendConstructor};
+pub use storage::{Direction, InMemoryStorage, StorageBackend, StorageBackendConstructor};
 pub use table::{ResolvedTableReference, TableRef, TableSelector};
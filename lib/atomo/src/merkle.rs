@@ -0,0 +1,510 @@
+//! The authenticated state of an [`crate::Atomo`] instance.
+//!
+//! Each table is committed to by a binary sparse Merkle tree where a key is placed at the path
+//! given by the blake3 hash of its serialized bytes. To keep the tree shallow, any subtree that
+//! holds a single leaf is replaced by that leaf, which makes the depth of the tree logarithmic in
+//! the number of keys and the shape of the tree a function of its content only.
+//!
+//! The tree is persistent: an update copies the nodes on the path to the changed leaves and shares
+//! everything else with the previous version. This allows every snapshot to hold on to its own
+//! version of the tree and produce proofs that are consistent with the data it can read.
+
+use std::sync::Arc;
+
+use fleek_blake3 as blake3;
+use serde::{Deserialize, Serialize};
+
+use crate::batch::{Operation, VerticalBatch};
+use crate::db::TableId;
+
+/// A 32-byte blake3 digest.
+pub type Hash = [u8; 32];
+
+/// The hash of an empty (sub)tree.
+pub const EMPTY_HASH: Hash = [0; 32];
+
+const LEAF_PREFIX: u8 = 0;
+const INTERNAL_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// The set of the table trees along with the state root that commits to all of them.
+#[derive(Clone, Default)]
+pub struct StateTree {
+    tables: Vec<Node>,
+    root: Hash,
+}
+
+#[derive(Clone, Default)]
+enum Node {
+    #[default]
+    Empty,
+    Leaf(Arc<LeafNode>),
+    Internal(Arc<InternalNode>),
+}
+
+struct LeafNode {
+    path: Hash,
+    value_hash: Hash,
+    hash: Hash,
+}
+
+struct InternalNode {
+    left: Node,
+    right: Node,
+    hash: Hash,
+}
+
+/// A proof for the value (or the absence) of a key in one of the tables, against a state root.
+///
+/// The proof can be verified using [`StateProof::verify`] without any access to the database.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateProof {
+    /// The roots of all of the tables, in the order they were opened.
+    pub table_roots: Vec<Hash>,
+    /// The table this proof is for.
+    pub table: TableId,
+    /// The hashes of the siblings on the path from the table root to the terminal node, starting
+    /// from the top of the tree.
+    pub siblings: Vec<Hash>,
+    /// The leaf at the end of the path. For an exclusion proof this is either `None` or a leaf
+    /// of another key that occupies the subtree the key would be placed in.
+    pub leaf: Option<ProofLeaf>,
+}
+
+/// The content of a leaf in a [`StateProof`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofLeaf {
+    /// The hash of the serialized key.
+    pub path: Hash,
+    /// The hash of the serialized value.
+    pub value_hash: Hash,
+}
+
+impl StateTree {
+    /// Create a new state tree with the given number of empty tables.
+    pub fn new(num_tables: usize) -> Self {
+        let mut tree = Self {
+            tables: vec![Node::Empty; num_tables],
+            root: EMPTY_HASH,
+        };
+        tree.update_root();
+        tree
+    }
+
+    /// Returns the state root.
+    #[inline(always)]
+    pub fn root(&self) -> Hash {
+        self.root
+    }
+
+    /// Set the value of a key in the given table, or remove it if the value is `None`. The state
+    /// root is not updated until [`StateTree::update_root`] is called.
+    pub fn update(&mut self, tid: TableId, key: &[u8], value: Option<&[u8]>) {
+        let path = *blake3::hash(key).as_bytes();
+        let table = &mut self.tables[tid as usize];
+        *table = match value {
+            Some(value) => insert(table, 0, &path, blake3::hash(value).into()),
+            None => remove(table, 0, &path),
+        };
+    }
+
+    /// Recompute the state root from the root of every table.
+    pub fn update_root(&mut self) {
+        let roots = self.tables.iter().map(Node::hash).collect::<Vec<_>>();
+        self.root = state_root(&roots);
+    }
+
+    /// Returns a new version of this tree with the given batch applied to it.
+    pub fn apply(&self, batch: &VerticalBatch) -> Self {
+        let mut tree = self.clone();
+
+        for tid in 0..self.tables.len() {
+            for (key, operation) in batch.get(tid) {
+                let value = match operation {
+                    Operation::Insert(value) => Some(&value[..]),
+                    Operation::Remove => None,
+                };
+                tree.update(tid as TableId, key, value);
+            }
+        }
+
+        tree.update_root();
+        tree
+    }
+
+    /// Returns the proof for the given key in the provided table.
+    pub fn prove(&self, tid: TableId, key: &[u8]) -> StateProof {
+        let path = *blake3::hash(key).as_bytes();
+        let mut siblings = Vec::new();
+        let mut node = &self.tables[tid as usize];
+
+        let leaf = loop {
+            match node {
+                Node::Empty => break None,
+                Node::Leaf(leaf) => {
+                    break Some(ProofLeaf {
+                        path: leaf.path,
+                        value_hash: leaf.value_hash,
+                    });
+                },
+                Node::Internal(internal) => {
+                    if bit(&path, siblings.len()) {
+                        siblings.push(internal.left.hash());
+                        node = &internal.right;
+                    } else {
+                        siblings.push(internal.right.hash());
+                        node = &internal.left;
+                    }
+                },
+            }
+        };
+
+        StateProof {
+            table_roots: self.tables.iter().map(Node::hash).collect(),
+            table: tid,
+            siblings,
+            leaf,
+        }
+    }
+}
+
+impl StateProof {
+    /// Verify the proof against the given state root. Passing `Some(value)` checks that `key` is
+    /// mapped to `value` and passing `None` checks that `key` is not present in the table. Both
+    /// the key and the value are expected in their serialized form.
+    pub fn verify(&self, root: &Hash, key: &[u8], value: Option<&[u8]>) -> bool {
+        let Some(table_root) = self.table_roots.get(self.table as usize) else {
+            return false;
+        };
+
+        if self.siblings.len() > 256 || state_root(&self.table_roots) != *root {
+            return false;
+        }
+
+        let path = *blake3::hash(key).as_bytes();
+        let mut current = match (&self.leaf, value) {
+            (Some(leaf), Some(value)) => {
+                if leaf.path != path || leaf.value_hash != *blake3::hash(value).as_bytes() {
+                    return false;
+                }
+                leaf_hash(&leaf.path, &leaf.value_hash)
+            },
+            (Some(leaf), None) => {
+                if leaf.path == path || common_prefix(&leaf.path, &path) < self.siblings.len() {
+                    return false;
+                }
+                leaf_hash(&leaf.path, &leaf.value_hash)
+            },
+            (None, None) => EMPTY_HASH,
+            (None, Some(_)) => return false,
+        };
+
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            current = if bit(&path, depth) {
+                internal_hash(sibling, &current)
+            } else {
+                internal_hash(&current, sibling)
+            };
+        }
+
+        current == *table_root
+    }
+}
+
+impl Node {
+    #[inline]
+    fn hash(&self) -> Hash {
+        match self {
+            Node::Empty => EMPTY_HASH,
+            Node::Leaf(leaf) => leaf.hash,
+            Node::Internal(internal) => internal.hash,
+        }
+    }
+
+    #[inline]
+    fn leaf(path: Hash, value_hash: Hash) -> Self {
+        let hash = leaf_hash(&path, &value_hash);
+        Node::Leaf(Arc::new(LeafNode {
+            path,
+            value_hash,
+            hash,
+        }))
+    }
+
+    /// Create an internal node from the two children, a subtree with only one leaf is collapsed
+    /// into the leaf itself.
+    #[inline]
+    fn internal(left: Node, right: Node) -> Self {
+        match (&left, &right) {
+            (Node::Empty, Node::Empty) => Node::Empty,
+            (Node::Leaf(_), Node::Empty) => left,
+            (Node::Empty, Node::Leaf(_)) => right,
+            _ => {
+                let hash = internal_hash(&left.hash(), &right.hash());
+                Node::Internal(Arc::new(InternalNode { left, right, hash }))
+            },
+        }
+    }
+}
+
+fn insert(node: &Node, depth: usize, path: &Hash, value_hash: Hash) -> Node {
+    match node {
+        Node::Empty => Node::leaf(*path, value_hash),
+        Node::Leaf(leaf) if leaf.path == *path => Node::leaf(*path, value_hash),
+        Node::Leaf(leaf) => split(
+            node.clone(),
+            &leaf.path,
+            Node::leaf(*path, value_hash),
+            path,
+            depth,
+        ),
+        Node::Internal(internal) => {
+            if bit(path, depth) {
+                let right = insert(&internal.right, depth + 1, path, value_hash);
+                Node::internal(internal.left.clone(), right)
+            } else {
+                let left = insert(&internal.left, depth + 1, path, value_hash);
+                Node::internal(left, internal.right.clone())
+            }
+        },
+    }
+}
+
+/// Put two leaves with different paths in the same subtree.
+fn split(a: Node, a_path: &Hash, b: Node, b_path: &Hash, depth: usize) -> Node {
+    match (bit(a_path, depth), bit(b_path, depth)) {
+        (false, false) => Node::internal(split(a, a_path, b, b_path, depth + 1), Node::Empty),
+        (true, true) => Node::internal(Node::Empty, split(a, a_path, b, b_path, depth + 1)),
+        (false, true) => Node::internal(a, b),
+        (true, false) => Node::internal(b, a),
+    }
+}
+
+fn remove(node: &Node, depth: usize, path: &Hash) -> Node {
+    match node {
+        Node::Empty => Node::Empty,
+        Node::Leaf(leaf) if leaf.path == *path => Node::Empty,
+        Node::Leaf(_) => node.clone(),
+        Node::Internal(internal) => {
+            if bit(path, depth) {
+                let right = remove(&internal.right, depth + 1, path);
+                Node::internal(internal.left.clone(), right)
+            } else {
+                let left = remove(&internal.left, depth + 1, path);
+                Node::internal(left, internal.right.clone())
+            }
+        },
+    }
+}
+
+#[inline(always)]
+fn bit(path: &Hash, depth: usize) -> bool {
+    (path[depth / 8] >> (7 - depth % 8)) & 1 == 1
+}
+
+#[inline]
+fn common_prefix(a: &Hash, b: &Hash) -> usize {
+    (0..256)
+        .take_while(|depth| bit(a, *depth) == bit(b, *depth))
+        .count()
+}
+
+#[inline]
+fn leaf_hash(path: &Hash, value_hash: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[LEAF_PREFIX]);
+    hasher.update(path);
+    hasher.update(value_hash);
+    hasher.finalize().into()
+}
+
+#[inline]
+fn internal_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[INTERNAL_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn state_root(table_roots: &[Hash]) -> Hash {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[ROOT_PREFIX]);
+    for root in table_roots {
+        hasher.update(root);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AtomoBuilder, BincodeSerde, InMemoryStorage, SerdeBackend};
+
+    fn batch(ops: &[(&[u8], Option<&[u8]>)]) -> VerticalBatch {
+        let mut batch = VerticalBatch::new(1);
+        let table = batch.get_mut(0);
+        for (key, value) in ops {
+            let op = match value {
+                Some(value) => Operation::Insert(value.to_vec().into_boxed_slice()),
+                None => Operation::Remove,
+            };
+            table.insert(key.to_vec().into_boxed_slice(), op);
+        }
+        batch
+    }
+
+    #[test]
+    fn root_depends_only_on_content() {
+        let empty = StateTree::new(1);
+
+        let a = empty
+            .apply(&batch(&[(b"a", Some(b"1")), (b"b", Some(b"2"))]))
+            .apply(&batch(&[(b"c", Some(b"3"))]));
+        let b = empty
+            .apply(&batch(&[(b"c", Some(b"3")), (b"x", Some(b"0"))]))
+            .apply(&batch(&[(b"b", Some(b"2")), (b"x", None)]))
+            .apply(&batch(&[(b"a", Some(b"1"))]));
+        assert_eq!(a.root(), b.root());
+
+        let c = a.apply(&batch(&[(b"a", None), (b"b", None), (b"c", None)]));
+        assert_eq!(c.root(), empty.root());
+        assert_ne!(a.root(), empty.root());
+    }
+
+    #[test]
+    fn update_does_not_change_old_version() {
+        let a = StateTree::new(1).apply(&batch(&[(b"a", Some(b"1"))]));
+        let root = a.root();
+        let b = a.apply(&batch(&[(b"a", Some(b"2")), (b"b", Some(b"3"))]));
+        assert_eq!(a.root(), root);
+        assert_ne!(b.root(), root);
+        assert!(a.prove(0, b"a").verify(&root, b"a", Some(b"1")));
+    }
+
+    #[test]
+    fn inclusion_and_exclusion_proofs() {
+        let ops = (0..64u8).map(|i| (vec![i], vec![i, i])).collect::<Vec<_>>();
+        let ops = ops
+            .iter()
+            .map(|(k, v)| (&k[..], Some(&v[..])))
+            .collect::<Vec<_>>();
+        let tree = StateTree::new(1).apply(&batch(&ops));
+        let root = tree.root();
+
+        for i in 0..64u8 {
+            let proof = tree.prove(0, &[i]);
+            assert!(proof.verify(&root, &[i], Some(&[i, i])));
+            assert!(!proof.verify(&root, &[i], Some(&[i])));
+            assert!(!proof.verify(&root, &[i], None));
+        }
+
+        for i in 64..128u8 {
+            let proof = tree.prove(0, &[i]);
+            assert!(proof.verify(&root, &[i], None));
+            assert!(!proof.verify(&root, &[i], Some(&[i, i])));
+        }
+
+        let proof = tree.prove(0, &[0]);
+        assert!(!proof.verify(&StateTree::new(1).root(), &[0], Some(&[0, 0])));
+    }
+
+    #[test]
+    fn proofs_on_empty_table() {
+        let tree = StateTree::new(2).apply(&{
+            let mut batch = VerticalBatch::new(2);
+            batch.get_mut(1).insert(
+                vec![0].into_boxed_slice(),
+                Operation::Insert(vec![1].into_boxed_slice()),
+            );
+            batch
+        });
+        let root = tree.root();
+
+        assert!(tree.prove(0, &[0]).verify(&root, &[0], None));
+        assert!(tree.prove(1, &[0]).verify(&root, &[0], Some(&[1])));
+        assert!(!tree.prove(1, &[0]).verify(&root, &[0], None));
+    }
+
+    #[test]
+    fn atomo_state_root_and_proofs() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<String, u64>("balances")
+            .with_table::<u64, String>("names")
+            .enable_state_tree()
+            .build()
+            .unwrap();
+        let query = db.query();
+
+        // The tree is built when the database is opened.
+        let empty_root = db.state_root().unwrap();
+        assert_eq!(empty_root, StateTree::new(2).root());
+        db.run(|ctx| {
+            let mut balances = ctx.get_table::<String, u64>("balances");
+            balances.insert("alice".to_string(), 10);
+            // Changes in the current run are not reflected.
+            assert_eq!(ctx.state_root(), Some(empty_root));
+        });
+        let initial_root = db.state_root().unwrap();
+        assert_ne!(initial_root, empty_root);
+        db.run(|ctx| {
+            let mut balances = ctx.get_table::<String, u64>("balances");
+            balances.insert("bob".to_string(), 20);
+            assert_eq!(ctx.state_root(), Some(initial_root));
+        });
+        let root = db.state_root().unwrap();
+        assert_ne!(root, initial_root);
+
+        let key = BincodeSerde::serialize(&"alice".to_string());
+        let mut expected = StateTree::new(2);
+        expected.update(0, &key, Some(&BincodeSerde::serialize(&10u64)));
+        expected.update(
+            0,
+            &BincodeSerde::serialize(&"bob".to_string()),
+            Some(&BincodeSerde::serialize(&20u64)),
+        );
+        expected.update_root();
+        assert_eq!(root, expected.root());
+
+        let old_key = BincodeSerde::serialize(&"carol".to_string());
+        let (value, proof) = query.run(|ctx| {
+            assert_eq!(ctx.state_root(), Some(root));
+            ctx.get_raw_with_proof("balances", &key).unwrap()
+        });
+        assert_eq!(value, Some(BincodeSerde::serialize(&10u64)));
+        assert!(proof.verify(&root, &key, value.as_deref()));
+
+        db.run(|ctx| {
+            let mut balances = ctx.get_table::<String, u64>("balances");
+            balances.remove("alice".to_string());
+            balances.insert("carol".to_string(), 5);
+        });
+        let new_root = db.state_root().unwrap();
+
+        query.run(|ctx| {
+            let (value, proof) = ctx.get_raw_with_proof("balances", &key).unwrap();
+            assert_eq!(value, None);
+            assert!(proof.verify(&new_root, &key, None));
+            assert!(!proof.verify(&root, &key, None));
+
+            let (value, proof) = ctx.get_raw_with_proof("balances", &old_key).unwrap();
+            assert!(proof.verify(&new_root, &old_key, value.as_deref()));
+            assert!(ctx.get_raw_with_proof("unknown", &key).is_none());
+        });
+    }
+
+    #[test]
+    fn state_tree_is_disabled_by_default() {
+        let db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<String, u64>("balances")
+            .build()
+            .unwrap();
+
+        assert_eq!(db.state_root(), None);
+        assert!(
+            db.query()
+                .run(|ctx| ctx.get_raw_with_proof("balances", &[0]))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Bound, RangeBounds};
+
+use crate::batch::{BoxedVec, VerticalBatch};
+use crate::range::{MergeIterator, Overlay};
+use crate::storage::{Direction, RawIterator, StorageBackend, StorageBackendConstructor};
+
+/// The constructor of an [`OverlayStorage`], which wraps the constructor of the base storage and
+/// the changes of each table by its name.
+pub struct OverlayStorageBuilder<C> {
+    base: C,
+    changes: BTreeMap<String, Overlay>,
+    names: Vec<String>,
+}
+
+impl<C> OverlayStorageBuilder<C> {
+    /// Create a new builder that serves the given changes on top of the base storage. The
+    /// changes are keyed by table name, tables without changes can be omitted.
+    pub fn new(base: C, changes: BTreeMap<String, Overlay>) -> Self {
+        Self {
+            base,
+            changes,
+            names: Vec::new(),
+        }
+    }
+}
+
+impl<C: StorageBackendConstructor> StorageBackendConstructor for OverlayStorageBuilder<C> {
+    type Storage = OverlayStorage<C::Storage>;
+
+    type Error = C::Error;
+
+    fn open_table(&mut self, name: String) {
+        self.base.open_table(name.clone());
+        self.names.push(name);
+    }
+
+    fn build(mut self) -> Result<Self::Storage, Self::Error> {
+        let tables = self
+            .names
+            .iter()
+            .map(|name| self.changes.remove(name).unwrap_or_default())
+            .collect();
+
+        Ok(OverlayStorage {
+            base: self.base.build()?,
+            tables,
+        })
+    }
+}
+
+/// A read-only storage backend that serves a fixed set of changes on top of another storage
+/// backend, without modifying it. This can be used to look at a past version of a database by
+/// providing the changes that revert it.
+pub struct OverlayStorage<B> {
+    base: B,
+    tables: Vec<Overlay>,
+}
+
+impl<B> OverlayStorage<B> {
+    /// Returns the base storage.
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+}
+
+impl<B: StorageBackend> StorageBackend for OverlayStorage<B> {
+    fn commit(&self, _batch: VerticalBatch) {
+        panic!("An overlay storage is read-only.");
+    }
+
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    fn keys(&self, tid: u8) -> Vec<BoxedVec> {
+        let overlay = &self.tables[tid as usize];
+        let mut keys = self
+            .base
+            .keys(tid)
+            .into_iter()
+            .filter(|key| !overlay.contains_key(key))
+            .collect::<BTreeSet<_>>();
+        for (key, value) in overlay {
+            if value.is_some() {
+                keys.insert(key.clone());
+            }
+        }
+        keys.into_iter().collect()
+    }
+
+    fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
+        match self.tables[tid as usize].get(key) {
+            Some(value) => value.as_ref().map(|value| value.to_vec()),
+            None => self.base.get(tid, key),
+        }
+    }
+
+    fn contains(&self, tid: u8, key: &[u8]) -> bool {
+        match self.tables[tid as usize].get(key) {
+            Some(value) => value.is_some(),
+            None => self.base.contains(tid, key),
+        }
+    }
+
+    fn range(
+        &self,
+        tid: u8,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_> {
+        let backend = self.base.range(tid, start, end, direction);
+        let bounds = (start, end);
+        let overlay = self.tables[tid as usize]
+            .iter()
+            .filter(|(key, _)| RangeBounds::<[u8]>::contains(&bounds, &key[..]))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        Box::new(MergeIterator::new(backend, overlay, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::Operation;
+    use crate::{AtomoBuilder, DefaultSerdeBackend, InMemoryStorage, SerdeBackend};
+
+    fn raw<T: serde::Serialize>(value: T) -> BoxedVec {
+        DefaultSerdeBackend::serialize(&value).into_boxed_slice()
+    }
+
+    #[test]
+    fn overlay_should_take_priority_over_base() {
+        // The base storage already has the table open at index 0.
+        let mut base = InMemoryStorage::default();
+        base.open_table("TABLE".into());
+        let mut batch = VerticalBatch::new(1);
+        for i in 0..5u8 {
+            batch.get_mut(0).insert(raw(i), Operation::Insert(raw(i)));
+        }
+        base.commit(batch);
+
+        let mut changes = Overlay::new();
+        changes.insert(raw(1u8), None);
+        changes.insert(raw(2u8), Some(raw(20u8)));
+        changes.insert(raw(7u8), Some(raw(70u8)));
+        let builder = OverlayStorageBuilder::new(
+            base,
+            [("TABLE".to_string(), changes)].into_iter().collect(),
+        );
+
+        let db = AtomoBuilder::<_, DefaultSerdeBackend>::new(builder)
+            .with_table::<u8, u8>("TABLE")
+            .enable_iter("TABLE")
+            .build()
+            .unwrap()
+            .query();
+
+        db.run(|ctx| {
+            let table = ctx.get_table::<u8, u8>("TABLE");
+            assert_eq!(table.get(0), Some(0));
+            assert_eq!(table.get(1), None);
+            assert_eq!(table.get(2), Some(20));
+            assert_eq!(table.get(7), Some(70));
+            assert!(!table.contains_key(1));
+            assert!(table.contains_key(7));
+
+            let mut keys = table.keys().collect::<Vec<_>>();
+            keys.sort();
+            assert_eq!(keys, vec![0, 2, 3, 4, 7]);
+
+            assert_eq!(
+                table.range(..).collect::<Vec<_>>(),
+                vec![(0, 0), (2, 20), (3, 3), (4, 4), (7, 70)]
+            );
+            assert_eq!(
+                table.range_rev(1..=3).collect::<Vec<_>>(),
+                vec![(3, 3), (2, 20)]
+            );
+        });
+    }
+
+    #[test]
+    fn inverse_overlay_should_restore_previous_state() {
+        let mut db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+            .with_table::<u8, u8>("TABLE")
+            .with_table::<u8, u8>("UNTOUCHED")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, u8>("TABLE");
+            for i in 0..5 {
+                table.insert(i, i);
+            }
+        });
+
+        let ((), changes) = db.run_with_inverse(|ctx| {
+            let mut table = ctx.get_table::<u8, u8>("TABLE");
+            table.remove(1);
+            table.insert(2, 20);
+            table.insert(3, 3);
+            table.insert(7, 70);
+        });
+
+        // Only the keys that actually changed are part of the inverse.
+        assert_eq!(changes.len(), 1);
+        let overlay = &changes["TABLE"];
+        assert_eq!(overlay.len(), 3);
+        assert_eq!(overlay[&raw(1u8)], Some(raw(1u8)));
+        assert_eq!(overlay[&raw(2u8)], Some(raw(2u8)));
+        assert_eq!(overlay[&raw(7u8)], None);
+
+        let base = db.get_storage_backend_unsafe().clone();
+        let previous =
+            AtomoBuilder::<_, DefaultSerdeBackend>::new(OverlayStorageBuilder::new(base, changes))
+                .with_table::<u8, u8>("TABLE")
+                .with_table::<u8, u8>("UNTOUCHED")
+                .enable_iter("TABLE")
+                .build()
+                .unwrap()
+                .query();
+
+        previous.run(|ctx| {
+            let table = ctx.get_table::<u8, u8>("TABLE");
+            let mut keys = table.keys().collect::<Vec<_>>();
+            keys.sort();
+            assert_eq!(keys, vec![0, 1, 2, 3, 4]);
+            for i in 0..5 {
+                assert_eq!(table.get(i), Some(i));
+            }
+            assert_eq!(table.get(7), None);
+        });
+    }
+}
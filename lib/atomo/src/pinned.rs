@@ -0,0 +1,168 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::db::{Atomo, QueryPerm};
+use crate::serder::SerdeBackend;
+use crate::storage::{InMemoryStorage, StorageBackend};
+use crate::table::TableSelector;
+use crate::DefaultSerdeBackend;
+
+/// A handle to a pinned version of an [`Atomo`] instance, created by [`Atomo::snapshot`].
+///
+/// Unlike [`Atomo::run`] which only reads a consistent version within a single closure, every
+/// query that is run through a snapshot reads the same version, even if updates are committed
+/// in between. A snapshot is cheap to clone and can be held across awaits.
+///
+/// The changes that are committed after a snapshot are kept in memory for as long as it is
+/// alive, so long-lived snapshots should be given a time to live using
+/// [`AtomoSnapshot::with_ttl`], after which they can no longer be used.
+pub struct AtomoSnapshot<B: StorageBackend = InMemoryStorage, S: SerdeBackend = DefaultSerdeBackend>
+{
+    atomo: Atomo<QueryPerm, B, S>,
+    expires_at: Option<Instant>,
+}
+
+/// The error returned when using a snapshot whose time to live has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotExpired;
+
+impl<B: StorageBackend, S: SerdeBackend> AtomoSnapshot<B, S> {
+    #[inline]
+    pub(crate) fn new(atomo: Atomo<QueryPerm, B, S>) -> Self {
+        Self {
+            atomo,
+            expires_at: None,
+        }
+    }
+
+    /// Set the time to live of this snapshot, counting from now.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(Instant::now() + ttl);
+        self
+    }
+
+    /// Returns the time after which this snapshot can no longer be used, if it has a time to
+    /// live.
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.expires_at
+    }
+
+    /// Returns true if the time to live of this snapshot has passed.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| Instant::now() >= expires_at)
+    }
+
+    /// Run a query on the pinned version of the data.
+    pub fn run<F, R>(&self, query: F) -> Result<R, SnapshotExpired>
+    where
+        F: FnOnce(&mut TableSelector<B, S>) -> R,
+    {
+        Ok(self.query()?.run(query))
+    }
+
+    /// Returns a query end that is pinned to the version of this snapshot. The query end is not
+    /// bound to the time to live of the snapshot and is meant to be used for a short time.
+    pub fn query(&self) -> Result<Atomo<QueryPerm, B, S>, SnapshotExpired> {
+        if self.is_expired() {
+            return Err(SnapshotExpired);
+        }
+        Ok(self.atomo.clone())
+    }
+}
+
+impl<B: StorageBackend, S: SerdeBackend> Clone for AtomoSnapshot<B, S> {
+    fn clone(&self) -> Self {
+        Self {
+            atomo: self.atomo.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+impl fmt::Display for SnapshotExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The snapshot has expired.")
+    }
+}
+
+impl std::error::Error for SnapshotExpired {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AtomoBuilder, DefaultSerdeBackend, InMemoryStorage};
+
+    #[test]
+    fn snapshot_should_pin_the_version() {
+        let mut db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+            .with_table::<u8, u8>("TABLE")
+            .enable_iter("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, u8>("TABLE");
+            table.insert(0, 0);
+            table.insert(1, 1);
+        });
+
+        let snapshot = db.snapshot();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<u8, u8>("TABLE");
+            table.insert(0, 10);
+            table.remove(1);
+            table.insert(2, 2);
+        });
+
+        // The snapshot can be moved to another thread and still reads the pinned version.
+        let handle = std::thread::spawn({
+            let snapshot = snapshot.clone();
+            move || {
+                snapshot
+                    .run(|ctx| {
+                        let table = ctx.get_table::<u8, u8>("TABLE");
+                        let mut keys = table.keys().collect::<Vec<_>>();
+                        keys.sort();
+                        (table.get(0), table.get(1), table.get(2), keys)
+                    })
+                    .unwrap()
+            }
+        });
+        assert_eq!(handle.join().unwrap(), (Some(0), Some(1), None, vec![0, 1]));
+
+        // Queries from a pinned query end are pinned as well.
+        let query = snapshot.query().unwrap().query();
+        db.run(|ctx| ctx.get_table::<u8, u8>("TABLE").insert(3, 3));
+        query.run(|ctx| {
+            let table = ctx.get_table::<u8, u8>("TABLE");
+            assert_eq!(table.range(..).collect::<Vec<_>>(), vec![(0, 0), (1, 1)]);
+        });
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<u8, u8>("TABLE");
+            assert_eq!(
+                table.range(..).collect::<Vec<_>>(),
+                vec![(0, 10), (2, 2), (3, 3)]
+            );
+        });
+    }
+
+    #[test]
+    fn snapshot_should_expire() {
+        let db = AtomoBuilder::<InMemoryStorage, DefaultSerdeBackend>::default()
+            .with_table::<u8, u8>("TABLE")
+            .build()
+            .unwrap();
+
+        let snapshot = db.snapshot().with_ttl(Duration::from_secs(60));
+        assert!(!snapshot.is_expired());
+        assert!(snapshot.run(|_| ()).is_ok());
+
+        let snapshot = db.snapshot().with_ttl(Duration::ZERO);
+        assert!(snapshot.is_expired());
+        assert_eq!(snapshot.run(|_| ()), Err(SnapshotExpired));
+        assert!(snapshot.query().is_err());
+    }
+}
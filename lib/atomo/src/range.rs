@@ -0,0 +1,235 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use serde::de::DeserializeOwned;
+
+use crate::batch::BoxedVec;
+use crate::storage::{Direction, RawIterator};
+use crate::{DefaultSerdeBackend, SerdeBackend};
+
+/// The changes on a set of keys that are not visible in a storage backend, mapping each key to
+/// its value or `None` if it is removed.
+pub type Overlay = BTreeMap<BoxedVec, Option<BoxedVec>>;
+
+/// An ordered iterator over the entries of a table within a range of keys.
+///
+/// The entries are ordered by their serialized keys. This is created by methods such as
+/// [`crate::TableRef::range`] and [`crate::TableRef::prefix`].
+pub struct RangeIterator<'a, K, V, S: SerdeBackend = DefaultSerdeBackend> {
+    raw: MergeIterator<'a>,
+    kv: PhantomData<(K, V, S)>,
+}
+
+impl<'a, K, V, S: SerdeBackend> RangeIterator<'a, K, V, S> {
+    pub(crate) fn new(backend: RawIterator<'a>, overlay: Overlay, direction: Direction) -> Self {
+        Self {
+            raw: MergeIterator::new(backend, overlay, direction),
+            kv: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V, S: SerdeBackend> Iterator for RangeIterator<'a, K, V, S>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+{
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw
+            .next()
+            .map(|(key, value)| (S::deserialize(&key), S::deserialize(&value)))
+    }
+}
+
+/// A raw iterator that merges the entries of a storage backend with an overlay of changes,
+/// giving priority to the overlay when both sides have the same key.
+pub(crate) struct MergeIterator<'a> {
+    backend: Peekable<RawIterator<'a>>,
+    overlay: Peekable<std::vec::IntoIter<(BoxedVec, Option<BoxedVec>)>>,
+    direction: Direction,
+}
+
+impl<'a> MergeIterator<'a> {
+    pub(crate) fn new(backend: RawIterator<'a>, overlay: Overlay, direction: Direction) -> Self {
+        let overlay = match direction {
+            Direction::Forward => overlay.into_iter().collect::<Vec<_>>(),
+            Direction::Reverse => overlay.into_iter().rev().collect::<Vec<_>>(),
+        };
+
+        Self {
+            backend: backend.peekable(),
+            overlay: overlay.into_iter().peekable(),
+            direction,
+        }
+    }
+}
+
+impl<'a> Iterator for MergeIterator<'a> {
+    type Item = (BoxedVec, BoxedVec);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let ordering = match (self.backend.peek(), self.overlay.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some((a, _)), Some((b, _))) => match self.direction {
+                    Direction::Forward => a.cmp(b),
+                    Direction::Reverse => b.cmp(a),
+                },
+            };
+
+            if ordering == Ordering::Less {
+                return self.backend.next();
+            }
+
+            if ordering == Ordering::Equal {
+                self.backend.next();
+            }
+
+            if let Some((key, Some(value))) = self.overlay.next() {
+                return Some((key, value));
+            }
+        }
+    }
+}
+
+/// Returns the raw bounds of every key that starts with the given prefix.
+pub(crate) fn prefix_bounds(prefix: &[u8]) -> (Bound<BoxedVec>, Bound<BoxedVec>) {
+    let start = Bound::Included(prefix.into());
+
+    // The smallest key that is larger than every key with this prefix is the prefix with its
+    // last non-0xff byte incremented, if there is no such byte the range is unbounded.
+    let end = match prefix.iter().rposition(|b| *b != u8::MAX) {
+        Some(index) => {
+            let mut end = prefix[..=index].to_vec();
+            end[index] += 1;
+            Bound::Excluded(end.into_boxed_slice())
+        },
+        None => Bound::Unbounded,
+    };
+
+    (start, end)
+}
+
+/// Borrow a raw bound as a slice.
+#[inline]
+pub(crate) fn as_slice(bound: &Bound<BoxedVec>) -> Bound<&[u8]> {
+    match bound {
+        Bound::Included(key) => Bound::Included(key),
+        Bound::Excluded(key) => Bound::Excluded(key),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// Serialize the bounds of a range of keys.
+pub(crate) fn serialize_bounds<K, S: SerdeBackend>(
+    range: &impl RangeBounds<K>,
+) -> (Bound<BoxedVec>, Bound<BoxedVec>)
+where
+    K: serde::Serialize,
+{
+    let map = |bound: Bound<&K>| match bound {
+        Bound::Included(key) => Bound::Included(S::serialize(key).into_boxed_slice()),
+        Bound::Excluded(key) => Bound::Excluded(S::serialize(key).into_boxed_slice()),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    (map(range.start_bound()), map(range.end_bound()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AtomoBuilder, BincodeSerde, InMemoryStorage};
+
+    #[test]
+    fn prefix_bounds_should_work() {
+        let (start, end) = prefix_bounds(&[1, 2]);
+        assert_eq!(start, Bound::Included(vec![1, 2].into_boxed_slice()));
+        assert_eq!(end, Bound::Excluded(vec![1, 3].into_boxed_slice()));
+
+        let (_, end) = prefix_bounds(&[1, 255, 255]);
+        assert_eq!(end, Bound::Excluded(vec![2].into_boxed_slice()));
+
+        let (_, end) = prefix_bounds(&[255]);
+        assert_eq!(end, Bound::Unbounded);
+
+        let (start, end) = prefix_bounds(&[]);
+        assert_eq!(start, Bound::Included(vec![].into_boxed_slice()));
+        assert_eq!(end, Bound::Unbounded);
+    }
+
+    #[test]
+    fn range_should_merge_batch_and_snapshots() {
+        let mut db = AtomoBuilder::<InMemoryStorage, BincodeSerde>::default()
+            .with_table::<(u8, u8), u8>("TABLE")
+            .build()
+            .unwrap();
+
+        db.run(|ctx| {
+            let mut table = ctx.get_table::<(u8, u8), u8>("TABLE");
+            for a in 0..4 {
+                for b in 0..4 {
+                    table.insert((a, b), a * 4 + b);
+                }
+            }
+        });
+
+        let query = db.query();
+        query.run(|ctx| {
+            // Changes committed after this query started should not be visible.
+            db.run(|ctx| {
+                let mut table = ctx.get_table::<(u8, u8), u8>("TABLE");
+                table.remove((1, 1));
+                table.insert((1, 9), 0);
+                table.insert((2, 0), 0);
+            });
+
+            let mut table = ctx.get_table::<(u8, u8), u8>("TABLE");
+            let keys = |iter: RangeIterator<(u8, u8), u8, BincodeSerde>| {
+                iter.map(|(key, _)| key).collect::<Vec<_>>()
+            };
+
+            assert_eq!(
+                keys(table.prefix(&1u8)),
+                vec![(1, 0), (1, 1), (1, 2), (1, 3)]
+            );
+            assert_eq!(table.prefix(&2u8).next(), Some(((2, 0), 8)));
+
+            // Uncommitted changes of this run are visible.
+            table.remove((1, 2));
+            table.insert((1, 7), 0);
+            assert_eq!(
+                keys(table.prefix(&1u8)),
+                vec![(1, 0), (1, 1), (1, 3), (1, 7)]
+            );
+            assert_eq!(
+                keys(table.prefix_rev(&1u8)),
+                vec![(1, 7), (1, 3), (1, 1), (1, 0)]
+            );
+            assert_eq!(
+                keys(table.range((0, 3)..=(1, 1))),
+                vec![(0, 3), (1, 0), (1, 1)]
+            );
+            assert_eq!(
+                keys(table.range_rev((2, 2)..)),
+                vec![(3, 3), (3, 2), (3, 1), (3, 0), (2, 3), (2, 2)]
+            );
+            assert_eq!(table.range(..).count(), 16);
+        });
+
+        db.query().run(|ctx| {
+            let table = ctx.get_table::<(u8, u8), u8>("TABLE");
+            assert_eq!(
+                table.prefix(&1u8).collect::<Vec<_>>(),
+                vec![((1, 0), 4), ((1, 2), 6), ((1, 3), 7), ((1, 9), 0)]
+            );
+        });
+    }
+}
@@ -93,9 +93,9 @@ pub fn get_metadata(&self) -> &U {
     /// the current one all the way up to the head. Returns `Some` as soon as the
     /// predicate returns `Some` and `None` otherwise.
     #[inline]
-    pub fn find<'a, F, R>(&'a self, predicate: F) -> Option<R>
+    pub fn find<'a, F, R>(&'a self, mut predicate: F) -> Option<R>
     where
-        F: Fn(&'a T) -> Option<R>,
+        F: FnMut(&'a T) -> Option<R>,
     {
         let mut current = self;
 
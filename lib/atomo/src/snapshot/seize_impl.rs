@@ -195,9 +195,9 @@ pub fn get_metadata(&self) -> &U {
     /// the current one all the way up to the head. Returns `Some` as soon as the
     /// predicate returns `Some` and `None` otherwise.
     #[inline]
-    pub fn find<'a, F, R>(&'a self, predicate: F) -> Option<R>
+    pub fn find<'a, F, R>(&'a self, mut predicate: F) -> Option<R>
     where
-        F: Fn(&'a T) -> Option<R>,
+        F: FnMut(&'a T) -> Option<R>,
     {
         let mut current_ptr = self.inner;
 
@@ -224,6 +224,17 @@ pub fn find<'a, F, R>(&'a self, predicate: F) -> Option<R>
     }
 }
 
+impl<T, U> Clone for Snapshot<T, U> {
+    fn clone(&self) -> Self {
+        Self::new(self.inner)
+    }
+}
+
+// Safety: A snapshot only hands out shared references to its node and the nodes after it, and
+// none of them is retired while its counter is above zero.
+unsafe impl<T: Send + Sync, U: Send + Sync> Send for Snapshot<T, U> {}
+unsafe impl<T: Send + Sync, U: Send + Sync> Sync for Snapshot<T, U> {}
+
 impl<T, U> Drop for Snapshot<T, U> {
     fn drop(&mut self) {
         debug_assert!(!self.inner.is_null());
@@ -1,9 +1,22 @@
 use std::fmt::Debug;
+use std::ops::{Bound, RangeBounds};
 
 use dashmap::DashMap;
 
 use crate::batch::{BoxedVec, Operation, VerticalBatch};
 
+/// An iterator over raw key-value pairs of a table.
+pub type RawIterator<'a> = Box<dyn Iterator<Item = (BoxedVec, BoxedVec)> + 'a>;
+
+/// The order in which a range of keys is visited. Keys are ordered by their raw bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the smallest key to the largest one.
+    Forward,
+    /// From the largest key to the smallest one.
+    Reverse,
+}
+
 pub trait StorageBackendConstructor {
     /// The storage API.
     type Storage: StorageBackend;
@@ -25,6 +38,12 @@ pub trait StorageBackend {
     /// Write the changes to the disk.
     fn commit(&self, batch: VerticalBatch);
 
+    /// Returns true if the storage can not be written to, in which case [`Self::commit`] must
+    /// never be called.
+    fn is_read_only(&self) -> bool {
+        false
+    }
+
     /// Return all of the keys from a table.
     fn keys(&self, tid: u8) -> Vec<BoxedVec>;
 
@@ -33,6 +52,17 @@ pub trait StorageBackend {
 
     /// Returns true if the table contains the provided key.
     fn contains(&self, tid: u8, key: &[u8]) -> bool;
+
+    /// Return the key-value pairs of a table with a key within the given bounds, visited in the
+    /// provided direction. The returned iterator must not observe commits that happen after
+    /// this call.
+    fn range(
+        &self,
+        tid: u8,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_>;
 }
 
 #[derive(Default, Clone)]
@@ -88,4 +118,28 @@ fn get(&self, tid: u8, key: &[u8]) -> Option<Vec<u8>> {
     fn contains(&self, tid: u8, key: &[u8]) -> bool {
         self.0[tid as usize].contains_key(key)
     }
+
+    fn range(
+        &self,
+        tid: u8,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+        direction: Direction,
+    ) -> RawIterator<'_> {
+        // The hash map does not keep the keys in order, so we have to collect and sort the
+        // matching entries.
+        let mut collection = Vec::new();
+        for item in self.0[tid as usize].iter() {
+            if RangeBounds::<[u8]>::contains(&(start, end), &item.key()[..]) {
+                collection.push((item.key().clone(), item.value().clone()));
+            }
+        }
+
+        collection.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        if direction == Direction::Reverse {
+            collection.reverse();
+        }
+
+        Box::new(collection.into_iter())
+    }
 }
@@ -3,22 +3,27 @@
 use std::cell::RefCell;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
 use std::sync::Arc;
 
 use fxhash::FxHashSet;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
-use crate::batch::{BatchReference, Operation, VerticalBatch};
+use crate::batch::{BatchReference, BoxedVec, Operation, VerticalBatch};
 use crate::db::TableId;
-use crate::inner::AtomoInner;
+use crate::index::IndexRef;
+use crate::inner::{AtomoInner, SnapshotMetadata};
 use crate::keys::VerticalKeys;
+use crate::merkle::StateProof;
+use crate::range::{as_slice, prefix_bounds, serialize_bounds, Overlay, RangeIterator};
 use crate::serder::SerdeBackend;
 use crate::snapshot::Snapshot;
+use crate::storage::Direction;
 use crate::{KeyIterator, StorageBackend};
 
 pub struct TableMeta {
-    pub _name: String,
+    pub name: String,
     pub k_id: TypeId,
     pub v_id: TypeId,
 }
@@ -54,7 +59,7 @@ pub struct TableSelector<B: StorageBackend, S: SerdeBackend> {
     /// The [`Atomo`] instance.
     atomo: Arc<AtomoInner<B, S>>,
     /// The current version of the data.
-    snapshot: Snapshot<VerticalBatch, VerticalKeys>,
+    snapshot: Snapshot<VerticalBatch, SnapshotMetadata>,
     /// A set of already claimed tables.
     // TODO(qti3e): Replace this with a UnsafeCell or a `SingleThreadedBoolVec`.
     selected: RefCell<FxHashSet<TableId>>,
@@ -81,10 +86,10 @@ pub struct TableRef<
 
 impl TableMeta {
     #[inline(always)]
-    pub fn new<K: Any, V: Any>(_name: String) -> Self {
+    pub fn new<K: Any, V: Any>(name: String) -> Self {
         let k_id = TypeId::of::<K>();
         let v_id = TypeId::of::<V>();
-        Self { _name, k_id, v_id }
+        Self { name, k_id, v_id }
     }
 }
 
@@ -103,10 +108,19 @@ impl<B: StorageBackend, S: SerdeBackend> TableSelector<B, S> {
     /// Create a new table selector for the head of an Atomo instance.
     #[inline]
     pub fn new(atomo: Arc<AtomoInner<B, S>>) -> Self {
+        let snapshot = atomo.snapshot_list.current();
+        Self::with_snapshot(atomo, snapshot)
+    }
+
+    /// Create a new table selector for the given version of an Atomo instance.
+    #[inline]
+    pub(crate) fn with_snapshot(
+        atomo: Arc<AtomoInner<B, S>>,
+        snapshot: Snapshot<VerticalBatch, SnapshotMetadata>,
+    ) -> Self {
         let num_tables = atomo.tables.len();
         let batch = VerticalBatch::new(num_tables);
-        let snapshot = atomo.snapshot_list.current();
-        let keys = snapshot.get_metadata().clone();
+        let keys = snapshot.get_metadata().keys.clone();
 
         Self {
             atomo,
@@ -134,6 +148,94 @@ pub fn get_table<K, V>(&self, name: impl AsRef<str>) -> TableRef<K, V, B, S>
     {
         self.atomo.resolve::<K, V>(name).get(self)
     }
+
+    /// Like [`TableSelector::get_table`] but does not check that the key-value types are the
+    /// ones the table was opened with. This is meant for reading entries that were written in a
+    /// previous format when migrating a table.
+    ///
+    /// # Panics
+    ///
+    /// If the table does not exist or is an index.
+    pub fn get_table_unchecked<K, V>(&self, name: impl AsRef<str>) -> TableRef<K, V, B, S>
+    where
+        K: Hash + Eq + Serialize + DeserializeOwned + Any,
+        V: Serialize + DeserializeOwned + Any,
+    {
+        let name = name.as_ref();
+        let tid = *self
+            .atomo
+            .table_name_to_id
+            .get(name)
+            .unwrap_or_else(|| panic!("Table {name} not found."));
+
+        assert!(
+            !self.atomo.is_index(tid),
+            "Table '{name}' is an index and can not be modified directly."
+        );
+
+        ResolvedTableReference::<K, V>::new(self.atomo.id, tid).get(self)
+    }
+
+    /// Return the reference to the index with the provided name, that maps index keys of type `I`
+    /// to the keys of type `K` of the table it is derived from.
+    ///
+    /// # Panics
+    ///
+    /// If the index does not exist or the types do not match the ones it was declared with.
+    pub fn get_index<I, K>(&self, name: impl AsRef<str>) -> IndexRef<I, K, B, S>
+    where
+        I: Hash + Eq + Serialize + DeserializeOwned + Any,
+        K: Hash + Eq + Serialize + DeserializeOwned + Any,
+    {
+        let tid = self.atomo.resolve_index::<I, K>(name);
+
+        // Safety: The batch of an index is never claimed through `get_table`, it is only updated
+        // by the table references of the indexed table and those never hold on to it.
+        let batch = unsafe { self.batch.claim(tid as usize) };
+
+        IndexRef {
+            table: TableRef {
+                tid,
+                batch,
+                selector: self,
+                kv: PhantomData,
+            },
+        }
+    }
+
+    /// Returns the state root of the snapshot this selector is running on, changes made during
+    /// this run are not reflected in it. Returns `None` if the state tree is not enabled.
+    pub fn state_root(&self) -> Option<[u8; 32]> {
+        self.snapshot
+            .get_metadata()
+            .tree
+            .as_ref()
+            .map(|tree| tree.root())
+    }
+
+    /// Returns the raw value of a raw key in the table with the provided name along with a proof
+    /// of it against [`TableSelector::state_root`]. Just like the state root, changes made during
+    /// this run are not visible here.
+    ///
+    /// Returns `None` if the state tree is not available or the table does not exist.
+    pub fn get_raw_with_proof(
+        &self,
+        name: impl AsRef<str>,
+        key: &[u8],
+    ) -> Option<(Option<Vec<u8>>, StateProof)> {
+        let tree = self.snapshot.get_metadata().tree.as_ref()?;
+        let tid = *self.atomo.table_name_to_id.get(name.as_ref())?;
+
+        // Same as `TableRef::get`, read the persisted value before checking the snapshots.
+        let tmp = self.atomo.get_raw(tid, key);
+        let value = match self.snapshot.find(|batch| batch.get(tid as usize).get(key)) {
+            Some(Operation::Insert(value)) => Some(value.to_vec()),
+            Some(Operation::Remove) => None,
+            None => tmp,
+        };
+
+        Some((value, tree.prove(tid, key)))
+    }
 }
 
 impl<K, V> ResolvedTableReference<K, V> {
@@ -196,6 +298,7 @@ impl<'selector, K, V, B: StorageBackend, S: SerdeBackend> TableRef<'selector, K,
     pub fn insert(&mut self, key: impl Borrow<K>, value: impl Borrow<V>) {
         let k = S::serialize(key.borrow()).into_boxed_slice();
         let v = S::serialize(value.borrow()).into_boxed_slice();
+        self.update_indexes(&k, Some(&v));
         self.selector
             .keys
             .borrow_mut()
@@ -208,6 +311,7 @@ pub fn insert(&mut self, key: impl Borrow<K>, value: impl Borrow<V>) {
     /// Remove the given key from the table.
     pub fn remove(&mut self, key: impl Borrow<K>) {
         let k = S::serialize(key.borrow()).into_boxed_slice();
+        self.update_indexes(&k, None);
         self.selector
             .keys
             .borrow_mut()
@@ -221,31 +325,71 @@ pub fn remove(&mut self, key: impl Borrow<K>) {
     /// [`None`] is returned.
     pub fn get(&self, key: impl Borrow<K>) -> Option<V> {
         let k = S::serialize(key.borrow()).into_boxed_slice();
+        self.get_raw(&k).map(|value| S::deserialize(&value))
+    }
+
+    /// Returns the raw value associated with a raw key.
+    pub(crate) fn get_raw(&self, k: &[u8]) -> Option<Vec<u8>> {
         // We get the underlying value before checking snapshots to fix a race condition where a
         // value is updated after checking the snapshot and before we grab the data
         // todo: optimize this
-        let tmp = self.selector.atomo.get::<V>(self.tid, &k);
-        match self.batch.get(&k) {
-            Some(Operation::Insert(value)) => return Some(S::deserialize(value)),
+        let tmp = self.selector.atomo.get_raw(self.tid, k);
+        match self.batch.get(k) {
+            Some(Operation::Insert(value)) => return Some(value.to_vec()),
             Some(Operation::Remove) => return None,
             _ => {},
         }
 
         let index = self.tid as usize;
-        if let Some(operation) = self
-            .selector
-            .snapshot
-            .find(|batch| batch.get(index).get(&k))
-        {
+        if let Some(operation) = self.selector.snapshot.find(|batch| batch.get(index).get(k)) {
             return match operation {
                 Operation::Remove => None,
-                Operation::Insert(value) => Some(S::deserialize(value)),
+                Operation::Insert(value) => Some(value.to_vec()),
             };
         }
 
         tmp
     }
 
+    /// Updates the entries of the indexes derived from this table for a change on a key, given
+    /// the new value or `None` if the key is removed.
+    fn update_indexes(&self, k: &BoxedVec, value: Option<&BoxedVec>) {
+        let atomo = &self.selector.atomo;
+        if !atomo.indexes.iter().any(|index| index.table == self.tid) {
+            return;
+        }
+
+        let old_value = self.get_raw(k);
+        for index in atomo.indexes.iter().filter(|index| index.table == self.tid) {
+            let old = old_value
+                .as_ref()
+                .map(|value| (index.extract)(k, value))
+                .unwrap_or_default();
+            let new = value
+                .map(|value| (index.extract)(k, value))
+                .unwrap_or_default();
+
+            // Safety: See `TableSelector::get_index`, the reference is dropped before returning.
+            let mut batch = unsafe { self.selector.batch.claim(index.index as usize) };
+            let mut keys = self.selector.keys.borrow_mut();
+            for entry in old.iter().filter(|entry| !new.contains(entry)) {
+                keys.update(index.index, |collection| {
+                    collection.remove(entry);
+                });
+                batch.as_mut().insert(entry.clone(), Operation::Remove);
+            }
+            for entry in new.into_iter().filter(|entry| !old.contains(entry)) {
+                keys.update(index.index, |collection| {
+                    collection.insert(entry.clone());
+                });
+                batch.as_mut().insert(
+                    entry,
+                    Operation::Insert(S::serialize(&()).into_boxed_slice()),
+                );
+            }
+        }
+    }
+
     /// Returns `true` if the key exists in the table.
     pub fn contains_key(&self, key: impl Borrow<K>) -> bool {
         let k = S::serialize(key.borrow()).into_boxed_slice();
@@ -296,4 +440,76 @@ pub fn keys(&self) -> KeyIterator<K> {
 
         KeyIterator::new(keys)
     }
+
+    /// Returns an iterator over the entries with a key in the given range, in the ascending
+    /// order of the serialized keys. The changes made in this run are also visible.
+    ///
+    /// The order is the order of the raw bytes of the keys, which depending on the serde
+    /// backend might differ from the natural order of `K`. For example bincode encodes
+    /// integers in little-endian.
+    pub fn range(&self, range: impl RangeBounds<K>) -> RangeIterator<K, V, S> {
+        let (start, end) = serialize_bounds::<K, S>(&range);
+        self.range_internal(start, end, Direction::Forward)
+    }
+
+    /// Like [`TableRef::range`] but visits the entries in descending order.
+    pub fn range_rev(&self, range: impl RangeBounds<K>) -> RangeIterator<K, V, S> {
+        let (start, end) = serialize_bounds::<K, S>(&range);
+        self.range_internal(start, end, Direction::Reverse)
+    }
+
+    /// Returns an iterator over the entries whose serialized key starts with the serialized
+    /// `prefix`, in the ascending order of the serialized keys. For example with bincode the
+    /// first element of a tuple key can be used to visit every entry that shares it.
+    pub fn prefix<P: Serialize>(&self, prefix: &P) -> RangeIterator<K, V, S> {
+        let (start, end) = prefix_bounds(&S::serialize(prefix));
+        self.range_internal(start, end, Direction::Forward)
+    }
+
+    /// Like [`TableRef::prefix`] but visits the entries in descending order.
+    pub fn prefix_rev<P: Serialize>(&self, prefix: &P) -> RangeIterator<K, V, S> {
+        let (start, end) = prefix_bounds(&S::serialize(prefix));
+        self.range_internal(start, end, Direction::Reverse)
+    }
+
+    fn range_internal(
+        &self,
+        start: Bound<BoxedVec>,
+        end: Bound<BoxedVec>,
+        direction: Direction,
+    ) -> RangeIterator<K, V, S> {
+        let bounds = (as_slice(&start), as_slice(&end));
+
+        // Just like `get` we open the iterator on the storage before checking the snapshots, so
+        // that a commit happening in between is covered by the snapshots.
+        let backend = self
+            .selector
+            .atomo
+            .range_raw(self.tid, bounds.0, bounds.1, direction);
+
+        let value = |operation: &Operation| match operation {
+            Operation::Insert(value) => Some(value.clone()),
+            Operation::Remove => None,
+        };
+
+        let mut overlay = Overlay::new();
+        for (key, operation) in self.batch.iter() {
+            if RangeBounds::<[u8]>::contains(&bounds, &key[..]) {
+                overlay.insert(key.clone(), value(operation));
+            }
+        }
+
+        // The first snapshot that has a change on a key has the value at our version.
+        let index = self.tid as usize;
+        self.selector.snapshot.find(|batch| {
+            for (key, operation) in batch.get(index) {
+                if RangeBounds::<[u8]>::contains(&bounds, &key[..]) && !overlay.contains_key(key) {
+                    overlay.insert(key.clone(), value(operation));
+                }
+            }
+            None::<()>
+        });
+
+        RangeIterator::new(backend, overlay, direction)
+    }
 }
@@ -0,0 +1,134 @@
+//! The codecs that blocks in the blockstore can be compressed with.
+//!
+//! A compressed block is stored next to where the uncompressed block would be, with the
+//! [`Codec::extension`] of its codec appended to the file name. Both the node and the services
+//! reading from the blockstore use this crate to locate and decompress blocks.
+
+use std::io::{self, Read, Write};
+
+/// A compression codec for a single block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    Snappy,
+    Gzip,
+    Brotli,
+    Lz4,
+}
+
+impl Codec {
+    /// All of the supported codecs.
+    pub const ALL: [Codec; 4] = [Codec::Snappy, Codec::Gzip, Codec::Brotli, Codec::Lz4];
+
+    /// Returns the extension of the file name of blocks compressed with this codec.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Snappy => "snappy",
+            Codec::Gzip => "gz",
+            Codec::Brotli => "br",
+            Codec::Lz4 => "lz4",
+        }
+    }
+
+    /// Returns the codec with the given file name extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|codec| codec.extension() == extension)
+    }
+
+    /// Compress the data.
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Codec::Snappy => snap::raw::Encoder::new()
+                .compress_vec(data)
+                .map_err(io::Error::other),
+            Codec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            },
+            Codec::Brotli => {
+                // A moderate quality, since blocks are compressed while they are written.
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(encoder.into_inner())
+            },
+            Codec::Lz4 => lz4::block::compress(data, None, true),
+        }
+    }
+
+    /// Decompress the data, fails if the decompressed data would be larger than `max_len`
+    /// bytes.
+    pub fn decompress(&self, data: &[u8], max_len: usize) -> io::Result<Vec<u8>> {
+        let too_large =
+            || io::Error::new(io::ErrorKind::InvalidData, "decompressed data too large");
+        match self {
+            Codec::Snappy => {
+                if snap::raw::decompress_len(data).map_err(io::Error::other)? > max_len {
+                    return Err(too_large());
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(data)
+                    .map_err(io::Error::other)
+            },
+            Codec::Gzip => read_limited(flate2::read::GzDecoder::new(data), max_len),
+            Codec::Brotli => read_limited(brotli::Decompressor::new(data, 4096), max_len),
+            Codec::Lz4 => {
+                // The decompressed size is prepended as a little endian `i32`.
+                let len = data
+                    .get(..4)
+                    .map(|len| i32::from_le_bytes(len.try_into().unwrap()))
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+                if len < 0 || len as usize > max_len {
+                    return Err(too_large());
+                }
+                lz4::block::decompress(data, None)
+            },
+        }
+    }
+}
+
+fn read_limited(reader: impl Read, max_len: usize) -> io::Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    reader.take(max_len as u64 + 1).read_to_end(&mut buffer)?;
+    if buffer.len() > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "decompressed data too large",
+        ));
+    }
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> Vec<u8> {
+        (0..256 << 10).map(|i| (i / 1000) as u8).collect()
+    }
+
+    #[test]
+    fn codecs_should_roundtrip() {
+        let data = data();
+        for codec in Codec::ALL {
+            let compressed = codec.compress(&data).unwrap();
+            assert!(compressed.len() < data.len(), "{codec:?}");
+            assert_eq!(codec.decompress(&compressed, data.len()).unwrap(), data);
+            assert_eq!(Codec::from_extension(codec.extension()), Some(codec));
+        }
+        assert_eq!(Codec::from_extension("zip"), None);
+    }
+
+    #[test]
+    fn decompress_should_respect_the_limit() {
+        let data = data();
+        for codec in Codec::ALL {
+            let compressed = codec.compress(&data).unwrap();
+            assert!(codec.decompress(&compressed, data.len() - 1).is_err());
+            assert!(codec.decompress(&data[..100], data.len()).is_err());
+        }
+    }
+}
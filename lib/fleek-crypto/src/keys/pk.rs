@@ -287,3 +287,35 @@ fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::S
         schemars::schema_for_value!(key).schema.into()
     }
 }
+
+impl schemars::JsonSchema for ClientPublicKey {
+    fn schema_name() -> String {
+        "ClientPublicKey".to_string()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        std::borrow::Cow::Borrowed(concat!(module_path!(), "::ClientPublicKey"))
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        let key = ClientPublicKey::from_str("u76G7q22Qc5nRC5Fi6dzbNE7FQxqRKEtTS9qjDftWFwhBKmoozGLv8wFiFmGnYDFMEKyYxozWRdM3wgjs1Na3fvxDARxi9CSNJUZJfPXC2WUu3uLnUw96jPBRp7rtHEzS5H").expect("valid client public key for example");
+
+        schemars::schema_for_value!(key).schema.into()
+    }
+}
+
+impl schemars::JsonSchema for ClientSignature {
+    fn schema_name() -> String {
+        "ClientSignature".to_string()
+    }
+
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        std::borrow::Cow::Borrowed(concat!(module_path!(), "::ClientSignature"))
+    }
+
+    fn json_schema(_gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        let sig = Self::from([0u8; 48]);
+
+        schemars::schema_for_value!(sig).schema.into()
+    }
+}
@@ -1,11 +1,16 @@
 use std::io::ErrorKind;
+use std::ops::Range;
 use std::path::PathBuf;
 
 use arrayvec::ArrayString;
 use blake3_tree::utils::HashTree;
+use blake3_tree::ProofBuf;
+use fleek_compression::Codec;
 
 use crate::ipc::BLOCKSTORE;
 
+const BLOCK_SIZE: usize = 256 << 10;
+
 /// Returns the root blockstore.
 ///
 /// # Panics
@@ -25,6 +30,26 @@ pub fn blockstore_root() -> &'static PathBuf {
     blockstore_root().join(format!("./block/{counter}-{}", to_hex(block_hash)))
 }
 
+/// Read a block from the file system, decompressing it if the blockstore stored it compressed.
+pub fn read_block(counter: usize, block_hash: &[u8; 32]) -> std::io::Result<Vec<u8>> {
+    let path = get_block_path(counter, block_hash);
+    match std::fs::read(&path) {
+        Err(e) if e.kind() == ErrorKind::NotFound => {},
+        result => return result,
+    }
+
+    // Compressed blocks have the extension of their codec appended to the file name.
+    for codec in Codec::ALL {
+        match std::fs::read(path.with_extension(codec.extension())) {
+            Ok(block) => return codec.decompress(&block, BLOCK_SIZE),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(ErrorKind::NotFound.into())
+}
+
 #[inline]
 fn to_hex(slice: &[u8; 32]) -> ArrayString<64> {
     let mut s = ArrayString::new();
@@ -65,18 +90,102 @@ pub fn len(&self) -> usize {
 
     /// Read a block from the file system.
     pub async fn read(&self, block: usize) -> std::io::Result<Vec<u8>> {
-        let path = get_block_path(block, &self.tree[block]);
-        std::fs::read(path)
+        read_block(block, &self.tree[block])
     }
 
     /// Read the entire content from the file system.
     pub async fn read_to_end(&self) -> std::io::Result<Vec<u8>> {
         // Reserve capacity for all but the last block, since we know all blocks but the last one
         // will be 256KiB
-        let mut buf = Vec::with_capacity((256 << 10) * (self.len() - 1));
+        let mut buf = Vec::with_capacity(BLOCK_SIZE * (self.len() - 1));
         for i in 0..self.len() {
             buf.append(&mut self.read(i).await?);
         }
         Ok(buf)
     }
+
+    /// Returns a reader over the blocks that cover `len` bytes of the content starting at the
+    /// byte `offset`. The range is cut off at the end of the content.
+    pub fn read_range(&self, offset: u64, len: u64) -> RangeReader<'_> {
+        let first = (offset / BLOCK_SIZE as u64) as usize;
+        RangeReader {
+            handle: self,
+            offset,
+            end: offset.saturating_add(len),
+            first,
+            next: first,
+        }
+    }
+}
+
+/// A reader over the blocks that cover a byte range of some content, see
+/// [`ContentHandle::read_range`].
+pub struct RangeReader<'a> {
+    handle: &'a ContentHandle,
+    offset: u64,
+    end: u64,
+    first: usize,
+    next: usize,
+}
+
+/// A block that covers part of a byte range.
+pub struct RangeBlock {
+    /// The counter of the block in the content.
+    pub counter: usize,
+    /// The proof to feed to an incremental verifier before the block, the verifier has to start
+    /// at the first block of the range.
+    pub proof: ProofBuf,
+    /// The entire block, which is what the proof verifies.
+    pub block: Vec<u8>,
+    /// The part of the block that is in the range.
+    pub range: Range<usize>,
+}
+
+impl RangeBlock {
+    /// Returns the bytes of the block that are in the range.
+    pub fn content(&self) -> &[u8] {
+        &self.block[self.range.clone()]
+    }
+}
+
+impl RangeReader<'_> {
+    /// Returns the counter of the first block of the range, which is the block an incremental
+    /// verifier of the range has to start at.
+    pub fn first_block(&self) -> usize {
+        self.first
+    }
+
+    /// Read the next block of the range. Returns [`None`] once the range is read.
+    pub async fn next_block(&mut self) -> Option<std::io::Result<RangeBlock>> {
+        let counter = self.next;
+        let block_offset = counter as u64 * BLOCK_SIZE as u64;
+        if counter >= self.handle.len() || block_offset >= self.end {
+            return None;
+        }
+
+        let block = match self.handle.read(counter).await {
+            Ok(block) => block,
+            Err(e) => return Some(Err(e)),
+        };
+        let start = self.offset.saturating_sub(block_offset) as usize;
+        let stop = block.len().min((self.end - block_offset) as usize);
+        if start >= stop {
+            // The offset is past the end of the content.
+            return None;
+        }
+
+        let tree: &[[u8; 32]] = self.handle.tree.as_ref();
+        let proof = if counter == self.first {
+            ProofBuf::new(tree, counter)
+        } else {
+            ProofBuf::resume(tree, counter)
+        };
+        self.next += 1;
+        Some(Ok(RangeBlock {
+            counter,
+            proof,
+            block,
+            range: start..stop,
+        }))
+    }
 }
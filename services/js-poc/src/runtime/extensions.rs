@@ -73,8 +73,7 @@ pub async fn read_block(
 ) -> anyhow::Result<Vec<u8>> {
     let tree = HashVec::from_inner(proof);
     let inner_hash = tree[tree_index(index)];
-    let path = fn_sdk::blockstore::get_block_path(index, &inner_hash);
-    let block = std::fs::read(path)?;
+    let block = fn_sdk::blockstore::read_block(index, &inner_hash)?;
 
     Ok(block)
 }